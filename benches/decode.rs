@@ -0,0 +1,241 @@
+use bifrost::frame::RespCodec;
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::io;
+use tokio_util::codec::Decoder;
+
+/// The decoder as it was before the incremental codec, kept as a reference point. Each
+/// call clones the whole buffer and parses it from the start, and every array element
+/// is parsed from a fresh copy of the rest of the buffer.
+mod baseline {
+    use bytes::{Buf, BytesMut};
+    use std::io;
+    use tokio_util::codec::Decoder;
+
+    const STRING: u8 = b'+';
+    const ERROR: u8 = b'-';
+    const INTEGER: u8 = b':';
+    const BULK: u8 = b'$';
+    const ARRAY: u8 = b'*';
+
+    #[allow(dead_code)]
+    #[derive(Debug)]
+    pub enum RespType {
+        BulkString(String),
+        SimpleString(String),
+        Integer(i64),
+        Array(Vec<RespType>),
+        Error(String),
+        Null,
+    }
+
+    #[derive(Debug)]
+    enum RespError {
+        Incomplete,
+        Invalid(String),
+    }
+
+    struct Resp {
+        buffer: BytesMut,
+    }
+
+    impl Resp {
+        fn new(buffer: BytesMut) -> Self {
+            Resp { buffer }
+        }
+
+        fn parse(&mut self) -> Result<(RespType, usize), RespError> {
+            // The only change from the original, which panicked when a read ended right
+            // after an array element.
+            if self.buffer.is_empty() {
+                return Err(RespError::Incomplete);
+            }
+            match self.buffer[0] {
+                BULK => Self::parse_bulk_string(self),
+                STRING => Self::parse_simple_string(self),
+                INTEGER => Self::parse_integer(self),
+                ARRAY => Self::parse_array(self),
+                ERROR => Self::parse_error(self),
+                _ => Err(RespError::Invalid(String::from("Invalid RESP data type!"))),
+            }
+        }
+
+        fn parse_bulk_string(&self) -> Result<(RespType, usize), RespError> {
+            let (str_length, mut len) = self.parse_integer_value(1)?;
+            if str_length == -1 {
+                return Ok((RespType::Null, len));
+            }
+
+            let str_length = str_length as usize;
+
+            if self.buffer.len() < len + str_length + 2 {
+                return Err(RespError::Incomplete);
+            }
+
+            let string = String::from_utf8(self.buffer[len..len + str_length].to_vec())
+                .map_err(|_| RespError::Invalid("Invalid UTF-8".to_string()))?;
+
+            len += str_length + 2;
+            Ok((RespType::BulkString(string), len))
+        }
+
+        fn parse_simple_string(&self) -> Result<(RespType, usize), RespError> {
+            let (line, len) = self.read_line(1)?;
+            Ok((RespType::SimpleString(line), len))
+        }
+
+        fn parse_integer(&self) -> Result<(RespType, usize), RespError> {
+            let (value, len) = self.parse_integer_value(1)?;
+            Ok((RespType::Integer(value), len))
+        }
+
+        fn parse_array(&self) -> Result<(RespType, usize), RespError> {
+            let (arr_size, mut len) = self.parse_integer_value(1)?;
+            if arr_size == -1 {
+                return Ok((RespType::Null, len));
+            }
+
+            let arr_size = arr_size as usize;
+
+            let mut array = Vec::with_capacity(arr_size);
+            for _ in 0..arr_size {
+                let mut resp = Resp::new(BytesMut::from(&self.buffer[len..]));
+                let (item, item_len) = resp.parse()?;
+                array.push(item);
+                len += item_len;
+            }
+
+            Ok((RespType::Array(array), len))
+        }
+
+        fn parse_error(&self) -> Result<(RespType, usize), RespError> {
+            let (line, len) = self.read_line(1)?;
+            Ok((RespType::Error(line), len))
+        }
+
+        fn read_line(&self, start: usize) -> Result<(String, usize), RespError> {
+            for i in start + 1..self.buffer.len() {
+                if self.buffer[i - 1] == b'\r' && self.buffer[i] == b'\n' {
+                    return Ok((
+                        String::from_utf8(self.buffer[start..i - 1].to_vec())
+                            .map_err(|_| RespError::Invalid("Invalid UTF-8".to_string()))?,
+                        i + 1,
+                    ));
+                }
+            }
+            Err(RespError::Incomplete)
+        }
+
+        fn parse_integer_value(&self, start: usize) -> Result<(i64, usize), RespError> {
+            let (line, pos) = self.read_line(start)?;
+            line.parse::<i64>()
+                .map_err(|_| RespError::Invalid("Invalid integer format".to_string()))
+                .map(|value| (value, pos))
+        }
+    }
+
+    pub struct RespCodec;
+
+    impl Decoder for RespCodec {
+        type Item = RespType;
+        type Error = io::Error;
+
+        fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+            if src.is_empty() {
+                return Ok(None);
+            }
+
+            let mut resp = Resp::new(src.clone());
+            match resp.parse() {
+                Ok((resp_type, consumed)) => {
+                    src.advance(consumed);
+                    Ok(Some(resp_type))
+                }
+                Err(RespError::Incomplete) => Ok(None),
+                Err(RespError::Invalid(message)) => Err(io::Error::new(io::ErrorKind::InvalidData, message)),
+            }
+        }
+    }
+}
+
+fn encode_command(args: &[&[u8]], out: &mut Vec<u8>) {
+    out.extend_from_slice(format!("*{}\r\n", args.len()).as_bytes());
+    for arg in args {
+        out.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        out.extend_from_slice(arg);
+        out.extend_from_slice(b"\r\n");
+    }
+}
+
+fn pipeline(commands: usize) -> Vec<u8> {
+    let value = vec![b'v'; 64];
+    let mut out = Vec::new();
+    for i in 0..commands {
+        let key = format!("key:{}", i);
+        encode_command(&[b"SET", key.as_bytes(), &value], &mut out);
+    }
+    out
+}
+
+fn mset(pairs: usize) -> Vec<u8> {
+    let keys: Vec<String> = (0..pairs).map(|i| format!("key:{}", i)).collect();
+    let value = vec![b'v'; 64];
+    let mut args: Vec<&[u8]> = vec![b"MSET"];
+    for key in &keys {
+        args.push(key.as_bytes());
+        args.push(&value);
+    }
+    let mut out = Vec::new();
+    encode_command(&args, &mut out);
+    out
+}
+
+// Feeds `input` to the decoder in reads of `chunk` bytes, like a socket would.
+fn decode_all<D>(decoder: &mut D, input: &[u8], chunk: usize) -> usize
+where
+    D: Decoder<Error = io::Error>,
+{
+    let mut buf = BytesMut::with_capacity(chunk);
+    let mut frames = 0;
+    for piece in input.chunks(chunk) {
+        buf.extend_from_slice(piece);
+        while let Some(_frame) = decoder.decode(&mut buf).unwrap() {
+            frames += 1;
+        }
+    }
+    frames
+}
+
+fn bench_pipeline(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pipeline");
+    for commands in [1_000, 10_000] {
+        let input = pipeline(commands);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("incremental", commands), &input, |b, input| {
+            b.iter(|| decode_all(&mut RespCodec::new(), input, 16 * 1024))
+        });
+        group.bench_with_input(BenchmarkId::new("baseline", commands), &input, |b, input| {
+            b.iter(|| decode_all(&mut baseline::RespCodec, input, 16 * 1024))
+        });
+    }
+    group.finish();
+}
+
+fn bench_large_mset(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mset");
+    group.sample_size(10);
+    for pairs in [1_000, 3_000] {
+        let input = mset(pairs);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("incremental", pairs), &input, |b, input| {
+            b.iter(|| decode_all(&mut RespCodec::new(), input, 4 * 1024))
+        });
+        group.bench_with_input(BenchmarkId::new("baseline", pairs), &input, |b, input| {
+            b.iter(|| decode_all(&mut baseline::RespCodec, input, 4 * 1024))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pipeline, bench_large_mset);
+criterion_main!(benches);
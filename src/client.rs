@@ -0,0 +1,28 @@
+use bytes::Bytes;
+
+use crate::resp::ProtocolVersion;
+
+/// State kept for a single connection across requests.
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub id: u64,
+    pub protocol: ProtocolVersion,
+    pub name: Option<Bytes>,
+    /// The index of the database the connection has selected.
+    pub db: usize,
+    /// What CLIENT CACHING asked for the next command, when tracking in OPTIN or OPTOUT
+    /// mode.
+    pub caching: Option<bool>,
+}
+
+impl Client {
+    pub fn new(id: u64) -> Self {
+        Client {
+            id,
+            protocol: ProtocolVersion::default(),
+            name: None,
+            db: 0,
+            caching: None,
+        }
+    }
+}
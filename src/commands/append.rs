@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct AppendCommand {
+    pub key: Bytes,
+    pub value: Bytes,
+}
+
+impl Command for AppendCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.append(&self.key, &self.value))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::bitmap::BitUnit;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct BitCountCommand {
+    pub key: Bytes,
+    pub range: Option<(i64, i64, BitUnit)>,
+}
+
+impl Command for BitCountCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.bitcount(&self.key, self.range))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
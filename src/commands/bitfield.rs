@@ -0,0 +1,21 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::bitmap::BitFieldOp;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+/// BITFIELD, and BITFIELD_RO which only accepts GET operations.
+pub struct BitFieldCommand {
+    pub key: Bytes,
+    pub ops: Vec<BitFieldOp>,
+}
+
+impl Command for BitFieldCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.bitfield(&self.key, &self.ops))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,21 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::bitmap::BitOperation;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct BitOpCommand {
+    pub operation: BitOperation,
+    pub destination: Bytes,
+    pub keys: Vec<Bytes>,
+}
+
+impl Command for BitOpCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.bitop(self.operation, self.destination.clone(), &self.keys))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
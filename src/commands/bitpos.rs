@@ -0,0 +1,27 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::bitmap::BitUnit;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct BitPosCommand {
+    pub key: Bytes,
+    pub bit: bool,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub unit: BitUnit,
+}
+
+impl Command for BitPosCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.bitpos(&self.key, self.bit, self.start, self.end, self.unit))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use std::time::Duration;
+use crate::storage::blocking::Blocking;
+use crate::storage::db::Db;
+use crate::storage::list::ListEnd;
+use super::{into_blocking, BlockingCommand};
+
+pub struct BLMoveCommand {
+    pub source: Bytes,
+    pub destination: Bytes,
+    pub from: ListEnd,
+    pub to: ListEnd,
+    pub timeout: Option<Duration>,
+}
+
+impl BlockingCommand for BLMoveCommand {
+    fn execute(&self, db: &Db) -> Blocking {
+        into_blocking(db.blocking_move(&self.source, &self.destination, self.from, self.to, self.timeout))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,21 @@
+use bytes::Bytes;
+use std::time::Duration;
+use crate::storage::blocking::Blocking;
+use crate::storage::db::Db;
+use crate::storage::list::ListEnd;
+use super::{into_blocking, BlockingCommand};
+
+pub struct BLPopCommand {
+    pub keys: Vec<Bytes>,
+    pub timeout: Option<Duration>,
+}
+
+impl BlockingCommand for BLPopCommand {
+    fn execute(&self, db: &Db) -> Blocking {
+        into_blocking(db.blocking_pop(&self.keys, ListEnd::Left, self.timeout))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
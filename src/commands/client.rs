@@ -0,0 +1,62 @@
+use crate::client::Client;
+use crate::resp::RespType;
+use crate::storage::databases::Databases;
+use crate::storage::tracking::TrackingOptions;
+use super::{into_response, ConnectionCommand};
+
+pub enum ClientCommand {
+    Id,
+    /// CLIENT TRACKING ON with its options, or OFF.
+    Tracking(Option<TrackingOptions>),
+    /// CLIENT CACHING YES or NO, which applies to the next command only.
+    Caching(bool),
+    GetRedir,
+}
+
+fn ok() -> RespType {
+    RespType::SimpleString("OK".to_string())
+}
+
+impl ConnectionCommand for ClientCommand {
+    fn execute(&self, client: &mut Client, databases: &Databases) -> RespType {
+        let tracker = databases.tracker();
+        match self {
+            ClientCommand::Id => RespType::Integer(client.id as i64),
+            ClientCommand::Tracking(Some(options)) => {
+                into_response(tracker.enable(client.id, options.clone()).map(|()| ok()))
+            }
+            ClientCommand::Tracking(None) => {
+                tracker.disable(client.id);
+                ok()
+            }
+            ClientCommand::Caching(yes) => match tracker.options(client.id) {
+                Some(options) if (options.optin && *yes) || (options.optout && !*yes) => {
+                    client.caching = Some(*yes);
+                    ok()
+                }
+                Some(options) if options.optin => RespType::Error(
+                    "ERR CLIENT CACHING NO is only valid when tracking is enabled in OPTOUT mode.".to_string(),
+                ),
+                Some(options) if options.optout => RespType::Error(
+                    "ERR CLIENT CACHING YES is only valid when tracking is enabled in OPTIN mode.".to_string(),
+                ),
+                _ => RespType::Error(
+                    "ERR CLIENT CACHING can be called only when the client is in tracking mode with OPTIN or OPTOUT mode enabled".to_string(),
+                ),
+            },
+            // -1 when tracking is off and 0 when invalidations are not redirected.
+            ClientCommand::GetRedir => RespType::Integer(match tracker.options(client.id) {
+                Some(options) => options.redirect.map_or(0, |id| id as i64),
+                None => -1,
+            }),
+        }
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn is_noscript(&self) -> bool {
+        true
+    }
+}
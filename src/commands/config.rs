@@ -0,0 +1,62 @@
+use bytes::Bytes;
+use crate::client::Client;
+use crate::resp::RespType;
+use crate::storage::databases::Databases;
+use crate::storage::notify::EventClasses;
+use crate::storage::scan::glob_match;
+use super::ConnectionCommand;
+
+// The parameters CONFIG GET reports.
+const PARAMETERS: [&str; 1] = ["notify-keyspace-events"];
+
+/// A validated value for one configuration parameter.
+pub enum ConfigSetting {
+    NotifyKeyspaceEvents(EventClasses),
+}
+
+pub enum ConfigCommand {
+    /// CONFIG GET with glob-style patterns matched against parameter names.
+    Get(Vec<Bytes>),
+    Set(Vec<ConfigSetting>),
+}
+
+impl ConnectionCommand for ConfigCommand {
+    fn execute(&self, _client: &mut Client, databases: &Databases) -> RespType {
+        match self {
+            ConfigCommand::Get(patterns) => {
+                let settings = PARAMETERS
+                    .iter()
+                    .filter(|name| {
+                        patterns.iter().any(|pattern| glob_match(&pattern.to_ascii_lowercase(), name.as_bytes()))
+                    })
+                    .map(|&name| {
+                        let value = match name {
+                            "notify-keyspace-events" => databases.notifier().classes().to_string(),
+                            _ => String::new(),
+                        };
+                        (RespType::BulkString(Bytes::from(name)), RespType::BulkString(Bytes::from(value)))
+                    })
+                    .collect();
+                RespType::Map(settings)
+            }
+            ConfigCommand::Set(settings) => {
+                for setting in settings {
+                    match setting {
+                        ConfigSetting::NotifyKeyspaceEvents(classes) => {
+                            databases.notifier().set_classes(*classes)
+                        }
+                    }
+                }
+                RespType::SimpleString("OK".to_string())
+            }
+        }
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn is_noscript(&self) -> bool {
+        true
+    }
+}
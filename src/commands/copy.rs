@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use crate::client::Client;
+use crate::resp::RespType;
+use crate::storage::databases::Databases;
+use super::{into_response, ConnectionCommand};
+
+/// COPY, within the selected database or with the DB option into another.
+pub struct CopyCommand {
+    pub source: Bytes,
+    pub destination: Bytes,
+    pub db: Option<usize>,
+    pub replace: bool,
+}
+
+impl ConnectionCommand for CopyCommand {
+    fn execute(&self, client: &mut Client, databases: &Databases) -> RespType {
+        let to = self.db.unwrap_or(client.db);
+        into_response(databases.copy(&self.source, client.db, self.destination.clone(), to, self.replace))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
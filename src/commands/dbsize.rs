@@ -0,0 +1,15 @@
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct DbSizeCommand;
+
+impl Command for DbSizeCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.dbsize()
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+}
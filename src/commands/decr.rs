@@ -1,9 +1,10 @@
+use bytes::Bytes;
 use crate::resp::RespType;
 use crate::storage::db::Db;
 use crate::error::BifrostError;
 use super::Command;
 
-pub struct DecrCommand(pub String);
+pub struct DecrCommand(pub Bytes);
 
 impl Command for DecrCommand {
     fn execute(&self, db: &Db) -> RespType {
@@ -15,4 +16,8 @@ impl Command for DecrCommand {
             }
         }
     }
+
+    fn is_write(&self) -> bool {
+        true
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct DecrByCommand {
+    pub key: Bytes,
+    pub delta: i64,
+}
+
+impl Command for DecrByCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.decrby(&self.key, self.delta))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -1,11 +1,16 @@
+use bytes::Bytes;
 use crate::resp::RespType;
 use crate::storage::db::Db;
 use super::Command;
 
-pub struct DelCommand(pub String);
+pub struct DelCommand(pub Vec<Bytes>);
 
 impl Command for DelCommand {
     fn execute(&self, db: &Db) -> RespType {
         db.del(&self.0)
     }
-} 
\ No newline at end of file
+
+    fn is_write(&self) -> bool {
+        true
+    }
+} 
@@ -8,4 +8,8 @@ impl Command for EchoCommand {
     fn execute(&self, _db: &Db) -> RespType {
         self.0.clone()
     }
+
+    fn is_write(&self) -> bool {
+        false
+    }
 } 
\ No newline at end of file
@@ -1,11 +1,20 @@
+use bytes::Bytes;
 use crate::resp::RespType;
 use crate::storage::db::Db;
 use super::Command;
 
-pub struct ExistsCommand(pub String);
+pub struct ExistsCommand(pub Vec<Bytes>);
 
 impl Command for ExistsCommand {
     fn execute(&self, db: &Db) -> RespType {
         db.exists(&self.0)
     }
-} 
\ No newline at end of file
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        &self.0
+    }
+} 
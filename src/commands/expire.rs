@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::{Db, ExpireCondition, Expiration};
+use super::Command;
+
+/// Backs EXPIRE, PEXPIRE, EXPIREAT and PEXPIREAT, which only differ in how the
+/// expiration argument is interpreted.
+pub struct ExpireCommand {
+    pub key: Bytes,
+    pub expiration: Expiration,
+    pub condition: ExpireCondition,
+    /// The command's name, for error replies.
+    pub name: &'static str,
+}
+
+impl Command for ExpireCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.expire(&self.key, self.expiration, self.condition, self.name)
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,16 @@
+use crate::client::Client;
+use crate::resp::RespType;
+use crate::storage::databases::Databases;
+use super::ConnectionCommand;
+
+pub struct FlushAllCommand;
+
+impl ConnectionCommand for FlushAllCommand {
+    fn execute(&self, _client: &mut Client, databases: &Databases) -> RespType {
+        databases.flush_all()
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,15 @@
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct FlushDbCommand;
+
+impl Command for FlushDbCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.flushdb()
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,21 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::sorted_set::ZaddOptions;
+use super::{into_response, Command};
+
+pub struct GeoAddCommand {
+    pub key: Bytes,
+    pub positions: Vec<(f64, f64, Bytes)>,
+    pub options: ZaddOptions,
+}
+
+impl Command for GeoAddCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.geoadd(&self.key, &self.positions, self.options))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
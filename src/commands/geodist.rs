@@ -0,0 +1,26 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::geo::DistanceUnit;
+use super::{into_response, Command};
+
+pub struct GeoDistCommand {
+    pub key: Bytes,
+    pub from: Bytes,
+    pub to: Bytes,
+    pub unit: DistanceUnit,
+}
+
+impl Command for GeoDistCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.geodist(&self.key, &self.from, &self.to, self.unit))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
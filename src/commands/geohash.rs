@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct GeoHashCommand {
+    pub key: Bytes,
+    pub members: Vec<Bytes>,
+}
+
+impl Command for GeoHashCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.geohash(&self.key, &self.members))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct GeoPosCommand {
+    pub key: Bytes,
+    pub members: Vec<Bytes>,
+}
+
+impl Command for GeoPosCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.geopos(&self.key, &self.members))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::geo::GeoSearch;
+use super::{into_response, Command};
+
+pub struct GeoSearchCommand {
+    pub key: Bytes,
+    pub search: GeoSearch,
+}
+
+impl Command for GeoSearchCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.geosearch(&self.key, &self.search))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
@@ -0,0 +1,22 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::geo::GeoSearch;
+use super::{into_response, Command};
+
+pub struct GeoSearchStoreCommand {
+    pub destination: Bytes,
+    pub key: Bytes,
+    pub search: GeoSearch,
+    pub store_dist: bool,
+}
+
+impl Command for GeoSearchStoreCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.geosearchstore(self.destination.clone(), &self.key, &self.search, self.store_dist))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
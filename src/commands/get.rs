@@ -1,11 +1,20 @@
+use bytes::Bytes;
 use crate::resp::RespType;
 use crate::storage::db::Db;
-use super::Command;
+use super::{into_response, Command};
 
-pub struct GetCommand(pub String);
+pub struct GetCommand(pub Bytes);
 
 impl Command for GetCommand {
     fn execute(&self, db: &Db) -> RespType {
-        db.get(&self.0).unwrap_or(RespType::Null)
+        into_response(db.get(&self.0).map(|value| value.map_or(RespType::Null, RespType::BulkString)))
     }
-} 
\ No newline at end of file
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.0)
+    }
+}
@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct GetBitCommand {
+    pub key: Bytes,
+    pub offset: u64,
+}
+
+impl Command for GetBitCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.getbit(&self.key, self.offset))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
@@ -0,0 +1,16 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct GetDelCommand(pub Bytes);
+
+impl Command for GetDelCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.getdel(&self.0))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
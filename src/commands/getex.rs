@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::string::GetExpiry;
+use super::{into_response, Command};
+
+pub struct GetExCommand {
+    pub key: Bytes,
+    pub expiry: Option<GetExpiry>,
+}
+
+impl Command for GetExCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.getex(&self.key, self.expiry))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct GetSetCommand {
+    pub key: Bytes,
+    pub value: Bytes,
+}
+
+impl Command for GetSetCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.getset(self.key.clone(), self.value.clone()))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
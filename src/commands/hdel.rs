@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct HDelCommand {
+    pub key: Bytes,
+    pub fields: Vec<Bytes>,
+}
+
+impl Command for HDelCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.hdel(&self.key, &self.fields))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
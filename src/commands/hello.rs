@@ -0,0 +1,57 @@
+use bytes::Bytes;
+use crate::client::Client;
+use crate::resp::{ProtocolVersion, RespType};
+use crate::storage::databases::Databases;
+use super::ConnectionCommand;
+
+pub struct HelloCommand {
+    pub protocol: Option<ProtocolVersion>,
+    pub auth: Option<(Bytes, Bytes)>,
+    pub name: Option<Bytes>,
+}
+
+impl ConnectionCommand for HelloCommand {
+    fn execute(&self, client: &mut Client, _databases: &Databases) -> RespType {
+        // No passwords are configured, so the default user accepts any password.
+        if let Some((username, _)) = &self.auth {
+            if username.as_ref() != b"default" {
+                return RespType::Error(
+                    "WRONGPASS invalid username-password pair or user is disabled.".to_string(),
+                );
+            }
+        }
+
+        if let Some(protocol) = self.protocol {
+            client.protocol = protocol;
+        }
+        if let Some(name) = &self.name {
+            client.name = Some(name.clone());
+        }
+
+        let proto = match client.protocol {
+            ProtocolVersion::Resp2 => 2,
+            ProtocolVersion::Resp3 => 3,
+        };
+        RespType::Map(vec![
+            (bulk("server"), bulk("bifrost")),
+            (bulk("version"), bulk(env!("CARGO_PKG_VERSION"))),
+            (bulk("proto"), RespType::Integer(proto)),
+            (bulk("id"), RespType::Integer(client.id as i64)),
+            (bulk("mode"), bulk("standalone")),
+            (bulk("role"), bulk("master")),
+            (bulk("modules"), RespType::Array(vec![])),
+        ])
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn is_noscript(&self) -> bool {
+        true
+    }
+}
+
+fn bulk(value: &'static str) -> RespType {
+    RespType::BulkString(Bytes::from_static(value.as_bytes()))
+}
@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct HExistsCommand {
+    pub key: Bytes,
+    pub field: Bytes,
+}
+
+impl Command for HExistsCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.hexists(&self.key, &self.field))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct HGetCommand {
+    pub key: Bytes,
+    pub field: Bytes,
+}
+
+impl Command for HGetCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.hget(&self.key, &self.field))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct HGetAllCommand(pub Bytes);
+
+impl Command for HGetAllCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.hgetall(&self.0))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.0)
+    }
+}
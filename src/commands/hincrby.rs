@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct HIncrByCommand {
+    pub key: Bytes,
+    pub field: Bytes,
+    pub delta: i64,
+}
+
+impl Command for HIncrByCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.hincrby(&self.key, self.field.clone(), self.delta))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
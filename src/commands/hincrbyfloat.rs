@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct HIncrByFloatCommand {
+    pub key: Bytes,
+    pub field: Bytes,
+    pub delta: f64,
+}
+
+impl Command for HIncrByFloatCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.hincrbyfloat(&self.key, self.field.clone(), self.delta))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
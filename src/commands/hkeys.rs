@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct HKeysCommand(pub Bytes);
+
+impl Command for HKeysCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.hkeys(&self.0))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.0)
+    }
+}
@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct HMGetCommand {
+    pub key: Bytes,
+    pub fields: Vec<Bytes>,
+}
+
+impl Command for HMGetCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.hmget(&self.key, &self.fields))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
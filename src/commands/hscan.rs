@@ -0,0 +1,22 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::scan::ScanOptions;
+use super::{into_response, Command};
+
+pub struct HScanCommand {
+    pub key: Bytes,
+    pub cursor: u64,
+    pub options: ScanOptions,
+    pub novalues: bool,
+}
+
+impl Command for HScanCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.hscan(&self.key, self.cursor, &self.options, self.novalues))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct HSetCommand {
+    pub key: Bytes,
+    pub pairs: Vec<(Bytes, Bytes)>,
+}
+
+impl Command for HSetCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.hset(&self.key, &self.pairs))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct HSetNxCommand {
+    pub key: Bytes,
+    pub field: Bytes,
+    pub value: Bytes,
+}
+
+impl Command for HSetNxCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.hsetnx(&self.key, self.field.clone(), self.value.clone()))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -1,9 +1,10 @@
+use bytes::Bytes;
 use crate::resp::RespType;
 use crate::storage::db::Db;
 use crate::error::BifrostError;
 use super::Command;
 
-pub struct IncrCommand(pub String);
+pub struct IncrCommand(pub Bytes);
 
 impl Command for IncrCommand {
     fn execute(&self, db: &Db) -> RespType {
@@ -15,4 +16,8 @@ impl Command for IncrCommand {
             }
         }
     }
+
+    fn is_write(&self) -> bool {
+        true
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct IncrByCommand {
+    pub key: Bytes,
+    pub delta: i64,
+}
+
+impl Command for IncrByCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.incrby(&self.key, self.delta))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct IncrByFloatCommand {
+    pub key: Bytes,
+    pub delta: f64,
+}
+
+impl Command for IncrByFloatCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.incrbyfloat(&self.key, self.delta))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
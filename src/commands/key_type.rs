@@ -0,0 +1,21 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+/// TYPE.
+pub struct TypeCommand(pub Bytes);
+
+impl Command for TypeCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.key_type(&self.0)
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.0)
+    }
+}
@@ -0,0 +1,16 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct KeysCommand(pub Bytes);
+
+impl Command for KeysCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.keys(&self.0)
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+}
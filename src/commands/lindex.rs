@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct LIndexCommand {
+    pub key: Bytes,
+    pub index: i64,
+}
+
+impl Command for LIndexCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.lindex(&self.key, self.index))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
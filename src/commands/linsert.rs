@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::list::ListEnd;
+use super::{into_response, Command};
+
+/// LINSERT key BEFORE|AFTER pivot element. BEFORE inserts on the `Left` of the pivot.
+pub struct LInsertCommand {
+    pub key: Bytes,
+    pub position: ListEnd,
+    pub pivot: Bytes,
+    pub value: Bytes,
+}
+
+impl Command for LInsertCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.linsert(&self.key, self.position, &self.pivot, self.value.clone()))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,22 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::list::ListEnd;
+use super::{into_response, Command};
+
+pub struct LMoveCommand {
+    pub source: Bytes,
+    pub destination: Bytes,
+    pub from: ListEnd,
+    pub to: ListEnd,
+}
+
+impl Command for LMoveCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.lmove(&self.source, &self.destination, self.from, self.to))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
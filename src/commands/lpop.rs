@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::list::ListEnd;
+use super::{into_response, Command};
+
+pub struct LPopCommand {
+    pub key: Bytes,
+    pub count: Option<usize>,
+}
+
+impl Command for LPopCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.pop(&self.key, ListEnd::Left, self.count))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
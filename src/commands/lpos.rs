@@ -0,0 +1,25 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::list::LposOptions;
+use super::{into_response, Command};
+
+pub struct LPosCommand {
+    pub key: Bytes,
+    pub value: Bytes,
+    pub options: LposOptions,
+}
+
+impl Command for LPosCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.lpos(&self.key, &self.value, self.options))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::list::ListEnd;
+use super::{into_response, Command};
+
+pub struct LPushCommand {
+    pub key: Bytes,
+    pub values: Vec<Bytes>,
+}
+
+impl Command for LPushCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.push(&self.key, &self.values, ListEnd::Left))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct LRangeCommand {
+    pub key: Bytes,
+    pub start: i64,
+    pub stop: i64,
+}
+
+impl Command for LRangeCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.lrange(&self.key, self.start, self.stop))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct LRemCommand {
+    pub key: Bytes,
+    pub count: i64,
+    pub value: Bytes,
+}
+
+impl Command for LRemCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.lrem(&self.key, self.count, &self.value))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
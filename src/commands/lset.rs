@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct LSetCommand {
+    pub key: Bytes,
+    pub index: i64,
+    pub value: Bytes,
+}
+
+impl Command for LSetCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.lset(&self.key, self.index, self.value.clone()))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct LTrimCommand {
+    pub key: Bytes,
+    pub start: i64,
+    pub stop: i64,
+}
+
+impl Command for LTrimCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.ltrim(&self.key, self.start, self.stop))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct MGetCommand(pub Vec<Bytes>);
+
+impl Command for MGetCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.mget(&self.0)
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        &self.0
+    }
+}
@@ -6,6 +6,124 @@ mod del;
 mod exists;
 mod incr;
 mod decr;
+mod expire;
+mod persist;
+mod ttl;
+mod pttl;
+mod hello;
+mod lpush;
+mod rpush;
+mod lpop;
+mod rpop;
+mod lrange;
+mod llen;
+mod lindex;
+mod lset;
+mod lrem;
+mod ltrim;
+mod linsert;
+mod lpos;
+mod lmove;
+mod blpop;
+mod brpop;
+mod blmove;
+mod hset;
+mod hsetnx;
+mod hget;
+mod hmget;
+mod hdel;
+mod hgetall;
+mod hkeys;
+mod hvals;
+mod hlen;
+mod hexists;
+mod hincrby;
+mod hincrbyfloat;
+mod hscan;
+mod sadd;
+mod srem;
+mod smembers;
+mod sismember;
+mod smismember;
+mod scard;
+mod spop;
+mod srandmember;
+mod sinter;
+mod sunion;
+mod sdiff;
+mod sinterstore;
+mod sunionstore;
+mod sdiffstore;
+mod zadd;
+mod zincrby;
+mod zcard;
+mod zscore;
+mod zrank;
+mod zrange;
+mod zrem;
+mod zpopmin;
+mod zpopmax;
+mod zunionstore;
+mod zinterstore;
+mod xadd;
+mod xlen;
+mod xrange;
+mod xrevrange;
+mod xtrim;
+mod xread;
+mod xgroup;
+mod xreadgroup;
+mod xack;
+mod xpending;
+mod xclaim;
+mod xautoclaim;
+mod xinfo;
+mod append;
+mod strlen;
+mod getrange;
+mod setrange;
+mod getset;
+mod getdel;
+mod getex;
+mod mget;
+mod mset;
+mod msetnx;
+mod setnx;
+mod incrby;
+mod decrby;
+mod incrbyfloat;
+mod setbit;
+mod getbit;
+mod bitcount;
+mod bitpos;
+mod bitop;
+mod bitfield;
+mod pfadd;
+mod pfcount;
+mod pfmerge;
+mod geoadd;
+mod geodist;
+mod geopos;
+mod geohash;
+mod geosearch;
+mod geosearchstore;
+mod select;
+mod move_key;
+mod swapdb;
+mod flushdb;
+mod flushall;
+mod dbsize;
+mod unlink;
+mod touch;
+mod keys;
+mod scan;
+mod key_type;
+mod randomkey;
+mod rename;
+mod renamenx;
+mod copy;
+mod config;
+mod client;
 
 pub use ping::PingCommand;
 pub use echo::EchoCommand;
@@ -15,10 +133,236 @@ pub use del::DelCommand;
 pub use exists::ExistsCommand;
 pub use incr::IncrCommand;
 pub use decr::DecrCommand;
+pub use expire::ExpireCommand;
+pub use persist::PersistCommand;
+pub use ttl::TtlCommand;
+pub use pttl::PttlCommand;
+pub use hello::HelloCommand;
+pub use lpush::LPushCommand;
+pub use rpush::RPushCommand;
+pub use lpop::LPopCommand;
+pub use rpop::RPopCommand;
+pub use lrange::LRangeCommand;
+pub use llen::LLenCommand;
+pub use lindex::LIndexCommand;
+pub use lset::LSetCommand;
+pub use lrem::LRemCommand;
+pub use ltrim::LTrimCommand;
+pub use linsert::LInsertCommand;
+pub use lpos::LPosCommand;
+pub use lmove::LMoveCommand;
+pub use blpop::BLPopCommand;
+pub use brpop::BRPopCommand;
+pub use blmove::BLMoveCommand;
+pub use hset::HSetCommand;
+pub use hsetnx::HSetNxCommand;
+pub use hget::HGetCommand;
+pub use hmget::HMGetCommand;
+pub use hdel::HDelCommand;
+pub use hgetall::HGetAllCommand;
+pub use hkeys::HKeysCommand;
+pub use hvals::HValsCommand;
+pub use hlen::HLenCommand;
+pub use hexists::HExistsCommand;
+pub use hincrby::HIncrByCommand;
+pub use hincrbyfloat::HIncrByFloatCommand;
+pub use hscan::HScanCommand;
+pub use sadd::SAddCommand;
+pub use srem::SRemCommand;
+pub use smembers::SMembersCommand;
+pub use sismember::SIsMemberCommand;
+pub use smismember::SMIsMemberCommand;
+pub use scard::SCardCommand;
+pub use spop::SPopCommand;
+pub use srandmember::SRandMemberCommand;
+pub use sinter::SInterCommand;
+pub use sunion::SUnionCommand;
+pub use sdiff::SDiffCommand;
+pub use sinterstore::SInterStoreCommand;
+pub use sunionstore::SUnionStoreCommand;
+pub use sdiffstore::SDiffStoreCommand;
+pub use zadd::ZAddCommand;
+pub use zincrby::ZIncrByCommand;
+pub use zcard::ZCardCommand;
+pub use zscore::ZScoreCommand;
+pub use zrank::ZRankCommand;
+pub use zrange::ZRangeCommand;
+pub use zrem::ZRemCommand;
+pub use zpopmin::ZPopMinCommand;
+pub use zpopmax::ZPopMaxCommand;
+pub use zunionstore::ZUnionStoreCommand;
+pub use zinterstore::ZInterStoreCommand;
+pub use xadd::XAddCommand;
+pub use xlen::XLenCommand;
+pub use xrange::XRangeCommand;
+pub use xrevrange::XRevRangeCommand;
+pub use xtrim::XTrimCommand;
+pub use xread::XReadCommand;
+pub use xgroup::XGroupCommand;
+pub use xreadgroup::XReadGroupCommand;
+pub use xack::XAckCommand;
+pub use xpending::XPendingCommand;
+pub use xclaim::XClaimCommand;
+pub use xautoclaim::XAutoClaimCommand;
+pub use xinfo::XInfoCommand;
+pub use append::AppendCommand;
+pub use strlen::StrLenCommand;
+pub use getrange::GetRangeCommand;
+pub use setrange::SetRangeCommand;
+pub use getset::GetSetCommand;
+pub use getdel::GetDelCommand;
+pub use getex::GetExCommand;
+pub use mget::MGetCommand;
+pub use mset::MSetCommand;
+pub use msetnx::MSetNxCommand;
+pub use setnx::SetNxCommand;
+pub use incrby::IncrByCommand;
+pub use decrby::DecrByCommand;
+pub use incrbyfloat::IncrByFloatCommand;
+pub use setbit::SetBitCommand;
+pub use getbit::GetBitCommand;
+pub use bitcount::BitCountCommand;
+pub use bitpos::BitPosCommand;
+pub use bitop::BitOpCommand;
+pub use bitfield::BitFieldCommand;
+pub use pfadd::PfAddCommand;
+pub use pfcount::PfCountCommand;
+pub use pfmerge::PfMergeCommand;
+pub use geoadd::GeoAddCommand;
+pub use geodist::GeoDistCommand;
+pub use geopos::GeoPosCommand;
+pub use geohash::GeoHashCommand;
+pub use geosearch::GeoSearchCommand;
+pub use geosearchstore::GeoSearchStoreCommand;
+pub use select::SelectCommand;
+pub use move_key::MoveCommand;
+pub use swapdb::SwapDbCommand;
+pub use flushdb::FlushDbCommand;
+pub use flushall::FlushAllCommand;
+pub use dbsize::DbSizeCommand;
+pub use unlink::UnlinkCommand;
+pub use touch::TouchCommand;
+pub use keys::KeysCommand;
+pub use scan::ScanCommand;
+pub use key_type::TypeCommand;
+pub use randomkey::RandomKeyCommand;
+pub use rename::RenameCommand;
+pub use renamenx::RenameNxCommand;
+pub use copy::CopyCommand;
+pub use config::{ConfigCommand, ConfigSetting};
+pub use client::ClientCommand;
 
+use bytes::Bytes;
+use crate::client::Client;
+use crate::error::BifrostError;
 use crate::resp::RespType;
+use crate::storage::blocking::Blocking;
+use crate::storage::databases::Databases;
 use crate::storage::db::Db;
 
-pub trait Command {
+pub trait Command: Send {
     fn execute(&self, db: &Db) -> RespType;
-} 
\ No newline at end of file
+
+    /// Whether the command may modify the database. Once a script has run one, it can
+    /// no longer be stopped with SCRIPT KILL.
+    fn is_write(&self) -> bool;
+
+    /// The keys a read-only command reads, which connections with CLIENT TRACKING on
+    /// are told about once they are modified. Commands that write read none.
+    fn keys_read(&self) -> &[Bytes] {
+        &[]
+    }
+}
+
+/// A command that reads or changes the state of the connection that sent it, or that
+/// acts on databases other than the selected one.
+pub trait ConnectionCommand: Send {
+    fn execute(&self, client: &mut Client, databases: &Databases) -> RespType;
+
+    /// Whether the command may modify the database, as for [`Command::is_write`].
+    fn is_write(&self) -> bool;
+
+    /// Whether Redis marks the command noscript, which keeps scripts from calling it.
+    fn is_noscript(&self) -> bool {
+        false
+    }
+}
+
+/// A command that may park the client until data it waits for arrives.
+pub trait BlockingCommand: Send {
+    fn execute(&self, db: &Db) -> Blocking;
+
+    /// Whether the command may modify the database, as for [`Command::is_write`].
+    fn is_write(&self) -> bool;
+}
+
+/// Runs `command` for `client`, remembering the keys it reads for CLIENT TRACKING
+/// first. Were they remembered after the read, a write landing in between would find
+/// nobody to invalidate, and the client would keep the stale value in its cache.
+/// `caching` is what CLIENT CACHING set for this command, if anything.
+pub fn execute_tracked(command: &dyn Command, client: &Client, caching: Option<bool>, databases: &Databases) -> RespType {
+    databases.tracker().track(client.id, command.keys_read(), caching);
+    command.execute(&databases[client.db])
+}
+
+// Turns the result of a fallible storage operation into the reply sent to the client.
+fn into_response(result: Result<RespType, BifrostError>) -> RespType {
+    match result {
+        Ok(resp) => resp,
+        Err(BifrostError::StorageError(msg)) => RespType::Error(msg),
+        Err(_) => RespType::Error("ERR internal error".to_string()),
+    }
+}
+
+fn into_blocking(result: Result<Blocking, BifrostError>) -> Blocking {
+    match result {
+        Ok(blocking) => blocking,
+        Err(err) => Blocking::Ready(into_response(Err(err))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pubsub::{Broker, Subscriptions};
+    use futures::FutureExt;
+
+    // A GET during which another connection overwrites the key, right after the read.
+    struct RacingGet(Bytes);
+
+    impl Command for RacingGet {
+        fn execute(&self, db: &Db) -> RespType {
+            let reply = GetCommand(self.0.clone()).execute(db);
+            db.set(self.0.clone(), Bytes::from("new"));
+            reply
+        }
+
+        fn is_write(&self) -> bool {
+            false
+        }
+
+        fn keys_read(&self) -> &[Bytes] {
+            std::slice::from_ref(&self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_right_after_tracked_read() {
+        let broker = Broker::default();
+        let databases = Databases::with_broker(1, broker.clone());
+        let mut subscriptions = Subscriptions::new(1, broker);
+        databases.tracker().enable(1, Default::default()).unwrap();
+        databases[0].set(Bytes::from("key"), Bytes::from("old"));
+
+        let reply = execute_tracked(&RacingGet(Bytes::from("key")), &Client::new(1), None, &databases);
+        assert_eq!(reply, RespType::BulkString(Bytes::from("old")));
+        // The invalidation is already waiting, rather than never coming.
+        assert_eq!(
+            subscriptions.next_message().now_or_never().flatten(),
+            Some(RespType::Push(vec![
+                RespType::BulkString(Bytes::from_static(b"invalidate")),
+                RespType::Array(vec![RespType::BulkString(Bytes::from("key"))]),
+            ]))
+        );
+    }
+}
@@ -0,0 +1,21 @@
+use bytes::Bytes;
+use crate::client::Client;
+use crate::resp::RespType;
+use crate::storage::databases::Databases;
+use super::{into_response, ConnectionCommand};
+
+/// MOVE, from the selected database to another.
+pub struct MoveCommand {
+    pub key: Bytes,
+    pub db: usize,
+}
+
+impl ConnectionCommand for MoveCommand {
+    fn execute(&self, client: &mut Client, databases: &Databases) -> RespType {
+        into_response(databases.move_key(&self.key, client.db, self.db))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,16 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct MSetCommand(pub Vec<(Bytes, Bytes)>);
+
+impl Command for MSetCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.mset(&self.0)
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
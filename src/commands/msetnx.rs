@@ -0,0 +1,16 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct MSetNxCommand(pub Vec<(Bytes, Bytes)>);
+
+impl Command for MSetNxCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.msetnx(&self.0)
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,16 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct PersistCommand(pub Bytes);
+
+impl Command for PersistCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.persist(&self.0)
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct PfAddCommand {
+    pub key: Bytes,
+    pub elements: Vec<Bytes>,
+}
+
+impl Command for PfAddCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.pfadd(&self.key, &self.elements))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,16 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct PfCountCommand(pub Vec<Bytes>);
+
+impl Command for PfCountCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.pfcount(&self.0))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+}
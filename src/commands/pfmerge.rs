@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct PfMergeCommand {
+    pub destination: Bytes,
+    pub sources: Vec<Bytes>,
+}
+
+impl Command for PfMergeCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.pfmerge(&self.destination, &self.sources))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -8,4 +8,8 @@ impl Command for PingCommand {
     fn execute(&self, _db: &Db) -> RespType {
         RespType::SimpleString("PONG".to_string())
     }
+
+    fn is_write(&self) -> bool {
+        false
+    }
 } 
\ No newline at end of file
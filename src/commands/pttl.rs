@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct PttlCommand(pub Bytes);
+
+impl Command for PttlCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.pttl(&self.0)
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.0)
+    }
+}
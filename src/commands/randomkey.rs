@@ -0,0 +1,15 @@
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct RandomKeyCommand;
+
+impl Command for RandomKeyCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.randomkey()
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+}
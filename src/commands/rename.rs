@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct RenameCommand {
+    pub key: Bytes,
+    pub destination: Bytes,
+}
+
+impl Command for RenameCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.rename(&self.key, self.destination.clone(), false))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct RenameNxCommand {
+    pub key: Bytes,
+    pub destination: Bytes,
+}
+
+impl Command for RenameNxCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.rename(&self.key, self.destination.clone(), true))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,20 @@
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::scan::ScanOptions;
+use super::Command;
+
+pub struct ScanCommand {
+    pub cursor: u64,
+    pub options: ScanOptions,
+    pub key_type: Option<String>,
+}
+
+impl Command for ScanCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.scan(self.cursor, &self.options, self.key_type.as_deref())
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,21 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::set::SetOperation;
+use super::{into_response, Command};
+
+pub struct SDiffCommand(pub Vec<Bytes>);
+
+impl Command for SDiffCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.combine_sets(&self.0, SetOperation::Difference))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        &self.0
+    }
+}
@@ -0,0 +1,19 @@
+use crate::client::Client;
+use crate::resp::RespType;
+use crate::storage::databases::Databases;
+use super::{into_response, ConnectionCommand};
+
+pub struct SelectCommand(pub usize);
+
+impl ConnectionCommand for SelectCommand {
+    fn execute(&self, client: &mut Client, databases: &Databases) -> RespType {
+        into_response(databases.check_index(self.0).map(|()| {
+            client.db = self.0;
+            RespType::SimpleString("OK".to_string())
+        }))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+}
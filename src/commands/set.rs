@@ -1,14 +1,20 @@
+use bytes::Bytes;
 use crate::resp::RespType;
-use crate::storage::db::Db;
+use crate::storage::db::{Db, SetOptions};
 use super::Command;
 
 pub struct SetCommand {
-    pub key: String,
-    pub value: RespType,
+    pub key: Bytes,
+    pub value: Bytes,
+    pub options: SetOptions,
 }
 
 impl Command for SetCommand {
     fn execute(&self, db: &Db) -> RespType {
-        db.set(self.key.clone(), self.value.clone())
+        db.set_with_options(self.key.clone(), self.value.clone(), self.options)
     }
-} 
\ No newline at end of file
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct SetBitCommand {
+    pub key: Bytes,
+    pub offset: u64,
+    pub bit: bool,
+}
+
+impl Command for SetBitCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.setbit(&self.key, self.offset, self.bit))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
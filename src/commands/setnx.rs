@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct SetNxCommand {
+    pub key: Bytes,
+    pub value: Bytes,
+}
+
+impl Command for SetNxCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.setnx(self.key.clone(), self.value.clone())
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct SetRangeCommand {
+    pub key: Bytes,
+    pub offset: usize,
+    pub value: Bytes,
+}
+
+impl Command for SetRangeCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.setrange(&self.key, self.offset, &self.value))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
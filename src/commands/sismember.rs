@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct SIsMemberCommand {
+    pub key: Bytes,
+    pub member: Bytes,
+}
+
+impl Command for SIsMemberCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.sismember(&self.key, &self.member))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct SMIsMemberCommand {
+    pub key: Bytes,
+    pub members: Vec<Bytes>,
+}
+
+impl Command for SMIsMemberCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.smismember(&self.key, &self.members))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
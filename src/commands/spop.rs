@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct SPopCommand {
+    pub key: Bytes,
+    pub count: Option<usize>,
+}
+
+impl Command for SPopCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.spop(&self.key, self.count))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
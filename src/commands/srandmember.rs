@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct SRandMemberCommand {
+    pub key: Bytes,
+    pub count: Option<i64>,
+}
+
+impl Command for SRandMemberCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.srandmember(&self.key, self.count))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
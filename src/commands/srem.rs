@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct SRemCommand {
+    pub key: Bytes,
+    pub members: Vec<Bytes>,
+}
+
+impl Command for SRemCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.srem(&self.key, &self.members))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::set::SetOperation;
+use super::{into_response, Command};
+
+pub struct SUnionStoreCommand {
+    pub destination: Bytes,
+    pub keys: Vec<Bytes>,
+}
+
+impl Command for SUnionStoreCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.combine_sets_into(self.destination.clone(), &self.keys, SetOperation::Union))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
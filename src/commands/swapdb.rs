@@ -0,0 +1,16 @@
+use crate::client::Client;
+use crate::resp::RespType;
+use crate::storage::databases::Databases;
+use super::{into_response, ConnectionCommand};
+
+pub struct SwapDbCommand(pub usize, pub usize);
+
+impl ConnectionCommand for SwapDbCommand {
+    fn execute(&self, _client: &mut Client, databases: &Databases) -> RespType {
+        into_response(databases.swap(self.0, self.1))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,16 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct TouchCommand(pub Vec<Bytes>);
+
+impl Command for TouchCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.touch(&self.0)
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+pub struct TtlCommand(pub Bytes);
+
+impl Command for TtlCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.ttl(&self.0)
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.0)
+    }
+}
@@ -0,0 +1,17 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::Command;
+
+/// UNLINK. Values are freed as soon as they are removed, so this is the same as DEL.
+pub struct UnlinkCommand(pub Vec<Bytes>);
+
+impl Command for UnlinkCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        db.del(&self.0)
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,21 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::stream::StreamId;
+use super::{into_response, Command};
+
+pub struct XAckCommand {
+    pub key: Bytes,
+    pub group: Bytes,
+    pub ids: Vec<StreamId>,
+}
+
+impl Command for XAckCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.xack(&self.key, &self.group, &self.ids))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
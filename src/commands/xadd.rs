@@ -0,0 +1,22 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::stream::{Fields, XaddId, XaddOptions};
+use super::{into_response, Command};
+
+pub struct XAddCommand {
+    pub key: Bytes,
+    pub id: XaddId,
+    pub fields: Fields,
+    pub options: XaddOptions,
+}
+
+impl Command for XAddCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.xadd(&self.key, self.id, self.fields.clone(), self.options))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,33 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::stream::StreamId;
+use super::{into_response, Command};
+
+pub struct XAutoClaimCommand {
+    pub key: Bytes,
+    pub group: Bytes,
+    pub consumer: Bytes,
+    pub min_idle: u64,
+    pub start: StreamId,
+    pub count: usize,
+    pub justid: bool,
+}
+
+impl Command for XAutoClaimCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.xautoclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            self.start,
+            self.count,
+            self.justid,
+        ))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
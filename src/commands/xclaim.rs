@@ -0,0 +1,25 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::consumer_group::ClaimOptions;
+use crate::storage::db::Db;
+use crate::storage::stream::StreamId;
+use super::{into_response, Command};
+
+pub struct XClaimCommand {
+    pub key: Bytes,
+    pub group: Bytes,
+    pub consumer: Bytes,
+    pub min_idle: u64,
+    pub ids: Vec<StreamId>,
+    pub options: ClaimOptions,
+}
+
+impl Command for XClaimCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.xclaim(&self.key, &self.group, &self.consumer, self.min_idle, &self.ids, &self.options))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
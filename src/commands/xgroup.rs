@@ -0,0 +1,58 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::stream::StreamPosition;
+use super::{into_response, Command};
+
+/// The XGROUP subcommands.
+pub enum XGroupCommand {
+    Create {
+        key: Bytes,
+        group: Bytes,
+        position: StreamPosition,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    },
+    SetId {
+        key: Bytes,
+        group: Bytes,
+        position: StreamPosition,
+        entries_read: Option<u64>,
+    },
+    Destroy {
+        key: Bytes,
+        group: Bytes,
+    },
+    CreateConsumer {
+        key: Bytes,
+        group: Bytes,
+        consumer: Bytes,
+    },
+    DelConsumer {
+        key: Bytes,
+        group: Bytes,
+        consumer: Bytes,
+    },
+}
+
+impl Command for XGroupCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(match self {
+            XGroupCommand::Create { key, group, position, mkstream, entries_read } => {
+                db.xgroup_create(key, group.clone(), *position, *mkstream, *entries_read)
+            }
+            XGroupCommand::SetId { key, group, position, entries_read } => {
+                db.xgroup_setid(key, group, *position, *entries_read)
+            }
+            XGroupCommand::Destroy { key, group } => db.xgroup_destroy(key, group),
+            XGroupCommand::CreateConsumer { key, group, consumer } => {
+                db.xgroup_create_consumer(key, group, consumer.clone())
+            }
+            XGroupCommand::DelConsumer { key, group, consumer } => db.xgroup_delete_consumer(key, group, consumer),
+        })
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,26 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+/// The XINFO subcommands.
+pub enum XInfoCommand {
+    /// `full` holds the COUNT of XINFO STREAM key FULL, where 0 means everything.
+    Stream { key: Bytes, full: Option<usize> },
+    Groups(Bytes),
+    Consumers { key: Bytes, group: Bytes },
+}
+
+impl Command for XInfoCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(match self {
+            XInfoCommand::Stream { key, full } => db.xinfo_stream(key, *full),
+            XInfoCommand::Groups(key) => db.xinfo_groups(key),
+            XInfoCommand::Consumers { key, group } => db.xinfo_consumers(key, group),
+        })
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+}
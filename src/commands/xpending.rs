@@ -0,0 +1,22 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::consumer_group::PendingRange;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct XPendingCommand {
+    pub key: Bytes,
+    pub group: Bytes,
+    /// The extended form; without it the reply summarises the pending entries.
+    pub range: Option<PendingRange>,
+}
+
+impl Command for XPendingCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.xpending(&self.key, &self.group, self.range.as_ref()))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,26 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::stream::StreamId;
+use super::{into_response, Command};
+
+pub struct XRangeCommand {
+    pub key: Bytes,
+    pub start: StreamId,
+    pub end: StreamId,
+    pub count: Option<usize>,
+}
+
+impl Command for XRangeCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.xrange(&self.key, self.start, self.end, self.count, false))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
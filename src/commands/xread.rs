@@ -0,0 +1,23 @@
+use bytes::Bytes;
+use std::time::Duration;
+use crate::storage::blocking::Blocking;
+use crate::storage::db::Db;
+use crate::storage::stream::StreamPosition;
+use super::{into_blocking, BlockingCommand};
+
+pub struct XReadCommand {
+    pub streams: Vec<(Bytes, StreamPosition)>,
+    pub count: Option<usize>,
+    /// Set by the BLOCK option; the inner `None` blocks forever.
+    pub block: Option<Option<Duration>>,
+}
+
+impl BlockingCommand for XReadCommand {
+    fn execute(&self, db: &Db) -> Blocking {
+        into_blocking(db.xread(&self.streams, self.count, self.block))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+}
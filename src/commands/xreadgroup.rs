@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use std::time::Duration;
+use crate::storage::blocking::Blocking;
+use crate::storage::consumer_group::{GroupPosition, GroupRead};
+use crate::storage::db::Db;
+use super::{into_blocking, BlockingCommand};
+
+pub struct XReadGroupCommand {
+    pub read: GroupRead,
+    pub streams: Vec<(Bytes, GroupPosition)>,
+    pub count: Option<usize>,
+    /// Set by the BLOCK option; the inner `None` blocks forever.
+    pub block: Option<Option<Duration>>,
+}
+
+impl BlockingCommand for XReadGroupCommand {
+    fn execute(&self, db: &Db) -> Blocking {
+        into_blocking(db.xreadgroup(&self.read, &self.streams, self.count, self.block))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,20 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::stream::TrimOptions;
+use super::{into_response, Command};
+
+pub struct XTrimCommand {
+    pub key: Bytes,
+    pub options: TrimOptions,
+}
+
+impl Command for XTrimCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.xtrim(&self.key, self.options))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
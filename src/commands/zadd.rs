@@ -0,0 +1,21 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::sorted_set::ZaddOptions;
+use super::{into_response, Command};
+
+pub struct ZAddCommand {
+    pub key: Bytes,
+    pub pairs: Vec<(f64, Bytes)>,
+    pub options: ZaddOptions,
+}
+
+impl Command for ZAddCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.zadd(&self.key, &self.pairs, self.options))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,22 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::sorted_set::ZaddOptions;
+use super::{into_response, Command};
+
+pub struct ZIncrByCommand {
+    pub key: Bytes,
+    pub delta: f64,
+    pub member: Bytes,
+}
+
+impl Command for ZIncrByCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        let options = ZaddOptions { incr: true, ..Default::default() };
+        into_response(db.zadd(&self.key, &[(self.delta, self.member.clone())], options))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
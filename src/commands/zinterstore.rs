@@ -0,0 +1,29 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::set::SetOperation;
+use crate::storage::sorted_set::Aggregate;
+use super::{into_response, Command};
+
+pub struct ZInterStoreCommand {
+    pub destination: Bytes,
+    pub keys: Vec<Bytes>,
+    pub weights: Vec<f64>,
+    pub aggregate: Aggregate,
+}
+
+impl Command for ZInterStoreCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.zcombine_into(
+            self.destination.clone(),
+            &self.keys,
+            &self.weights,
+            self.aggregate,
+            SetOperation::Intersection,
+        ))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
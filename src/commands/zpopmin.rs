@@ -0,0 +1,19 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct ZPopMinCommand {
+    pub key: Bytes,
+    pub count: Option<usize>,
+}
+
+impl Command for ZPopMinCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.zpop(&self.key, self.count, false))
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use crate::storage::sorted_set::RangeQuery;
+use super::{into_response, Command};
+
+pub struct ZRangeCommand {
+    pub key: Bytes,
+    pub query: RangeQuery,
+}
+
+impl Command for ZRangeCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.zrange(&self.key, &self.query))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use crate::resp::RespType;
+use crate::storage::db::Db;
+use super::{into_response, Command};
+
+pub struct ZRankCommand {
+    pub key: Bytes,
+    pub member: Bytes,
+    pub with_score: bool,
+}
+
+impl Command for ZRankCommand {
+    fn execute(&self, db: &Db) -> RespType {
+        into_response(db.zrank(&self.key, &self.member, self.with_score))
+    }
+
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    fn keys_read(&self) -> &[Bytes] {
+        std::slice::from_ref(&self.key)
+    }
+}
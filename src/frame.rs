@@ -1,31 +1,152 @@
-use bytes::Buf;
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use std::io;
 use tokio_util::codec::{Decoder, Encoder};
 
+use crate::resp::{self, ProtocolVersion};
 use crate::resp::Resp;
 use crate::resp::RespError;
 use crate::resp::RespType;
 
-pub struct RespCodec;
+/// Bounds on what a client may send, mirroring Redis' `proto-max-bulk-len` and
+/// `client-query-buffer-limit` settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolLimits {
+    /// Largest accepted bulk string payload in bytes.
+    pub max_bulk_len: usize,
+    /// Largest accepted number of elements in an aggregate.
+    pub max_multibulk_len: usize,
+    /// How many aggregates may be nested inside each other.
+    pub max_depth: usize,
+    /// How many bytes of an incomplete frame may be buffered for a client.
+    pub max_query_buffer: usize,
+}
+
+impl Default for ProtocolLimits {
+    fn default() -> Self {
+        ProtocolLimits {
+            max_bulk_len: 512 * 1024 * 1024,
+            max_multibulk_len: 1024 * 1024,
+            max_depth: 32,
+            max_query_buffer: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+// Longest header line, or inline command, accepted before its line ending arrives.
+const MAX_HEADER_LEN: usize = 64 * 1024;
+
+/// Decodes RESP frames incrementally. The first pass only scans for the end of the
+/// frame at the front of the buffer, remembering how far it got so that a frame
+/// arriving in many reads is scanned once in total. Once the frame is complete it is
+/// split off the buffer without copying and parsed into values that slice into it.
+#[derive(Debug, Default)]
+pub struct RespCodec {
+    protocol: ProtocolVersion,
+    limits: ProtocolLimits,
+    // Offset of the first element of the current frame that has not been scanned yet.
+    scanned: usize,
+    // Elements still expected by each aggregate the scan is currently inside.
+    pending: Vec<usize>,
+}
+
+enum Scan {
+    Incomplete,
+    // A complete element ending at the given offset.
+    Element(usize),
+    // An aggregate header ending at the given offset and how many elements follow it.
+    Aggregate(usize, usize),
+}
+
+impl RespCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limits(limits: ProtocolLimits) -> Self {
+        RespCodec {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the protocol version replies are encoded with.
+    pub fn set_protocol(&mut self, protocol: ProtocolVersion) {
+        self.protocol = protocol;
+    }
+
+    // Marks one element as complete, closing every aggregate it was the last element of.
+    fn finish_element(&mut self) {
+        while let Some(remaining) = self.pending.last_mut() {
+            *remaining -= 1;
+            if *remaining > 0 {
+                break;
+            }
+            self.pending.pop();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.scanned = 0;
+        self.pending.clear();
+    }
+}
 
 impl Decoder for RespCodec {
     type Item = RespType;
     type Error = io::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        if src.is_empty() {
-            return Ok(None);
-        }
+        loop {
+            // Anything that does not start like a RESP value is an inline command, as
+            // typed into telnet or nc.
+            let at_frame_start = self.scanned == 0 && self.pending.is_empty();
+            if at_frame_start && src.first().is_some_and(|b| !is_type_byte(*b)) {
+                match decode_inline(src).map_err(invalid_data)? {
+                    Some(args) if args.is_empty() => continue,
+                    Some(args) => {
+                        let args = args.into_iter().map(RespType::BulkString).collect();
+                        return Ok(Some(RespType::Array(args)));
+                    }
+                    None => return Ok(None),
+                }
+            }
 
-        let mut resp = Resp::new(src.clone());
-        match resp.parse() {
-            Ok((resp_type, consumed)) => {
-                src.advance(consumed);
-                Ok(Some(resp_type))
+            match scan_element(src, self.scanned, &self.limits).map_err(invalid_data)? {
+                Scan::Incomplete if src.len() > self.limits.max_query_buffer => {
+                    return Err(invalid_data(RespError::QueryBufferExceeded));
+                }
+                Scan::Incomplete => return Ok(None),
+                Scan::Element(end) => {
+                    self.scanned = end;
+                    self.finish_element();
+                }
+                Scan::Aggregate(end, 0) => {
+                    self.scanned = end;
+                    self.finish_element();
+                }
+                Scan::Aggregate(_, _) if self.pending.len() >= self.limits.max_depth => {
+                    return Err(invalid_data(RespError::Protocol(
+                        "too many nested aggregates".to_string(),
+                    )));
+                }
+                Scan::Aggregate(end, count) => {
+                    self.scanned = end;
+                    self.pending.push(count);
+                }
+            }
+
+            if self.pending.is_empty() {
+                let frame = src.split_to(self.scanned).freeze();
+                self.reset();
+                return match Resp::new(frame).parse() {
+                    Ok((resp_type, _)) => Ok(Some(resp_type)),
+                    // The scan already saw the whole frame, so running out of input is a bug.
+                    Err(RespError::Incomplete) => Err(invalid_data(RespError::Other(
+                        "Frame ended unexpectedly".to_string(),
+                    ))),
+                    Err(e) => Err(invalid_data(e)),
+                };
             }
-            Err(RespError::Incomplete) => Ok(None),
-            Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
         }
     }
 }
@@ -34,7 +155,325 @@ impl Encoder<RespType> for RespCodec {
     type Error = io::Error;
 
     fn encode(&mut self, item: RespType, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        dst.extend_from_slice(&item.to_bytes());
+        dst.extend_from_slice(&item.encode(self.protocol));
         Ok(())
     }
 }
+
+fn invalid_data(e: RespError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn is_type_byte(byte: u8) -> bool {
+    matches!(
+        byte,
+        resp::BULK
+            | resp::VERBATIM
+            | resp::ARRAY
+            | resp::SET
+            | resp::PUSH
+            | resp::MAP
+            | resp::ATTRIBUTE
+            | resp::STRING
+            | resp::ERROR
+            | resp::INTEGER
+            | resp::NULL
+            | resp::BOOLEAN
+            | resp::DOUBLE
+            | resp::BIG_NUMBER
+    )
+}
+
+// Splits off the first line of `src` and returns its arguments, or `None` until the
+// line is complete. Lines may end with either LF or CRLF.
+fn decode_inline(src: &mut BytesMut) -> Result<Option<Vec<Bytes>>, RespError> {
+    let Some(newline) = src.iter().position(|b| *b == b'\n') else {
+        if src.len() > MAX_HEADER_LEN {
+            return Err(RespError::Protocol("too big inline request".to_string()));
+        }
+        return Ok(None);
+    };
+    let line = src.split_to(newline + 1);
+    let line = line.strip_suffix(b"\n").unwrap_or(&line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    split_inline_args(line)
+        .map(Some)
+        .ok_or_else(|| RespError::Protocol("unbalanced quotes in request".to_string()))
+}
+
+/// Splits an inline command into arguments using the quoting rules of `redis-cli`:
+/// double quotes support `\n`, `\r`, `\t`, `\b`, `\a`, `\\`, `\"` and `\xHH` escapes,
+/// single quotes only `\'`, and a closing quote must be followed by a space or the end
+/// of the line. Returns `None` for unbalanced quotes.
+pub fn split_inline_args(line: &[u8]) -> Option<Vec<Bytes>> {
+    let mut args = Vec::new();
+    let mut i = 0;
+    loop {
+        while line.get(i).is_some_and(|b| b.is_ascii_whitespace()) {
+            i += 1;
+        }
+        if i >= line.len() {
+            return Some(args);
+        }
+
+        let mut arg = Vec::new();
+        let mut in_double_quotes = false;
+        let mut in_single_quotes = false;
+        loop {
+            let Some(&byte) = line.get(i) else {
+                if in_double_quotes || in_single_quotes {
+                    return None;
+                }
+                break;
+            };
+            let closes_quote = |i: usize| line.get(i + 1).is_none_or(|b| b.is_ascii_whitespace());
+
+            if in_double_quotes {
+                match byte {
+                    b'\\' if line.get(i + 1) == Some(&b'x')
+                        && line.get(i + 2).is_some_and(u8::is_ascii_hexdigit)
+                        && line.get(i + 3).is_some_and(u8::is_ascii_hexdigit) =>
+                    {
+                        let hex = std::str::from_utf8(&line[i + 2..i + 4]).ok()?;
+                        arg.push(u8::from_str_radix(hex, 16).ok()?);
+                        i += 3;
+                    }
+                    b'\\' if i + 1 < line.len() => {
+                        i += 1;
+                        arg.push(match line[i] {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            other => other,
+                        });
+                    }
+                    b'"' => {
+                        if !closes_quote(i) {
+                            return None;
+                        }
+                        in_double_quotes = false;
+                    }
+                    _ => arg.push(byte),
+                }
+            } else if in_single_quotes {
+                match byte {
+                    b'\\' if line.get(i + 1) == Some(&b'\'') => {
+                        i += 1;
+                        arg.push(b'\'');
+                    }
+                    b'\'' => {
+                        if !closes_quote(i) {
+                            return None;
+                        }
+                        in_single_quotes = false;
+                    }
+                    _ => arg.push(byte),
+                }
+            } else {
+                match byte {
+                    b' ' | b'\n' | b'\r' | b'\t' | b'\0' => break,
+                    b'"' => in_double_quotes = true,
+                    b'\'' => in_single_quotes = true,
+                    _ => arg.push(byte),
+                }
+            }
+            i += 1;
+        }
+        args.push(Bytes::from(arg));
+    }
+}
+
+// Finds where the element starting at `start` ends without building any values.
+fn scan_element(buf: &[u8], start: usize, limits: &ProtocolLimits) -> Result<Scan, RespError> {
+    let Some(&type_byte) = buf.get(start) else {
+        return Ok(Scan::Incomplete);
+    };
+    let Some(line_end) = resp::find_crlf(buf, start + 1) else {
+        if buf.len() - start > MAX_HEADER_LEN {
+            return Err(RespError::Protocol("too big header line".to_string()));
+        }
+        return Ok(Scan::Incomplete);
+    };
+    let header_end = line_end + 2;
+
+    match type_byte {
+        resp::BULK | resp::VERBATIM => {
+            let invalid = || RespError::Protocol("invalid bulk length".to_string());
+            let length = resp::parse_i64(&buf[start + 1..line_end]).map_err(|_| invalid())?;
+            if length == -1 {
+                return Ok(Scan::Element(header_end));
+            }
+            let length = usize::try_from(length)
+                .ok()
+                .filter(|length| *length <= limits.max_bulk_len)
+                .ok_or_else(invalid)?;
+            let end = header_end + length + 2;
+            if buf.len() < end {
+                Ok(Scan::Incomplete)
+            } else {
+                Ok(Scan::Element(end))
+            }
+        }
+        resp::ARRAY | resp::SET | resp::PUSH | resp::MAP | resp::ATTRIBUTE => {
+            let invalid = || RespError::Protocol("invalid multibulk length".to_string());
+            let count = resp::parse_i64(&buf[start + 1..line_end]).map_err(|_| invalid())?;
+            if count == -1 && type_byte == resp::ARRAY {
+                return Ok(Scan::Element(header_end));
+            }
+            let count = usize::try_from(count)
+                .ok()
+                .filter(|count| *count <= limits.max_multibulk_len)
+                .ok_or_else(invalid)?;
+            let count = match type_byte {
+                resp::MAP => count * 2,
+                // The attribute pairs are followed by the value they describe.
+                resp::ATTRIBUTE => count * 2 + 1,
+                _ => count,
+            };
+            Ok(Scan::Aggregate(header_end, count))
+        }
+        resp::STRING
+        | resp::ERROR
+        | resp::INTEGER
+        | resp::NULL
+        | resp::BOOLEAN
+        | resp::DOUBLE
+        | resp::BIG_NUMBER => Ok(Scan::Element(header_end)),
+        _ => Err(RespError::Other(String::from("Invalid RESP data type!"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn bulk(value: &str) -> RespType {
+        RespType::BulkString(Bytes::copy_from_slice(value.as_bytes()))
+    }
+
+    #[test]
+    fn test_decode_pipeline() {
+        let mut codec = RespCodec::new();
+        let mut buf = BytesMut::from("*1\r\n$4\r\nPING\r\n*2\r\n$3\r\nGET\r\n$1\r\nk\r\n:5\r\n");
+
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(RespType::Array(vec![bulk("PING")])));
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(RespType::Array(vec![bulk("GET"), bulk("k")]))
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(RespType::Integer(5)));
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_decode_resumes_partial_frames() {
+        let input = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n%1\r\n+a\r\n*0\r\n";
+        let mut codec = RespCodec::new();
+        let mut buf = BytesMut::new();
+
+        // Feed one byte at a time; nothing is produced until the last byte arrives.
+        for (i, byte) in input.iter().enumerate() {
+            buf.extend_from_slice(&[*byte]);
+            let decoded = codec.decode(&mut buf).unwrap();
+            if i + 1 < input.len() {
+                assert_eq!(decoded, None, "decoded early at byte {}", i);
+            } else {
+                assert_eq!(
+                    decoded,
+                    Some(RespType::Array(vec![
+                        bulk("SET"),
+                        bulk("key"),
+                        RespType::Map(vec![(
+                            RespType::SimpleString("a".to_string()),
+                            RespType::Array(vec![])
+                        )]),
+                    ]))
+                );
+            }
+        }
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let mut codec = RespCodec::new();
+        let mut buf = BytesMut::from("$-5\r\n");
+        assert!(codec.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_decode_inline() {
+        let mut codec = RespCodec::new();
+        let mut buf = BytesMut::from("\r\nSET key \"hello world\"\r\nPING\n*1\r\n$4\r\nPING\r\nGET");
+
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(RespType::Array(vec![bulk("SET"), bulk("key"), bulk("hello world")]))
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(RespType::Array(vec![bulk("PING")])));
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(RespType::Array(vec![bulk("PING")])));
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        assert_eq!(&buf[..], b"GET");
+
+        let mut buf = BytesMut::from("GET \"key\r\n");
+        assert!(codec.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_split_inline_args() {
+        let args = split_inline_args(br#"set  "a\x41\n\"b"  'it\'s' x"#).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                Bytes::from("set"),
+                Bytes::from("aA\n\"b"),
+                Bytes::from("it's"),
+                Bytes::from("x"),
+            ]
+        );
+        assert_eq!(split_inline_args(b"  ").unwrap(), Vec::<Bytes>::new());
+        assert_eq!(split_inline_args(br#"'a'b"#), None);
+        assert_eq!(split_inline_args(br#""abc"#), None);
+    }
+
+    fn decode_error(limits: ProtocolLimits, input: &str) -> RespError {
+        let mut codec = RespCodec::with_limits(limits);
+        let mut buf = BytesMut::from(input);
+        let err = codec.decode(&mut buf).unwrap_err();
+        *err.into_inner().unwrap().downcast::<RespError>().unwrap()
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let limits = ProtocolLimits {
+            max_bulk_len: 4,
+            max_multibulk_len: 2,
+            max_depth: 2,
+            max_query_buffer: 16,
+        };
+
+        let err = decode_error(limits, "$5\r\n");
+        assert!(matches!(err, RespError::Protocol(msg) if msg == "invalid bulk length"));
+
+        let err = decode_error(limits, "*3\r\n");
+        assert!(matches!(err, RespError::Protocol(msg) if msg == "invalid multibulk length"));
+
+        let err = decode_error(limits, "*-2\r\n");
+        assert!(matches!(err, RespError::Protocol(msg) if msg == "invalid multibulk length"));
+
+        let err = decode_error(limits, "*1\r\n*1\r\n*1\r\n:1\r\n");
+        assert!(matches!(err, RespError::Protocol(msg) if msg == "too many nested aggregates"));
+
+        let err = decode_error(limits, "*2\r\n$4\r\nabcd\r\n$4\r\nab");
+        assert!(matches!(err, RespError::QueryBufferExceeded));
+
+        // Complete frames within the limits still decode.
+        let mut codec = RespCodec::with_limits(limits);
+        let mut buf = BytesMut::from("*1\r\n*1\r\n$4\r\nabcd\r\n");
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+    }
+}
@@ -1,7 +1,12 @@
+pub mod client;
 pub mod commands;
 pub mod error;
 pub mod frame;
 pub mod parser;
+pub mod pubsub;
 pub mod resp;
+pub mod scripting;
 pub mod server;
-pub mod storage; 
\ No newline at end of file
+pub mod slot;
+pub mod storage;
+pub mod transaction;
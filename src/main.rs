@@ -1,12 +1,4 @@
-mod commands;
-mod error;
-mod frame;
-mod parser;
-mod resp;
-mod server;
-mod storage;
-
-use server::Server;
+use bifrost::server::Server;
 use tokio::net::TcpListener;
 
 #[tokio::main]
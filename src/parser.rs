@@ -1,15 +1,236 @@
+use std::time::{Duration, Instant, SystemTime};
+
 use crate::resp::RespType;
 use crate::error::BifrostError;
 use crate::commands::{
     Command, PingCommand, EchoCommand, GetCommand, 
-    SetCommand, DelCommand, ExistsCommand, IncrCommand, DecrCommand
+    SetCommand, DelCommand, ExistsCommand, IncrCommand, DecrCommand,
+    ExpireCommand, PersistCommand, TtlCommand, PttlCommand,
+    ConnectionCommand, HelloCommand, LPushCommand, RPushCommand, LPopCommand,
+    RPopCommand, LRangeCommand, LLenCommand, LIndexCommand, LSetCommand, LRemCommand,
+    LTrimCommand, LInsertCommand, LPosCommand, LMoveCommand, BlockingCommand,
+    BLPopCommand, BRPopCommand, BLMoveCommand, HSetCommand, HSetNxCommand, HGetCommand,
+    HMGetCommand, HDelCommand, HGetAllCommand, HKeysCommand, HValsCommand, HLenCommand,
+    HExistsCommand, HIncrByCommand, HIncrByFloatCommand, HScanCommand, SAddCommand,
+    SRemCommand, SMembersCommand, SIsMemberCommand, SMIsMemberCommand, SCardCommand,
+    SPopCommand, SRandMemberCommand, SInterCommand, SUnionCommand, SDiffCommand,
+    SInterStoreCommand, SUnionStoreCommand, SDiffStoreCommand, ZAddCommand, ZIncrByCommand,
+    ZCardCommand, ZScoreCommand, ZRankCommand, ZRangeCommand, ZRemCommand, ZPopMinCommand,
+    ZPopMaxCommand, ZUnionStoreCommand, ZInterStoreCommand, XAddCommand, XLenCommand,
+    XRangeCommand, XRevRangeCommand, XTrimCommand, XReadCommand, XGroupCommand,
+    XReadGroupCommand, XAckCommand, XPendingCommand, XClaimCommand, XAutoClaimCommand,
+    XInfoCommand, AppendCommand, StrLenCommand, GetRangeCommand, SetRangeCommand, GetSetCommand,
+    GetDelCommand, GetExCommand, MGetCommand, MSetCommand, MSetNxCommand, SetNxCommand,
+    IncrByCommand, DecrByCommand, IncrByFloatCommand, SetBitCommand, GetBitCommand,
+    BitCountCommand, BitPosCommand, BitOpCommand, BitFieldCommand, PfAddCommand, PfCountCommand,
+    PfMergeCommand, GeoAddCommand, GeoDistCommand, GeoPosCommand, GeoHashCommand, GeoSearchCommand,
+    GeoSearchStoreCommand, SelectCommand, MoveCommand, SwapDbCommand, FlushDbCommand, FlushAllCommand,
+    DbSizeCommand, UnlinkCommand, TouchCommand, KeysCommand, ScanCommand, TypeCommand,
+    RandomKeyCommand, RenameCommand, RenameNxCommand, CopyCommand, ConfigCommand, ConfigSetting,
+    ClientCommand
+};
+use crate::storage::bitmap::{BitFieldOp, BitFieldType, BitOperation, BitUnit, Overflow};
+use crate::storage::consumer_group::{ClaimOptions, GroupPosition, GroupRead, PendingRange};
+use crate::storage::geo::{check_coordinates, DistanceUnit, GeoOrder, GeoOrigin, GeoSearch, GeoShape};
+use crate::storage::list::{ListEnd, LposOptions};
+use crate::storage::notify::EventClasses;
+use crate::storage::scan::ScanOptions;
+use crate::storage::string::GetExpiry;
+use crate::storage::tracking::TrackingOptions;
+use crate::storage::stream::{
+    StreamId, StreamPosition, TrimOptions, TrimStrategy, XaddId, XaddOptions,
+};
+use crate::storage::sorted_set::{
+    Aggregate, LexBound, RangeBy, RangeQuery, ScoreBound, ScoreComparison, ZaddOptions,
 };
+use bytes::Bytes;
+use crate::resp::ProtocolVersion;
+use crate::pubsub::PubSubCommand;
+use crate::scripting::ScriptCommand;
+use crate::slot::key_slot;
+use crate::transaction::TransactionCommand;
+use crate::storage::db::{Expiration, ExpireCondition, SetCondition, SetOptions};
+
+/// Parses commands that act on the calling connection rather than on the keyspace.
+/// Returns `None` when the request is not a connection command.
+pub fn parse_connection_command(
+    request: &RespType,
+) -> Option<Result<Box<dyn ConnectionCommand>, BifrostError>> {
+    let RespType::Array(array) = request else {
+        return None;
+    };
+    let Some(RespType::BulkString(command)) = array.first() else {
+        return None;
+    };
+    match String::from_utf8_lossy(command).to_uppercase().as_str() {
+        "HELLO" => Some(parse_hello(array)),
+        "SELECT" => Some(arguments(array, "select", 2).and_then(|args| {
+            Ok(Box::new(SelectCommand(parse_db_index(&args[0])?)) as _)
+        })),
+        "MOVE" => Some(arguments(array, "move", 3).and_then(|args| {
+            Ok(Box::new(MoveCommand { key: args[0].clone(), db: parse_db_index(&args[1])? }) as _)
+        })),
+        "SWAPDB" => Some(arguments(array, "swapdb", 3).and_then(|args| {
+            let invalid = |which: &str| BifrostError::CommandError(format!("ERR invalid {} DB index", which));
+            let first = parse_integer(&args[0]).map_err(|_| invalid("first"))?;
+            let second = parse_integer(&args[1]).map_err(|_| invalid("second"))?;
+            let index = |index: i64| {
+                usize::try_from(index)
+                    .map_err(|_| BifrostError::CommandError("ERR DB index is out of range".to_string()))
+            };
+            Ok(Box::new(SwapDbCommand(index(first)?, index(second)?)) as _)
+        })),
+        "FLUSHALL" => Some(arguments(array, "flushall", -1).and_then(|args| {
+            parse_flush_mode(&args)?;
+            Ok(Box::new(FlushAllCommand) as _)
+        })),
+        "CONFIG" => Some(arguments(array, "config", -2).and_then(|args| {
+            Ok(Box::new(parse_config(&args)?) as _)
+        })),
+        "CLIENT" => Some(arguments(array, "client", -2).and_then(|args| {
+            Ok(Box::new(parse_client(&args)?) as _)
+        })),
+        "COPY" => Some(arguments(array, "copy", -3).and_then(|args| {
+            let mut command = CopyCommand {
+                source: args[0].clone(),
+                destination: args[1].clone(),
+                db: None,
+                replace: false,
+            };
+            let mut options = args[2..].iter();
+            while let Some(option) = options.next() {
+                match String::from_utf8_lossy(option).to_uppercase().as_str() {
+                    "DB" => command.db = Some(parse_db_index(options.next().ok_or_else(syntax_error)?)?),
+                    "REPLACE" => command.replace = true,
+                    _ => return Err(syntax_error()),
+                }
+            }
+            Ok(Box::new(command) as _)
+        })),
+        _ => None,
+    }
+}
+
+/// Parses the commands that make up transactions. Returns `None` when the request is
+/// not one of them.
+pub fn parse_transaction_command(
+    request: &RespType,
+) -> Option<Result<TransactionCommand, BifrostError>> {
+    let RespType::Array(array) = request else {
+        return None;
+    };
+    let Some(RespType::BulkString(command)) = array.first() else {
+        return None;
+    };
+    match String::from_utf8_lossy(command).to_uppercase().as_str() {
+        "MULTI" => Some(arguments(array, "multi", 1).map(|_| TransactionCommand::Multi)),
+        "EXEC" => Some(arguments(array, "exec", 1).map(|_| TransactionCommand::Exec)),
+        "DISCARD" => Some(arguments(array, "discard", 1).map(|_| TransactionCommand::Discard)),
+        "WATCH" => Some(arguments(array, "watch", -2).map(TransactionCommand::Watch)),
+        "UNWATCH" => Some(arguments(array, "unwatch", 1).map(|_| TransactionCommand::Unwatch)),
+        _ => None,
+    }
+}
+
+/// Parses the commands that run and manage Lua scripts. Returns `None` when the request
+/// is not one of them.
+pub fn parse_script_command(request: &RespType) -> Option<Result<ScriptCommand, BifrostError>> {
+    let RespType::Array(array) = request else {
+        return None;
+    };
+    let Some(RespType::BulkString(command)) = array.first() else {
+        return None;
+    };
+    match String::from_utf8_lossy(command).to_uppercase().as_str() {
+        "EVAL" => Some(arguments(array, "eval", -3).and_then(|args| {
+            let script = args[0].clone();
+            let (keys, args) = parse_script_arguments(&args[1..])?;
+            Ok(ScriptCommand::Eval { script, keys, args })
+        })),
+        "EVALSHA" => Some(arguments(array, "evalsha", -3).and_then(|args| {
+            let sha = String::from_utf8_lossy(&args[0]).to_lowercase();
+            let (keys, args) = parse_script_arguments(&args[1..])?;
+            Ok(ScriptCommand::EvalSha { sha, keys, args })
+        })),
+        "SCRIPT" => Some(arguments(array, "script", -2).and_then(|args| parse_script(&args))),
+        _ => None,
+    }
+}
+
+
+pub fn parse_pubsub_command(request: &RespType) -> Option<Result<PubSubCommand, BifrostError>> {
+    let RespType::Array(array) = request else {
+        return None;
+    };
+    let Some(RespType::BulkString(command)) = array.first() else {
+        return None;
+    };
+    match String::from_utf8_lossy(command).to_uppercase().as_str() {
+        "SUBSCRIBE" => Some(arguments(array, "subscribe", -2).map(PubSubCommand::Subscribe)),
+        "UNSUBSCRIBE" => Some(arguments(array, "unsubscribe", -1).map(PubSubCommand::Unsubscribe)),
+        "PSUBSCRIBE" => Some(arguments(array, "psubscribe", -2).map(PubSubCommand::PSubscribe)),
+        "PUNSUBSCRIBE" => Some(arguments(array, "punsubscribe", -1).map(PubSubCommand::PUnsubscribe)),
+        "PUBLISH" => Some(arguments(array, "publish", 3).map(|args| PubSubCommand::Publish {
+            channel: args[0].clone(),
+            message: args[1].clone(),
+        })),
+        "SSUBSCRIBE" => Some(
+            arguments(array, "ssubscribe", -2).and_then(same_slot).map(PubSubCommand::SSubscribe),
+        ),
+        "SUNSUBSCRIBE" => Some(
+            arguments(array, "sunsubscribe", -1).and_then(same_slot).map(PubSubCommand::SUnsubscribe),
+        ),
+        "SPUBLISH" => Some(arguments(array, "spublish", 3).map(|args| PubSubCommand::SPublish {
+            channel: args[0].clone(),
+            message: args[1].clone(),
+        })),
+        "PUBSUB" => Some(arguments(array, "pubsub", -2).and_then(|args| parse_pubsub(&args))),
+        _ => None,
+    }
+}
+
+/// Parses commands that may block the connection until data arrives. Returns `None`
+/// when the request is not a blocking command.
+pub fn parse_blocking_command(
+    request: &RespType,
+) -> Option<Result<Box<dyn BlockingCommand>, BifrostError>> {
+    let RespType::Array(array) = request else {
+        return None;
+    };
+    let Some(RespType::BulkString(command)) = array.first() else {
+        return None;
+    };
+    let command: Result<Box<dyn BlockingCommand>, BifrostError> =
+        match String::from_utf8_lossy(command).to_uppercase().as_str() {
+            "BLPOP" => arguments(array, "blpop", -3).and_then(|args| {
+                let (timeout, keys) = args.split_last().ok_or_else(syntax_error)?;
+                Ok(Box::new(BLPopCommand { keys: keys.to_vec(), timeout: parse_timeout(timeout)? }) as _)
+            }),
+            "BRPOP" => arguments(array, "brpop", -3).and_then(|args| {
+                let (timeout, keys) = args.split_last().ok_or_else(syntax_error)?;
+                Ok(Box::new(BRPopCommand { keys: keys.to_vec(), timeout: parse_timeout(timeout)? }) as _)
+            }),
+            "BLMOVE" => arguments(array, "blmove", 6).and_then(|args| {
+                Ok(Box::new(BLMoveCommand {
+                    source: args[0].clone(),
+                    destination: args[1].clone(),
+                    from: parse_list_end(&args[2])?,
+                    to: parse_list_end(&args[3])?,
+                    timeout: parse_timeout(&args[4])?,
+                }) as _)
+            }),
+            "XREAD" => arguments(array, "xread", -4).and_then(|args| parse_xread(&args)),
+            "XREADGROUP" => arguments(array, "xreadgroup", -7).and_then(|args| parse_xreadgroup(&args)),
+            _ => return None,
+        };
+    Some(command)
+}
 
 pub fn parse_command(request: RespType) -> Result<Box<dyn Command>, BifrostError> {
     match request {
         RespType::Array(array) => {
             if let Some(RespType::BulkString(command)) = array.first() {
-                match command.to_uppercase().as_str() {
+                match String::from_utf8_lossy(command).to_uppercase().as_str() {
                     "PING" => Ok(Box::new(PingCommand)),
                     "ECHO" => {
                         let message = array.get(1)
@@ -28,46 +249,592 @@ pub fn parse_command(request: RespType) -> Result<Box<dyn Command>, BifrostError
                         }
                     }
                     "SET" => {
-                        match (array.get(1), array.get(2)) {
-                            (Some(RespType::BulkString(key)), Some(value)) => {
-                                Ok(Box::new(SetCommand {
-                                    key: key.clone(),
-                                    value: value.clone(),
-                                }))
-                            }
-                            _ => Err(BifrostError::CommandError(
-                                "ERR wrong number of arguments for 'set' command".to_string()
-                            ))
+                        let args = arguments(&array, "set", -3)?;
+                        Ok(Box::new(SetCommand {
+                            key: args[0].clone(),
+                            value: args[1].clone(),
+                            options: parse_set_options(&array[3..])?,
+                        }))
+                    }
+                    "DEL" => Ok(Box::new(DelCommand(arguments(&array, "del", -2)?))),
+                    "EXISTS" => Ok(Box::new(ExistsCommand(arguments(&array, "exists", -2)?))),
+                    "INCR" => {
+                        if let Some(RespType::BulkString(key)) = array.get(1) {
+                            Ok(Box::new(IncrCommand(key.clone())))
+                        } else {
+                            Err(BifrostError::CommandError("ERR wrong number of arguments for 'incr' command".to_string()))
                         }
                     }
-                    "DEL" => {
+                    "DECR" => {
                         if let Some(RespType::BulkString(key)) = array.get(1) {
-                            Ok(Box::new(DelCommand(key.clone())))
+                            Ok(Box::new(DecrCommand(key.clone())))
                         } else {
-                            Err(BifrostError::CommandError("ERR wrong number of arguments for 'del' command".to_string()))
+                            Err(BifrostError::CommandError("ERR wrong number of arguments for 'decr' command".to_string()))
                         }
                     }
-                    "EXISTS" => {
+                    "EXPIRE" => parse_expire(&array, "expire", |n| Some(Expiration::In(Duration::from_secs(n)))),
+                    "PEXPIRE" => parse_expire(&array, "pexpire", |n| Some(Expiration::In(Duration::from_millis(n)))),
+                    "EXPIREAT" => parse_expire(&array, "expireat", |n| {
+                        SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(n)).map(Expiration::At)
+                    }),
+                    "PEXPIREAT" => parse_expire(&array, "pexpireat", |n| {
+                        SystemTime::UNIX_EPOCH.checked_add(Duration::from_millis(n)).map(Expiration::At)
+                    }),
+                    "TTL" => {
                         if let Some(RespType::BulkString(key)) = array.get(1) {
-                            Ok(Box::new(ExistsCommand(key.clone())))
+                            Ok(Box::new(TtlCommand(key.clone())))
                         } else {
-                            Err(BifrostError::CommandError("ERR wrong number of arguments for 'exists' command".to_string()))
+                            Err(BifrostError::CommandError("ERR wrong number of arguments for 'ttl' command".to_string()))
                         }
                     }
-                    "INCR" => {
+                    "PTTL" => {
                         if let Some(RespType::BulkString(key)) = array.get(1) {
-                            Ok(Box::new(IncrCommand(key.clone())))
+                            Ok(Box::new(PttlCommand(key.clone())))
                         } else {
-                            Err(BifrostError::CommandError("ERR wrong number of arguments for 'incr' command".to_string()))
+                            Err(BifrostError::CommandError("ERR wrong number of arguments for 'pttl' command".to_string()))
                         }
                     }
-                    "DECR" => {
+                    "PERSIST" => {
                         if let Some(RespType::BulkString(key)) = array.get(1) {
-                            Ok(Box::new(DecrCommand(key.clone())))
+                            Ok(Box::new(PersistCommand(key.clone())))
                         } else {
-                            Err(BifrostError::CommandError("ERR wrong number of arguments for 'decr' command".to_string()))
+                            Err(BifrostError::CommandError("ERR wrong number of arguments for 'persist' command".to_string()))
                         }
                     }
+                    "LPUSH" => {
+                        let args = arguments(&array, "lpush", -3)?;
+                        Ok(Box::new(LPushCommand { key: args[0].clone(), values: args[1..].to_vec() }))
+                    }
+                    "RPUSH" => {
+                        let args = arguments(&array, "rpush", -3)?;
+                        Ok(Box::new(RPushCommand { key: args[0].clone(), values: args[1..].to_vec() }))
+                    }
+                    "LPOP" => {
+                        let args = arguments(&array, "lpop", -2)?;
+                        Ok(Box::new(LPopCommand { key: args[0].clone(), count: parse_pop_count(&args)? }))
+                    }
+                    "RPOP" => {
+                        let args = arguments(&array, "rpop", -2)?;
+                        Ok(Box::new(RPopCommand { key: args[0].clone(), count: parse_pop_count(&args)? }))
+                    }
+                    "LRANGE" => {
+                        let args = arguments(&array, "lrange", 4)?;
+                        Ok(Box::new(LRangeCommand {
+                            key: args[0].clone(),
+                            start: parse_integer(&args[1])?,
+                            stop: parse_integer(&args[2])?,
+                        }))
+                    }
+                    "LLEN" => {
+                        let args = arguments(&array, "llen", 2)?;
+                        Ok(Box::new(LLenCommand(args[0].clone())))
+                    }
+                    "LINDEX" => {
+                        let args = arguments(&array, "lindex", 3)?;
+                        Ok(Box::new(LIndexCommand { key: args[0].clone(), index: parse_integer(&args[1])? }))
+                    }
+                    "LSET" => {
+                        let args = arguments(&array, "lset", 4)?;
+                        Ok(Box::new(LSetCommand {
+                            key: args[0].clone(),
+                            index: parse_integer(&args[1])?,
+                            value: args[2].clone(),
+                        }))
+                    }
+                    "LREM" => {
+                        let args = arguments(&array, "lrem", 4)?;
+                        Ok(Box::new(LRemCommand {
+                            key: args[0].clone(),
+                            count: parse_integer(&args[1])?,
+                            value: args[2].clone(),
+                        }))
+                    }
+                    "LTRIM" => {
+                        let args = arguments(&array, "ltrim", 4)?;
+                        Ok(Box::new(LTrimCommand {
+                            key: args[0].clone(),
+                            start: parse_integer(&args[1])?,
+                            stop: parse_integer(&args[2])?,
+                        }))
+                    }
+                    "LINSERT" => {
+                        let args = arguments(&array, "linsert", 5)?;
+                        let position = match String::from_utf8_lossy(&args[1]).to_uppercase().as_str() {
+                            "BEFORE" => ListEnd::Left,
+                            "AFTER" => ListEnd::Right,
+                            _ => return Err(syntax_error()),
+                        };
+                        Ok(Box::new(LInsertCommand {
+                            key: args[0].clone(),
+                            position,
+                            pivot: args[2].clone(),
+                            value: args[3].clone(),
+                        }))
+                    }
+                    "LMOVE" => {
+                        let args = arguments(&array, "lmove", 5)?;
+                        Ok(Box::new(LMoveCommand {
+                            source: args[0].clone(),
+                            destination: args[1].clone(),
+                            from: parse_list_end(&args[2])?,
+                            to: parse_list_end(&args[3])?,
+                        }))
+                    }
+                    "LPOS" => {
+                        let args = arguments(&array, "lpos", -3)?;
+                        Ok(Box::new(LPosCommand {
+                            key: args[0].clone(),
+                            value: args[1].clone(),
+                            options: parse_lpos_options(&args[2..])?,
+                        }))
+                    }
+                    "HSET" => {
+                        let args = arguments(&array, "hset", -4)?;
+                        if args.len() % 2 == 0 {
+                            return Err(wrong_number_of_arguments("hset"));
+                        }
+                        let pairs = args[1..]
+                            .chunks(2)
+                            .map(|pair| (pair[0].clone(), pair[1].clone()))
+                            .collect();
+                        Ok(Box::new(HSetCommand { key: args[0].clone(), pairs }))
+                    }
+                    "HSETNX" => {
+                        let args = arguments(&array, "hsetnx", 4)?;
+                        Ok(Box::new(HSetNxCommand {
+                            key: args[0].clone(),
+                            field: args[1].clone(),
+                            value: args[2].clone(),
+                        }))
+                    }
+                    "HGET" => {
+                        let args = arguments(&array, "hget", 3)?;
+                        Ok(Box::new(HGetCommand { key: args[0].clone(), field: args[1].clone() }))
+                    }
+                    "HMGET" => {
+                        let args = arguments(&array, "hmget", -3)?;
+                        Ok(Box::new(HMGetCommand { key: args[0].clone(), fields: args[1..].to_vec() }))
+                    }
+                    "HDEL" => {
+                        let args = arguments(&array, "hdel", -3)?;
+                        Ok(Box::new(HDelCommand { key: args[0].clone(), fields: args[1..].to_vec() }))
+                    }
+                    "HGETALL" => {
+                        let args = arguments(&array, "hgetall", 2)?;
+                        Ok(Box::new(HGetAllCommand(args[0].clone())))
+                    }
+                    "HKEYS" => {
+                        let args = arguments(&array, "hkeys", 2)?;
+                        Ok(Box::new(HKeysCommand(args[0].clone())))
+                    }
+                    "HVALS" => {
+                        let args = arguments(&array, "hvals", 2)?;
+                        Ok(Box::new(HValsCommand(args[0].clone())))
+                    }
+                    "HLEN" => {
+                        let args = arguments(&array, "hlen", 2)?;
+                        Ok(Box::new(HLenCommand(args[0].clone())))
+                    }
+                    "HEXISTS" => {
+                        let args = arguments(&array, "hexists", 3)?;
+                        Ok(Box::new(HExistsCommand { key: args[0].clone(), field: args[1].clone() }))
+                    }
+                    "HINCRBY" => {
+                        let args = arguments(&array, "hincrby", 4)?;
+                        Ok(Box::new(HIncrByCommand {
+                            key: args[0].clone(),
+                            field: args[1].clone(),
+                            delta: parse_integer(&args[2])?,
+                        }))
+                    }
+                    "HINCRBYFLOAT" => {
+                        let args = arguments(&array, "hincrbyfloat", 4)?;
+                        Ok(Box::new(HIncrByFloatCommand {
+                            key: args[0].clone(),
+                            field: args[1].clone(),
+                            delta: parse_float(&args[2])?,
+                        }))
+                    }
+                    "HSCAN" => {
+                        let args = arguments(&array, "hscan", -3)?;
+                        let mut novalues = false;
+                        let options = parse_scan_options(&args[2..], |option, _| {
+                            let matched = option == "NOVALUES";
+                            novalues |= matched;
+                            Ok(matched)
+                        })?;
+                        Ok(Box::new(HScanCommand {
+                            key: args[0].clone(),
+                            cursor: parse_cursor(&args[1])?,
+                            options,
+                            novalues,
+                        }))
+                    }
+                    "SADD" => {
+                        let args = arguments(&array, "sadd", -3)?;
+                        Ok(Box::new(SAddCommand { key: args[0].clone(), members: args[1..].to_vec() }))
+                    }
+                    "SREM" => {
+                        let args = arguments(&array, "srem", -3)?;
+                        Ok(Box::new(SRemCommand { key: args[0].clone(), members: args[1..].to_vec() }))
+                    }
+                    "SMEMBERS" => {
+                        let args = arguments(&array, "smembers", 2)?;
+                        Ok(Box::new(SMembersCommand(args[0].clone())))
+                    }
+                    "SISMEMBER" => {
+                        let args = arguments(&array, "sismember", 3)?;
+                        Ok(Box::new(SIsMemberCommand { key: args[0].clone(), member: args[1].clone() }))
+                    }
+                    "SMISMEMBER" => {
+                        let args = arguments(&array, "smismember", -3)?;
+                        Ok(Box::new(SMIsMemberCommand { key: args[0].clone(), members: args[1..].to_vec() }))
+                    }
+                    "SCARD" => {
+                        let args = arguments(&array, "scard", 2)?;
+                        Ok(Box::new(SCardCommand(args[0].clone())))
+                    }
+                    "SPOP" => {
+                        let args = arguments(&array, "spop", -2)?;
+                        Ok(Box::new(SPopCommand { key: args[0].clone(), count: parse_pop_count(&args)? }))
+                    }
+                    "SRANDMEMBER" => {
+                        let args = arguments(&array, "srandmember", -2)?;
+                        let count = match &args[1..] {
+                            [] => None,
+                            [count] => Some(parse_integer(count)?),
+                            _ => return Err(syntax_error()),
+                        };
+                        Ok(Box::new(SRandMemberCommand { key: args[0].clone(), count }))
+                    }
+                    "SINTER" => Ok(Box::new(SInterCommand(arguments(&array, "sinter", -2)?))),
+                    "SUNION" => Ok(Box::new(SUnionCommand(arguments(&array, "sunion", -2)?))),
+                    "SDIFF" => Ok(Box::new(SDiffCommand(arguments(&array, "sdiff", -2)?))),
+                    "SINTERSTORE" => {
+                        let args = arguments(&array, "sinterstore", -3)?;
+                        Ok(Box::new(SInterStoreCommand { destination: args[0].clone(), keys: args[1..].to_vec() }))
+                    }
+                    "SUNIONSTORE" => {
+                        let args = arguments(&array, "sunionstore", -3)?;
+                        Ok(Box::new(SUnionStoreCommand { destination: args[0].clone(), keys: args[1..].to_vec() }))
+                    }
+                    "SDIFFSTORE" => {
+                        let args = arguments(&array, "sdiffstore", -3)?;
+                        Ok(Box::new(SDiffStoreCommand { destination: args[0].clone(), keys: args[1..].to_vec() }))
+                    }
+                    "ZADD" => {
+                        let args = arguments(&array, "zadd", -4)?;
+                        parse_zadd(&args)
+                    }
+                    "ZINCRBY" => {
+                        let args = arguments(&array, "zincrby", 4)?;
+                        Ok(Box::new(ZIncrByCommand {
+                            key: args[0].clone(),
+                            delta: parse_score(&args[1])?,
+                            member: args[2].clone(),
+                        }))
+                    }
+                    "ZCARD" => {
+                        let args = arguments(&array, "zcard", 2)?;
+                        Ok(Box::new(ZCardCommand(args[0].clone())))
+                    }
+                    "ZSCORE" => {
+                        let args = arguments(&array, "zscore", 3)?;
+                        Ok(Box::new(ZScoreCommand { key: args[0].clone(), member: args[1].clone() }))
+                    }
+                    "ZRANK" => {
+                        let args = arguments(&array, "zrank", -3)?;
+                        let with_score = match &args[2..] {
+                            [] => false,
+                            [option] if option.eq_ignore_ascii_case(b"WITHSCORE") => true,
+                            _ => return Err(syntax_error()),
+                        };
+                        Ok(Box::new(ZRankCommand { key: args[0].clone(), member: args[1].clone(), with_score }))
+                    }
+                    "ZRANGE" => {
+                        let args = arguments(&array, "zrange", -4)?;
+                        Ok(Box::new(ZRangeCommand { key: args[0].clone(), query: parse_zrange(&args)? }))
+                    }
+                    "ZREM" => {
+                        let args = arguments(&array, "zrem", -3)?;
+                        Ok(Box::new(ZRemCommand { key: args[0].clone(), members: args[1..].to_vec() }))
+                    }
+                    "ZPOPMIN" => {
+                        let args = arguments(&array, "zpopmin", -2)?;
+                        Ok(Box::new(ZPopMinCommand { key: args[0].clone(), count: parse_pop_count(&args)? }))
+                    }
+                    "ZPOPMAX" => {
+                        let args = arguments(&array, "zpopmax", -2)?;
+                        Ok(Box::new(ZPopMaxCommand { key: args[0].clone(), count: parse_pop_count(&args)? }))
+                    }
+                    "ZUNIONSTORE" => {
+                        let args = arguments(&array, "zunionstore", -4)?;
+                        let (keys, weights, aggregate) = parse_zstore(&args[1..], "zunionstore")?;
+                        Ok(Box::new(ZUnionStoreCommand { destination: args[0].clone(), keys, weights, aggregate }))
+                    }
+                    "ZINTERSTORE" => {
+                        let args = arguments(&array, "zinterstore", -4)?;
+                        let (keys, weights, aggregate) = parse_zstore(&args[1..], "zinterstore")?;
+                        Ok(Box::new(ZInterStoreCommand { destination: args[0].clone(), keys, weights, aggregate }))
+                    }
+                    "XADD" => {
+                        let args = arguments(&array, "xadd", -5)?;
+                        parse_xadd(&args)
+                    }
+                    "XLEN" => {
+                        let args = arguments(&array, "xlen", 2)?;
+                        Ok(Box::new(XLenCommand(args[0].clone())))
+                    }
+                    "XRANGE" => {
+                        let args = arguments(&array, "xrange", -4)?;
+                        Ok(Box::new(XRangeCommand {
+                            key: args[0].clone(),
+                            start: parse_range_start(&args[1])?,
+                            end: parse_range_end(&args[2])?,
+                            count: parse_xrange_count(&args[3..])?,
+                        }))
+                    }
+                    "XREVRANGE" => {
+                        let args = arguments(&array, "xrevrange", -4)?;
+                        Ok(Box::new(XRevRangeCommand {
+                            key: args[0].clone(),
+                            start: parse_range_start(&args[2])?,
+                            end: parse_range_end(&args[1])?,
+                            count: parse_xrange_count(&args[3..])?,
+                        }))
+                    }
+                    "XTRIM" => {
+                        let args = arguments(&array, "xtrim", -4)?;
+                        let mut rest = args[1..].iter().peekable();
+                        let options = parse_trim_options(&mut rest)?.ok_or_else(syntax_error)?;
+                        if rest.next().is_some() {
+                            return Err(syntax_error());
+                        }
+                        Ok(Box::new(XTrimCommand { key: args[0].clone(), options }))
+                    }
+                    "XGROUP" => {
+                        let args = arguments(&array, "xgroup", -2)?;
+                        parse_xgroup(&args)
+                    }
+                    "XACK" => {
+                        let args = arguments(&array, "xack", -4)?;
+                        let ids = args[2..].iter().map(|id| parse_stream_id(id, 0)).collect::<Result<_, _>>()?;
+                        Ok(Box::new(XAckCommand { key: args[0].clone(), group: args[1].clone(), ids }))
+                    }
+                    "XPENDING" => {
+                        let args = arguments(&array, "xpending", -3)?;
+                        parse_xpending(&args)
+                    }
+                    "XCLAIM" => {
+                        let args = arguments(&array, "xclaim", -6)?;
+                        parse_xclaim(&args)
+                    }
+                    "XAUTOCLAIM" => {
+                        let args = arguments(&array, "xautoclaim", -6)?;
+                        parse_xautoclaim(&args)
+                    }
+                    "XINFO" => {
+                        let args = arguments(&array, "xinfo", -2)?;
+                        parse_xinfo(&args)
+                    }
+                    "APPEND" => {
+                        let args = arguments(&array, "append", 3)?;
+                        Ok(Box::new(AppendCommand { key: args[0].clone(), value: args[1].clone() }))
+                    }
+                    "STRLEN" => {
+                        let args = arguments(&array, "strlen", 2)?;
+                        Ok(Box::new(StrLenCommand(args[0].clone())))
+                    }
+                    "GETRANGE" => {
+                        let args = arguments(&array, "getrange", 4)?;
+                        Ok(Box::new(GetRangeCommand {
+                            key: args[0].clone(),
+                            start: parse_integer(&args[1])?,
+                            end: parse_integer(&args[2])?,
+                        }))
+                    }
+                    "SETRANGE" => {
+                        let args = arguments(&array, "setrange", 4)?;
+                        let offset = usize::try_from(parse_integer(&args[1])?)
+                            .map_err(|_| BifrostError::CommandError("ERR offset is out of range".to_string()))?;
+                        Ok(Box::new(SetRangeCommand { key: args[0].clone(), offset, value: args[2].clone() }))
+                    }
+                    "GETSET" => {
+                        let args = arguments(&array, "getset", 3)?;
+                        Ok(Box::new(GetSetCommand { key: args[0].clone(), value: args[1].clone() }))
+                    }
+                    "GETDEL" => {
+                        let args = arguments(&array, "getdel", 2)?;
+                        Ok(Box::new(GetDelCommand(args[0].clone())))
+                    }
+                    "GETEX" => {
+                        let args = arguments(&array, "getex", -2)?;
+                        parse_getex(&args)
+                    }
+                    "MGET" => {
+                        let args = arguments(&array, "mget", -2)?;
+                        Ok(Box::new(MGetCommand(args)))
+                    }
+                    "MSET" => {
+                        let args = arguments(&array, "mset", -3)?;
+                        Ok(Box::new(MSetCommand(parse_pairs(&args, "mset")?)))
+                    }
+                    "MSETNX" => {
+                        let args = arguments(&array, "msetnx", -3)?;
+                        Ok(Box::new(MSetNxCommand(parse_pairs(&args, "msetnx")?)))
+                    }
+                    "SETNX" => {
+                        let args = arguments(&array, "setnx", 3)?;
+                        Ok(Box::new(SetNxCommand { key: args[0].clone(), value: args[1].clone() }))
+                    }
+                    "INCRBY" => {
+                        let args = arguments(&array, "incrby", 3)?;
+                        Ok(Box::new(IncrByCommand { key: args[0].clone(), delta: parse_integer(&args[1])? }))
+                    }
+                    "DECRBY" => {
+                        let args = arguments(&array, "decrby", 3)?;
+                        Ok(Box::new(DecrByCommand { key: args[0].clone(), delta: parse_integer(&args[1])? }))
+                    }
+                    "INCRBYFLOAT" => {
+                        let args = arguments(&array, "incrbyfloat", 3)?;
+                        Ok(Box::new(IncrByFloatCommand { key: args[0].clone(), delta: parse_float(&args[1])? }))
+                    }
+                    "SETBIT" => {
+                        let args = arguments(&array, "setbit", 4)?;
+                        let bit = match args[2].as_ref() {
+                            b"0" => false,
+                            b"1" => true,
+                            _ => {
+                                return Err(BifrostError::CommandError(
+                                    "ERR bit is not an integer or out of range".to_string(),
+                                ))
+                            }
+                        };
+                        Ok(Box::new(SetBitCommand { key: args[0].clone(), offset: parse_bit_offset(&args[1])?, bit }))
+                    }
+                    "GETBIT" => {
+                        let args = arguments(&array, "getbit", 3)?;
+                        Ok(Box::new(GetBitCommand { key: args[0].clone(), offset: parse_bit_offset(&args[1])? }))
+                    }
+                    "BITCOUNT" => {
+                        let args = arguments(&array, "bitcount", -2)?;
+                        let range = match &args[1..] {
+                            [] => None,
+                            [start, end] => Some((parse_integer(start)?, parse_integer(end)?, BitUnit::Byte)),
+                            [start, end, unit] => Some((parse_integer(start)?, parse_integer(end)?, parse_bit_unit(unit)?)),
+                            _ => return Err(syntax_error()),
+                        };
+                        Ok(Box::new(BitCountCommand { key: args[0].clone(), range }))
+                    }
+                    "BITPOS" => {
+                        let args = arguments(&array, "bitpos", -3)?;
+                        parse_bitpos(&args)
+                    }
+                    "BITOP" => {
+                        let args = arguments(&array, "bitop", -4)?;
+                        parse_bitop(&args)
+                    }
+                    "BITFIELD" => {
+                        let args = arguments(&array, "bitfield", -2)?;
+                        let ops = parse_bitfield_ops(&args[1..], false)?;
+                        Ok(Box::new(BitFieldCommand { key: args[0].clone(), ops }))
+                    }
+                    "BITFIELD_RO" => {
+                        let args = arguments(&array, "bitfield_ro", -2)?;
+                        let ops = parse_bitfield_ops(&args[1..], true)?;
+                        Ok(Box::new(BitFieldCommand { key: args[0].clone(), ops }))
+                    }
+                    "PFADD" => {
+                        let args = arguments(&array, "pfadd", -2)?;
+                        Ok(Box::new(PfAddCommand { key: args[0].clone(), elements: args[1..].to_vec() }))
+                    }
+                    "PFCOUNT" => {
+                        let args = arguments(&array, "pfcount", -2)?;
+                        Ok(Box::new(PfCountCommand(args)))
+                    }
+                    "PFMERGE" => {
+                        let args = arguments(&array, "pfmerge", -2)?;
+                        Ok(Box::new(PfMergeCommand { destination: args[0].clone(), sources: args[1..].to_vec() }))
+                    }
+                    "FLUSHDB" => {
+                        let args = arguments(&array, "flushdb", -1)?;
+                        parse_flush_mode(&args)?;
+                        Ok(Box::new(FlushDbCommand))
+                    }
+                    "DBSIZE" => {
+                        arguments(&array, "dbsize", 1)?;
+                        Ok(Box::new(DbSizeCommand))
+                    }
+                    "UNLINK" => Ok(Box::new(UnlinkCommand(arguments(&array, "unlink", -2)?))),
+                    "TOUCH" => Ok(Box::new(TouchCommand(arguments(&array, "touch", -2)?))),
+                    "KEYS" => {
+                        let args = arguments(&array, "keys", 2)?;
+                        Ok(Box::new(KeysCommand(args[0].clone())))
+                    }
+                    "SCAN" => {
+                        let args = arguments(&array, "scan", -2)?;
+                        let mut key_type = None;
+                        let options = parse_scan_options(&args[1..], |option, rest| {
+                            if option != "TYPE" {
+                                return Ok(false);
+                            }
+                            key_type = Some(parse_type_name(rest.next().ok_or_else(syntax_error)?)?);
+                            Ok(true)
+                        })?;
+                        Ok(Box::new(ScanCommand { cursor: parse_cursor(&args[0])?, options, key_type }))
+                    }
+                    "TYPE" => {
+                        let args = arguments(&array, "type", 2)?;
+                        Ok(Box::new(TypeCommand(args[0].clone())))
+                    }
+                    "RANDOMKEY" => {
+                        arguments(&array, "randomkey", 1)?;
+                        Ok(Box::new(RandomKeyCommand))
+                    }
+                    "RENAME" => {
+                        let args = arguments(&array, "rename", 3)?;
+                        Ok(Box::new(RenameCommand { key: args[0].clone(), destination: args[1].clone() }))
+                    }
+                    "RENAMENX" => {
+                        let args = arguments(&array, "renamenx", 3)?;
+                        Ok(Box::new(RenameNxCommand { key: args[0].clone(), destination: args[1].clone() }))
+                    }
+                    "GEOADD" => {
+                        let args = arguments(&array, "geoadd", -5)?;
+                        parse_geoadd(&args)
+                    }
+                    "GEODIST" => {
+                        let args = arguments(&array, "geodist", -4)?;
+                        let unit = match &args[3..] {
+                            [] => DistanceUnit::Meters,
+                            [unit] => parse_distance_unit(unit)?,
+                            _ => return Err(syntax_error()),
+                        };
+                        Ok(Box::new(GeoDistCommand { key: args[0].clone(), from: args[1].clone(), to: args[2].clone(), unit }))
+                    }
+                    "GEOPOS" => {
+                        let args = arguments(&array, "geopos", -2)?;
+                        Ok(Box::new(GeoPosCommand { key: args[0].clone(), members: args[1..].to_vec() }))
+                    }
+                    "GEOHASH" => {
+                        let args = arguments(&array, "geohash", -2)?;
+                        Ok(Box::new(GeoHashCommand { key: args[0].clone(), members: args[1..].to_vec() }))
+                    }
+                    "GEOSEARCH" => {
+                        let args = arguments(&array, "geosearch", -7)?;
+                        let (search, _) = parse_geosearch(&args[1..], "geosearch")?;
+                        Ok(Box::new(GeoSearchCommand { key: args[0].clone(), search }))
+                    }
+                    "GEOSEARCHSTORE" => {
+                        let args = arguments(&array, "geosearchstore", -8)?;
+                        let (search, store_dist) = parse_geosearch(&args[2..], "geosearchstore")?;
+                        Ok(Box::new(GeoSearchStoreCommand {
+                            destination: args[0].clone(),
+                            key: args[1].clone(),
+                            search,
+                            store_dist,
+                        }))
+                    }
                     _ => Err(BifrostError::CommandError("ERR unknown command".to_string()))
                 }
             } else {
@@ -76,4 +843,1510 @@ pub fn parse_command(request: RespType) -> Result<Box<dyn Command>, BifrostError
         }
         _ => Err(BifrostError::ProtocolError("ERR invalid request".to_string()))
     }
-} 
\ No newline at end of file
+}
+
+fn syntax_error() -> BifrostError {
+    BifrostError::CommandError("ERR syntax error".to_string())
+}
+
+fn wrong_number_of_arguments(name: &str) -> BifrostError {
+    BifrostError::CommandError(format!("ERR wrong number of arguments for '{}' command", name))
+}
+
+// Checks the number of arguments the way Redis does, counting the command name: a
+// positive arity is exact and a negative one a minimum. Returns the arguments after the
+// command name.
+fn arguments(array: &[RespType], name: &str, arity: i64) -> Result<Vec<Bytes>, BifrostError> {
+    let len = array.len() as i64;
+    if (arity > 0 && len != arity) || len < arity.abs() {
+        return Err(wrong_number_of_arguments(name));
+    }
+    array[1..]
+        .iter()
+        .map(|arg| match arg {
+            RespType::BulkString(arg) => Ok(arg.clone()),
+            RespType::Integer(value) => Ok(Bytes::from(value.to_string())),
+            _ => Err(BifrostError::ProtocolError("ERR invalid request".to_string())),
+        })
+        .collect()
+}
+
+fn parse_integer(arg: &[u8]) -> Result<i64, BifrostError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .ok_or_else(|| {
+            BifrostError::CommandError("ERR value is not an integer or out of range".to_string())
+        })
+}
+
+// Parses an argument that must not be negative, such as a COUNT.
+fn parse_count(arg: &[u8]) -> Result<usize, BifrostError> {
+    usize::try_from(parse_integer(arg)?).map_err(|_| {
+        BifrostError::CommandError("ERR value is out of range, must be positive".to_string())
+    })
+}
+
+fn parse_integer_arg(arg: Option<&RespType>) -> Result<i64, BifrostError> {
+    match arg {
+        Some(RespType::BulkString(value)) => parse_integer(value),
+        Some(RespType::Integer(value)) => Ok(*value),
+        _ => Err(syntax_error()),
+    }
+}
+
+fn parse_list_end(arg: &[u8]) -> Result<ListEnd, BifrostError> {
+    match String::from_utf8_lossy(arg).to_uppercase().as_str() {
+        "LEFT" => Ok(ListEnd::Left),
+        "RIGHT" => Ok(ListEnd::Right),
+        _ => Err(syntax_error()),
+    }
+}
+
+fn parse_float(arg: &[u8]) -> Result<f64, BifrostError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| BifrostError::CommandError("ERR value is not a valid float".to_string()))
+}
+
+fn parse_cursor(arg: &[u8]) -> Result<u64, BifrostError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .ok_or_else(|| BifrostError::CommandError("ERR invalid cursor".to_string()))
+}
+
+// [MATCH pattern] [COUNT count] shared by the SCAN family. Options specific to one command
+// are passed to `option` along with the remaining arguments, so that it can take a value,
+// and it returns whether it accepted them.
+fn parse_scan_options<'a>(
+    args: &'a [Bytes],
+    mut option: impl FnMut(&str, &mut std::slice::Iter<'a, Bytes>) -> Result<bool, BifrostError>,
+) -> Result<ScanOptions, BifrostError> {
+    let mut options = ScanOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match String::from_utf8_lossy(arg).to_uppercase().as_str() {
+            "MATCH" => options.pattern = Some(args.next().ok_or_else(syntax_error)?.clone()),
+            "COUNT" => {
+                options.count = parse_count(args.next().ok_or_else(syntax_error)?)?;
+                if options.count == 0 {
+                    return Err(syntax_error());
+                }
+            }
+            other => {
+                if !option(other, &mut args)? {
+                    return Err(syntax_error());
+                }
+            }
+        }
+    }
+    Ok(options)
+}
+
+// Parses the TYPE option of SCAN, which names one of the types TYPE replies with.
+fn parse_type_name(arg: &[u8]) -> Result<String, BifrostError> {
+    let name = String::from_utf8_lossy(arg);
+    match name.to_lowercase().as_str() {
+        known @ ("string" | "list" | "set" | "zset" | "hash" | "stream") => Ok(known.to_string()),
+        _ => Err(BifrostError::CommandError(format!("ERR unknown type name '{}'", name))),
+    }
+}
+
+// Parses a blocking timeout in seconds, where 0 blocks forever.
+fn parse_timeout(arg: &[u8]) -> Result<Option<Duration>, BifrostError> {
+    let seconds = std::str::from_utf8(arg)
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| {
+            BifrostError::CommandError("ERR timeout is not a float or out of range".to_string())
+        })?;
+    if seconds < 0.0 {
+        return Err(BifrostError::CommandError("ERR timeout is negative".to_string()));
+    }
+    if seconds == 0.0 {
+        return Ok(None);
+    }
+    Duration::try_from_secs_f64(seconds).map(Some).map_err(|_| {
+        BifrostError::CommandError("ERR timeout is out of range".to_string())
+    })
+}
+
+// Parses a sorted set score, which unlike other floats may be infinite.
+fn parse_score(arg: &[u8]) -> Result<f64, BifrostError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|value| !value.is_nan())
+        .ok_or_else(|| BifrostError::CommandError("ERR value is not a valid float".to_string()))
+}
+
+// ZADD key [NX|XX] [GT|LT] [CH] [INCR] score member [score member ...]
+fn parse_zadd(args: &[Bytes]) -> Result<Box<dyn Command>, BifrostError> {
+    let mut options = ZaddOptions::default();
+    let (mut nx, mut xx, mut gt, mut lt) = (false, false, false, false);
+    let mut position = 1;
+    while let Some(arg) = args.get(position) {
+        match String::from_utf8_lossy(arg).to_uppercase().as_str() {
+            "NX" => nx = true,
+            "XX" => xx = true,
+            "GT" => gt = true,
+            "LT" => lt = true,
+            "CH" => options.changed = true,
+            "INCR" => options.incr = true,
+            _ => break,
+        }
+        position += 1;
+    }
+
+    let elements = &args[position..];
+    if elements.is_empty() || !elements.len().is_multiple_of(2) {
+        return Err(syntax_error());
+    }
+    if nx && xx {
+        return Err(BifrostError::CommandError(
+            "ERR XX and NX options at the same time are not compatible".to_string(),
+        ));
+    }
+    if (gt && lt) || (nx && (gt || lt)) {
+        return Err(BifrostError::CommandError(
+            "ERR GT, LT, and/or NX options at the same time are not compatible".to_string(),
+        ));
+    }
+    if options.incr && elements.len() > 2 {
+        return Err(BifrostError::CommandError(
+            "ERR INCR option supports a single increment-element pair".to_string(),
+        ));
+    }
+    options.condition = match (nx, xx) {
+        (true, _) => SetCondition::IfNotExists,
+        (_, true) => SetCondition::IfExists,
+        _ => SetCondition::Always,
+    };
+    options.comparison = match (gt, lt) {
+        (true, _) => ScoreComparison::Greater,
+        (_, true) => ScoreComparison::Less,
+        _ => ScoreComparison::Any,
+    };
+
+    let pairs = elements
+        .chunks(2)
+        .map(|pair| Ok((parse_score(&pair[0])?, pair[1].clone())))
+        .collect::<Result<_, BifrostError>>()?;
+    Ok(Box::new(ZAddCommand { key: args[0].clone(), pairs, options }))
+}
+
+fn parse_score_bound(arg: &[u8]) -> Result<ScoreBound, BifrostError> {
+    let error = || BifrostError::CommandError("ERR min or max is not a float".to_string());
+    match arg.strip_prefix(b"(") {
+        Some(score) => parse_score(score).map(ScoreBound::Exclusive).map_err(|_| error()),
+        None => parse_score(arg).map(ScoreBound::Inclusive).map_err(|_| error()),
+    }
+}
+
+fn parse_lex_bound(arg: &Bytes) -> Result<LexBound, BifrostError> {
+    match arg.first() {
+        Some(b'-') if arg.len() == 1 => Ok(LexBound::Min),
+        Some(b'+') if arg.len() == 1 => Ok(LexBound::Max),
+        Some(b'[') => Ok(LexBound::Inclusive(arg.slice(1..))),
+        Some(b'(') => Ok(LexBound::Exclusive(arg.slice(1..))),
+        _ => Err(BifrostError::CommandError(
+            "ERR min or max not valid string range item".to_string(),
+        )),
+    }
+}
+
+// ZRANGE key start stop [BYSCORE|BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+fn parse_zrange(args: &[Bytes]) -> Result<RangeQuery, BifrostError> {
+    let mut by_score = false;
+    let mut by_lex = false;
+    let mut reverse = false;
+    let mut limit = None;
+    let mut with_scores = false;
+    let mut options = args[3..].iter();
+    while let Some(option) = options.next() {
+        match String::from_utf8_lossy(option).to_uppercase().as_str() {
+            "BYSCORE" => by_score = true,
+            "BYLEX" => by_lex = true,
+            "REV" => reverse = true,
+            "WITHSCORES" => with_scores = true,
+            "LIMIT" => {
+                let offset = parse_integer(options.next().ok_or_else(syntax_error)?)?;
+                let count = parse_integer(options.next().ok_or_else(syntax_error)?)?;
+                limit = Some((offset, count));
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    if by_lex && (by_score || with_scores) {
+        return Err(syntax_error());
+    }
+    if limit.is_some() && !by_score && !by_lex {
+        return Err(BifrostError::CommandError(
+            "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                .to_string(),
+        ));
+    }
+
+    // With REV the range is given from the highest score or member to the lowest.
+    let (start, stop) = if reverse && (by_score || by_lex) { (&args[2], &args[1]) } else { (&args[1], &args[2]) };
+    let by = if by_score {
+        RangeBy::Score(parse_score_bound(start)?, parse_score_bound(stop)?)
+    } else if by_lex {
+        RangeBy::Lex(parse_lex_bound(start)?, parse_lex_bound(stop)?)
+    } else {
+        RangeBy::Rank(parse_integer(start)?, parse_integer(stop)?)
+    };
+    Ok(RangeQuery { by, reverse, limit, with_scores })
+}
+
+// ZUNIONSTORE and ZINTERSTORE destination numkeys key [key ...] [WEIGHTS weight ...]
+// [AGGREGATE SUM|MIN|MAX], given the arguments after the destination.
+fn parse_zstore(args: &[Bytes], name: &str) -> Result<(Vec<Bytes>, Vec<f64>, Aggregate), BifrostError> {
+    let numkeys = usize::try_from(parse_integer(&args[0])?).unwrap_or(0);
+    if numkeys == 0 {
+        return Err(BifrostError::CommandError(format!(
+            "ERR at least 1 input key is needed for '{}' command",
+            name
+        )));
+    }
+    let keys = args.get(1..=numkeys).ok_or_else(syntax_error)?.to_vec();
+
+    let mut weights = Vec::new();
+    let mut aggregate = Aggregate::default();
+    let mut options = args[numkeys + 1..].iter();
+    while let Some(option) = options.next() {
+        match String::from_utf8_lossy(option).to_uppercase().as_str() {
+            "WEIGHTS" => {
+                weights = (0..numkeys)
+                    .map(|_| {
+                        let weight = options.next().ok_or_else(syntax_error)?;
+                        parse_score(weight).map_err(|_| {
+                            BifrostError::CommandError("ERR weight value is not a float".to_string())
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "AGGREGATE" => {
+                let value = options.next().ok_or_else(syntax_error)?;
+                aggregate = match String::from_utf8_lossy(value).to_uppercase().as_str() {
+                    "SUM" => Aggregate::Sum,
+                    "MIN" => Aggregate::Min,
+                    "MAX" => Aggregate::Max,
+                    _ => return Err(syntax_error()),
+                };
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok((keys, weights, aggregate))
+}
+
+fn invalid_stream_id() -> BifrostError {
+    BifrostError::CommandError(
+        "ERR Invalid stream ID specified as stream command argument".to_string(),
+    )
+}
+
+// Parses `<ms>-<seq>`, or just `<ms>` with the sequence number defaulting to `seq`.
+fn parse_stream_id(arg: &[u8], seq: u64) -> Result<StreamId, BifrostError> {
+    let arg = std::str::from_utf8(arg).map_err(|_| invalid_stream_id())?;
+    let (ms, seq) = match arg.split_once('-') {
+        Some((ms, seq)) => (ms, seq.parse().map_err(|_| invalid_stream_id())?),
+        None => (arg, seq),
+    };
+    let ms = ms.parse().map_err(|_| invalid_stream_id())?;
+    Ok(StreamId::new(ms, seq))
+}
+
+// The start of an XRANGE interval: `-`, an ID, or an exclusive `(ID`.
+fn parse_range_start(arg: &[u8]) -> Result<StreamId, BifrostError> {
+    match arg {
+        b"-" => Ok(StreamId::MIN),
+        [b'(', id @ ..] => parse_stream_id(id, 0)?.next().ok_or_else(|| {
+            BifrostError::CommandError("ERR invalid start ID for the interval".to_string())
+        }),
+        id => parse_stream_id(id, 0),
+    }
+}
+
+// The end of an XRANGE interval: `+`, an ID, or an exclusive `(ID`.
+fn parse_range_end(arg: &[u8]) -> Result<StreamId, BifrostError> {
+    match arg {
+        b"+" => Ok(StreamId::MAX),
+        [b'(', id @ ..] => parse_stream_id(id, u64::MAX)?.prev().ok_or_else(|| {
+            BifrostError::CommandError("ERR invalid end ID for the interval".to_string())
+        }),
+        id => parse_stream_id(id, u64::MAX),
+    }
+}
+
+// XRANGE key start end [COUNT count]
+fn parse_xrange_count(args: &[Bytes]) -> Result<Option<usize>, BifrostError> {
+    match args {
+        [] => Ok(None),
+        [option, count] if option.eq_ignore_ascii_case(b"COUNT") => {
+            // A negative count is treated as zero, as in Redis.
+            Ok(Some(usize::try_from(parse_integer(count)?).unwrap_or(0)))
+        }
+        _ => Err(syntax_error()),
+    }
+}
+
+// MAXLEN|MINID [=|~] threshold [LIMIT count], if the next argument starts a trimming
+// strategy.
+fn parse_trim_options<'a>(
+    args: &mut std::iter::Peekable<impl Iterator<Item = &'a Bytes>>,
+) -> Result<Option<TrimOptions>, BifrostError> {
+    let Some(strategy) = args.peek() else {
+        return Ok(None);
+    };
+    let max_len = if strategy.eq_ignore_ascii_case(b"MAXLEN") {
+        true
+    } else if strategy.eq_ignore_ascii_case(b"MINID") {
+        false
+    } else {
+        return Ok(None);
+    };
+    args.next();
+
+    let mut threshold = args.next().ok_or_else(syntax_error)?;
+    let mut approximate = false;
+    if threshold[..] == *b"~" || threshold[..] == *b"=" {
+        approximate = threshold[..] == *b"~";
+        threshold = args.next().ok_or_else(syntax_error)?;
+    }
+    let strategy = if max_len {
+        TrimStrategy::MaxLen(parse_count(threshold)?)
+    } else {
+        TrimStrategy::MinId(parse_stream_id(threshold, 0)?)
+    };
+
+    let mut limit = None;
+    if args.peek().is_some_and(|arg| arg.eq_ignore_ascii_case(b"LIMIT")) {
+        args.next();
+        if !approximate {
+            return Err(BifrostError::CommandError(
+                "ERR syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+            ));
+        }
+        // LIMIT 0 means no limit.
+        limit = Some(parse_count(args.next().ok_or_else(syntax_error)?)?).filter(|limit| *limit > 0);
+    }
+    Ok(Some(TrimOptions { strategy, limit }))
+}
+
+// XADD key [NOMKSTREAM] [MAXLEN|MINID [=|~] threshold [LIMIT count]] *|id field value
+// [field value ...]
+fn parse_xadd(args: &[Bytes]) -> Result<Box<dyn Command>, BifrostError> {
+    let mut options = XaddOptions::default();
+    let mut rest = args[1..].iter().peekable();
+    loop {
+        if rest.peek().is_some_and(|arg| arg.eq_ignore_ascii_case(b"NOMKSTREAM")) {
+            rest.next();
+            options.nomkstream = true;
+        } else if let Some(trim) = parse_trim_options(&mut rest)? {
+            options.trim = Some(trim);
+        } else {
+            break;
+        }
+    }
+
+    let id = match rest.next().ok_or_else(syntax_error)?.as_ref() {
+        b"*" => XaddId::Auto,
+        id => match id.strip_suffix(b"-*") {
+            Some(ms) => XaddId::AutoSeq(parse_stream_id(ms, 0)?.ms),
+            None => XaddId::Explicit(parse_stream_id(id, 0)?),
+        },
+    };
+    let values: Vec<&Bytes> = rest.collect();
+    if values.is_empty() || !values.len().is_multiple_of(2) {
+        return Err(wrong_number_of_arguments("xadd"));
+    }
+    let fields = values.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect();
+    Ok(Box::new(XAddCommand { key: args[0].clone(), id, fields, options }))
+}
+
+// The options and streams shared by XREAD and XREADGROUP:
+// [COUNT count] [BLOCK milliseconds] [NOACK] STREAMS key [key ...] id [id ...]
+// NOACK is only accepted by XREADGROUP.
+struct StreamReads<'a> {
+    count: Option<usize>,
+    block: Option<Option<Duration>>,
+    noack: bool,
+    streams: Vec<(&'a Bytes, &'a Bytes)>,
+}
+
+fn parse_stream_reads<'a>(args: &'a [Bytes], name: &str) -> Result<StreamReads<'a>, BifrostError> {
+    let mut count = None;
+    let mut block = None;
+    let mut noack = false;
+    let mut position = 0;
+    while let Some(option) = args.get(position) {
+        match String::from_utf8_lossy(option).to_uppercase().as_str() {
+            "COUNT" => {
+                let value = args.get(position + 1).ok_or_else(syntax_error)?;
+                // COUNT 0 reads everything, as in Redis.
+                count = Some(usize::try_from(parse_integer(value)?).unwrap_or(0)).filter(|count| *count > 0);
+                position += 2;
+            }
+            "BLOCK" => {
+                let value = args.get(position + 1).ok_or_else(syntax_error)?;
+                let ms = parse_integer(value).map_err(|_| {
+                    BifrostError::CommandError("ERR timeout is not an integer or out of range".to_string())
+                })?;
+                let ms = u64::try_from(ms)
+                    .map_err(|_| BifrostError::CommandError("ERR timeout is negative".to_string()))?;
+                block = Some(Some(Duration::from_millis(ms)).filter(|timeout| !timeout.is_zero()));
+                position += 2;
+            }
+            "NOACK" if name == "xreadgroup" => {
+                noack = true;
+                position += 1;
+            }
+            "STREAMS" => {
+                position += 1;
+                break;
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    let streams = &args[position..];
+    if position == 0 || !args[position - 1].eq_ignore_ascii_case(b"STREAMS") {
+        return Err(syntax_error());
+    }
+    if streams.is_empty() || !streams.len().is_multiple_of(2) {
+        return Err(BifrostError::CommandError(format!(
+            "ERR Unbalanced '{}' list of streams: for each stream key an ID or '$' must be specified.",
+            name
+        )));
+    }
+    let (keys, ids) = streams.split_at(streams.len() / 2);
+    Ok(StreamReads { count, block, noack, streams: keys.iter().zip(ids).collect() })
+}
+
+// XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]
+fn parse_xread(args: &[Bytes]) -> Result<Box<dyn BlockingCommand>, BifrostError> {
+    let reads = parse_stream_reads(args, "xread")?;
+    let streams = reads
+        .streams
+        .into_iter()
+        .map(|(key, id)| {
+            let position = match id.as_ref() {
+                b"$" => StreamPosition::Latest,
+                id => StreamPosition::After(parse_stream_id(id, 0)?),
+            };
+            Ok((key.clone(), position))
+        })
+        .collect::<Result<_, BifrostError>>()?;
+    Ok(Box::new(XReadCommand { streams, count: reads.count, block: reads.block }))
+}
+
+// XREADGROUP GROUP group consumer [COUNT count] [BLOCK milliseconds] [NOACK] STREAMS key
+// [key ...] id [id ...]
+fn parse_xreadgroup(args: &[Bytes]) -> Result<Box<dyn BlockingCommand>, BifrostError> {
+    if !args[0].eq_ignore_ascii_case(b"GROUP") {
+        return Err(BifrostError::CommandError(
+            "ERR Missing GROUP option for XREADGROUP".to_string(),
+        ));
+    }
+    let reads = parse_stream_reads(&args[3..], "xreadgroup")?;
+    let streams = reads
+        .streams
+        .into_iter()
+        .map(|(key, id)| {
+            let position = match id.as_ref() {
+                b">" => GroupPosition::Undelivered,
+                b"$" => {
+                    return Err(BifrostError::CommandError(
+                        "ERR The $ ID is meaningless in the context of XREADGROUP: you want to read the history of this consumer by specifying a proper ID, or use the > ID to get new messages. The $ ID would just return an empty result set.".to_string(),
+                    ))
+                }
+                id => GroupPosition::Pending(parse_stream_id(id, 0)?),
+            };
+            Ok((key.clone(), position))
+        })
+        .collect::<Result<_, BifrostError>>()?;
+    let read = GroupRead { group: args[1].clone(), consumer: args[2].clone(), noack: reads.noack };
+    Ok(Box::new(XReadGroupCommand { read, streams, count: reads.count, block: reads.block }))
+}
+
+// The position of XGROUP CREATE and SETID: an ID, or `$` for the last entry.
+fn parse_group_position(arg: &[u8]) -> Result<StreamPosition, BifrostError> {
+    match arg {
+        b"$" => Ok(StreamPosition::Latest),
+        id => parse_stream_id(id, 0).map(StreamPosition::After),
+    }
+}
+
+// [MKSTREAM] [ENTRIESREAD entries-read], where MKSTREAM is only accepted by CREATE.
+fn parse_group_options(args: &[Bytes], create: bool) -> Result<(bool, Option<u64>), BifrostError> {
+    let mut mkstream = false;
+    let mut entries_read = None;
+    let mut rest = args.iter();
+    while let Some(option) = rest.next() {
+        if create && option.eq_ignore_ascii_case(b"MKSTREAM") {
+            mkstream = true;
+        } else if option.eq_ignore_ascii_case(b"ENTRIESREAD") {
+            let value = parse_integer(rest.next().ok_or_else(syntax_error)?)?;
+            // Like Redis, -1 marks the counter as unknown.
+            entries_read = match value {
+                -1 => None,
+                value => Some(u64::try_from(value).map_err(|_| {
+                    BifrostError::CommandError("ERR value for ENTRIESREAD must be positive or -1".to_string())
+                })?),
+            };
+        } else {
+            return Err(syntax_error());
+        }
+    }
+    Ok((mkstream, entries_read))
+}
+
+// XGROUP CREATE key group id|$ [MKSTREAM] [ENTRIESREAD entries-read]
+// XGROUP SETID key group id|$ [ENTRIESREAD entries-read]
+// XGROUP DESTROY key group
+// XGROUP CREATECONSUMER|DELCONSUMER key group consumer
+fn parse_xgroup(args: &[Bytes]) -> Result<Box<dyn Command>, BifrostError> {
+    let subcommand = String::from_utf8_lossy(&args[0]).to_lowercase();
+    let (min, max) = match subcommand.as_str() {
+        "create" => (4, 7),
+        "setid" => (4, 6),
+        "destroy" => (3, 3),
+        "createconsumer" | "delconsumer" => (4, 4),
+        _ => {
+            return Err(BifrostError::CommandError(format!(
+                "ERR unknown subcommand '{}'. Try XGROUP HELP.",
+                String::from_utf8_lossy(&args[0])
+            )))
+        }
+    };
+    if args.len() < min || args.len() > max {
+        return Err(wrong_number_of_arguments(&format!("xgroup|{}", subcommand)));
+    }
+    let (key, group) = (args[1].clone(), args[2].clone());
+    let command = match subcommand.as_str() {
+        "create" => {
+            let (mkstream, entries_read) = parse_group_options(&args[4..], true)?;
+            XGroupCommand::Create { key, group, position: parse_group_position(&args[3])?, mkstream, entries_read }
+        }
+        "setid" => {
+            let (_, entries_read) = parse_group_options(&args[4..], false)?;
+            XGroupCommand::SetId { key, group, position: parse_group_position(&args[3])?, entries_read }
+        }
+        "destroy" => XGroupCommand::Destroy { key, group },
+        "createconsumer" => XGroupCommand::CreateConsumer { key, group, consumer: args[3].clone() },
+        _ => XGroupCommand::DelConsumer { key, group, consumer: args[3].clone() },
+    };
+    Ok(Box::new(command))
+}
+
+// A minimum idle time in milliseconds, where negative values count as zero.
+fn parse_min_idle(arg: &[u8]) -> Result<u64, BifrostError> {
+    Ok(u64::try_from(parse_integer(arg)?).unwrap_or(0))
+}
+
+// XPENDING key group [[IDLE min-idle-time] start end count [consumer]]
+fn parse_xpending(args: &[Bytes]) -> Result<Box<dyn Command>, BifrostError> {
+    let (key, group) = (args[0].clone(), args[1].clone());
+    let mut rest = &args[2..];
+    if rest.is_empty() {
+        return Ok(Box::new(XPendingCommand { key, group, range: None }));
+    }
+    let mut min_idle = 0;
+    if rest[0].eq_ignore_ascii_case(b"IDLE") {
+        min_idle = parse_min_idle(rest.get(1).ok_or_else(syntax_error)?)?;
+        rest = &rest[2..];
+    }
+    let [start, end, count, consumer @ ..] = rest else {
+        return Err(syntax_error());
+    };
+    if consumer.len() > 1 {
+        return Err(syntax_error());
+    }
+    let range = PendingRange {
+        min_idle,
+        start: parse_range_start(start)?,
+        end: parse_range_end(end)?,
+        // A negative count is treated as zero, as in Redis.
+        count: usize::try_from(parse_integer(count)?).unwrap_or(0),
+        consumer: consumer.first().cloned(),
+    };
+    Ok(Box::new(XPendingCommand { key, group, range: Some(range) }))
+}
+
+// XCLAIM key group consumer min-idle-time id [id ...] [IDLE ms] [TIME unix-time-ms]
+// [RETRYCOUNT count] [FORCE] [JUSTID] [LASTID lastid]
+fn parse_xclaim(args: &[Bytes]) -> Result<Box<dyn Command>, BifrostError> {
+    let min_idle = parse_min_idle(&args[3]).map_err(|_| {
+        BifrostError::CommandError("ERR Invalid min-idle-time argument for XCLAIM".to_string())
+    })?;
+    // IDs run until the first argument that is not one.
+    let mut rest = args[4..].iter().peekable();
+    let mut ids = Vec::new();
+    while let Some(id) = rest.peek().and_then(|arg| parse_stream_id(arg, 0).ok()) {
+        ids.push(id);
+        rest.next();
+    }
+    if ids.is_empty() {
+        return Err(invalid_stream_id());
+    }
+
+    let mut options = ClaimOptions::default();
+    while let Some(option) = rest.next() {
+        let mut value = || rest.next().ok_or_else(syntax_error);
+        match String::from_utf8_lossy(option).to_uppercase().as_str() {
+            "IDLE" => options.idle = Some(parse_min_idle(value()?)?),
+            "TIME" => options.time = Some(parse_min_idle(value()?)?),
+            "RETRYCOUNT" => options.retry_count = Some(parse_min_idle(value()?)?),
+            "LASTID" => options.last_id = Some(parse_stream_id(value()?, 0)?),
+            "FORCE" => options.force = true,
+            "JUSTID" => options.justid = true,
+            _ => {
+                return Err(BifrostError::CommandError(format!(
+                    "ERR Unrecognized XCLAIM option '{}'",
+                    String::from_utf8_lossy(option)
+                )))
+            }
+        }
+    }
+    Ok(Box::new(XClaimCommand {
+        key: args[0].clone(),
+        group: args[1].clone(),
+        consumer: args[2].clone(),
+        min_idle,
+        ids,
+        options,
+    }))
+}
+
+// XAUTOCLAIM key group consumer min-idle-time start [COUNT count] [JUSTID]
+fn parse_xautoclaim(args: &[Bytes]) -> Result<Box<dyn Command>, BifrostError> {
+    let min_idle = parse_min_idle(&args[3]).map_err(|_| {
+        BifrostError::CommandError("ERR Invalid min-idle-time argument for XAUTOCLAIM".to_string())
+    })?;
+    let start = parse_range_start(&args[4])?;
+    let mut count = 100;
+    let mut justid = false;
+    let mut rest = args[5..].iter();
+    while let Some(option) = rest.next() {
+        if option.eq_ignore_ascii_case(b"COUNT") {
+            count = usize::try_from(parse_integer(rest.next().ok_or_else(syntax_error)?)?)
+                .ok()
+                .filter(|count| *count > 0)
+                .ok_or_else(|| BifrostError::CommandError("ERR COUNT must be > 0".to_string()))?;
+        } else if option.eq_ignore_ascii_case(b"JUSTID") {
+            justid = true;
+        } else {
+            return Err(syntax_error());
+        }
+    }
+    Ok(Box::new(XAutoClaimCommand {
+        key: args[0].clone(),
+        group: args[1].clone(),
+        consumer: args[2].clone(),
+        min_idle,
+        start,
+        count,
+        justid,
+    }))
+}
+
+// XINFO STREAM key [FULL [COUNT count]]
+// XINFO GROUPS key
+// XINFO CONSUMERS key group
+fn parse_xinfo(args: &[Bytes]) -> Result<Box<dyn Command>, BifrostError> {
+    let subcommand = String::from_utf8_lossy(&args[0]).to_lowercase();
+    let command = match (subcommand.as_str(), &args[1..]) {
+        ("stream", [key]) => XInfoCommand::Stream { key: key.clone(), full: None },
+        ("stream", [key, full, rest @ ..]) if full.eq_ignore_ascii_case(b"FULL") => {
+            // FULL lists 10 entries by default, and everything with COUNT 0.
+            let full = match rest {
+                [] => 10,
+                [option, count] if option.eq_ignore_ascii_case(b"COUNT") => {
+                    usize::try_from(parse_integer(count)?).unwrap_or(0)
+                }
+                _ => return Err(syntax_error()),
+            };
+            XInfoCommand::Stream { key: key.clone(), full: Some(full) }
+        }
+        ("stream", [_, ..]) => return Err(syntax_error()),
+        ("groups", [key]) => XInfoCommand::Groups(key.clone()),
+        ("consumers", [key, group]) => XInfoCommand::Consumers { key: key.clone(), group: group.clone() },
+        ("stream" | "groups" | "consumers", _) => {
+            return Err(wrong_number_of_arguments(&format!("xinfo|{}", subcommand)))
+        }
+        _ => {
+            return Err(BifrostError::CommandError(format!(
+                "ERR unknown subcommand '{}'. Try XINFO HELP.",
+                String::from_utf8_lossy(&args[0])
+            )))
+        }
+    };
+    Ok(Box::new(command))
+}
+
+// CONFIG GET parameter [parameter ...]
+// CONFIG SET parameter value [parameter value ...]
+fn parse_config(args: &[Bytes]) -> Result<ConfigCommand, BifrostError> {
+    let subcommand = String::from_utf8_lossy(&args[0]).to_lowercase();
+    match (subcommand.as_str(), &args[1..]) {
+        ("get", patterns) if !patterns.is_empty() => Ok(ConfigCommand::Get(patterns.to_vec())),
+        ("set", pairs) if !pairs.is_empty() && pairs.len() % 2 == 0 => pairs
+            .chunks(2)
+            .map(|pair| parse_config_setting(&pair[0], &pair[1]))
+            .collect::<Result<_, _>>()
+            .map(ConfigCommand::Set),
+        ("get" | "set", _) => Err(wrong_number_of_arguments(&format!("config|{}", subcommand))),
+        _ => Err(BifrostError::CommandError(format!(
+            "ERR unknown subcommand '{}'. Try CONFIG HELP.",
+            String::from_utf8_lossy(&args[0])
+        ))),
+    }
+}
+
+fn parse_config_setting(name: &[u8], value: &[u8]) -> Result<ConfigSetting, BifrostError> {
+    let name = String::from_utf8_lossy(name).to_lowercase();
+    match name.as_str() {
+        "notify-keyspace-events" => EventClasses::parse(value)
+            .map(ConfigSetting::NotifyKeyspaceEvents)
+            .ok_or_else(|| BifrostError::CommandError(format!(
+                "ERR CONFIG SET failed (possibly related to argument '{}') - Invalid event class character. Use 'Ag$lshzxKEtn'.",
+                name
+            ))),
+        _ => Err(BifrostError::CommandError(format!(
+            "ERR Unknown option or number of arguments for CONFIG SET - '{}'",
+            name
+        ))),
+    }
+}
+
+// CLIENT ID
+// CLIENT TRACKING <ON | OFF> [REDIRECT client-id] [PREFIX prefix ...] [BCAST] [OPTIN]
+//   [OPTOUT] [NOLOOP]
+// CLIENT CACHING <YES | NO>
+// CLIENT GETREDIR
+fn parse_client(args: &[Bytes]) -> Result<ClientCommand, BifrostError> {
+    let subcommand = String::from_utf8_lossy(&args[0]).to_lowercase();
+    match (subcommand.as_str(), &args[1..]) {
+        ("id", []) => Ok(ClientCommand::Id),
+        ("getredir", []) => Ok(ClientCommand::GetRedir),
+        ("tracking", [switch, options @ ..]) => match String::from_utf8_lossy(switch).to_lowercase().as_str() {
+            "on" => parse_tracking_options(options).map(|options| ClientCommand::Tracking(Some(options))),
+            "off" => Ok(ClientCommand::Tracking(None)),
+            _ => Err(syntax_error()),
+        },
+        ("caching", [answer]) => match String::from_utf8_lossy(answer).to_lowercase().as_str() {
+            "yes" => Ok(ClientCommand::Caching(true)),
+            "no" => Ok(ClientCommand::Caching(false)),
+            _ => Err(syntax_error()),
+        },
+        ("id" | "getredir" | "tracking" | "caching", _) => {
+            Err(wrong_number_of_arguments(&format!("client|{}", subcommand)))
+        }
+        _ => Err(BifrostError::CommandError(format!(
+            "ERR unknown subcommand '{}'. Try CLIENT HELP.",
+            String::from_utf8_lossy(&args[0])
+        ))),
+    }
+}
+
+fn parse_tracking_options(args: &[Bytes]) -> Result<TrackingOptions, BifrostError> {
+    let mut options = TrackingOptions::default();
+    let mut args = args.iter();
+    while let Some(option) = args.next() {
+        match String::from_utf8_lossy(option).to_uppercase().as_str() {
+            "REDIRECT" => {
+                let id = parse_integer(args.next().ok_or_else(syntax_error)?)?;
+                options.redirect = Some(u64::try_from(id).map_err(|_| {
+                    BifrostError::CommandError(
+                        "ERR The client ID you want redirect to does not exist".to_string(),
+                    )
+                })?);
+            }
+            "PREFIX" => options.prefixes.push(args.next().ok_or_else(syntax_error)?.clone()),
+            "BCAST" => options.bcast = true,
+            "OPTIN" => options.optin = true,
+            "OPTOUT" => options.optout = true,
+            "NOLOOP" => options.noloop = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+    let invalid = |message: &str| Err(BifrostError::CommandError(format!("ERR {}", message)));
+    if !options.bcast && !options.prefixes.is_empty() {
+        return invalid("PREFIX option requires BCAST mode to be enabled");
+    }
+    if options.optin && options.optout {
+        return invalid("You can't use both OPTIN and OPTOUT options at the same time");
+    }
+    if options.bcast && (options.optin || options.optout) {
+        return invalid("OPTIN and OPTOUT are not compatible with BCAST");
+    }
+    Ok(options)
+}
+
+// numkeys key [key ...] arg [arg ...], as taken by EVAL and EVALSHA after the script.
+fn parse_script_arguments(args: &[Bytes]) -> Result<(Vec<Bytes>, Vec<Bytes>), BifrostError> {
+    let numkeys = parse_integer(&args[0])?;
+    let rest = &args[1..];
+    let numkeys = usize::try_from(numkeys)
+        .map_err(|_| BifrostError::CommandError("ERR Number of keys can't be negative".to_string()))?;
+    if numkeys > rest.len() {
+        return Err(BifrostError::CommandError(
+            "ERR Number of keys can't be greater than number of args".to_string(),
+        ));
+    }
+    Ok((rest[..numkeys].to_vec(), rest[numkeys..].to_vec()))
+}
+
+// SCRIPT LOAD script
+// SCRIPT EXISTS sha1 [sha1 ...]
+// SCRIPT FLUSH [ASYNC | SYNC]
+// SCRIPT KILL
+fn parse_script(args: &[Bytes]) -> Result<ScriptCommand, BifrostError> {
+    let subcommand = String::from_utf8_lossy(&args[0]).to_lowercase();
+    match (subcommand.as_str(), &args[1..]) {
+        ("load", [script]) => Ok(ScriptCommand::Load(script.clone())),
+        ("exists", shas) if !shas.is_empty() => Ok(ScriptCommand::Exists(
+            shas.iter().map(|sha| String::from_utf8_lossy(sha).to_lowercase()).collect(),
+        )),
+        ("flush", mode) if mode.len() <= 1 => {
+            parse_flush_mode(mode)?;
+            Ok(ScriptCommand::Flush)
+        }
+        ("kill", []) => Ok(ScriptCommand::Kill),
+        ("load" | "exists" | "flush" | "kill", _) => {
+            Err(wrong_number_of_arguments(&format!("script|{}", subcommand)))
+        }
+        _ => Err(BifrostError::CommandError(format!(
+            "ERR unknown subcommand '{}'. Try SCRIPT HELP.",
+            String::from_utf8_lossy(&args[0])
+        ))),
+    }
+}
+
+// PUBSUB CHANNELS [pattern]
+// PUBSUB NUMSUB [channel ...]
+// PUBSUB NUMPAT
+// PUBSUB SHARDCHANNELS [pattern]
+// PUBSUB SHARDNUMSUB [shardchannel ...]
+fn parse_pubsub(args: &[Bytes]) -> Result<PubSubCommand, BifrostError> {
+    let subcommand = String::from_utf8_lossy(&args[0]).to_lowercase();
+    match (subcommand.as_str(), &args[1..]) {
+        ("channels", []) => Ok(PubSubCommand::Channels(None)),
+        ("channels", [pattern]) => Ok(PubSubCommand::Channels(Some(pattern.clone()))),
+        ("numsub", channels) => Ok(PubSubCommand::NumSub(channels.to_vec())),
+        ("numpat", []) => Ok(PubSubCommand::NumPat),
+        ("shardchannels", []) => Ok(PubSubCommand::ShardChannels(None)),
+        ("shardchannels", [pattern]) => Ok(PubSubCommand::ShardChannels(Some(pattern.clone()))),
+        ("shardnumsub", channels) => Ok(PubSubCommand::ShardNumSub(channels.to_vec())),
+        ("channels" | "numpat" | "shardchannels", _) => Err(wrong_number_of_arguments(&format!("pubsub|{}", subcommand))),
+        _ => Err(BifrostError::CommandError(format!(
+            "ERR unknown subcommand '{}'. Try PUBSUB HELP.",
+            String::from_utf8_lossy(&args[0])
+        ))),
+    }
+}
+
+// Shard channels given together must belong to the same hash slot, as in Redis Cluster.
+fn same_slot(channels: Vec<Bytes>) -> Result<Vec<Bytes>, BifrostError> {
+    match channels.split_first() {
+        Some((first, rest)) if rest.iter().any(|channel| key_slot(channel) != key_slot(first)) => {
+            Err(BifrostError::CommandError(
+                "CROSSSLOT Keys in request don't hash to the same slot".to_string(),
+            ))
+        }
+        _ => Ok(channels),
+    }
+}
+
+// LPOP, RPOP, SPOP, ZPOPMIN and ZPOPMAX key [count]
+fn parse_pop_count(args: &[Bytes]) -> Result<Option<usize>, BifrostError> {
+    match args {
+        [_] => Ok(None),
+        [_, count] => parse_count(count).map(Some),
+        _ => Err(syntax_error()),
+    }
+}
+
+// LPOS key element [RANK rank] [COUNT num-matches] [MAXLEN len]
+fn parse_lpos_options(args: &[Bytes]) -> Result<LposOptions, BifrostError> {
+    let mut options = LposOptions::default();
+    let mut pairs = args.chunks(2);
+    for pair in &mut pairs {
+        let [option, value] = pair else {
+            return Err(syntax_error());
+        };
+        let value = parse_integer(value)?;
+        match String::from_utf8_lossy(option).to_uppercase().as_str() {
+            "RANK" if value == 0 => {
+                return Err(BifrostError::CommandError(
+                    "ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list".to_string(),
+                ))
+            }
+            "RANK" if value == i64::MIN => {
+                return Err(BifrostError::CommandError(
+                    "ERR value is out of range".to_string(),
+                ))
+            }
+            "RANK" => options.rank = value,
+            "COUNT" => {
+                options.count = Some(usize::try_from(value).map_err(|_| {
+                    BifrostError::CommandError("ERR COUNT can't be negative".to_string())
+                })?)
+            }
+            "MAXLEN" => {
+                options.max_len = usize::try_from(value).map_err(|_| {
+                    BifrostError::CommandError("ERR MAXLEN can't be negative".to_string())
+                })?
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok(options)
+}
+
+// SET key value [NX | XX] [EX seconds | PX milliseconds | EXAT unix-time-seconds |
+// PXAT unix-time-milliseconds | KEEPTTL]
+fn parse_set_options(args: &[RespType]) -> Result<SetOptions, BifrostError> {
+    let mut options = SetOptions::default();
+    let mut i = 0;
+    while i < args.len() {
+        let RespType::BulkString(option) = &args[i] else {
+            return Err(syntax_error());
+        };
+        let option = String::from_utf8_lossy(option).to_uppercase();
+        let has_expiry = options.expiration.is_some() || options.keep_ttl;
+        match option.as_str() {
+            "NX" if options.condition == SetCondition::Always => {
+                options.condition = SetCondition::IfNotExists;
+            }
+            "XX" if options.condition == SetCondition::Always => {
+                options.condition = SetCondition::IfExists;
+            }
+            "KEEPTTL" if !has_expiry => options.keep_ttl = true,
+            "EX" | "PX" | "EXAT" | "PXAT" if !has_expiry => {
+                i += 1;
+                let amount = parse_integer_arg(args.get(i))?;
+                options.expiration = Some(parse_expiration(&option, amount, "set")?);
+            }
+            _ => return Err(syntax_error()),
+        }
+        i += 1;
+    }
+    Ok(options)
+}
+
+// The EX, PX, EXAT or PXAT option of SET and GETEX, whose amount must be positive.
+fn parse_expiration(option: &str, amount: i64, name: &str) -> Result<Expiration, BifrostError> {
+    let invalid_expire_time = || {
+        BifrostError::CommandError(format!("ERR invalid expire time in '{}' command", name))
+    };
+    if amount <= 0 {
+        return Err(invalid_expire_time());
+    }
+    let amount = amount as u64;
+    let expiration = match option {
+        "EX" => Some(Expiration::In(Duration::from_secs(amount))),
+        "PX" => Some(Expiration::In(Duration::from_millis(amount))),
+        "EXAT" => SystemTime::UNIX_EPOCH
+            .checked_add(Duration::from_secs(amount))
+            .map(Expiration::At),
+        _ => SystemTime::UNIX_EPOCH
+            .checked_add(Duration::from_millis(amount))
+            .map(Expiration::At),
+    };
+    // Catch amounts too far ahead for the clock up front, rather than when they apply.
+    expiration
+        .filter(|expiration| expiration.deadline(Instant::now()).is_some())
+        .ok_or_else(invalid_expire_time)
+}
+
+// GETEX key [EX seconds | PX milliseconds | EXAT unix-time-seconds |
+// PXAT unix-time-milliseconds | PERSIST]
+fn parse_getex(args: &[Bytes]) -> Result<Box<dyn Command>, BifrostError> {
+    let expiry = match &args[1..] {
+        [] => None,
+        [option] if option.eq_ignore_ascii_case(b"PERSIST") => Some(GetExpiry::Persist),
+        [option, amount] => {
+            let option = String::from_utf8_lossy(option).to_uppercase();
+            if !matches!(option.as_str(), "EX" | "PX" | "EXAT" | "PXAT") {
+                return Err(syntax_error());
+            }
+            Some(GetExpiry::Set(parse_expiration(&option, parse_integer(amount)?, "getex")?))
+        }
+        _ => return Err(syntax_error()),
+    };
+    Ok(Box::new(GetExCommand { key: args[0].clone(), expiry }))
+}
+
+// MSET and MSETNX key value [key value ...]
+fn parse_pairs(args: &[Bytes], name: &str) -> Result<Vec<(Bytes, Bytes)>, BifrostError> {
+    if !args.len().is_multiple_of(2) {
+        return Err(wrong_number_of_arguments(name));
+    }
+    Ok(args.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect())
+}
+
+// Bit offsets address strings of up to 512MB.
+fn parse_bit_offset(arg: &[u8]) -> Result<u64, BifrostError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .filter(|offset| *offset < 1 << 32)
+        .ok_or_else(|| {
+            BifrostError::CommandError("ERR bit offset is not an integer or out of range".to_string())
+        })
+}
+
+fn parse_bit_unit(arg: &[u8]) -> Result<BitUnit, BifrostError> {
+    match String::from_utf8_lossy(arg).to_uppercase().as_str() {
+        "BYTE" => Ok(BitUnit::Byte),
+        "BIT" => Ok(BitUnit::Bit),
+        _ => Err(syntax_error()),
+    }
+}
+
+// BITPOS key bit [start [end [BYTE | BIT]]]
+fn parse_bitpos(args: &[Bytes]) -> Result<Box<dyn Command>, BifrostError> {
+    let bit = match parse_integer(&args[1])? {
+        0 => false,
+        1 => true,
+        _ => return Err(BifrostError::CommandError("ERR The bit argument must be 1 or 0.".to_string())),
+    };
+    let (start, end, unit) = match &args[2..] {
+        [] => (None, None, BitUnit::Byte),
+        [start] => (Some(parse_integer(start)?), None, BitUnit::Byte),
+        [start, end] => (Some(parse_integer(start)?), Some(parse_integer(end)?), BitUnit::Byte),
+        [start, end, unit] => (Some(parse_integer(start)?), Some(parse_integer(end)?), parse_bit_unit(unit)?),
+        _ => return Err(syntax_error()),
+    };
+    Ok(Box::new(BitPosCommand { key: args[0].clone(), bit, start, end, unit }))
+}
+
+// BITOP AND | OR | XOR | NOT destkey key [key ...]
+fn parse_bitop(args: &[Bytes]) -> Result<Box<dyn Command>, BifrostError> {
+    let operation = match String::from_utf8_lossy(&args[0]).to_uppercase().as_str() {
+        "AND" => BitOperation::And,
+        "OR" => BitOperation::Or,
+        "XOR" => BitOperation::Xor,
+        "NOT" => BitOperation::Not,
+        _ => return Err(syntax_error()),
+    };
+    if operation == BitOperation::Not && args.len() != 3 {
+        return Err(BifrostError::CommandError(
+            "ERR BITOP NOT must be called with a single source key.".to_string(),
+        ));
+    }
+    Ok(Box::new(BitOpCommand { operation, destination: args[1].clone(), keys: args[2..].to_vec() }))
+}
+
+// A BITFIELD type: i1 to i64 or u1 to u63.
+fn parse_bitfield_type(arg: &[u8]) -> Result<BitFieldType, BifrostError> {
+    let invalid = || {
+        BifrostError::CommandError(
+            "ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
+                .to_string(),
+        )
+    };
+    let (signed, bits) = match arg.split_first() {
+        Some((b'i' | b'I', bits)) => (true, bits),
+        Some((b'u' | b'U', bits)) => (false, bits),
+        _ => return Err(invalid()),
+    };
+    let bits = std::str::from_utf8(bits).ok().and_then(|bits| bits.parse::<u32>().ok()).ok_or_else(invalid)?;
+    let max = if signed { 64 } else { 63 };
+    if !(1..=max).contains(&bits) {
+        return Err(invalid());
+    }
+    Ok(BitFieldType { signed, bits })
+}
+
+// A BITFIELD offset in bits, or in multiples of the type's width when prefixed with `#`.
+fn parse_bitfield_offset(arg: &[u8], ty: BitFieldType) -> Result<u64, BifrostError> {
+    let (multiplier, offset) = match arg.strip_prefix(b"#") {
+        Some(offset) => (ty.bits as u64, offset),
+        None => (1, arg),
+    };
+    parse_bit_offset(offset)?
+        .checked_mul(multiplier)
+        .filter(|offset| offset + ty.bits as u64 <= 1 << 32)
+        .ok_or_else(|| {
+            BifrostError::CommandError("ERR bit offset is not an integer or out of range".to_string())
+        })
+}
+
+// BITFIELD key [GET type offset] [SET type offset value] [INCRBY type offset increment]
+// [OVERFLOW WRAP | SAT | FAIL] ...; BITFIELD_RO only accepts GET.
+fn parse_bitfield_ops(args: &[Bytes], read_only: bool) -> Result<Vec<BitFieldOp>, BifrostError> {
+    let mut ops = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let subcommand = String::from_utf8_lossy(&args[i]).to_uppercase();
+        if read_only && subcommand != "GET" {
+            return Err(BifrostError::CommandError(
+                "ERR BITFIELD_RO only supports the GET subcommand".to_string(),
+            ));
+        }
+        let arity = match subcommand.as_str() {
+            "GET" => 2,
+            "SET" | "INCRBY" => 3,
+            "OVERFLOW" => 1,
+            _ => return Err(syntax_error()),
+        };
+        let operands = args.get(i + 1..=i + arity).ok_or_else(syntax_error)?;
+        i += arity + 1;
+        if subcommand == "OVERFLOW" {
+            let overflow = match String::from_utf8_lossy(&operands[0]).to_uppercase().as_str() {
+                "WRAP" => Overflow::Wrap,
+                "SAT" => Overflow::Sat,
+                "FAIL" => Overflow::Fail,
+                _ => {
+                    return Err(BifrostError::CommandError(
+                        "ERR Invalid OVERFLOW type specified".to_string(),
+                    ))
+                }
+            };
+            ops.push(BitFieldOp::Overflow(overflow));
+            continue;
+        }
+        let ty = parse_bitfield_type(&operands[0])?;
+        let offset = parse_bitfield_offset(&operands[1], ty)?;
+        ops.push(match subcommand.as_str() {
+            "GET" => BitFieldOp::Get(ty, offset),
+            "SET" => BitFieldOp::Set(ty, offset, parse_integer(&operands[2])?),
+            _ => BitFieldOp::IncrBy(ty, offset, parse_integer(&operands[2])?),
+        });
+    }
+    Ok(ops)
+}
+
+fn parse_db_index(arg: &[u8]) -> Result<usize, BifrostError> {
+    usize::try_from(parse_integer(arg)?)
+        .map_err(|_| BifrostError::CommandError("ERR DB index is out of range".to_string()))
+}
+
+// FLUSHDB and FLUSHALL [ASYNC | SYNC]. Flushing is always synchronous.
+fn parse_flush_mode(args: &[Bytes]) -> Result<(), BifrostError> {
+    match args {
+        [] => Ok(()),
+        [mode] if mode.eq_ignore_ascii_case(b"ASYNC") || mode.eq_ignore_ascii_case(b"SYNC") => Ok(()),
+        _ => Err(syntax_error()),
+    }
+}
+
+// GEOADD key [NX | XX] [CH] longitude latitude member [longitude latitude member ...]
+fn parse_geoadd(args: &[Bytes]) -> Result<Box<dyn Command>, BifrostError> {
+    let mut options = ZaddOptions::default();
+    let (mut nx, mut xx) = (false, false);
+    let mut position = 1;
+    while let Some(arg) = args.get(position) {
+        match String::from_utf8_lossy(arg).to_uppercase().as_str() {
+            "NX" => nx = true,
+            "XX" => xx = true,
+            "CH" => options.changed = true,
+            _ => break,
+        }
+        position += 1;
+    }
+
+    let elements = &args[position..];
+    if elements.is_empty() || !elements.len().is_multiple_of(3) {
+        return Err(syntax_error());
+    }
+    if nx && xx {
+        return Err(BifrostError::CommandError(
+            "ERR XX and NX options at the same time are not compatible".to_string(),
+        ));
+    }
+    options.condition = match (nx, xx) {
+        (true, _) => SetCondition::IfNotExists,
+        (_, true) => SetCondition::IfExists,
+        _ => SetCondition::Always,
+    };
+    let positions = elements
+        .chunks(3)
+        .map(|chunk| Ok((parse_float(&chunk[0])?, parse_float(&chunk[1])?, chunk[2].clone())))
+        .collect::<Result<Vec<_>, BifrostError>>()?;
+    Ok(Box::new(GeoAddCommand { key: args[0].clone(), positions, options }))
+}
+
+fn parse_distance_unit(arg: &[u8]) -> Result<DistanceUnit, BifrostError> {
+    match String::from_utf8_lossy(arg).to_lowercase().as_str() {
+        "m" => Ok(DistanceUnit::Meters),
+        "km" => Ok(DistanceUnit::Kilometers),
+        "ft" => Ok(DistanceUnit::Feet),
+        "mi" => Ok(DistanceUnit::Miles),
+        _ => Err(BifrostError::CommandError(
+            "ERR unsupported unit provided. please use M, KM, FT, MI".to_string(),
+        )),
+    }
+}
+
+// A non-negative distance argument of GEOSEARCH, such as the radius.
+fn parse_geo_distance(arg: &[u8], what: &str) -> Result<f64, BifrostError> {
+    parse_float(arg).map_err(|_| BifrostError::CommandError(format!("ERR need numeric {}", what)))
+}
+
+// The arguments of GEOSEARCH and GEOSEARCHSTORE after the keys:
+// FROMMEMBER member | FROMLONLAT longitude latitude
+// BYRADIUS radius unit | BYBOX width height unit
+// [ASC | DESC] [COUNT count [ANY]] [WITHCOORD] [WITHDIST] [WITHHASH] [STOREDIST]
+// Returns the search and whether STOREDIST was given.
+fn parse_geosearch(args: &[Bytes], name: &str) -> Result<(GeoSearch, bool), BifrostError> {
+    let store = name == "geosearchstore";
+    let mut origin = None;
+    let mut shape = None;
+    let mut unit = DistanceUnit::Meters;
+    let mut order = GeoOrder::Unsorted;
+    let mut count = None;
+    let mut any = false;
+    let (mut with_coord, mut with_dist, mut with_hash, mut store_dist) = (false, false, false, false);
+    let one_origin = || {
+        BifrostError::CommandError(format!(
+            "ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for {}",
+            name
+        ))
+    };
+    let one_shape = || {
+        BifrostError::CommandError(format!(
+            "ERR exactly one of BYRADIUS and BYBOX can be specified for {}",
+            name
+        ))
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        let option = String::from_utf8_lossy(&args[i]).to_uppercase();
+        let operands = |n: usize| args.get(i + 1..=i + n).ok_or_else(syntax_error);
+        match option.as_str() {
+            "FROMMEMBER" => {
+                let operands = operands(1)?;
+                if origin.is_some() {
+                    return Err(one_origin());
+                }
+                origin = Some(GeoOrigin::Member(operands[0].clone()));
+                i += 1;
+            }
+            "FROMLONLAT" => {
+                let operands = operands(2)?;
+                if origin.is_some() {
+                    return Err(one_origin());
+                }
+                let (longitude, latitude) = (parse_float(&operands[0])?, parse_float(&operands[1])?);
+                check_coordinates(longitude, latitude)?;
+                origin = Some(GeoOrigin::LonLat(longitude, latitude));
+                i += 2;
+            }
+            "BYRADIUS" => {
+                let operands = operands(2)?;
+                if shape.is_some() {
+                    return Err(one_shape());
+                }
+                let radius = parse_geo_distance(&operands[0], "radius")?;
+                if radius < 0.0 {
+                    return Err(BifrostError::CommandError("ERR radius cannot be negative".to_string()));
+                }
+                shape = Some(GeoShape::Radius(radius));
+                unit = parse_distance_unit(&operands[1])?;
+                i += 2;
+            }
+            "BYBOX" => {
+                let operands = operands(3)?;
+                if shape.is_some() {
+                    return Err(one_shape());
+                }
+                let width = parse_geo_distance(&operands[0], "width")?;
+                let height = parse_geo_distance(&operands[1], "height")?;
+                if width < 0.0 || height < 0.0 {
+                    return Err(BifrostError::CommandError(
+                        "ERR height or width cannot be negative".to_string(),
+                    ));
+                }
+                shape = Some(GeoShape::Box(width, height));
+                unit = parse_distance_unit(&operands[2])?;
+                i += 3;
+            }
+            "ASC" => order = GeoOrder::Ascending,
+            "DESC" => order = GeoOrder::Descending,
+            "COUNT" => {
+                let operands = operands(1)?;
+                let n = parse_integer(&operands[0])?;
+                if n <= 0 {
+                    return Err(BifrostError::CommandError("ERR COUNT must be > 0".to_string()));
+                }
+                count = Some(n as usize);
+                i += 1;
+            }
+            "ANY" => any = true,
+            "WITHCOORD" => with_coord = true,
+            "WITHDIST" => with_dist = true,
+            "WITHHASH" => with_hash = true,
+            "STOREDIST" if store => store_dist = true,
+            _ => return Err(syntax_error()),
+        }
+        i += 1;
+    }
+
+    if store && (with_coord || with_dist || with_hash) {
+        return Err(BifrostError::CommandError(
+            "ERR GEOSEARCHSTORE is not compatible with WITHDIST, WITHHASH and WITHCOORD options".to_string(),
+        ));
+    }
+    let origin = origin.ok_or_else(one_origin)?;
+    let shape = shape.ok_or_else(one_shape)?;
+    if any && count.is_none() {
+        return Err(BifrostError::CommandError(
+            "ERR the ANY argument requires COUNT argument".to_string(),
+        ));
+    }
+    let search = GeoSearch {
+        origin,
+        shape,
+        unit,
+        order,
+        count: count.map(|count| (count, any)),
+        with_coord,
+        with_dist,
+        with_hash,
+    };
+    Ok((search, store_dist))
+}
+
+// EXPIRE key seconds [NX | XX | GT | LT], and likewise for PEXPIRE, EXPIREAT and PEXPIREAT.
+// `to_expiration` turns the non-negative amount into an expiration in the command's unit.
+fn parse_expire(
+    array: &[RespType],
+    name: &'static str,
+    to_expiration: impl Fn(u64) -> Option<Expiration>,
+) -> Result<Box<dyn Command>, BifrostError> {
+    let Some(RespType::BulkString(key)) = array.get(1) else {
+        return Err(BifrostError::CommandError(format!(
+            "ERR wrong number of arguments for '{}' command",
+            name
+        )));
+    };
+    if array.len() < 3 {
+        return Err(BifrostError::CommandError(format!(
+            "ERR wrong number of arguments for '{}' command",
+            name
+        )));
+    }
+
+    let invalid_expire_time = || {
+        BifrostError::CommandError(format!("ERR invalid expire time in '{}' command", name))
+    };
+    let amount = parse_integer_arg(array.get(2))?;
+    // Negative amounts are valid and expire the key immediately.
+    let expiration = if amount < 0 {
+        Expiration::At(SystemTime::UNIX_EPOCH)
+    } else {
+        to_expiration(amount as u64)
+            .filter(|expiration| expiration.deadline(Instant::now()).is_some())
+            .ok_or_else(invalid_expire_time)?
+    };
+
+    let mut condition = ExpireCondition::Always;
+    for arg in &array[3..] {
+        let RespType::BulkString(option) = arg else {
+            return Err(syntax_error());
+        };
+        let requested = match String::from_utf8_lossy(option).to_uppercase().as_str() {
+            "NX" => ExpireCondition::IfNoExpiry,
+            "XX" => ExpireCondition::IfHasExpiry,
+            "GT" => ExpireCondition::IfGreater,
+            "LT" => ExpireCondition::IfLess,
+            _ => {
+                return Err(BifrostError::CommandError(format!(
+                    "ERR Unsupported option {}",
+                    String::from_utf8_lossy(option)
+                )))
+            }
+        };
+        condition = match (condition, requested) {
+            (ExpireCondition::Always, requested) => requested,
+            (current, requested) if current == requested => current,
+            (ExpireCondition::IfHasExpiry, ExpireCondition::IfGreater | ExpireCondition::IfLess) => requested,
+            (ExpireCondition::IfGreater | ExpireCondition::IfLess, ExpireCondition::IfHasExpiry) => condition,
+            (ExpireCondition::IfGreater, ExpireCondition::IfLess) | (ExpireCondition::IfLess, ExpireCondition::IfGreater) => {
+                return Err(BifrostError::CommandError(
+                    "ERR GT and LT options at the same time are not compatible".to_string(),
+                ))
+            }
+            _ => {
+                return Err(BifrostError::CommandError(
+                    "ERR NX and XX, GT or LT options at the same time are not compatible".to_string(),
+                ))
+            }
+        };
+    }
+
+    Ok(Box::new(ExpireCommand {
+        key: key.clone(),
+        expiration,
+        condition,
+        name,
+    }))
+}
+
+// HELLO [protover [AUTH username password] [SETNAME clientname]]
+fn parse_hello(array: &[RespType]) -> Result<Box<dyn ConnectionCommand>, BifrostError> {
+    let mut hello = HelloCommand {
+        protocol: None,
+        auth: None,
+        name: None,
+    };
+    if array.len() < 2 {
+        return Ok(Box::new(hello));
+    }
+
+    let version = parse_integer_arg(array.get(1)).map_err(|_| {
+        BifrostError::CommandError(
+            "ERR Protocol version is not an integer or out of range".to_string(),
+        )
+    })?;
+    hello.protocol = match version {
+        2 => Some(ProtocolVersion::Resp2),
+        3 => Some(ProtocolVersion::Resp3),
+        _ => {
+            return Err(BifrostError::CommandError(
+                "NOPROTO unsupported protocol version".to_string(),
+            ))
+        }
+    };
+
+    let mut i = 2;
+    while i < array.len() {
+        let RespType::BulkString(option) = &array[i] else {
+            return Err(syntax_error());
+        };
+        match String::from_utf8_lossy(option).to_uppercase().as_str() {
+            "AUTH" => match (array.get(i + 1), array.get(i + 2)) {
+                (Some(RespType::BulkString(username)), Some(RespType::BulkString(password))) => {
+                    hello.auth = Some((username.clone(), password.clone()));
+                    i += 3;
+                }
+                _ => return Err(syntax_error()),
+            },
+            "SETNAME" => match array.get(i + 1) {
+                Some(RespType::BulkString(name)) => {
+                    if name.iter().any(|b| *b <= b' ' || *b > b'~') {
+                        return Err(BifrostError::CommandError(
+                            "ERR Client names cannot contain spaces, newlines or special characters."
+                                .to_string(),
+                        ));
+                    }
+                    hello.name = Some(name.clone());
+                    i += 2;
+                }
+                _ => return Err(syntax_error()),
+            },
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok(Box::new(hello))
+}
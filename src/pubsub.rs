@@ -0,0 +1,569 @@
+use bytes::Bytes;
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio_util::sync::CancellationToken;
+
+use crate::resp::{ProtocolVersion, RespType};
+use crate::slot::key_slot;
+use crate::storage::scan::glob_match;
+
+/// How many messages may wait to be written to a subscriber before it is disconnected
+/// for not keeping up, like the pubsub client output buffer limit of Redis.
+pub const SUBSCRIBER_BACKLOG: usize = 4096;
+
+/// The channel a RESP2 connection subscribes to in order to receive the invalidations
+/// of the clients that redirect their CLIENT TRACKING to it.
+pub const INVALIDATION_CHANNEL: &[u8] = b"__redis__:invalidate";
+
+/// The commands that publish messages, manage a connection's subscriptions and
+/// inspect the broker.
+pub enum PubSubCommand {
+    Subscribe(Vec<Bytes>),
+    Unsubscribe(Vec<Bytes>),
+    PSubscribe(Vec<Bytes>),
+    PUnsubscribe(Vec<Bytes>),
+    SSubscribe(Vec<Bytes>),
+    SUnsubscribe(Vec<Bytes>),
+    Publish { channel: Bytes, message: Bytes },
+    SPublish { channel: Bytes, message: Bytes },
+    Channels(Option<Bytes>),
+    NumSub(Vec<Bytes>),
+    NumPat,
+    ShardChannels(Option<Bytes>),
+    ShardNumSub(Vec<Bytes>),
+}
+
+// What a subscription is to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Channel,
+    Pattern,
+    ShardChannel,
+}
+
+impl Kind {
+    fn subscribe(self) -> &'static str {
+        match self {
+            Kind::Channel => "subscribe",
+            Kind::Pattern => "psubscribe",
+            Kind::ShardChannel => "ssubscribe",
+        }
+    }
+
+    fn unsubscribe(self) -> &'static str {
+        match self {
+            Kind::Channel => "unsubscribe",
+            Kind::Pattern => "punsubscribe",
+            Kind::ShardChannel => "sunsubscribe",
+        }
+    }
+}
+
+// The queue a connection's messages are pushed to, and the token cancelled when the
+// queue overflows.
+#[derive(Debug, Clone)]
+struct Subscriber {
+    sender: mpsc::Sender<RespType>,
+    evicted: CancellationToken,
+}
+
+impl Subscriber {
+    // Queues a message without waiting. A subscriber that lets its queue fill up is
+    // evicted rather than slowing down the publisher or growing without bound.
+    fn push(&self, message: RespType) {
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(message) {
+            self.evicted.cancel();
+        }
+    }
+}
+
+// Subscribers by channel or pattern, keyed by client ID.
+type Subscribers = HashMap<Bytes, HashMap<u64, Subscriber>>;
+
+#[derive(Debug, Default)]
+struct Registry {
+    channels: Subscribers,
+    patterns: Subscribers,
+    // Shard channels, partitioned by hash slot so that a message only ever reaches the
+    // subscribers of the slot its channel belongs to.
+    shards: HashMap<u16, Subscribers>,
+    // The queue of every connection, for messages addressed to a single client.
+    clients: HashMap<u64, Subscriber>,
+}
+
+impl Registry {
+    fn add(&mut self, kind: Kind, name: &Bytes, id: u64, subscriber: &Subscriber) {
+        let subscribers = match kind {
+            Kind::Channel => &mut self.channels,
+            Kind::Pattern => &mut self.patterns,
+            Kind::ShardChannel => self.shards.entry(key_slot(name)).or_default(),
+        };
+        subscribers.entry(name.clone()).or_default().insert(id, subscriber.clone());
+    }
+
+    fn remove(&mut self, kind: Kind, name: &[u8], id: u64) {
+        let slot = key_slot(name);
+        let Some(subscribers) = (match kind {
+            Kind::Channel => Some(&mut self.channels),
+            Kind::Pattern => Some(&mut self.patterns),
+            Kind::ShardChannel => self.shards.get_mut(&slot),
+        }) else {
+            return;
+        };
+        if let Some(clients) = subscribers.get_mut(name) {
+            clients.remove(&id);
+            if clients.is_empty() {
+                subscribers.remove(name);
+            }
+        }
+        if subscribers.is_empty() && kind == Kind::ShardChannel {
+            self.shards.remove(&slot);
+        }
+    }
+
+    fn shard_channel(&self, channel: &[u8]) -> Option<&HashMap<u64, Subscriber>> {
+        self.shards.get(&key_slot(channel))?.get(channel)
+    }
+}
+
+/// Routes published messages to the connections subscribed to their channel, either
+/// by name or through a glob-style pattern, and shard messages to the subscribers of
+/// their shard channel. Shared by every connection.
+#[derive(Debug, Clone, Default)]
+pub struct Broker {
+    registry: Arc<RwLock<Registry>>,
+}
+
+impl Broker {
+    /// Runs a pub/sub command that needs no subscriptions of its own: PUBLISH, SPUBLISH
+    /// and the PUBSUB subcommands. Returns `None` for the others.
+    pub fn execute(&self, command: PubSubCommand) -> Option<RespType> {
+        match command {
+            PubSubCommand::Publish { channel, message } => {
+                Some(RespType::Integer(self.publish(&channel, &message) as i64))
+            }
+            PubSubCommand::SPublish { channel, message } => {
+                Some(RespType::Integer(self.spublish(&channel, &message) as i64))
+            }
+            PubSubCommand::Channels(pattern) => {
+                let channels = self.channels(pattern.as_deref());
+                Some(RespType::Array(channels.into_iter().map(RespType::BulkString).collect()))
+            }
+            PubSubCommand::NumSub(channels) => {
+                let counts = channels
+                    .into_iter()
+                    .flat_map(|channel| {
+                        let count = self.numsub(&channel) as i64;
+                        [RespType::BulkString(channel), RespType::Integer(count)]
+                    })
+                    .collect();
+                Some(RespType::Array(counts))
+            }
+            PubSubCommand::NumPat => Some(RespType::Integer(self.numpat() as i64)),
+            PubSubCommand::ShardChannels(pattern) => {
+                let channels = self.shard_channels(pattern.as_deref());
+                Some(RespType::Array(channels.into_iter().map(RespType::BulkString).collect()))
+            }
+            PubSubCommand::ShardNumSub(channels) => {
+                let counts = channels
+                    .into_iter()
+                    .flat_map(|channel| {
+                        let count = self.shard_numsub(&channel) as i64;
+                        [RespType::BulkString(channel), RespType::Integer(count)]
+                    })
+                    .collect();
+                Some(RespType::Array(counts))
+            }
+            PubSubCommand::Subscribe(_)
+            | PubSubCommand::PSubscribe(_)
+            | PubSubCommand::SSubscribe(_)
+            | PubSubCommand::Unsubscribe(_)
+            | PubSubCommand::PUnsubscribe(_)
+            | PubSubCommand::SUnsubscribe(_) => None,
+        }
+    }
+
+    /// PUBLISH. Returns how many subscriptions the message was delivered to.
+    pub fn publish(&self, channel: &Bytes, message: &Bytes) -> usize {
+        let registry = self.registry.read();
+        let mut receivers = 0;
+        for subscriber in registry.channels.get(channel).into_iter().flat_map(HashMap::values) {
+            subscriber.push(RespType::Push(vec![
+                bulk("message"),
+                RespType::BulkString(channel.clone()),
+                RespType::BulkString(message.clone()),
+            ]));
+            receivers += 1;
+        }
+        for (pattern, subscribers) in &registry.patterns {
+            if !glob_match(pattern, channel) {
+                continue;
+            }
+            for subscriber in subscribers.values() {
+                subscriber.push(RespType::Push(vec![
+                    bulk("pmessage"),
+                    RespType::BulkString(pattern.clone()),
+                    RespType::BulkString(channel.clone()),
+                    RespType::BulkString(message.clone()),
+                ]));
+                receivers += 1;
+            }
+        }
+        receivers
+    }
+
+    /// SPUBLISH. Only the subscribers of the channel's slot are considered. Returns how
+    /// many the message was delivered to.
+    pub fn spublish(&self, channel: &Bytes, message: &Bytes) -> usize {
+        let registry = self.registry.read();
+        let Some(subscribers) = registry.shard_channel(channel) else {
+            return 0;
+        };
+        for subscriber in subscribers.values() {
+            subscriber.push(RespType::Push(vec![
+                bulk("smessage"),
+                RespType::BulkString(channel.clone()),
+                RespType::BulkString(message.clone()),
+            ]));
+        }
+        subscribers.len()
+    }
+
+    /// PUBSUB CHANNELS. The channels with at least one subscriber, optionally only
+    /// those matching a glob-style pattern.
+    pub fn channels(&self, pattern: Option<&[u8]>) -> Vec<Bytes> {
+        let registry = self.registry.read();
+        registry
+            .channels
+            .keys()
+            .filter(|channel| pattern.is_none_or(|pattern| glob_match(pattern, channel)))
+            .cloned()
+            .collect()
+    }
+
+    /// PUBSUB NUMSUB for a single channel.
+    pub fn numsub(&self, channel: &[u8]) -> usize {
+        self.registry.read().channels.get(channel).map_or(0, HashMap::len)
+    }
+
+    /// PUBSUB NUMPAT. The number of distinct patterns subscribed to.
+    pub fn numpat(&self) -> usize {
+        self.registry.read().patterns.len()
+    }
+
+    /// PUBSUB SHARDCHANNELS. Like [`Broker::channels`] for shard channels.
+    pub fn shard_channels(&self, pattern: Option<&[u8]>) -> Vec<Bytes> {
+        let registry = self.registry.read();
+        registry
+            .shards
+            .values()
+            .flat_map(HashMap::keys)
+            .filter(|channel| pattern.is_none_or(|pattern| glob_match(pattern, channel)))
+            .cloned()
+            .collect()
+    }
+
+    /// PUBSUB SHARDNUMSUB for a single shard channel.
+    pub fn shard_numsub(&self, channel: &[u8]) -> usize {
+        self.registry.read().shard_channel(channel).map_or(0, HashMap::len)
+    }
+
+    /// Whether a connection with this client ID is open.
+    pub fn has_client(&self, id: u64) -> bool {
+        self.registry.read().clients.contains_key(&id)
+    }
+
+    /// Queues a message for a single connection, whether or not it subscribed to
+    /// anything. Returns false if no connection has this client ID.
+    pub fn send_to(&self, id: u64, message: RespType) -> bool {
+        match self.registry.read().clients.get(&id) {
+            Some(subscriber) => {
+                subscriber.push(message);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn bulk(value: &'static str) -> RespType {
+    RespType::BulkString(Bytes::from_static(value.as_bytes()))
+}
+
+/// The channels and patterns a connection subscribed to, and the queue their messages
+/// arrive on. Dropping it unsubscribes from everything.
+pub struct Subscriptions {
+    id: u64,
+    broker: Broker,
+    channels: HashSet<Bytes>,
+    patterns: HashSet<Bytes>,
+    shard_channels: HashSet<Bytes>,
+    subscriber: Subscriber,
+    receiver: mpsc::Receiver<RespType>,
+}
+
+impl Subscriptions {
+    pub fn new(id: u64, broker: Broker) -> Self {
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_BACKLOG);
+        let subscriber = Subscriber { sender, evicted: CancellationToken::new() };
+        broker.registry.write().clients.insert(id, subscriber.clone());
+        Subscriptions {
+            id,
+            broker,
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+            shard_channels: HashSet::new(),
+            subscriber,
+            receiver,
+        }
+    }
+
+    /// How many channels, patterns and shard channels the connection is subscribed to.
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len() + self.shard_channels.len()
+    }
+
+    fn subscribed(&mut self, kind: Kind) -> &mut HashSet<Bytes> {
+        match kind {
+            Kind::Channel => &mut self.channels,
+            Kind::Pattern => &mut self.patterns,
+            Kind::ShardChannel => &mut self.shard_channels,
+        }
+    }
+
+    // The count subscription replies carry. Shard channels are counted apart from
+    // channels and patterns, as in Redis.
+    fn reply_count(&self, kind: Kind) -> i64 {
+        match kind {
+            Kind::ShardChannel => self.shard_channels.len() as i64,
+            _ => (self.channels.len() + self.patterns.len()) as i64,
+        }
+    }
+
+    /// Whether the connection is subscribed to anything, which in RESP2 restricts the
+    /// commands it may send.
+    pub fn is_active(&self) -> bool {
+        self.count() > 0
+    }
+
+    /// Waits for the next message published to the connection. Returns `None` once the
+    /// connection fell too far behind and should be closed.
+    pub async fn next_message(&mut self) -> Option<RespType> {
+        tokio::select! {
+            biased;
+            _ = self.subscriber.evicted.cancelled() => None,
+            message = self.receiver.recv() => message,
+        }
+    }
+
+    /// Shapes a queued message for the connection's protocol, or returns `None` if it
+    /// cannot be delivered. RESP2 has no pushes, so invalidations arrive as messages on
+    /// [`INVALIDATION_CHANNEL`], and only once the connection subscribed to it.
+    pub fn deliverable(&self, message: RespType, protocol: ProtocolVersion) -> Option<RespType> {
+        if protocol == ProtocolVersion::Resp3 {
+            return Some(message);
+        }
+        let RespType::Push(mut items) = message else {
+            return Some(message);
+        };
+        match items.first() {
+            Some(RespType::BulkString(kind)) if kind.as_ref() == b"invalidate" => {
+                let keys = items.pop()?;
+                let channel = RespType::BulkString(Bytes::from_static(INVALIDATION_CHANNEL));
+                self.channels
+                    .contains(INVALIDATION_CHANNEL)
+                    .then(|| RespType::Push(vec![bulk("message"), channel, keys]))
+            }
+            Some(RespType::BulkString(kind)) if kind.as_ref() == b"tracking-redir-broken" => None,
+            _ => Some(RespType::Push(items)),
+        }
+    }
+
+    /// Runs a pub/sub command. Subscribing and unsubscribing reply once per channel or
+    /// pattern, so this returns every reply to send.
+    pub fn execute(&mut self, command: PubSubCommand) -> Vec<RespType> {
+        match command {
+            PubSubCommand::Subscribe(channels) => self.subscribe(channels, Kind::Channel),
+            PubSubCommand::PSubscribe(patterns) => self.subscribe(patterns, Kind::Pattern),
+            PubSubCommand::SSubscribe(channels) => self.subscribe(channels, Kind::ShardChannel),
+            PubSubCommand::Unsubscribe(channels) => self.unsubscribe(channels, Kind::Channel),
+            PubSubCommand::PUnsubscribe(patterns) => self.unsubscribe(patterns, Kind::Pattern),
+            PubSubCommand::SUnsubscribe(channels) => self.unsubscribe(channels, Kind::ShardChannel),
+            command => self.broker.execute(command).into_iter().collect(),
+        }
+    }
+
+    fn subscribe(&mut self, names: Vec<Bytes>, kind: Kind) -> Vec<RespType> {
+        let registry = Arc::clone(&self.broker.registry);
+        let mut registry = registry.write();
+        names
+            .into_iter()
+            .map(|name| {
+                if self.subscribed(kind).insert(name.clone()) {
+                    registry.add(kind, &name, self.id, &self.subscriber);
+                }
+                let count = self.reply_count(kind);
+                RespType::Push(vec![bulk(kind.subscribe()), RespType::BulkString(name), RespType::Integer(count)])
+            })
+            .collect()
+    }
+
+    // Without any names, unsubscribes from everything of that kind. Replies even if
+    // there was nothing to unsubscribe from.
+    fn unsubscribe(&mut self, names: Vec<Bytes>, kind: Kind) -> Vec<RespType> {
+        let names = if names.is_empty() { self.subscribed(kind).iter().cloned().collect() } else { names };
+        if names.is_empty() {
+            let count = self.reply_count(kind);
+            return vec![RespType::Push(vec![bulk(kind.unsubscribe()), RespType::Null, RespType::Integer(count)])];
+        }
+
+        let registry = Arc::clone(&self.broker.registry);
+        let mut registry = registry.write();
+        names
+            .into_iter()
+            .map(|name| {
+                if self.subscribed(kind).remove(&name) {
+                    registry.remove(kind, &name, self.id);
+                }
+                let count = self.reply_count(kind);
+                RespType::Push(vec![bulk(kind.unsubscribe()), RespType::BulkString(name), RespType::Integer(count)])
+            })
+            .collect()
+    }
+}
+
+impl Drop for Subscriptions {
+    fn drop(&mut self) {
+        let mut registry = self.broker.registry.write();
+        registry.clients.remove(&self.id);
+        for channel in &self.channels {
+            registry.remove(Kind::Channel, channel, self.id);
+        }
+        for pattern in &self.patterns {
+            registry.remove(Kind::Pattern, pattern, self.id);
+        }
+        for channel in &self.shard_channels {
+            registry.remove(Kind::ShardChannel, channel, self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&'static str]) -> Vec<Bytes> {
+        names.iter().map(|name| Bytes::from_static(name.as_bytes())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_subscribe() {
+        let broker = Broker::default();
+        let mut first = Subscriptions::new(1, broker.clone());
+        let mut second = Subscriptions::new(2, broker.clone());
+
+        let replies = first.execute(PubSubCommand::Subscribe(names(&["news", "sport"])));
+        assert_eq!(replies[1], RespType::Push(vec![bulk("subscribe"), bulk("sport"), RespType::Integer(2)]));
+        second.execute(PubSubCommand::PSubscribe(names(&["n*"])));
+
+        assert_eq!(broker.publish(&Bytes::from("news"), &Bytes::from("hello")), 2);
+        assert_eq!(broker.publish(&Bytes::from("weather"), &Bytes::from("rain")), 0);
+        assert_eq!(
+            first.next_message().await,
+            Some(RespType::Push(vec![bulk("message"), bulk("news"), bulk("hello")]))
+        );
+        assert_eq!(
+            second.next_message().await,
+            Some(RespType::Push(vec![bulk("pmessage"), bulk("n*"), bulk("news"), bulk("hello")]))
+        );
+
+        let mut channels = broker.channels(None);
+        channels.sort();
+        assert_eq!(channels, names(&["news", "sport"]));
+        assert_eq!(broker.channels(Some(b"s*")), names(&["sport"]));
+        assert_eq!(broker.numsub(b"news"), 1);
+        assert_eq!(broker.numpat(), 1);
+
+        drop(second);
+        assert_eq!(broker.numpat(), 0);
+    }
+
+    #[test]
+    fn test_unsubscribe() {
+        let broker = Broker::default();
+        let mut subscriptions = Subscriptions::new(1, broker.clone());
+        assert_eq!(
+            subscriptions.execute(PubSubCommand::Unsubscribe(Vec::new())),
+            vec![RespType::Push(vec![bulk("unsubscribe"), RespType::Null, RespType::Integer(0)])]
+        );
+
+        subscriptions.execute(PubSubCommand::Subscribe(names(&["a", "b"])));
+        subscriptions.execute(PubSubCommand::PSubscribe(names(&["c*"])));
+        assert_eq!(subscriptions.execute(PubSubCommand::Unsubscribe(Vec::new())).len(), 2);
+        assert_eq!(subscriptions.count(), 1);
+        assert_eq!(broker.numsub(b"a"), 0);
+        assert!(broker.channels(None).is_empty());
+        assert!(subscriptions.is_active());
+    }
+
+    #[tokio::test]
+    async fn test_shard_channels() {
+        let broker = Broker::default();
+        let mut subscriptions = Subscriptions::new(1, broker.clone());
+        subscriptions.execute(PubSubCommand::Subscribe(names(&["news"])));
+        let replies = subscriptions.execute(PubSubCommand::SSubscribe(names(&["{user}.a", "{user}.b"])));
+        assert_eq!(replies[1], RespType::Push(vec![bulk("ssubscribe"), bulk("{user}.b"), RespType::Integer(2)]));
+
+        // Shard channels and regular channels of the same name are separate.
+        assert_eq!(broker.publish(&Bytes::from("{user}.a"), &Bytes::from("hello")), 0);
+        assert_eq!(broker.spublish(&Bytes::from("news"), &Bytes::from("hello")), 0);
+        assert_eq!(broker.spublish(&Bytes::from("{user}.a"), &Bytes::from("hello")), 1);
+        assert_eq!(
+            subscriptions.next_message().await,
+            Some(RespType::Push(vec![bulk("smessage"), bulk("{user}.a"), bulk("hello")]))
+        );
+        assert_eq!(broker.shard_numsub(b"{user}.b"), 1);
+        assert_eq!(broker.channels(None), names(&["news"]));
+
+        assert_eq!(subscriptions.execute(PubSubCommand::SUnsubscribe(Vec::new())).len(), 2);
+        assert!(broker.shard_channels(None).is_empty());
+        assert!(broker.registry.read().shards.is_empty());
+        assert_eq!(subscriptions.count(), 1);
+    }
+
+    #[test]
+    fn test_invalidations_in_resp2() {
+        let broker = Broker::default();
+        let mut subscriptions = Subscriptions::new(1, broker.clone());
+        assert!(broker.has_client(1));
+        let keys = RespType::Array(vec![bulk("key")]);
+        let invalidation = RespType::Push(vec![bulk("invalidate"), keys.clone()]);
+        assert_eq!(subscriptions.deliverable(invalidation.clone(), ProtocolVersion::Resp2), None);
+        assert_eq!(
+            subscriptions.deliverable(invalidation.clone(), ProtocolVersion::Resp3),
+            Some(invalidation.clone())
+        );
+
+        subscriptions.execute(PubSubCommand::Subscribe(names(&["__redis__:invalidate"])));
+        assert_eq!(
+            subscriptions.deliverable(invalidation, ProtocolVersion::Resp2),
+            Some(RespType::Push(vec![bulk("message"), bulk("__redis__:invalidate"), keys]))
+        );
+        drop(subscriptions);
+        assert!(!broker.has_client(1));
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_is_evicted() {
+        let broker = Broker::default();
+        let mut subscriptions = Subscriptions::new(1, broker.clone());
+        subscriptions.execute(PubSubCommand::Subscribe(names(&["news"])));
+        for _ in 0..=SUBSCRIBER_BACKLOG {
+            broker.publish(&Bytes::from("news"), &Bytes::from("hello"));
+        }
+        assert_eq!(subscriptions.next_message().await, None);
+    }
+}
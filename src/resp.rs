@@ -1,49 +1,173 @@
-use bytes::{Bytes, BytesMut};
-
-const STRING: u8 = b'+';
-const ERROR: u8 = b'-';
-const INTEGER: u8 = b':';
-const BULK: u8 = b'$';
-const ARRAY: u8 = b'*';
+use bytes::Bytes;
+
+pub(crate) const STRING: u8 = b'+';
+pub(crate) const ERROR: u8 = b'-';
+pub(crate) const INTEGER: u8 = b':';
+pub(crate) const BULK: u8 = b'$';
+pub(crate) const ARRAY: u8 = b'*';
+pub(crate) const NULL: u8 = b'_';
+pub(crate) const BOOLEAN: u8 = b'#';
+pub(crate) const DOUBLE: u8 = b',';
+pub(crate) const BIG_NUMBER: u8 = b'(';
+pub(crate) const VERBATIM: u8 = b'=';
+pub(crate) const MAP: u8 = b'%';
+pub(crate) const SET: u8 = b'~';
+pub(crate) const PUSH: u8 = b'>';
+pub(crate) const ATTRIBUTE: u8 = b'|';
+
+/// The protocol version negotiated with HELLO. RESP3 types are downgraded to their
+/// closest RESP2 equivalent when encoding for a RESP2 connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProtocolVersion {
+    #[default]
+    Resp2,
+    Resp3,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum RespType {
-    BulkString(String),
+    BulkString(Bytes),
     SimpleString(String),
     Integer(i64),
     Array(Vec<RespType>),
     Error(String),
     Null,
+    /// The null RESP2 sends as `*-1` instead of the `$-1` of [`RespType::Null`], where
+    /// Redis replies with a null array. RESP3 has a single null for both.
+    NullArray,
+    Boolean(bool),
+    Double(f64),
+    BigNumber(String),
+    VerbatimString { format: String, text: Bytes },
+    Map(Vec<(RespType, RespType)>),
+    Set(Vec<RespType>),
+    Push(Vec<RespType>),
+    /// Out-of-band metadata that precedes the reply it describes.
+    Attribute(Vec<(RespType, RespType)>, Box<RespType>),
 }
 
 impl RespType {
     pub fn to_bytes(&self) -> Bytes {
+        self.encode(ProtocolVersion::Resp2)
+    }
+
+    pub fn encode(&self, protocol: ProtocolVersion) -> Bytes {
+        let mut buf = Vec::new();
+        self.write_to(&mut buf, protocol);
+        Bytes::from(buf)
+    }
+
+    fn write_to(&self, buf: &mut Vec<u8>, protocol: ProtocolVersion) {
+        let resp3 = protocol == ProtocolVersion::Resp3;
         match self {
-            RespType::BulkString(bs) => {
-                let bulkstr_bytes = format!("${}\r\n{}\r\n", bs.chars().count(), bs).into_bytes();
-                Bytes::from_iter(bulkstr_bytes)
+            RespType::BulkString(bs) => write_bulk(buf, BULK, bs),
+            RespType::SimpleString(ss) => write_line(buf, STRING, ss),
+            RespType::Integer(i) => write_line(buf, INTEGER, &i.to_string()),
+            RespType::Array(arr) => write_aggregate(buf, ARRAY, arr, protocol),
+            RespType::Error(es) => write_line(buf, ERROR, es),
+            RespType::Null if resp3 => buf.extend_from_slice(b"_\r\n"),
+            RespType::Null => buf.extend_from_slice(b"$-1\r\n"),
+            RespType::NullArray if resp3 => buf.extend_from_slice(b"_\r\n"),
+            RespType::NullArray => buf.extend_from_slice(b"*-1\r\n"),
+            RespType::Boolean(b) if resp3 => write_line(buf, BOOLEAN, if *b { "t" } else { "f" }),
+            RespType::Boolean(b) => write_line(buf, INTEGER, if *b { "1" } else { "0" }),
+            RespType::Double(d) if resp3 => write_line(buf, DOUBLE, &format_double(*d)),
+            RespType::Double(d) => write_bulk(buf, BULK, format_double(*d).as_bytes()),
+            RespType::BigNumber(n) if resp3 => write_line(buf, BIG_NUMBER, n),
+            RespType::BigNumber(n) => write_bulk(buf, BULK, n.as_bytes()),
+            RespType::VerbatimString { format, text } if resp3 => {
+                let mut payload = Vec::with_capacity(format.len() + 1 + text.len());
+                payload.extend_from_slice(format.as_bytes());
+                payload.push(b':');
+                payload.extend_from_slice(text);
+                write_bulk(buf, VERBATIM, &payload);
             }
-            RespType::SimpleString(ss) => Bytes::from_iter(format!("+{}\r\n", ss).into_bytes()),
-            RespType::Integer(i) => Bytes::from(format!(":{}\r\n", i)),
-            RespType::Array(arr) => {
-                let mut result = format!("*{}\r\n", arr.len()).into_bytes();
-                for item in arr {
-                    result.extend_from_slice(&item.to_bytes());
+            RespType::VerbatimString { text, .. } => write_bulk(buf, BULK, text),
+            RespType::Map(pairs) if resp3 => write_pairs(buf, MAP, pairs, protocol),
+            RespType::Map(pairs) => {
+                buf.extend_from_slice(format!("*{}\r\n", pairs.len() * 2).as_bytes());
+                for (key, value) in pairs {
+                    key.write_to(buf, protocol);
+                    value.write_to(buf, protocol);
                 }
-                Bytes::from(result)
             }
-            RespType::Error(es) => Bytes::from_iter(format!("-{}\r\n", es).into_bytes()),
-            RespType::Null => Bytes::from("$-1\r\n"),
+            RespType::Set(items) if resp3 => write_aggregate(buf, SET, items, protocol),
+            RespType::Set(items) => write_aggregate(buf, ARRAY, items, protocol),
+            RespType::Push(items) if resp3 => write_aggregate(buf, PUSH, items, protocol),
+            RespType::Push(items) => write_aggregate(buf, ARRAY, items, protocol),
+            RespType::Attribute(attributes, value) => {
+                if resp3 {
+                    write_pairs(buf, ATTRIBUTE, attributes, protocol);
+                }
+                value.write_to(buf, protocol);
+            }
         }
     }
 }
 
+fn write_line(buf: &mut Vec<u8>, prefix: u8, line: &str) {
+    buf.push(prefix);
+    buf.extend_from_slice(line.as_bytes());
+    buf.extend_from_slice(b"\r\n");
+}
+
+fn write_bulk(buf: &mut Vec<u8>, prefix: u8, data: &[u8]) {
+    write_line(buf, prefix, &data.len().to_string());
+    buf.extend_from_slice(data);
+    buf.extend_from_slice(b"\r\n");
+}
+
+fn write_aggregate(buf: &mut Vec<u8>, prefix: u8, items: &[RespType], protocol: ProtocolVersion) {
+    write_line(buf, prefix, &items.len().to_string());
+    for item in items {
+        item.write_to(buf, protocol);
+    }
+}
+
+fn write_pairs(
+    buf: &mut Vec<u8>,
+    prefix: u8,
+    pairs: &[(RespType, RespType)],
+    protocol: ProtocolVersion,
+) {
+    write_line(buf, prefix, &pairs.len().to_string());
+    for (key, value) in pairs {
+        key.write_to(buf, protocol);
+        value.write_to(buf, protocol);
+    }
+}
+
+/// Formats a double the way Redis replies with one: the shortest representation that
+/// round-trips, switching to exponent notation for very large or small magnitudes like
+/// C's `%.17g`.
+pub fn format_double(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else if value != 0.0 && (value.abs() >= 1e17 || value.abs() < 1e-4) {
+        let formatted = format!("{:e}", value);
+        let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+        let (sign, digits) = match exponent.strip_prefix('-') {
+            Some(digits) => ('-', digits),
+            None => ('+', exponent),
+        };
+        format!("{}e{}{:0>2}", mantissa, sign, digits)
+    } else {
+        value.to_string()
+    }
+}
+
 #[derive(Debug)]
 pub enum RespError {
     InvalidBulkString(String),
     InvalidSimpleString(String),
     InvalidInteger(String),
     Incomplete,
+    /// The peer broke a protocol limit; the connection is closed after reporting it.
+    Protocol(String),
+    /// The peer sent more unparsed data than the query buffer limit allows.
+    QueryBufferExceeded,
     Other(String),
 }
 
@@ -56,113 +180,252 @@ impl std::fmt::Display for RespError {
             RespError::InvalidSimpleString(msg) => write!(f, "Invalid simple string: {}", msg),
             RespError::InvalidInteger(msg) => write!(f, "Invalid integer: {}", msg),
             RespError::Incomplete => write!(f, "Incomplete RESP data"),
+            RespError::Protocol(msg) => write!(f, "Protocol error: {}", msg),
+            RespError::QueryBufferExceeded => write!(f, "Query buffer limit exceeded"),
             RespError::Other(msg) => write!(f, "Other error: {}", msg),
         }
     }
 }
 
+/// Parses a single RESP value from the front of a buffer. Bulk payloads are returned as
+/// slices of the buffer rather than copies.
 pub struct Resp {
-    buffer: BytesMut,
+    buffer: Bytes,
 }
 
 impl Resp {
-    pub fn new(buffer: BytesMut) -> Self {
-        Resp { buffer }
+    pub fn new(buffer: impl Into<Bytes>) -> Self {
+        Resp { buffer: buffer.into() }
     }
 
     pub fn parse(&mut self) -> Result<(RespType, usize), RespError> {
-        match self.buffer[0] {
-            BULK => Self::parse_bulk_string(self),
-            STRING => Self::parse_simple_string(self),
-            INTEGER => Self::parse_integer(self),
-            ARRAY => Self::parse_array(self),
-            ERROR => Self::parse_error(self),
+        self.parse_at(0)
+    }
+
+    fn parse_at(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let Some(&type_byte) = self.buffer.get(start) else {
+            return Err(RespError::Incomplete);
+        };
+        match type_byte {
+            BULK => self.parse_bulk_string(start),
+            STRING => self.parse_simple_string(start),
+            INTEGER => self.parse_integer(start),
+            ARRAY => self.parse_array(start),
+            ERROR => self.parse_error(start),
+            NULL => self.parse_null(start),
+            BOOLEAN => self.parse_boolean(start),
+            DOUBLE => self.parse_double(start),
+            BIG_NUMBER => self.parse_big_number(start),
+            VERBATIM => self.parse_verbatim_string(start),
+            MAP => self.parse_map(start),
+            SET => self.parse_set(start),
+            PUSH => self.parse_push(start),
+            ATTRIBUTE => self.parse_attribute(start),
             _ => Err(RespError::Other(String::from("Invalid RESP data type!"))),
         }
     }
 
     // $5\r\nhello\r\n
-    fn parse_bulk_string(&self) -> Result<(RespType, usize), RespError> {
-        let (str_length, mut len) = self.parse_integer_value(1)?;
+    fn parse_bulk_string(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (str_length, mut len) = self.parse_integer_value(start + 1)?;
         if str_length == -1 {
             return Ok((RespType::Null, len));
         }
 
-        let str_length = str_length as usize;
+        let str_length = usize::try_from(str_length)
+            .map_err(|_| RespError::InvalidBulkString("Invalid length".to_string()))?;
 
         if self.buffer.len() < len + str_length + 2 {
             return Err(RespError::Incomplete);
         }
 
-        let string = String::from_utf8(self.buffer[len..len + str_length].to_vec())
-            .map_err(|_| RespError::InvalidBulkString("Invalid UTF-8".to_string()))?;
+        if &self.buffer[len + str_length..len + str_length + 2] != b"\r\n" {
+            return Err(RespError::InvalidBulkString("Missing CRLF terminator".to_string()));
+        }
+
+        let data = self.buffer.slice(len..len + str_length);
 
         len += str_length + 2; // +2 for \r\n
-        Ok((RespType::BulkString(string), len))
+        Ok((RespType::BulkString(data), len))
     }
 
     // +OK\r\n
-    fn parse_simple_string(&self) -> Result<(RespType, usize), RespError> {
-        let (line, len) = self.read_line(1)?;
+    fn parse_simple_string(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (line, len) = self.read_line(start + 1)?;
         Ok((RespType::SimpleString(line), len))
     }
 
     // :23\r\n
-    fn parse_integer(&self) -> Result<(RespType, usize), RespError> {
-        let (value, len) = self.parse_integer_value(1)?;
+    fn parse_integer(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (value, len) = self.parse_integer_value(start + 1)?;
         Ok((RespType::Integer(value), len))
     }
 
     // *2\r\n$5\r\nhello\r\n$5\r\nworld\r\n
-    fn parse_array(&self) -> Result<(RespType, usize), RespError> {
-        let (arr_size, mut len) = self.parse_integer_value(1)?;
+    fn parse_array(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (arr_size, len) = self.parse_integer_value(start + 1)?;
         if arr_size == -1 {
-            return Ok((RespType::Null, len));
+            return Ok((RespType::NullArray, len));
         }
 
-        let arr_size = arr_size as usize;
+        let (array, len) = self.parse_elements(len, Self::aggregate_size(arr_size)?)?;
+        Ok((RespType::Array(array), len))
+    }
 
-        let mut array = Vec::with_capacity(arr_size);
-        for _ in 0..arr_size {
-            let mut resp = Resp::new(BytesMut::from(&self.buffer[len..]));
-            let (item, item_len) = resp.parse()?;
-            array.push(item);
-            len += item_len;
+    // _\r\n
+    fn parse_null(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (line, len) = self.read_line(start + 1)?;
+        if !line.is_empty() {
+            return Err(RespError::Other("Invalid null".to_string()));
         }
+        Ok((RespType::Null, len))
+    }
 
-        Ok((RespType::Array(array), len))
+    // #t\r\n
+    fn parse_boolean(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (line, len) = self.read_line(start + 1)?;
+        match line.as_str() {
+            "t" => Ok((RespType::Boolean(true), len)),
+            "f" => Ok((RespType::Boolean(false), len)),
+            _ => Err(RespError::Other("Invalid boolean".to_string())),
+        }
+    }
+
+    // ,3.14\r\n
+    fn parse_double(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (line, len) = self.read_line(start + 1)?;
+        let value = line
+            .parse::<f64>()
+            .map_err(|_| RespError::Other("Invalid double".to_string()))?;
+        Ok((RespType::Double(value), len))
+    }
+
+    // (3492890328409238509324850943850943825024385\r\n
+    fn parse_big_number(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (line, len) = self.read_line(start + 1)?;
+        let digits = line.strip_prefix(['-', '+']).unwrap_or(&line);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(RespError::Other("Invalid big number".to_string()));
+        }
+        Ok((RespType::BigNumber(line), len))
+    }
+
+    // =15\r\ntxt:Some string\r\n
+    fn parse_verbatim_string(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (payload, len) = match self.parse_bulk_string(start)? {
+            (RespType::BulkString(payload), len) => (payload, len),
+            _ => return Err(RespError::Other("Invalid verbatim string".to_string())),
+        };
+        if payload.len() < 4 || payload[3] != b':' {
+            return Err(RespError::Other("Invalid verbatim string".to_string()));
+        }
+        let format = String::from_utf8(payload[..3].to_vec())
+            .map_err(|_| RespError::Other("Invalid verbatim string".to_string()))?;
+        Ok((RespType::VerbatimString { format, text: payload.slice(4..) }, len))
+    }
+
+    // %1\r\n+key\r\n:1\r\n
+    fn parse_map(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (pairs, len) = self.parse_pairs(start)?;
+        Ok((RespType::Map(pairs), len))
+    }
+
+    // ~2\r\n+a\r\n+b\r\n
+    fn parse_set(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (size, len) = self.parse_integer_value(start + 1)?;
+        let (items, len) = self.parse_elements(len, Self::aggregate_size(size)?)?;
+        Ok((RespType::Set(items), len))
+    }
+
+    // >2\r\n+message\r\n+hello\r\n
+    fn parse_push(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (size, len) = self.parse_integer_value(start + 1)?;
+        let (items, len) = self.parse_elements(len, Self::aggregate_size(size)?)?;
+        Ok((RespType::Push(items), len))
+    }
+
+    // |1\r\n+ttl\r\n:3600\r\n+value\r\n
+    fn parse_attribute(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (attributes, len) = self.parse_pairs(start)?;
+        let (value, len) = self.parse_at(len)?;
+        Ok((RespType::Attribute(attributes, Box::new(value)), len))
+    }
+
+    fn parse_pairs(&self, start: usize) -> Result<(Vec<(RespType, RespType)>, usize), RespError> {
+        let (size, mut len) = self.parse_integer_value(start + 1)?;
+        let size = Self::aggregate_size(size)?;
+        let mut pairs = Vec::with_capacity(size.min(PREALLOCATE_LIMIT));
+        for _ in 0..size {
+            let (key, key_end) = self.parse_at(len)?;
+            let (value, value_end) = self.parse_at(key_end)?;
+            pairs.push((key, value));
+            len = value_end;
+        }
+        Ok((pairs, len))
+    }
+
+    fn parse_elements(&self, mut len: usize, count: usize) -> Result<(Vec<RespType>, usize), RespError> {
+        let mut elements = Vec::with_capacity(count.min(PREALLOCATE_LIMIT));
+        for _ in 0..count {
+            let (item, end) = self.parse_at(len)?;
+            elements.push(item);
+            len = end;
+        }
+        Ok((elements, len))
+    }
+
+    fn aggregate_size(size: i64) -> Result<usize, RespError> {
+        usize::try_from(size).map_err(|_| RespError::InvalidInteger("Invalid aggregate size".to_string()))
     }
 
     // -Error message\r\n
-    fn parse_error(&self) -> Result<(RespType, usize), RespError> {
-        let (line, len) = self.read_line(1)?;
+    fn parse_error(&self, start: usize) -> Result<(RespType, usize), RespError> {
+        let (line, len) = self.read_line(start + 1)?;
         Ok((RespType::Error(line), len))
     }
 
     fn read_line(&self, start: usize) -> Result<(String, usize), RespError> {
-        for i in start + 1..self.buffer.len() {
-            if self.buffer[i - 1] == b'\r' && self.buffer[i] == b'\n' {
-                return Ok((
-                    String::from_utf8(self.buffer[start..i - 1].to_vec())
-                        .map_err(|_| RespError::InvalidSimpleString("Invalid UTF-8".to_string()))?,
-                    i + 1,
-                ));
-            }
-        }
-        Err(RespError::Incomplete)
+        let end = find_crlf(&self.buffer, start).ok_or(RespError::Incomplete)?;
+        let line = String::from_utf8(self.buffer[start..end].to_vec())
+            .map_err(|_| RespError::InvalidSimpleString("Invalid UTF-8".to_string()))?;
+        Ok((line, end + 2))
     }
 
     fn parse_integer_value(&self, start: usize) -> Result<(i64, usize), RespError> {
-        let (line, pos) = self.read_line(start)?;
-        line.parse::<i64>()
-            .map_err(|_| RespError::InvalidInteger("Invalid integer format".to_string()))
-            .map(|value| (value, pos))
+        let end = find_crlf(&self.buffer, start).ok_or(RespError::Incomplete)?;
+        parse_i64(&self.buffer[start..end]).map(|value| (value, end + 2))
     }
 }
 
+// Aggregate headers come from the peer, so never reserve more than this up front.
+const PREALLOCATE_LIMIT: usize = 1024;
+
+/// Returns the index of the `\r` of the first CRLF at or after `start`.
+pub(crate) fn find_crlf(buffer: &[u8], start: usize) -> Option<usize> {
+    let haystack = buffer.get(start..)?;
+    let mut offset = 0;
+    while let Some(i) = haystack[offset..].iter().position(|b| *b == b'\r') {
+        let cr = offset + i;
+        match haystack.get(cr + 1) {
+            Some(b'\n') => return Some(start + cr),
+            Some(_) => offset = cr + 1,
+            None => return None,
+        }
+    }
+    None
+}
+
+pub(crate) fn parse_i64(digits: &[u8]) -> Result<i64, RespError> {
+    std::str::from_utf8(digits)
+        .ok()
+        .and_then(|digits| digits.parse::<i64>().ok())
+        .ok_or_else(|| RespError::InvalidInteger("Invalid integer format".to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bytes::BytesMut;
 
     fn assert_resp_eq(
         result: Result<(RespType, usize), RespError>,
@@ -190,7 +453,23 @@ mod tests {
     #[test]
     fn test_parse_bulk_string() {
         let mut resp = Resp::new(BytesMut::from("$5\r\nhello\r\n"));
-        assert_resp_eq(resp.parse(), RespType::BulkString("hello".to_string()), 11);
+        assert_resp_eq(resp.parse(), RespType::BulkString(Bytes::from("hello")), 11);
+    }
+
+    #[test]
+    fn test_parse_binary_bulk_string() {
+        let mut resp = Resp::new(BytesMut::from(&b"$4\r\n\xff\x00\r\n\r\n"[..]));
+        assert_resp_eq(
+            resp.parse(),
+            RespType::BulkString(Bytes::from_static(b"\xff\x00\r\n")),
+            10,
+        );
+    }
+
+    #[test]
+    fn test_encode_multibyte_bulk_string() {
+        let encoded = RespType::BulkString(Bytes::from("héllo")).to_bytes();
+        assert_eq!(encoded, Bytes::from("$6\r\nhéllo\r\n"));
     }
 
     #[test]
@@ -211,8 +490,8 @@ mod tests {
         assert_resp_eq(
             resp.parse(),
             RespType::Array(vec![
-                RespType::BulkString("hello".to_string()),
-                RespType::BulkString("world".to_string()),
+                RespType::BulkString(Bytes::from("hello")),
+                RespType::BulkString(Bytes::from("world")),
             ]),
             26,
         );
@@ -232,6 +511,92 @@ mod tests {
     fn test_parse_null() {
         let mut resp = Resp::new(BytesMut::from("$-1\r\n"));
         assert_resp_eq(resp.parse(), RespType::Null, 5);
+
+        let mut resp = Resp::new(BytesMut::from("*-1\r\n"));
+        assert_resp_eq(resp.parse(), RespType::NullArray, 5);
+    }
+
+    #[test]
+    fn test_parse_resp3_scalars() {
+        let mut resp = Resp::new(BytesMut::from("_\r\n"));
+        assert_resp_eq(resp.parse(), RespType::Null, 3);
+
+        let mut resp = Resp::new(BytesMut::from("#t\r\n"));
+        assert_resp_eq(resp.parse(), RespType::Boolean(true), 4);
+
+        let mut resp = Resp::new(BytesMut::from(",-1.5\r\n"));
+        assert_resp_eq(resp.parse(), RespType::Double(-1.5), 7);
+
+        let mut resp = Resp::new(BytesMut::from("(12345678901234567890\r\n"));
+        assert_resp_eq(resp.parse(), RespType::BigNumber("12345678901234567890".to_string()), 23);
+
+        let mut resp = Resp::new(BytesMut::from("=9\r\ntxt:hello\r\n"));
+        assert_resp_eq(
+            resp.parse(),
+            RespType::VerbatimString { format: "txt".to_string(), text: Bytes::from("hello") },
+            15,
+        );
+    }
+
+    #[test]
+    fn test_parse_resp3_aggregates() {
+        let mut resp = Resp::new(BytesMut::from("%1\r\n+key\r\n:1\r\n"));
+        assert_resp_eq(
+            resp.parse(),
+            RespType::Map(vec![(RespType::SimpleString("key".to_string()), RespType::Integer(1))]),
+            14,
+        );
+
+        let mut resp = Resp::new(BytesMut::from("~2\r\n:1\r\n:2\r\n"));
+        assert_resp_eq(resp.parse(), RespType::Set(vec![RespType::Integer(1), RespType::Integer(2)]), 12);
+
+        let mut resp = Resp::new(BytesMut::from(">1\r\n+hi\r\n"));
+        assert_resp_eq(resp.parse(), RespType::Push(vec![RespType::SimpleString("hi".to_string())]), 9);
+
+        let mut resp = Resp::new(BytesMut::from("|1\r\n+ttl\r\n:3\r\n:7\r\n"));
+        assert_resp_eq(
+            resp.parse(),
+            RespType::Attribute(
+                vec![(RespType::SimpleString("ttl".to_string()), RespType::Integer(3))],
+                Box::new(RespType::Integer(7)),
+            ),
+            18,
+        );
+
+        let mut resp = Resp::new(BytesMut::from("%1\r\n+key\r\n"));
+        assert!(matches!(resp.parse(), Err(RespError::Incomplete)));
+    }
+
+    #[test]
+    fn test_format_double() {
+        assert_eq!(format_double(0.0), "0");
+        assert_eq!(format_double(-2.5), "-2.5");
+        assert_eq!(format_double(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(format_double(1e300), "1e+300");
+        assert_eq!(format_double(-1.5e-7), "-1.5e-07");
+        assert_eq!(format_double(f64::NEG_INFINITY), "-inf");
+    }
+
+    #[test]
+    fn test_encode_by_protocol() {
+        let map = RespType::Map(vec![(RespType::BulkString(Bytes::from("a")), RespType::Double(1.5))]);
+        assert_eq!(map.encode(ProtocolVersion::Resp3), Bytes::from("%1\r\n$1\r\na\r\n,1.5\r\n"));
+        assert_eq!(map.encode(ProtocolVersion::Resp2), Bytes::from("*2\r\n$1\r\na\r\n$3\r\n1.5\r\n"));
+
+        assert_eq!(RespType::Null.encode(ProtocolVersion::Resp3), Bytes::from("_\r\n"));
+        assert_eq!(RespType::Null.encode(ProtocolVersion::Resp2), Bytes::from("$-1\r\n"));
+        assert_eq!(RespType::NullArray.encode(ProtocolVersion::Resp3), Bytes::from("_\r\n"));
+        assert_eq!(RespType::NullArray.encode(ProtocolVersion::Resp2), Bytes::from("*-1\r\n"));
+        assert_eq!(RespType::Boolean(true).encode(ProtocolVersion::Resp2), Bytes::from(":1\r\n"));
+        assert_eq!(RespType::Double(f64::INFINITY).encode(ProtocolVersion::Resp3), Bytes::from(",inf\r\n"));
+        assert_eq!(RespType::Double(3.0).encode(ProtocolVersion::Resp2), Bytes::from("$1\r\n3\r\n"));
+
+        let push = RespType::Push(vec![RespType::BulkString(Bytes::from("message"))]);
+        assert_eq!(push.encode(ProtocolVersion::Resp3), Bytes::from(">1\r\n$7\r\nmessage\r\n"));
+        assert_eq!(push.encode(ProtocolVersion::Resp2), Bytes::from("*1\r\n$7\r\nmessage\r\n"));
+
+        let verbatim = RespType::VerbatimString { format: "txt".to_string(), text: Bytes::from("hi") };
+        assert_eq!(verbatim.encode(ProtocolVersion::Resp3), Bytes::from("=6\r\ntxt:hi\r\n"));
     }
 
     #[test]
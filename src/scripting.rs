@@ -0,0 +1,660 @@
+use bytes::Bytes;
+use mlua::{HookTriggers, Lua, LuaOptions, MultiValue, RegistryKey, StdLib, Table, Value};
+use parking_lot::{Mutex, RwLockWriteGuard};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+use crate::client::Client;
+use crate::commands::execute_tracked;
+use crate::error::BifrostError;
+use crate::parser::{
+    parse_blocking_command, parse_command, parse_connection_command, parse_pubsub_command,
+    parse_script_command, parse_transaction_command,
+};
+use crate::resp::{format_double, RespType};
+use crate::storage::blocking::Blocking;
+use crate::storage::databases::{hand_off_other_tasks, Databases};
+
+/// How long a script may run before other connections are turned away with BUSY and
+/// SCRIPT KILL may stop it, like busy-reply-threshold in Redis.
+pub const DEFAULT_SCRIPT_TIME_LIMIT: Duration = Duration::from_secs(5);
+
+// How many Lua instructions run between checks of the time limit and of SCRIPT KILL.
+const HOOK_INSTRUCTIONS: u32 = 10_000;
+
+/// The commands that run Lua scripts and manage the script cache.
+pub enum ScriptCommand {
+    Eval { script: Bytes, keys: Vec<Bytes>, args: Vec<Bytes> },
+    EvalSha { sha: String, keys: Vec<Bytes>, args: Vec<Bytes> },
+    Load(Bytes),
+    Exists(Vec<String>),
+    Flush,
+    Kill,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Run {
+    Idle,
+    Running,
+    // Running for longer than the time limit.
+    Busy,
+}
+
+// An error reply raised inside a script, which is passed on to the client unchanged
+// when the script does not catch it.
+#[derive(Debug)]
+struct ReplyError(String);
+
+impl fmt::Display for ReplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ReplyError {}
+
+fn sha1_hex(script: &[u8]) -> String {
+    sha1_smol::Sha1::from(script).digest().to_string()
+}
+
+// A Lua state with the redis library, and the scripts compiled in it by SHA1.
+struct Interpreter {
+    lua: Lua,
+    functions: HashMap<String, RegistryKey>,
+}
+
+impl Interpreter {
+    fn new() -> Self {
+        // Like Redis, scripts get no access to files or the operating system.
+        let lua = Lua::new_with(StdLib::TABLE | StdLib::STRING | StdLib::MATH, LuaOptions::default())
+            .and_then(|lua| redis_library(&lua).map(|()| lua))
+            .expect("failed to set up the Lua interpreter");
+        Interpreter { lua, functions: HashMap::new() }
+    }
+
+    // Compiles a script unless it is cached already. Returns its SHA1.
+    fn load(&mut self, script: &[u8]) -> Result<String, RespType> {
+        let sha = sha1_hex(script);
+        if !self.functions.contains_key(&sha) {
+            let compiled = self
+                .lua
+                .load(script)
+                .set_name("@user_script")
+                .into_function()
+                .and_then(|function| self.lua.create_registry_value(function));
+            let function = compiled.map_err(|e| {
+                RespType::Error(format!("ERR Error compiling script (new function): {}", lua_message(&e)))
+            })?;
+            self.functions.insert(sha.clone(), function);
+        }
+        Ok(sha)
+    }
+}
+
+// The parts of the redis table that do not depend on the running script.
+fn redis_library(lua: &Lua) -> mlua::Result<()> {
+    let redis = lua.create_table()?;
+    redis.set(
+        "status_reply",
+        lua.create_function(|lua, status: mlua::String| lua.create_table_from([("ok", status)]))?,
+    )?;
+    redis.set(
+        "error_reply",
+        lua.create_function(|lua, error: mlua::String| lua.create_table_from([("err", error)]))?,
+    )?;
+    redis.set("sha1hex", lua.create_function(|_, script: mlua::String| Ok(sha1_hex(script.as_bytes())))?)?;
+    redis.set(
+        "log",
+        lua.create_function(|_, (_level, message): (i64, mlua::String)| {
+            eprintln!("Script: {}", message.to_string_lossy());
+            Ok(())
+        })?,
+    )?;
+    for (name, level) in [("LOG_DEBUG", 0), ("LOG_VERBOSE", 1), ("LOG_NOTICE", 2), ("LOG_WARNING", 3)] {
+        redis.set(name, level)?;
+    }
+    let globals = lua.globals();
+    globals.set("redis", redis)?;
+    for name in ["loadfile", "dofile"] {
+        globals.set(name, Value::Nil)?;
+    }
+    Ok(())
+}
+
+struct Inner {
+    interpreter: Mutex<Interpreter>,
+    time_limit: Duration,
+    run: watch::Sender<Run>,
+    // Set by SCRIPT KILL and checked by the running script.
+    kill: AtomicBool,
+    // Whether the running script called a command that may have written, after which
+    // killing it would leave a half-applied change behind.
+    wrote: AtomicBool,
+}
+
+/// Runs Lua scripts with EVAL and EVALSHA, and caches them by SHA1. Scripts share one
+/// interpreter and run one at a time, with every other command held off. Shared by
+/// every connection.
+#[derive(Clone)]
+pub struct Scripts {
+    inner: Arc<Inner>,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        Self::new(DEFAULT_SCRIPT_TIME_LIMIT)
+    }
+}
+
+fn ok() -> RespType {
+    RespType::SimpleString("OK".to_string())
+}
+
+impl Scripts {
+    pub fn new(time_limit: Duration) -> Self {
+        Scripts {
+            inner: Arc::new(Inner {
+                interpreter: Mutex::new(Interpreter::new()),
+                time_limit,
+                run: watch::Sender::new(Run::Idle),
+                kill: AtomicBool::new(false),
+                wrote: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Waits for the running script, if any, to finish. Fails with BUSY instead once
+    /// it has run for longer than the time limit.
+    pub async fn wait_until_idle(&self) -> Result<(), BifrostError> {
+        let mut run = self.inner.run.subscribe();
+        match run.wait_for(|run| *run != Run::Running).await.map(|run| *run) {
+            Ok(Run::Busy) => Err(BifrostError::CommandError(
+                "BUSY Bifrost is busy running a script. You can only call SCRIPT KILL.".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs a scripting command for `client`. A script holds every other command off
+    /// with [`Databases::exclusive`] until it finishes.
+    pub fn execute(&self, command: ScriptCommand, client: &Client, databases: &Databases) -> RespType {
+        self.execute_with(command, client, databases, true)
+    }
+
+    /// Like [`Scripts::execute`], for the commands EXEC runs, which hold every other
+    /// command off already.
+    pub fn execute_queued(&self, command: ScriptCommand, client: &Client, databases: &Databases) -> RespType {
+        self.execute_with(command, client, databases, false)
+    }
+
+    fn execute_with(&self, command: ScriptCommand, client: &Client, databases: &Databases, hold_off: bool) -> RespType {
+        // The running script holds the interpreter, so this must not wait for it.
+        if let ScriptCommand::Kill = command {
+            return self.kill();
+        }
+        let interpreter = &self.inner.interpreter;
+        let mut interpreter = interpreter.try_lock().unwrap_or_else(|| hand_off_other_tasks(|| interpreter.lock()));
+        match command {
+            ScriptCommand::Eval { script, keys, args } => match interpreter.load(&script) {
+                Ok(sha) => {
+                    let _exclusive = self.start(databases, hold_off);
+                    self.run(&interpreter, &sha, &keys, &args, client, databases)
+                }
+                Err(error) => error,
+            },
+            ScriptCommand::EvalSha { sha, keys, args } => {
+                if !interpreter.functions.contains_key(&sha) {
+                    return RespType::Error("NOSCRIPT No matching script. Please use EVAL.".to_string());
+                }
+                let _exclusive = self.start(databases, hold_off);
+                self.run(&interpreter, &sha, &keys, &args, client, databases)
+            }
+            ScriptCommand::Load(script) => match interpreter.load(&script) {
+                Ok(sha) => RespType::BulkString(Bytes::from(sha)),
+                Err(error) => error,
+            },
+            ScriptCommand::Exists(shas) => RespType::Array(
+                shas.iter()
+                    .map(|sha| RespType::Integer(interpreter.functions.contains_key(sha) as i64))
+                    .collect(),
+            ),
+            ScriptCommand::Flush => {
+                *interpreter = Interpreter::new();
+                ok()
+            }
+            ScriptCommand::Kill => unreachable!("SCRIPT KILL is handled above"),
+        }
+    }
+
+    // SCRIPT KILL. Only scripts that have not written anything may be killed.
+    fn kill(&self) -> RespType {
+        if *self.inner.run.borrow() == Run::Idle {
+            return RespType::Error("NOTBUSY No scripts in execution right now.".to_string());
+        }
+        if self.inner.wrote.load(Ordering::Relaxed) {
+            return RespType::Error(
+                "UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or kill the server in a hard way using the SHUTDOWN NOSAVE command.".to_string(),
+            );
+        }
+        self.inner.kill.store(true, Ordering::Relaxed);
+        ok()
+    }
+
+    // Marks a script as running, then holds off the other commands unless EXEC does
+    // already. In that order, commands that arrive while this waits for the ones in
+    // progress wait for the script in wait_until_idle, not on the lock.
+    fn start<'a>(&self, databases: &'a Databases, hold_off: bool) -> Option<RwLockWriteGuard<'a, ()>> {
+        self.inner.kill.store(false, Ordering::Relaxed);
+        self.inner.wrote.store(false, Ordering::Relaxed);
+        self.inner.run.send_replace(Run::Running);
+        hold_off.then(|| databases.exclusive())
+    }
+
+    // Calls a compiled script with KEYS and ARGV set, while watching the clock.
+    fn run(
+        &self,
+        interpreter: &Interpreter,
+        sha: &str,
+        keys: &[Bytes],
+        args: &[Bytes],
+        client: &Client,
+        databases: &Databases,
+    ) -> RespType {
+        let lua = &interpreter.lua;
+        let started = Instant::now();
+        let inner = Arc::clone(&self.inner);
+        lua.set_hook(HookTriggers::new().every_nth_instruction(HOOK_INSTRUCTIONS), move |_, _| {
+            if started.elapsed() > inner.time_limit {
+                inner.run.send_if_modified(|run| std::mem::replace(run, Run::Busy) == Run::Running);
+            }
+            if inner.kill.load(Ordering::Relaxed) {
+                return Err(mlua::Error::external(ReplyError(
+                    "ERR Script killed by user with SCRIPT KILL...".to_string(),
+                )));
+            }
+            Ok(())
+        });
+
+        // SELECT in a script changes the database of the rest of the script only.
+        let client = RefCell::new(client.clone());
+        let result = hand_off_other_tasks(|| lua.scope(|scope| {
+            let redis: Table = lua.globals().get("redis")?;
+            let client = &client;
+            let call = |raise| {
+                scope.create_function(move |lua, args: MultiValue| {
+                    self.call(lua, args, raise, &mut client.borrow_mut(), databases)
+                })
+            };
+            redis.set("call", call(true)?)?;
+            redis.set("pcall", call(false)?)?;
+            let strings = |values: &[Bytes]| {
+                lua.create_sequence_from(values.iter().map(|value| lua.create_string(value)).collect::<mlua::Result<Vec<_>>>()?)
+            };
+            lua.globals().set("KEYS", strings(keys)?)?;
+            lua.globals().set("ARGV", strings(args)?)?;
+
+            let function: mlua::Function = lua.registry_value(&interpreter.functions[sha])?;
+            function.call::<_, Value>(()).map(to_reply)
+        }));
+
+        lua.remove_hook();
+        self.inner.run.send_replace(Run::Idle);
+        result.unwrap_or_else(|e| match reply_error(&e) {
+            Some(ReplyError(message)) => RespType::Error(message.clone()),
+            None => RespType::Error(format!("ERR {} script: {}", lua_message(&e), sha)),
+        })
+    }
+
+    // redis.call and redis.pcall. An error reply is raised by the first and returned as
+    // an error table by the second.
+    fn call<'lua>(
+        &self,
+        lua: &'lua Lua,
+        args: MultiValue<'lua>,
+        raise: bool,
+        client: &mut Client,
+        databases: &Databases,
+    ) -> mlua::Result<Value<'lua>> {
+        let reply = match request(args) {
+            Ok(request) => self.dispatch(request, client, databases),
+            Err(error) => error,
+        };
+        match reply {
+            RespType::Error(message) if raise => Err(mlua::Error::external(ReplyError(message))),
+            reply => to_lua(lua, reply),
+        }
+    }
+
+    // Runs a command on behalf of a script, which may call anything Redis does not mark
+    // noscript. Blocking commands reply as if they timed out right away. The script
+    // holds every other command off, so commands on other databases are safe too.
+    fn dispatch(&self, request: RespType, client: &mut Client, databases: &Databases) -> RespType {
+        let not_allowed = || RespType::Error("ERR This Redis command is not allowed from script".to_string());
+        if parse_transaction_command(&request).is_some() || parse_script_command(&request).is_some() {
+            return not_allowed();
+        }
+        if let Some(command) = parse_connection_command(&request) {
+            return match command {
+                Ok(command) if command.is_noscript() => not_allowed(),
+                Ok(command) => {
+                    if command.is_write() {
+                        self.inner.wrote.store(true, Ordering::Relaxed);
+                    }
+                    command.execute(client, databases)
+                }
+                Err(err) => error_reply(err),
+            };
+        }
+        if let Some(command) = parse_pubsub_command(&request) {
+            // Subscribing is left to connections.
+            return match command {
+                Ok(command) => databases.broker().execute(command).unwrap_or_else(not_allowed),
+                Err(err) => error_reply(err),
+            };
+        }
+        let db = &databases[client.db];
+        if let Some(command) = parse_blocking_command(&request) {
+            return match command {
+                Ok(command) => {
+                    if command.is_write() {
+                        self.inner.wrote.store(true, Ordering::Relaxed);
+                    }
+                    match command.execute(db) {
+                        Blocking::Ready(reply) => reply,
+                        Blocking::Blocked(..) => RespType::NullArray,
+                    }
+                }
+                Err(err) => error_reply(err),
+            };
+        }
+        match parse_command(request) {
+            Ok(command) => {
+                if command.is_write() {
+                    self.inner.wrote.store(true, Ordering::Relaxed);
+                }
+                execute_tracked(command.as_ref(), client, None, databases)
+            }
+            Err(err) => error_reply(err),
+        }
+    }
+}
+
+fn error_reply(err: BifrostError) -> RespType {
+    match err {
+        BifrostError::CommandError(msg) | BifrostError::StorageError(msg) | BifrostError::ProtocolError(msg) => {
+            RespType::Error(msg)
+        }
+        BifrostError::IoError(e) => RespType::Error(format!("ERR {}", e)),
+    }
+}
+
+// The arguments of redis.call as a request. Numbers are sent the way Lua prints them.
+fn request(args: MultiValue) -> Result<RespType, RespType> {
+    if args.is_empty() {
+        return Err(RespType::Error("ERR Please specify at least one argument for this redis lib call".to_string()));
+    }
+    args.into_iter()
+        .map(|arg| match arg {
+            Value::String(arg) => Ok(Bytes::copy_from_slice(arg.as_bytes())),
+            Value::Integer(arg) => Ok(Bytes::from(arg.to_string())),
+            Value::Number(arg) if arg.fract() == 0.0 && arg.abs() < 1e15 => Ok(Bytes::from((arg as i64).to_string())),
+            Value::Number(arg) => Ok(Bytes::from(arg.to_string())),
+            _ => Err(RespType::Error("ERR Lua redis lib command arguments must be strings or integers".to_string())),
+        })
+        .map(|arg| arg.map(RespType::BulkString))
+        .collect::<Result<_, _>>()
+        .map(RespType::Array)
+}
+
+// Converts a command reply to the Lua value a script sees, following the RESP2
+// conversion rules of Redis: nulls become false, and status and error replies become
+// tables with an `ok` or `err` field.
+fn to_lua(lua: &Lua, reply: RespType) -> mlua::Result<Value<'_>> {
+    let string = |bytes: &[u8]| lua.create_string(bytes).map(Value::String);
+    match reply {
+        RespType::Integer(value) => Ok(Value::Integer(value)),
+        RespType::BulkString(value) | RespType::VerbatimString { text: value, .. } => string(&value),
+        RespType::Double(value) => string(format_double(value).as_bytes()),
+        RespType::BigNumber(value) => string(value.as_bytes()),
+        RespType::Boolean(value) => Ok(Value::Integer(value as i64)),
+        RespType::Null | RespType::NullArray => Ok(Value::Boolean(false)),
+        RespType::SimpleString(status) => lua.create_table_from([("ok", status)]).map(Value::Table),
+        RespType::Error(error) => lua.create_table_from([("err", error)]).map(Value::Table),
+        RespType::Array(items) | RespType::Set(items) | RespType::Push(items) => {
+            let items = items.into_iter().map(|item| to_lua(lua, item)).collect::<mlua::Result<Vec<_>>>()?;
+            lua.create_sequence_from(items).map(Value::Table)
+        }
+        RespType::Map(pairs) => {
+            let items = pairs
+                .into_iter()
+                .flat_map(|(key, value)| [to_lua(lua, key), to_lua(lua, value)])
+                .collect::<mlua::Result<Vec<_>>>()?;
+            lua.create_sequence_from(items).map(Value::Table)
+        }
+        RespType::Attribute(_, value) => to_lua(lua, *value),
+    }
+}
+
+// Converts what a script returned to the reply sent to the client. Tables are read as
+// arrays up to their first nil, unless they carry an `ok` or `err` field.
+fn to_reply(value: Value) -> RespType {
+    match value {
+        Value::String(value) => RespType::BulkString(Bytes::copy_from_slice(value.as_bytes())),
+        Value::Integer(value) => RespType::Integer(value),
+        Value::Number(value) => RespType::Integer(value as i64),
+        Value::Boolean(true) => RespType::Integer(1),
+        Value::Table(table) => {
+            if let Ok(Value::String(error)) = table.raw_get("err") {
+                return RespType::Error(error.to_string_lossy().into_owned());
+            }
+            if let Ok(Value::String(status)) = table.raw_get("ok") {
+                return RespType::SimpleString(status.to_string_lossy().into_owned());
+            }
+            RespType::Array(table.sequence_values::<Value>().map_while(Result::ok).map(to_reply).collect())
+        }
+        _ => RespType::Null,
+    }
+}
+
+// The error reply a script raised, if that is what ended it.
+fn reply_error(error: &mlua::Error) -> Option<&ReplyError> {
+    match error {
+        mlua::Error::CallbackError { cause, .. } => reply_error(cause),
+        error => error.downcast_ref(),
+    }
+}
+
+// The message of a Lua error without the prefix mlua adds to it.
+fn lua_message(error: &mlua::Error) -> String {
+    match error {
+        mlua::Error::SyntaxError { message, .. } | mlua::Error::RuntimeError(message) => message.clone(),
+        mlua::Error::CallbackError { cause, .. } => lua_message(cause),
+        error => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(scripts: &Scripts, databases: &Databases, script: &str, keys: &[&str], args: &[&str]) -> RespType {
+        let bytes = |values: &[&str]| values.iter().map(|value| Bytes::copy_from_slice(value.as_bytes())).collect();
+        let command = ScriptCommand::Eval { script: Bytes::copy_from_slice(script.as_bytes()), keys: bytes(keys), args: bytes(args) };
+        scripts.execute(command, &Client::new(1), databases)
+    }
+
+    fn bulk(value: &'static str) -> RespType {
+        RespType::BulkString(Bytes::from_static(value.as_bytes()))
+    }
+
+    #[test]
+    fn test_eval() {
+        let scripts = Scripts::default();
+        let databases = Databases::default();
+        assert_eq!(
+            eval(&scripts, &databases, "return redis.call('SET', KEYS[1], ARGV[1])", &["key"], &["1"]),
+            RespType::SimpleString("OK".to_string())
+        );
+        assert_eq!(eval(&scripts, &databases, "return redis.call('INCRBY', KEYS[1], 41)", &["key"], &[]), RespType::Integer(42));
+        assert_eq!(
+            eval(&scripts, &databases, "return {1, 'two', {3.9}, redis.call('GET', 'missing'), 5}", &[], &[]),
+            RespType::Array(vec![
+                RespType::Integer(1),
+                bulk("two"),
+                RespType::Array(vec![RespType::Integer(3)]),
+                RespType::Null,
+                RespType::Integer(5),
+            ])
+        );
+        assert_eq!(eval(&scripts, &databases, "return redis.call('GET', 'missing') == false", &[], &[]), RespType::Integer(1));
+        assert_eq!(
+            eval(&scripts, &databases, "return redis.status_reply('DONE')", &[], &[]),
+            RespType::SimpleString("DONE".to_string())
+        );
+
+        // redis.call raises error replies, which reach the client as they are, while
+        // redis.pcall returns them.
+        databases[0].set(Bytes::from("text"), Bytes::from("abc"));
+        assert_eq!(
+            eval(&scripts, &databases, "return redis.call('INCR', 'text')", &[], &[]),
+            RespType::Error("ERR value is not an integer or out of range".to_string())
+        );
+        assert_eq!(
+            eval(&scripts, &databases, "local reply = redis.pcall('INCR', 'text'); return reply.err", &[], &[]),
+            bulk("ERR value is not an integer or out of range")
+        );
+        assert_eq!(
+            eval(&scripts, &databases, "return redis.call('MULTI')", &[], &[]),
+            RespType::Error("ERR This Redis command is not allowed from script".to_string())
+        );
+        assert!(matches!(eval(&scripts, &databases, "return nosuch()", &[], &[]), RespType::Error(e) if e.starts_with("ERR user_script:1:")));
+        assert!(matches!(eval(&scripts, &databases, "return +", &[], &[]), RespType::Error(e) if e.starts_with("ERR Error compiling script")));
+        assert!(matches!(eval(&scripts, &databases, "return os.time()", &[], &[]), RespType::Error(_)));
+    }
+
+    #[test]
+    fn test_commands_allowed_in_scripts() {
+        let scripts = Scripts::default();
+        let databases = Databases::default();
+        let ok = RespType::SimpleString("OK".to_string());
+
+        // SELECT only applies to the rest of the script, and commands that act on other
+        // databases run under the lock the script holds.
+        let script = "redis.call('SELECT', 1); redis.call('SET', 'key', 'one'); return redis.call('MOVE', 'key', 2)";
+        assert_eq!(eval(&scripts, &databases, script, &[], &[]), RespType::Integer(1));
+        assert_eq!(eval(&scripts, &databases, "return redis.call('SWAPDB', 0, 2)", &[], &[]), ok);
+        assert_eq!(eval(&scripts, &databases, "return redis.call('COPY', 'key', 'copy', 'DB', 3)", &[], &[]), RespType::Integer(1));
+        assert_eq!(databases[0].get(b"key").unwrap(), Some(Bytes::from("one")));
+        assert_eq!(databases[3].get(b"copy").unwrap(), Some(Bytes::from("one")));
+        assert_eq!(eval(&scripts, &databases, "return redis.call('FLUSHALL')", &[], &[]), ok);
+        assert_eq!(databases[3].get(b"copy").unwrap(), None);
+        assert_eq!(eval(&scripts, &databases, "return redis.call('PUBLISH', 'channel', 'message')", &[], &[]), RespType::Integer(0));
+
+        for script in ["return redis.call('SUBSCRIBE', 'channel')", "return redis.call('HELLO')", "return redis.call('CONFIG', 'GET', '*')"] {
+            assert_eq!(
+                eval(&scripts, &databases, script, &[], &[]),
+                RespType::Error("ERR This Redis command is not allowed from script".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_script_cache() {
+        let scripts = Scripts::default();
+        let databases = Databases::default();
+        let client = Client::new(1);
+        let sha = "e0e1f9fabfc9d4800c877a703b823ac0578ff8db".to_string();
+        let exists = |scripts: &Scripts| {
+            scripts.execute(ScriptCommand::Exists(vec![sha.clone(), "ffff".to_string()]), &client, &databases)
+        };
+        let evalsha = || ScriptCommand::EvalSha { sha: sha.clone(), keys: Vec::new(), args: Vec::new() };
+
+        assert!(matches!(scripts.execute(evalsha(), &client, &databases), RespType::Error(e) if e.starts_with("NOSCRIPT")));
+        assert_eq!(
+            scripts.execute(ScriptCommand::Load(Bytes::from("return 1")), &client, &databases),
+            RespType::BulkString(Bytes::from(sha.clone()))
+        );
+        assert_eq!(exists(&scripts), RespType::Array(vec![RespType::Integer(1), RespType::Integer(0)]));
+        assert_eq!(scripts.execute(evalsha(), &client, &databases), RespType::Integer(1));
+
+        scripts.execute(ScriptCommand::Flush, &client, &databases);
+        assert_eq!(exists(&scripts), RespType::Array(vec![RespType::Integer(0), RespType::Integer(0)]));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_script_kill() {
+        let scripts = Scripts::new(Duration::from_millis(50));
+        let databases = Databases::default();
+        assert!(matches!(scripts.execute(ScriptCommand::Kill, &Client::new(2), &databases), RespType::Error(e) if e.starts_with("NOTBUSY")));
+
+        let mut run = scripts.inner.run.subscribe();
+        let running = {
+            let (scripts, databases) = (scripts.clone(), databases.clone());
+            tokio::task::spawn_blocking(move || eval(&scripts, &databases, "while true do end", &[], &[]))
+        };
+        run.wait_for(|run| *run == Run::Busy).await.unwrap();
+        assert!(scripts.wait_until_idle().await.is_err());
+        assert_eq!(scripts.execute(ScriptCommand::Kill, &Client::new(2), &databases), ok());
+        assert_eq!(
+            running.await.unwrap(),
+            RespType::Error("ERR Script killed by user with SCRIPT KILL...".to_string())
+        );
+        assert!(scripts.wait_until_idle().await.is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_running_before_holding_off() {
+        let scripts = Scripts::default();
+        let databases = Databases::default();
+        let (held, release) = (std::sync::mpsc::channel(), std::sync::mpsc::channel::<()>());
+        let in_progress = {
+            let databases = databases.clone();
+            std::thread::spawn(move || {
+                let _shared = databases.shared();
+                held.0.send(()).unwrap();
+                let _ = release.1.recv();
+            })
+        };
+        held.1.recv().unwrap();
+
+        // The script counts as running while it waits for the command in progress, so
+        // new commands wait for it in wait_until_idle instead of on the lock.
+        let mut run = scripts.inner.run.subscribe();
+        let running = {
+            let (scripts, databases) = (scripts.clone(), databases.clone());
+            tokio::task::spawn_blocking(move || eval(&scripts, &databases, "return 1", &[], &[]))
+        };
+        run.wait_for(|run| *run == Run::Running).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(20), scripts.wait_until_idle()).await.is_err());
+        drop(release.0);
+        in_progress.join().unwrap();
+        assert_eq!(running.await.unwrap(), RespType::Integer(1));
+        assert!(scripts.wait_until_idle().await.is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_read_only_script_kill() {
+        let scripts = Scripts::new(Duration::from_millis(50));
+        let databases = Databases::default();
+        databases[0].set(Bytes::from("key"), Bytes::from("value"));
+
+        // Commands without keys, or that only read them, leave the script killable.
+        let mut run = scripts.inner.run.subscribe();
+        let running = {
+            let (scripts, databases) = (scripts.clone(), databases.clone());
+            let script = "while true do redis.call('KEYS', '*') redis.call('PING') end";
+            tokio::task::spawn_blocking(move || eval(&scripts, &databases, script, &[], &[]))
+        };
+        run.wait_for(|run| *run == Run::Busy).await.unwrap();
+        assert_eq!(scripts.execute(ScriptCommand::Kill, &Client::new(2), &databases), ok());
+        assert_eq!(
+            running.await.unwrap(),
+            RespType::Error("ERR Script killed by user with SCRIPT KILL...".to_string())
+        );
+    }
+}
@@ -1,71 +1,342 @@
-use crate::storage::db::Db;
-use crate::{frame::RespCodec, resp::RespType};
-use crate::parser::parse_command;
+use crate::client::Client;
+use crate::commands::{execute_tracked, BlockingCommand};
+use crate::storage::databases::{Databases, DEFAULT_DATABASES};
+use crate::frame::{ProtocolLimits, RespCodec};
+use crate::resp::{ProtocolVersion, RespError, RespType};
+use crate::parser::{
+    parse_blocking_command, parse_command, parse_connection_command, parse_pubsub_command,
+    parse_script_command, parse_transaction_command,
+};
+use crate::pubsub::{Broker, Subscriptions};
+use crate::scripting::{ScriptCommand, Scripts};
+use crate::storage::blocking::Blocking;
+use crate::storage::tracking::on_behalf_of;
 use crate::error::BifrostError;
+use crate::transaction::Transaction;
 
+use bytes::Bytes;
 use futures::{SinkExt, StreamExt};
+use std::collections::VecDeque;
 use std::io;
-use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
 use tokio_util::codec::Framed;
 
+// How often the background task actively removes expired keys.
+const EXPIRY_SWEEP_INTERVAL: Duration = Duration::from_millis(100);
+
 pub struct Server {
     listener: TcpListener,
-    db: Arc<Db>,
+    databases: Databases,
+    broker: Broker,
+    scripts: Scripts,
+    limits: ProtocolLimits,
 }
 
 impl Server {
     pub fn new(listener: TcpListener) -> Server {
+        let broker = Broker::default();
         Server {
             listener,
-            db: Arc::new(Db::new()),
+            databases: Databases::with_broker(DEFAULT_DATABASES, broker.clone()),
+            broker,
+            scripts: Scripts::default(),
+            limits: ProtocolLimits::default(),
         }
     }
 
+    /// Overrides the number of databases, 16 by default.
+    pub fn with_databases(mut self, count: usize) -> Server {
+        self.databases = Databases::with_broker(count.max(1), self.broker.clone());
+        self
+    }
+
+    /// Overrides the limits enforced on what clients send.
+    pub fn with_limits(mut self, limits: ProtocolLimits) -> Server {
+        self.limits = limits;
+        self
+    }
+
+    /// Overrides how long a script may run before other clients are refused with BUSY
+    /// and SCRIPT KILL can stop it, 5 seconds by default.
+    pub fn with_script_time_limit(mut self, limit: Duration) -> Server {
+        self.scripts = Scripts::new(limit);
+        self
+    }
+
     pub async fn start(self) -> io::Result<()> {
+        tokio::spawn(purge_expired_keys(self.databases.clone()));
+
+        let mut next_client_id = 1;
         loop {
             let (stream, addr) = self.listener.accept().await?;
             println!("New connection from {}", addr);
 
-            let db_clone = Arc::clone(&self.db);
+            let databases = self.databases.clone();
+            let scripts = self.scripts.clone();
+            let client = Client::new(next_client_id);
+            let subscriptions = Subscriptions::new(client.id, self.broker.clone());
+            next_client_id += 1;
+            let codec = RespCodec::with_limits(self.limits);
 
             tokio::spawn(async move {
-                if let Err(e) = handle_connection(stream, &db_clone).await {
+                let id = client.id;
+                if let Err(e) = handle_connection(stream, codec, &databases, &scripts, client, subscriptions).await {
                     eprintln!("Error handling connection: {}", e);
                 }
+                databases.tracker().disable(id);
             });
         }
     }
 }
 
-async fn handle_connection(stream: TcpStream, db: &Arc<Db>) -> io::Result<()> {
-    let mut framed = Framed::new(stream, RespCodec);
+async fn purge_expired_keys(databases: Databases) {
+    let mut interval = tokio::time::interval(EXPIRY_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        databases.purge_expired_keys();
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    codec: RespCodec,
+    databases: &Databases,
+    scripts: &Scripts,
+    mut client: Client,
+    mut subscriptions: Subscriptions,
+) -> io::Result<()> {
+    let mut framed = Framed::new(stream, codec);
+    // Requests that arrived while the client was blocked, in the order they were sent.
+    let mut backlog = VecDeque::new();
+    let mut transaction = Transaction::default();
 
-    while let Some(result) = framed.next().await {
-        match result {
-            Ok(request) => {
-                let response = process_request(request, db);
-                framed.send(response).await?;
-            }
-            Err(e) => {
-                eprintln!("Error decoding frame: {}", e);
-                let error_response = RespType::Error(format!("Error: {}", e));
-                framed.send(error_response).await?;
+    loop {
+        let request = match backlog.pop_front() {
+            Some(request) => request,
+            None => tokio::select! {
+                request = framed.next() => match request {
+                    Some(Ok(request)) => request,
+                    Some(Err(e)) => {
+                        send_decode_error(&mut framed, e).await?;
+                        break;
+                    }
+                    None => break,
+                },
+                message = subscriptions.next_message() => match message {
+                    Some(message) => {
+                        if let Some(message) = subscriptions.deliverable(message, client.protocol) {
+                            framed.send(message).await?;
+                        }
+                        continue;
+                    }
+                    None => {
+                        eprintln!("Closing connection {}: too many messages pending", client.id);
+                        break;
+                    }
+                },
+            },
+        };
+
+        // Commands wait for a running script to finish, and are refused once it runs past
+        // the time limit, so that SCRIPT KILL is the only way to stop it.
+        if !matches!(parse_script_command(&request), Some(Ok(ScriptCommand::Kill))) {
+            if let Err(err) = scripts.wait_until_idle().await {
+                framed.send(error_response(err)).await?;
+                continue;
             }
         }
+
+        // CLIENT CACHING only applies to the command right after it.
+        let caching = client.caching.take();
+        let restricted = subscriptions.is_active() && client.protocol == ProtocolVersion::Resp2;
+        let response = if let Some(response) = restricted.then(|| subscribed_reply(&request)).flatten() {
+            response
+        } else if let Some(command) = parse_transaction_command(&request) {
+            match command {
+                Ok(command) => {
+                    let response = on_behalf_of(client.id, || {
+                        transaction.execute(command, &mut client, databases, &mut subscriptions, scripts)
+                    });
+                    // EXEC may have run a HELLO.
+                    framed.codec_mut().set_protocol(client.protocol);
+                    response
+                }
+                Err(err) => {
+                    transaction.fail();
+                    error_response(err)
+                }
+            }
+        } else if transaction.is_queuing() {
+            transaction.queue(request).unwrap_or_else(error_response)
+        } else if let Some(command) = parse_script_command(&request) {
+            match command {
+                Ok(command) => on_behalf_of(client.id, || scripts.execute(command, &client, databases)),
+                Err(err) => error_response(err),
+            }
+        } else if let Some(command) = parse_pubsub_command(&request) {
+            match command {
+                Ok(command) => {
+                    for reply in subscriptions.execute(command) {
+                        framed.feed(reply).await?;
+                    }
+                    framed.flush().await?;
+                    continue;
+                }
+                Err(err) => error_response(err),
+            }
+        } else if let Some(command) = parse_connection_command(&request) {
+            match command {
+                Ok(command) => {
+                    let response = {
+                        let _shared = databases.shared();
+                        on_behalf_of(client.id, || command.execute(&mut client, databases))
+                    };
+                    // HELLO replies in the protocol version it just negotiated.
+                    framed.codec_mut().set_protocol(client.protocol);
+                    response
+                }
+                Err(err) => error_response(err),
+            }
+        } else if let Some(command) = parse_blocking_command(&request) {
+            match command {
+                Ok(command) => match run_blocking(command.as_ref(), &client, databases) {
+                    Blocking::Ready(response) => response,
+                    Blocking::Blocked(receiver, timeout) => {
+                        let waiting = wait_for_reply(&mut framed, &mut backlog, &mut subscriptions, &client, receiver, timeout);
+                        match waiting.await? {
+                            Some(response) => response,
+                            // The client disconnected while blocked.
+                            None => break,
+                        }
+                    }
+                },
+                Err(err) => error_response(err),
+            }
+        } else {
+            let _shared = databases.shared();
+            process_request(request, &client, caching, databases)
+        };
+        framed.send(response).await?;
     }
 
     Ok(())
 }
 
-fn process_request(request: RespType, db: &Db) -> RespType {
-    match parse_command(request) {
-        Ok(command) => command.execute(db),
-        Err(err) => match err {
-            BifrostError::CommandError(msg) | 
-            BifrostError::StorageError(msg) |
-            BifrostError::ProtocolError(msg) => RespType::Error(msg),
-            BifrostError::IoError(e) => RespType::Error(format!("ERR {}", e)),
+// A RESP2 connection with subscriptions may only change them or PING, which replies in
+// the shape of a message. Returns the reply to anything else.
+fn subscribed_reply(request: &RespType) -> Option<RespType> {
+    let name = match request {
+        RespType::Array(array) => match array.first() {
+            Some(RespType::BulkString(name)) => String::from_utf8_lossy(name).to_lowercase(),
+            _ => String::new(),
+        },
+        _ => String::new(),
+    };
+    match name.as_str() {
+        "subscribe" | "unsubscribe" | "psubscribe" | "punsubscribe" | "ssubscribe" | "sunsubscribe" => None,
+        "ping" => {
+            let message = match request {
+                RespType::Array(array) => match array.get(1) {
+                    Some(RespType::BulkString(message)) => message.clone(),
+                    _ => Bytes::new(),
+                },
+                _ => Bytes::new(),
+            };
+            Some(RespType::Array(vec![
+                RespType::BulkString(Bytes::from_static(b"pong")),
+                RespType::BulkString(message),
+            ]))
         }
+        _ => Some(RespType::Error(format!(
+            "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+            name
+        ))),
+    }
+}
+
+// Runs a blocking command up to the point where it either replies or parks the client,
+// without holding off transactions while it waits.
+fn run_blocking(command: &dyn BlockingCommand, client: &Client, databases: &Databases) -> Blocking {
+    let _shared = databases.shared();
+    on_behalf_of(client.id, || command.execute(&databases[client.db]))
+}
+
+// Parks a blocked client until its reply arrives or the timeout elapses. Requests the
+// client pipelines meanwhile are kept for later, as Redis does, while messages and
+// invalidations are still pushed to it. Returns `None` if the connection closes first.
+async fn wait_for_reply(
+    framed: &mut Framed<TcpStream, RespCodec>,
+    backlog: &mut VecDeque<RespType>,
+    subscriptions: &mut Subscriptions,
+    client: &Client,
+    mut receiver: oneshot::Receiver<RespType>,
+    timeout: Option<Duration>,
+) -> io::Result<Option<RespType>> {
+    let deadline = timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+    let mut connection_open = true;
+    loop {
+        tokio::select! {
+            reply = &mut receiver => return Ok(reply.ok()),
+            _ = sleep_until(deadline) => break,
+            request = framed.next(), if connection_open => match request {
+                Some(Ok(request)) => backlog.push_back(request),
+                _ => connection_open = false,
+            },
+            message = subscriptions.next_message() => match message {
+                Some(message) => {
+                    if let Some(message) = subscriptions.deliverable(message, client.protocol) {
+                        framed.send(message).await?;
+                    }
+                }
+                None => {
+                    eprintln!("Closing connection {}: too many messages pending", client.id);
+                    connection_open = false;
+                }
+            },
+        }
+        if !connection_open {
+            return Ok(None);
+        }
+    }
+    // A reply may have been sent just as the timeout fired.
+    receiver.close();
+    Ok(Some(receiver.try_recv().unwrap_or(RespType::NullArray)))
+}
+
+async fn sleep_until(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn send_decode_error(framed: &mut Framed<TcpStream, RespCodec>, e: io::Error) -> io::Result<()> {
+    eprintln!("Error decoding frame: {}", e);
+    // Like Redis, report protocol violations and then close the connection.
+    let error_response = match e.get_ref().and_then(|e| e.downcast_ref::<RespError>()) {
+        Some(RespError::QueryBufferExceeded) => return Ok(()),
+        Some(RespError::Protocol(msg)) => RespType::Error(format!("ERR Protocol error: {}", msg)),
+        _ => RespType::Error(format!("Error: {}", e)),
+    };
+    framed.send(error_response).await
+}
+
+// Runs a regular command, remembering the keys it reads for the client's CLIENT
+// TRACKING.
+fn process_request(request: RespType, client: &Client, caching: Option<bool>, databases: &Databases) -> RespType {
+    match parse_command(request) {
+        Ok(command) => on_behalf_of(client.id, || execute_tracked(command.as_ref(), client, caching, databases)),
+        Err(err) => error_response(err),
+    }
+}
+
+fn error_response(err: BifrostError) -> RespType {
+    match err {
+        BifrostError::CommandError(msg) | 
+        BifrostError::StorageError(msg) |
+        BifrostError::ProtocolError(msg) => RespType::Error(msg),
+        BifrostError::IoError(e) => RespType::Error(format!("ERR {}", e)),
     }
 }
@@ -0,0 +1,403 @@
+use bytes::Bytes;
+use std::time::Instant;
+
+use crate::error::BifrostError;
+use crate::resp::RespType;
+use super::db::Db;
+use super::notify::EventClasses;
+use super::value::Value;
+
+/// Whether the range of BITCOUNT and BITPOS is in bytes or bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitUnit {
+    #[default]
+    Byte,
+    Bit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+/// A BITFIELD type such as `i5` or `u8`: signed up to 64 bits, unsigned up to 63.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitFieldType {
+    pub signed: bool,
+    pub bits: u32,
+}
+
+/// How BITFIELD SET and INCRBY handle values that do not fit their type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    #[default]
+    Wrap,
+    Sat,
+    Fail,
+}
+
+/// One BITFIELD operation. OVERFLOW applies to the SET and INCRBY operations after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldOp {
+    Get(BitFieldType, u64),
+    Set(BitFieldType, u64, i64),
+    IncrBy(BitFieldType, u64, i64),
+    Overflow(Overflow),
+}
+
+fn string_or_create() -> Value {
+    Value::String(Bytes::new())
+}
+
+// Resolves a BITCOUNT or BITPOS range, where negative offsets count from the end, to
+// the first and last bit it covers. `end` defaults to the end of the string.
+fn bit_range(len: usize, start: i64, end: Option<i64>, unit: BitUnit) -> Option<(u64, u64)> {
+    let total = match unit {
+        BitUnit::Byte => len as i64,
+        BitUnit::Bit => len as i64 * 8,
+    };
+    let end = end.unwrap_or(-1);
+    if start < 0 && end < 0 && start > end {
+        return None;
+    }
+    let start = if start < 0 { (total + start).max(0) } else { start };
+    let end = if end < 0 { (total + end).max(0) } else { end.min(total - 1) };
+    if total == 0 || start > end {
+        return None;
+    }
+    Some(match unit {
+        BitUnit::Byte => (start as u64 * 8, end as u64 * 8 + 7),
+        BitUnit::Bit => (start as u64, end as u64),
+    })
+}
+
+// The bytes covering bits `first..=last`, each with a mask of the bits in the range.
+fn bytes_in_range(bytes: &[u8], first: u64, last: u64) -> impl Iterator<Item = (usize, u8, u8)> + '_ {
+    let (first_byte, last_byte) = ((first / 8) as usize, (last / 8) as usize);
+    (first_byte..=last_byte).map(move |i| {
+        let mut mask = 0xFFu8;
+        if i == first_byte {
+            mask &= 0xFF >> (first % 8);
+        }
+        if i == last_byte {
+            mask &= 0xFF << (7 - last % 8);
+        }
+        (i, bytes[i], mask)
+    })
+}
+
+fn get_bit(bytes: &[u8], offset: u64) -> bool {
+    bytes.get((offset / 8) as usize).is_some_and(|byte| byte & (0x80 >> (offset % 8)) != 0)
+}
+
+// Sets a bit, growing the string with zero bytes as needed.
+fn set_bit(bytes: &mut Vec<u8>, offset: u64, bit: bool) {
+    let index = (offset / 8) as usize;
+    if bytes.len() <= index {
+        bytes.resize(index + 1, 0);
+    }
+    let mask = 0x80 >> (offset % 8);
+    if bit {
+        bytes[index] |= mask;
+    } else {
+        bytes[index] &= !mask;
+    }
+}
+
+impl BitFieldType {
+    fn min(&self) -> i128 {
+        if self.signed { -(1i128 << (self.bits - 1)) } else { 0 }
+    }
+
+    fn max(&self) -> i128 {
+        if self.signed { (1i128 << (self.bits - 1)) - 1 } else { (1i128 << self.bits) - 1 }
+    }
+
+    fn get(&self, bytes: &[u8], offset: u64) -> i64 {
+        let mut value: u64 = 0;
+        for i in 0..self.bits as u64 {
+            value = (value << 1) | get_bit(bytes, offset + i) as u64;
+        }
+        if self.signed && self.bits < 64 {
+            let shift = 64 - self.bits;
+            ((value << shift) as i64) >> shift
+        } else {
+            value as i64
+        }
+    }
+
+    fn set(&self, bytes: &mut Vec<u8>, offset: u64, value: i64) {
+        let value = value as u64;
+        for i in 0..self.bits as u64 {
+            set_bit(bytes, offset + i, (value >> (self.bits as u64 - 1 - i)) & 1 != 0);
+        }
+    }
+
+    // Fits a value to the type according to the overflow policy, or None to fail.
+    fn fit(&self, value: i128, overflow: Overflow) -> Option<i64> {
+        if (self.min()..=self.max()).contains(&value) {
+            return Some(value as i64);
+        }
+        match overflow {
+            Overflow::Wrap => {
+                let wrapped = value.rem_euclid(1i128 << self.bits);
+                Some(if wrapped > self.max() { wrapped - (1i128 << self.bits) } else { wrapped } as i64)
+            }
+            Overflow::Sat => Some(value.clamp(self.min(), self.max()) as i64),
+            Overflow::Fail => None,
+        }
+    }
+}
+
+impl Db {
+    /// SETBIT. Replies with the bit's previous value.
+    pub fn setbit(&self, key: &[u8], offset: u64, bit: bool) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let string = data.value_or_insert(key, Instant::now(), Value::as_string_mut, string_or_create)?;
+        let mut bytes = string.to_vec();
+        let previous = get_bit(&bytes, offset);
+        set_bit(&mut bytes, offset, bit);
+        // Like Redis, setting a bit to the value it has already is no change.
+        if **string != bytes {
+            *string = Bytes::from(bytes);
+            data.touch(key);
+            data.notify(EventClasses::STRING, "setbit", key);
+        }
+        Ok(RespType::Integer(previous as i64))
+    }
+
+    pub fn getbit(&self, key: &[u8], offset: u64) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let bit = data.value(key, Instant::now(), Value::as_string)?.is_some_and(|string| get_bit(string, offset));
+        Ok(RespType::Integer(bit as i64))
+    }
+
+    /// BITCOUNT, over the whole string or the given inclusive range.
+    pub fn bitcount(&self, key: &[u8], range: Option<(i64, i64, BitUnit)>) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let Some(string) = data.value(key, Instant::now(), Value::as_string)? else {
+            return Ok(RespType::Integer(0));
+        };
+        let (start, end, unit) = range.unwrap_or((0, -1, BitUnit::Byte));
+        let count = match bit_range(string.len(), start, Some(end), unit) {
+            Some((first, last)) => bytes_in_range(string, first, last)
+                .map(|(_, byte, mask)| (byte & mask).count_ones() as i64)
+                .sum(),
+            None => 0,
+        };
+        Ok(RespType::Integer(count))
+    }
+
+    /// BITPOS. Replies with the position of the first bit set to `bit` in the range,
+    /// or -1. Looking for a clear bit without an explicit end treats the string as
+    /// padded with zeros on the right.
+    pub fn bitpos(
+        &self,
+        key: &[u8],
+        bit: bool,
+        start: Option<i64>,
+        end: Option<i64>,
+        unit: BitUnit,
+    ) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let Some(string) = data.value(key, Instant::now(), Value::as_string)? else {
+            return Ok(RespType::Integer(if bit { -1 } else { 0 }));
+        };
+        let Some((first, last)) = bit_range(string.len(), start.unwrap_or(0), end, unit) else {
+            return Ok(RespType::Integer(-1));
+        };
+        for (i, byte, mask) in bytes_in_range(string, first, last) {
+            let candidates = if bit { byte & mask } else { !byte & mask };
+            if candidates != 0 {
+                return Ok(RespType::Integer(i as i64 * 8 + candidates.leading_zeros() as i64));
+            }
+        }
+        if !bit && end.is_none() {
+            return Ok(RespType::Integer(last as i64 + 1));
+        }
+        Ok(RespType::Integer(-1))
+    }
+
+    /// BITOP. Stores the result in `destination`, deleting it if the result is empty,
+    /// and replies with its length. Missing keys count as strings of zero bytes.
+    pub fn bitop(&self, operation: BitOperation, destination: Bytes, keys: &[Bytes]) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        let mut sources = Vec::with_capacity(keys.len());
+        for key in keys {
+            sources.push(data.value(key, now, Value::as_string)?.cloned().unwrap_or_default());
+        }
+        let len = sources.iter().map(Bytes::len).max().unwrap_or(0);
+        let byte_at = |source: &Bytes, i: usize| source.get(i).copied().unwrap_or(0);
+        let result: Vec<u8> = (0..len)
+            .map(|i| {
+                let mut bytes = sources.iter().map(|source| byte_at(source, i));
+                let first = bytes.next().unwrap_or(0);
+                match operation {
+                    BitOperation::And => bytes.fold(first, |acc, byte| acc & byte),
+                    BitOperation::Or => bytes.fold(first, |acc, byte| acc | byte),
+                    BitOperation::Xor => bytes.fold(first, |acc, byte| acc ^ byte),
+                    BitOperation::Not => !first,
+                }
+            })
+            .collect();
+
+        if result.is_empty() {
+            if data.remove(&destination).is_some() {
+                data.notify(EventClasses::GENERIC, "del", &destination);
+            }
+        } else {
+            data.insert(destination.clone(), Value::String(Bytes::from(result)), None);
+            data.notify(EventClasses::STRING, "set", &destination);
+        }
+        Ok(RespType::Integer(len as i64))
+    }
+
+    /// BITFIELD and BITFIELD_RO. Replies with one value per GET, SET (the old value) and
+    /// INCRBY (the new value), or null when an operation fails with OVERFLOW FAIL.
+    pub fn bitfield(&self, key: &[u8], ops: &[BitFieldOp]) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        let mut bytes = data.value(key, now, Value::as_string)?.map(|string| string.to_vec()).unwrap_or_default();
+
+        let mut overflow = Overflow::default();
+        let mut replies = Vec::new();
+        // The writes that applied, as opposed to failing with OVERFLOW FAIL.
+        let mut applied = 0;
+        for op in ops {
+            let reply = match *op {
+                BitFieldOp::Get(ty, offset) => Some(ty.get(&bytes, offset)),
+                BitFieldOp::Set(ty, offset, value) => {
+                    let old = ty.get(&bytes, offset);
+                    ty.fit(value as i128, overflow).map(|value| {
+                        ty.set(&mut bytes, offset, value);
+                        old
+                    })
+                }
+                BitFieldOp::IncrBy(ty, offset, increment) => {
+                    let old = ty.get(&bytes, offset);
+                    ty.fit(old as i128 + increment as i128, overflow).inspect(|value| ty.set(&mut bytes, offset, *value))
+                }
+                BitFieldOp::Overflow(policy) => {
+                    overflow = policy;
+                    continue;
+                }
+            };
+            if reply.is_some() && !matches!(op, BitFieldOp::Get(..)) {
+                applied += 1;
+            }
+            replies.push(reply.map_or(RespType::Null, RespType::Integer));
+        }
+
+        // The key is only created once a write applies, so one that fails leaves none.
+        if applied > 0 {
+            *data.value_or_insert(key, now, Value::as_string_mut, string_or_create)? = Bytes::from(bytes);
+            data.touch(key);
+            data.notify(EventClasses::STRING, "setbit", key);
+        }
+        Ok(RespType::Array(replies))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(bits: u32) -> BitFieldType {
+        BitFieldType { signed: false, bits }
+    }
+
+    fn i(bits: u32) -> BitFieldType {
+        BitFieldType { signed: true, bits }
+    }
+
+    #[test]
+    fn test_bits() {
+        let db = Db::new();
+        assert_eq!(db.setbit(b"bits", 7, true).unwrap(), RespType::Integer(0));
+        assert_eq!(db.setbit(b"bits", 7, true).unwrap(), RespType::Integer(1));
+        assert_eq!(db.get(b"bits").unwrap(), Some(Bytes::from_static(b"\x01")));
+        assert_eq!(db.getbit(b"bits", 7).unwrap(), RespType::Integer(1));
+        assert_eq!(db.getbit(b"bits", 100).unwrap(), RespType::Integer(0));
+
+        db.set(Bytes::from("key"), Bytes::from("foobar"));
+        assert_eq!(db.bitcount(b"key", None).unwrap(), RespType::Integer(26));
+        assert_eq!(db.bitcount(b"key", Some((1, 1, BitUnit::Byte))).unwrap(), RespType::Integer(6));
+        assert_eq!(db.bitcount(b"key", Some((5, 30, BitUnit::Bit))).unwrap(), RespType::Integer(17));
+        assert_eq!(db.bitcount(b"key", Some((-1, -2, BitUnit::Byte))).unwrap(), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_bitpos() {
+        let db = Db::new();
+        db.set(Bytes::from("key"), Bytes::from_static(b"\xff\xf0\x00"));
+        assert_eq!(db.bitpos(b"key", false, None, None, BitUnit::Byte).unwrap(), RespType::Integer(12));
+        assert_eq!(db.bitpos(b"key", true, Some(2), None, BitUnit::Byte).unwrap(), RespType::Integer(-1));
+        assert_eq!(db.bitpos(b"key", true, Some(7), Some(15), BitUnit::Bit).unwrap(), RespType::Integer(7));
+
+        db.set(Bytes::from("ones"), Bytes::from_static(b"\xff\xff"));
+        // Without an end the string is padded with zeros, with one it is not.
+        assert_eq!(db.bitpos(b"ones", false, None, None, BitUnit::Byte).unwrap(), RespType::Integer(16));
+        assert_eq!(db.bitpos(b"ones", false, Some(0), Some(-1), BitUnit::Byte).unwrap(), RespType::Integer(-1));
+        assert_eq!(db.bitpos(b"missing", false, None, None, BitUnit::Byte).unwrap(), RespType::Integer(0));
+        assert_eq!(db.bitpos(b"missing", true, None, None, BitUnit::Byte).unwrap(), RespType::Integer(-1));
+    }
+
+    #[test]
+    fn test_bitop() {
+        let db = Db::new();
+        db.set(Bytes::from("a"), Bytes::from_static(b"\xf0\x0f"));
+        db.set(Bytes::from("b"), Bytes::from_static(b"\xff"));
+        let keys = [Bytes::from("a"), Bytes::from("b")];
+        assert_eq!(db.bitop(BitOperation::And, Bytes::from("and"), &keys).unwrap(), RespType::Integer(2));
+        assert_eq!(db.get(b"and").unwrap(), Some(Bytes::from_static(b"\xf0\x00")));
+        db.bitop(BitOperation::Or, Bytes::from("or"), &keys).unwrap();
+        assert_eq!(db.get(b"or").unwrap(), Some(Bytes::from_static(b"\xff\x0f")));
+        db.bitop(BitOperation::Xor, Bytes::from("xor"), &keys).unwrap();
+        assert_eq!(db.get(b"xor").unwrap(), Some(Bytes::from_static(b"\x0f\x0f")));
+        db.bitop(BitOperation::Not, Bytes::from("not"), &keys[..1]).unwrap();
+        assert_eq!(db.get(b"not").unwrap(), Some(Bytes::from_static(b"\x0f\xf0")));
+
+        assert_eq!(db.bitop(BitOperation::Or, Bytes::from("not"), &[Bytes::from("missing")]).unwrap(), RespType::Integer(0));
+        assert_eq!(db.exists(&[Bytes::from("not")]), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_bitfield() {
+        let db = Db::new();
+        let ops = [
+            BitFieldOp::Set(u(8), 0, 255),
+            BitFieldOp::Get(i(8), 0),
+            BitFieldOp::IncrBy(u(8), 0, 10),
+            BitFieldOp::Overflow(Overflow::Sat),
+            BitFieldOp::IncrBy(u(8), 0, 1000),
+            BitFieldOp::IncrBy(i(4), 8, -100),
+            BitFieldOp::Overflow(Overflow::Fail),
+            BitFieldOp::IncrBy(u(8), 0, 1),
+            BitFieldOp::Get(u(8), 0),
+        ];
+        let expected = [
+            RespType::Integer(0),
+            RespType::Integer(-1),
+            RespType::Integer(9),
+            RespType::Integer(255),
+            RespType::Integer(-8),
+            RespType::Null,
+            RespType::Integer(255),
+        ];
+        assert_eq!(db.bitfield(b"field", &ops).unwrap(), RespType::Array(expected.to_vec()));
+        assert_eq!(db.get(b"field").unwrap(), Some(Bytes::from_static(b"\xff\x80")));
+
+        let ops = [BitFieldOp::Set(i(64), 3, i64::MIN), BitFieldOp::Get(i(64), 3)];
+        assert_eq!(db.bitfield(b"wide", &ops).unwrap(), RespType::Array(vec![RespType::Integer(0), RespType::Integer(i64::MIN)]));
+        assert_eq!(db.bitfield(b"missing", &[BitFieldOp::Get(u(4), 0)]).unwrap(), RespType::Array(vec![RespType::Integer(0)]));
+        assert_eq!(db.exists(&[Bytes::from("missing")]), RespType::Integer(0));
+
+        let ops = [BitFieldOp::Overflow(Overflow::Fail), BitFieldOp::IncrBy(u(8), 0, 256)];
+        assert_eq!(db.bitfield(b"missing", &ops).unwrap(), RespType::Array(vec![RespType::Null]));
+        assert_eq!(db.exists(&[Bytes::from("missing")]), RespType::Integer(0));
+    }
+}
@@ -0,0 +1,85 @@
+use bytes::Bytes;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use crate::resp::RespType;
+use super::consumer_group::GroupRead;
+use super::list::ListEnd;
+use super::stream::StreamId;
+
+/// The outcome of a command that may have to wait for data.
+pub enum Blocking {
+    /// The reply is available right away.
+    Ready(RespType),
+    /// The client is parked until a reply arrives on the receiver or the timeout
+    /// elapses, in which case it gets a null reply. `None` waits forever.
+    Blocked(oneshot::Receiver<RespType>, Option<Duration>),
+}
+
+/// The sending half shared by every key a client is blocked on. Whichever key is
+/// served first takes the sender, so a client is woken at most once.
+pub(super) type SharedReply = Arc<Mutex<Option<oneshot::Sender<RespType>>>>;
+
+pub(super) fn shared_reply() -> (SharedReply, oneshot::Receiver<RespType>) {
+    let (sender, receiver) = oneshot::channel();
+    (Arc::new(Mutex::new(Some(sender))), receiver)
+}
+
+// False once the client was served on another key, timed out or disconnected.
+fn is_pending(reply: &SharedReply) -> bool {
+    reply.lock().as_ref().is_some_and(|sender| !sender.is_closed())
+}
+
+/// A client blocked in BLPOP, BRPOP or BLMOVE.
+pub(super) struct ListWaiter {
+    pub(super) end: ListEnd,
+    // Where BLMOVE pushes the element it pops.
+    pub(super) destination: Option<(Bytes, ListEnd)>,
+    pub(super) reply: SharedReply,
+}
+
+impl ListWaiter {
+    /// False once the waiter was served elsewhere, timed out or disconnected.
+    pub(super) fn is_pending(&self) -> bool {
+        is_pending(&self.reply)
+    }
+}
+
+impl std::fmt::Debug for ListWaiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ListWaiter")
+            .field("end", &self.end)
+            .field("destination", &self.destination)
+            .field("pending", &self.is_pending())
+            .finish()
+    }
+}
+
+/// A client blocked in XREAD or XREADGROUP on one of its streams.
+pub(super) struct StreamWaiter {
+    // Only entries with a greater ID wake an XREAD client.
+    pub(super) after: StreamId,
+    pub(super) count: Option<usize>,
+    // Set for XREADGROUP, which is woken by entries not yet delivered to the group.
+    pub(super) group: Option<GroupRead>,
+    pub(super) reply: SharedReply,
+}
+
+impl StreamWaiter {
+    pub(super) fn is_pending(&self) -> bool {
+        is_pending(&self.reply)
+    }
+}
+
+impl std::fmt::Debug for StreamWaiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamWaiter")
+            .field("after", &self.after)
+            .field("count", &self.count)
+            .field("group", &self.group)
+            .field("pending", &self.is_pending())
+            .finish()
+    }
+}
@@ -0,0 +1,929 @@
+use bytes::Bytes;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+use std::time::{Duration, Instant};
+
+use crate::error::BifrostError;
+use crate::resp::RespType;
+use super::blocking::{shared_reply, Blocking, StreamWaiter};
+use super::db::{Db, State};
+use super::notify::EventClasses;
+use super::stream::{entry_reply, now_ms, Fields, Stream, StreamId, StreamPosition};
+use super::value::Value;
+
+/// An entry delivered to a consumer that it has not acknowledged yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEntry {
+    pub consumer: Bytes,
+    // Milliseconds since the epoch of the last delivery, from which idle times are computed.
+    pub delivery_time: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Consumer {
+    // When the consumer last tried to read or claim, and last actually got entries.
+    pub seen_time: u64,
+    pub active_time: Option<u64>,
+    pub pending: BTreeSet<StreamId>,
+}
+
+/// A consumer group: the last entry delivered to any of its consumers and the pending
+/// entries list (PEL) of everything delivered but not acknowledged.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConsumerGroup {
+    pub last_delivered: StreamId,
+    // How many entries of the stream the group has read, unknown after some deletions.
+    pub entries_read: Option<u64>,
+    pub pending: BTreeMap<StreamId, PendingEntry>,
+    pub consumers: BTreeMap<Bytes, Consumer>,
+}
+
+/// The GROUP and NOACK options of XREADGROUP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupRead {
+    pub group: Bytes,
+    pub consumer: Bytes,
+    pub noack: bool,
+}
+
+/// Where XREADGROUP reads a stream: entries never delivered to the group (`>`), or the
+/// consumer's own pending entries after an ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupPosition {
+    Undelivered,
+    Pending(StreamId),
+}
+
+/// The extended form of XPENDING: `[IDLE min-idle] start end count [consumer]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingRange {
+    pub min_idle: u64,
+    pub start: StreamId,
+    pub end: StreamId,
+    pub count: usize,
+    pub consumer: Option<Bytes>,
+}
+
+/// The options of XCLAIM. IDLE and TIME set the delivery time, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClaimOptions {
+    pub idle: Option<u64>,
+    pub time: Option<u64>,
+    pub retry_count: Option<u64>,
+    pub force: bool,
+    pub justid: bool,
+    pub last_id: Option<StreamId>,
+}
+
+enum Claim {
+    Claimed,
+    // The entry was deleted from the stream, so it was dropped from the PEL instead.
+    Deleted,
+    Skipped,
+}
+
+// Looks up a consumer, creating it if needed, and records that it was seen.
+fn touch<'a>(consumers: &'a mut BTreeMap<Bytes, Consumer>, name: &Bytes, now: u64) -> &'a mut Consumer {
+    let consumer = consumers.entry(name.clone()).or_insert_with(|| Consumer {
+        seen_time: now,
+        active_time: None,
+        pending: BTreeSet::new(),
+    });
+    consumer.seen_time = now;
+    consumer
+}
+
+fn deleted_entry_reply(id: StreamId) -> RespType {
+    RespType::Array(vec![RespType::BulkString(id.to_bytes()), RespType::Null])
+}
+
+impl ConsumerGroup {
+    fn new(last_delivered: StreamId, entries_read: Option<u64>) -> Self {
+        ConsumerGroup {
+            last_delivered,
+            entries_read,
+            ..Default::default()
+        }
+    }
+
+    fn drop_pending(&mut self, id: StreamId) -> bool {
+        let Some(pending) = self.pending.remove(&id) else {
+            return false;
+        };
+        if let Some(owner) = self.consumers.get_mut(&pending.consumer) {
+            owner.pending.remove(&id);
+        }
+        true
+    }
+
+    // Makes `consumer` the owner of the pending entry `id`, creating the entry with no
+    // deliveries if needed. The caller updates the delivery time and count.
+    fn assign(&mut self, id: StreamId, consumer: &Bytes, now: u64) -> &mut PendingEntry {
+        let pending = self.pending.entry(id).or_insert_with(|| PendingEntry {
+            consumer: consumer.clone(),
+            delivery_time: now,
+            delivery_count: 0,
+        });
+        if pending.consumer != *consumer {
+            if let Some(owner) = self.consumers.get_mut(&pending.consumer) {
+                owner.pending.remove(&id);
+            }
+            pending.consumer = consumer.clone();
+        }
+        touch(&mut self.consumers, consumer, now).pending.insert(id);
+        pending
+    }
+
+    // Delivers the consumer's pending entries after `after` again. Entries deleted from
+    // the stream since are replied with null fields.
+    fn read_pending(
+        &mut self,
+        entries: &BTreeMap<StreamId, Fields>,
+        consumer: &Bytes,
+        after: StreamId,
+        count: Option<usize>,
+        now: u64,
+    ) -> Vec<RespType> {
+        let ids: Vec<StreamId> = touch(&mut self.consumers, consumer, now)
+            .pending
+            .range((Bound::Excluded(after), Bound::Unbounded))
+            .take(count.unwrap_or(usize::MAX))
+            .copied()
+            .collect();
+        ids.into_iter()
+            .map(|id| match entries.get(&id) {
+                Some(fields) => {
+                    if let Some(pending) = self.pending.get_mut(&id) {
+                        pending.delivery_time = now;
+                        pending.delivery_count += 1;
+                    }
+                    entry_reply(id, fields)
+                }
+                None => deleted_entry_reply(id),
+            })
+            .collect()
+    }
+
+    fn claim(
+        &mut self,
+        entries: &BTreeMap<StreamId, Fields>,
+        id: StreamId,
+        consumer: &Bytes,
+        min_idle: u64,
+        options: &ClaimOptions,
+        now: u64,
+    ) -> Claim {
+        if !entries.contains_key(&id) {
+            return if self.drop_pending(id) { Claim::Deleted } else { Claim::Skipped };
+        }
+        match self.pending.get(&id) {
+            Some(pending) if now.saturating_sub(pending.delivery_time) < min_idle => return Claim::Skipped,
+            None if !options.force => return Claim::Skipped,
+            _ => {}
+        }
+        let delivery_time = match (options.time, options.idle) {
+            (Some(time), _) => time,
+            (None, Some(idle)) => now.saturating_sub(idle),
+            (None, None) => now,
+        };
+        let pending = self.assign(id, consumer, now);
+        pending.delivery_time = delivery_time;
+        match options.retry_count {
+            Some(count) => pending.delivery_count = count,
+            None if !options.justid => pending.delivery_count += 1,
+            None => {}
+        }
+        Claim::Claimed
+    }
+
+    fn pending_summary(&self) -> RespType {
+        let (Some(min), Some(max)) = (self.pending.keys().next(), self.pending.keys().next_back()) else {
+            return RespType::Array(vec![RespType::Integer(0), RespType::Null, RespType::Null, RespType::Null]);
+        };
+        let mut counts: BTreeMap<&Bytes, usize> = BTreeMap::new();
+        for pending in self.pending.values() {
+            *counts.entry(&pending.consumer).or_default() += 1;
+        }
+        let consumers = counts
+            .into_iter()
+            .map(|(consumer, count)| {
+                RespType::Array(vec![
+                    RespType::BulkString(consumer.clone()),
+                    RespType::BulkString(Bytes::from(count.to_string())),
+                ])
+            })
+            .collect();
+        RespType::Array(vec![
+            RespType::Integer(self.pending.len() as i64),
+            RespType::BulkString(min.to_bytes()),
+            RespType::BulkString(max.to_bytes()),
+            RespType::Array(consumers),
+        ])
+    }
+
+    fn pending_range(&self, range: &PendingRange, now: u64) -> RespType {
+        if range.start > range.end {
+            return RespType::Array(vec![]);
+        }
+        let entries = self
+            .pending
+            .range(range.start..=range.end)
+            .filter(|(_, pending)| range.consumer.as_ref().is_none_or(|consumer| *consumer == pending.consumer))
+            .filter(|(_, pending)| now.saturating_sub(pending.delivery_time) >= range.min_idle)
+            .take(range.count)
+            .map(|(id, pending)| {
+                RespType::Array(vec![
+                    RespType::BulkString(id.to_bytes()),
+                    RespType::BulkString(pending.consumer.clone()),
+                    RespType::Integer(now.saturating_sub(pending.delivery_time) as i64),
+                    RespType::Integer(pending.delivery_count as i64),
+                ])
+            })
+            .collect();
+        RespType::Array(entries)
+    }
+}
+
+impl Stream {
+    // Whether an entry at or after `id` was deleted, which makes counting the entries a
+    // group has read by incrementing unreliable.
+    fn has_tombstones_from(&self, id: StreamId) -> bool {
+        !self.entries.is_empty() && self.max_deleted_id != StreamId::MIN && self.max_deleted_id >= id
+    }
+
+    // How many entries were added up to and including `id`, if that can be known
+    // despite deletions.
+    fn entries_up_to(&self, id: StreamId) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        if id >= self.last_id || self.entries.is_empty() {
+            return (id <= self.last_id).then_some(self.entries_added);
+        }
+        let first = *self.entries.keys().next()?;
+        let no_gaps = self.max_deleted_id == StreamId::MIN || self.max_deleted_id < first;
+        (no_gaps && id < first).then_some(self.entries_added - self.entries.len() as u64)
+    }
+
+    // The number of entries in the stream the group has not read yet.
+    fn lag(&self, group: &ConsumerGroup) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        let read = match group.entries_read {
+            Some(read) if !self.has_tombstones_from(group.last_delivered) => Some(read),
+            _ => self.entries_up_to(group.last_delivered),
+        };
+        read.map(|read| self.entries_added.saturating_sub(read))
+    }
+
+    /// Delivers entries the group has never delivered to the reading consumer, adding
+    /// them to its PEL unless NOACK was given.
+    pub(super) fn read_undelivered(&mut self, read: &GroupRead, count: Option<usize>, now: u64) -> Vec<RespType> {
+        let Some(group) = self.groups.get(&read.group) else {
+            return vec![];
+        };
+        let delivered: Vec<StreamId> = match group.last_delivered.next() {
+            Some(start) => self
+                .entries
+                .range(start..)
+                .take(count.unwrap_or(usize::MAX))
+                .map(|(id, _)| *id)
+                .collect(),
+            None => vec![],
+        };
+        let mut entries_read = group.entries_read;
+        for id in &delivered {
+            entries_read = match entries_read {
+                Some(read) if !self.has_tombstones_from(*id) => Some(read + 1),
+                _ => self.entries_up_to(*id),
+            };
+        }
+        let replies = delivered
+            .iter()
+            .filter_map(|id| self.entries.get(id).map(|fields| entry_reply(*id, fields)))
+            .collect();
+
+        let Some(group) = self.groups.get_mut(&read.group) else {
+            return vec![];
+        };
+        let consumer = touch(&mut group.consumers, &read.consumer, now);
+        let Some(last) = delivered.last() else {
+            return replies;
+        };
+        consumer.active_time = Some(now);
+        group.last_delivered = *last;
+        group.entries_read = entries_read;
+        if !read.noack {
+            for id in delivered {
+                let pending = group.assign(id, &read.consumer, now);
+                pending.delivery_time = now;
+                pending.delivery_count = 1;
+            }
+        }
+        replies
+    }
+
+    fn group_info(&self, name: &Bytes, group: &ConsumerGroup) -> RespType {
+        RespType::Map(vec![
+            (field("name"), RespType::BulkString(name.clone())),
+            (field("consumers"), RespType::Integer(group.consumers.len() as i64)),
+            (field("pending"), RespType::Integer(group.pending.len() as i64)),
+            (field("last-delivered-id"), RespType::BulkString(group.last_delivered.to_bytes())),
+            (field("entries-read"), optional_integer(group.entries_read)),
+            (field("lag"), optional_integer(self.lag(group))),
+        ])
+    }
+
+    // The group as listed by XINFO STREAM FULL, with its PEL and consumers.
+    fn full_group_info(&self, name: &Bytes, group: &ConsumerGroup, count: usize) -> RespType {
+        let pending = group
+            .pending
+            .iter()
+            .take(count)
+            .map(|(id, pending)| {
+                RespType::Array(vec![
+                    RespType::BulkString(id.to_bytes()),
+                    RespType::BulkString(pending.consumer.clone()),
+                    RespType::Integer(pending.delivery_time as i64),
+                    RespType::Integer(pending.delivery_count as i64),
+                ])
+            })
+            .collect();
+        let consumers = group
+            .consumers
+            .iter()
+            .map(|(name, consumer)| {
+                let pending = consumer
+                    .pending
+                    .iter()
+                    .take(count)
+                    .filter_map(|id| group.pending.get(id).map(|pending| (id, pending)))
+                    .map(|(id, pending)| {
+                        RespType::Array(vec![
+                            RespType::BulkString(id.to_bytes()),
+                            RespType::Integer(pending.delivery_time as i64),
+                            RespType::Integer(pending.delivery_count as i64),
+                        ])
+                    })
+                    .collect();
+                RespType::Map(vec![
+                    (field("name"), RespType::BulkString(name.clone())),
+                    (field("seen-time"), RespType::Integer(consumer.seen_time as i64)),
+                    (field("active-time"), optional_integer(consumer.active_time)),
+                    (field("pel-count"), RespType::Integer(consumer.pending.len() as i64)),
+                    (field("pending"), RespType::Array(pending)),
+                ])
+            })
+            .collect();
+        RespType::Map(vec![
+            (field("name"), RespType::BulkString(name.clone())),
+            (field("last-delivered-id"), RespType::BulkString(group.last_delivered.to_bytes())),
+            (field("entries-read"), optional_integer(group.entries_read)),
+            (field("lag"), optional_integer(self.lag(group))),
+            (field("pel-count"), RespType::Integer(group.pending.len() as i64)),
+            (field("pending"), RespType::Array(pending)),
+            (field("consumers"), RespType::Array(consumers)),
+        ])
+    }
+}
+
+fn field(name: &'static str) -> RespType {
+    RespType::BulkString(Bytes::from_static(name.as_bytes()))
+}
+
+fn optional_integer(value: Option<u64>) -> RespType {
+    value.map_or(RespType::Null, |value| RespType::Integer(value as i64))
+}
+
+fn no_group(key: &[u8], group: &[u8]) -> BifrostError {
+    BifrostError::StorageError(format!(
+        "NOGROUP No such key '{}' or consumer group '{}'",
+        String::from_utf8_lossy(key),
+        String::from_utf8_lossy(group)
+    ))
+}
+
+fn no_such_group(key: &[u8], group: &[u8]) -> BifrostError {
+    BifrostError::StorageError(format!(
+        "NOGROUP No such consumer group '{}' for key name '{}'",
+        String::from_utf8_lossy(group),
+        String::from_utf8_lossy(key)
+    ))
+}
+
+fn key_required() -> BifrostError {
+    BifrostError::StorageError(
+        "ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically."
+            .to_string(),
+    )
+}
+
+fn no_such_key() -> BifrostError {
+    BifrostError::StorageError("ERR no such key".to_string())
+}
+
+type GroupMut<'a> = (&'a BTreeMap<StreamId, Fields>, &'a mut ConsumerGroup);
+
+impl State {
+    // The entries of the stream at `key` along with one of its groups, or None if either
+    // does not exist.
+    fn group_mut(
+        &mut self,
+        key: &[u8],
+        group: &[u8],
+        now: Instant,
+    ) -> Result<Option<GroupMut<'_>>, BifrostError> {
+        let Some(stream) = self.value_mut(key, now, Value::as_stream_mut)? else {
+            return Ok(None);
+        };
+        Ok(stream.groups.get_mut(group).map(|group| (&stream.entries, group)))
+    }
+
+    fn existing_stream_mut(&mut self, key: &[u8], now: Instant) -> Result<&mut Stream, BifrostError> {
+        self.value_mut(key, now, Value::as_stream_mut)?.ok_or_else(key_required)
+    }
+}
+
+impl Db {
+    /// XGROUP CREATE. The group starts after `position`, or after the last entry for `$`.
+    pub fn xgroup_create(
+        &self,
+        key: &[u8],
+        group: Bytes,
+        position: StreamPosition,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    ) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        if !mkstream && data.value(key, now, Value::as_stream)?.is_none() {
+            return Err(key_required());
+        }
+        let stream = data.value_or_insert(key, now, Value::as_stream_mut, || Value::Stream(Stream::default()))?;
+        if stream.groups.contains_key(&group) {
+            return Err(BifrostError::StorageError("BUSYGROUP Consumer Group name already exists".to_string()));
+        }
+        let last_delivered = match position {
+            StreamPosition::After(id) => id,
+            StreamPosition::Latest => stream.last_id,
+        };
+        stream.groups.insert(group, ConsumerGroup::new(last_delivered, entries_read));
+        data.touch(key);
+        data.notify(EventClasses::STREAM, "xgroup-create", key);
+        Ok(RespType::SimpleString("OK".to_string()))
+    }
+
+    /// XGROUP SETID. Entries after the new position are delivered to the group again.
+    pub fn xgroup_setid(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        position: StreamPosition,
+        entries_read: Option<u64>,
+    ) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let stream = data.existing_stream_mut(key, Instant::now())?;
+        let last_id = stream.last_id;
+        let group = stream.groups.get_mut(group).ok_or_else(|| no_such_group(key, group))?;
+        group.last_delivered = match position {
+            StreamPosition::After(id) => id,
+            StreamPosition::Latest => last_id,
+        };
+        group.entries_read = entries_read;
+        data.touch(key);
+        data.notify(EventClasses::STREAM, "xgroup-setid", key);
+        Ok(RespType::SimpleString("OK".to_string()))
+    }
+
+    /// XGROUP DESTROY. Clients blocked reading from the group get an error.
+    pub fn xgroup_destroy(&self, key: &[u8], group: &[u8]) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let stream = data.existing_stream_mut(key, Instant::now())?;
+        if stream.groups.remove(group).is_none() {
+            return Ok(RespType::Integer(0));
+        }
+        if let Some(waiters) = data.stream_waiters.get_mut(key) {
+            waiters.retain(|waiter| {
+                if waiter.group.as_ref().is_none_or(|read| read.group != group) {
+                    return true;
+                }
+                if let Some(sender) = waiter.reply.lock().take() {
+                    let _ = sender.send(RespType::Error(
+                        "NOGROUP the consumer group this client was blocked on no longer exists".to_string(),
+                    ));
+                }
+                false
+            });
+        }
+        data.touch(key);
+        data.notify(EventClasses::STREAM, "xgroup-destroy", key);
+        Ok(RespType::Integer(1))
+    }
+
+    /// XGROUP CREATECONSUMER. Replies with 1 if the consumer was created.
+    pub fn xgroup_create_consumer(&self, key: &[u8], group: &[u8], consumer: Bytes) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let stream = data.existing_stream_mut(key, Instant::now())?;
+        let group = stream.groups.get_mut(group).ok_or_else(|| no_such_group(key, group))?;
+        if group.consumers.contains_key(&consumer) {
+            return Ok(RespType::Integer(0));
+        }
+        touch(&mut group.consumers, &consumer, now_ms());
+        data.touch(key);
+        data.notify(EventClasses::STREAM, "xgroup-createconsumer", key);
+        Ok(RespType::Integer(1))
+    }
+
+    /// XGROUP DELCONSUMER. Replies with the number of pending entries the consumer had,
+    /// which are dropped from the group's PEL.
+    pub fn xgroup_delete_consumer(&self, key: &[u8], group: &[u8], consumer: &[u8]) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let stream = data.existing_stream_mut(key, Instant::now())?;
+        let group = stream.groups.get_mut(group).ok_or_else(|| no_such_group(key, group))?;
+        let Some(consumer) = group.consumers.remove(consumer) else {
+            return Ok(RespType::Integer(0));
+        };
+        for id in &consumer.pending {
+            group.pending.remove(id);
+        }
+        data.touch(key);
+        data.notify(EventClasses::STREAM, "xgroup-delconsumer", key);
+        Ok(RespType::Integer(consumer.pending.len() as i64))
+    }
+
+    /// XREADGROUP. New entries (`>`) are delivered to the consumer and, when none are
+    /// available and `block` is set, the client waits for some. Reading pending entries
+    /// by ID never blocks.
+    pub fn xreadgroup(
+        &self,
+        read: &GroupRead,
+        streams: &[(Bytes, GroupPosition)],
+        count: Option<usize>,
+        block: Option<Option<Duration>>,
+    ) -> Result<Blocking, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        for (key, _) in streams {
+            if data.group_mut(key, &read.group, now)?.is_none() {
+                return Err(BifrostError::StorageError(format!(
+                    "NOGROUP No such key '{}' or consumer group '{}' in XREADGROUP with GROUP option",
+                    String::from_utf8_lossy(key),
+                    String::from_utf8_lossy(&read.group)
+                )));
+            }
+        }
+
+        let time = now_ms();
+        let mut replies = Vec::new();
+        let mut history = false;
+        for (key, position) in streams {
+            let entries = match position {
+                GroupPosition::Undelivered => match data.value_mut(key, now, Value::as_stream_mut)? {
+                    Some(stream) => stream.read_undelivered(read, count, time),
+                    None => vec![],
+                },
+                GroupPosition::Pending(after) => {
+                    history = true;
+                    match data.group_mut(key, &read.group, now)? {
+                        Some((entries, group)) => group.read_pending(entries, &read.consumer, *after, count, time),
+                        None => vec![],
+                    }
+                }
+            };
+            if !entries.is_empty() || matches!(position, GroupPosition::Pending(_)) {
+                replies.push(RespType::Array(vec![
+                    RespType::BulkString(key.clone()),
+                    RespType::Array(entries),
+                ]));
+            }
+        }
+
+        let timeout = match block {
+            _ if !replies.is_empty() || history => return Ok(Blocking::Ready(RespType::Array(replies))),
+            None => return Ok(Blocking::Ready(RespType::Null)),
+            Some(timeout) => timeout,
+        };
+        let (reply, receiver) = shared_reply();
+        for (key, _) in streams {
+            data.stream_waiters.entry(key.clone()).or_default().push(StreamWaiter {
+                after: StreamId::MIN,
+                count,
+                group: Some(read.clone()),
+                reply: reply.clone(),
+            });
+        }
+        Ok(Blocking::Blocked(receiver, timeout))
+    }
+
+    /// XACK. Replies with the number of entries removed from the group's PEL.
+    pub fn xack(&self, key: &[u8], group: &[u8], ids: &[StreamId]) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let acknowledged = match data.group_mut(key, group, Instant::now())? {
+            Some((_, group)) => ids.iter().filter(|id| group.drop_pending(**id)).count(),
+            None => 0,
+        };
+        Ok(RespType::Integer(acknowledged as i64))
+    }
+
+    /// XPENDING. Without a range, replies with a summary of the group's PEL.
+    pub fn xpending(&self, key: &[u8], group: &[u8], range: Option<&PendingRange>) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let (_, consumer_group) = data.group_mut(key, group, Instant::now())?.ok_or_else(|| no_group(key, group))?;
+        Ok(match range {
+            Some(range) => consumer_group.pending_range(range, now_ms()),
+            None => consumer_group.pending_summary(),
+        })
+    }
+
+    /// XCLAIM. Moves pending entries idle for at least `min_idle` milliseconds to
+    /// `consumer` and replies with them, or with their IDs for JUSTID.
+    pub fn xclaim(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &Bytes,
+        min_idle: u64,
+        ids: &[StreamId],
+        options: &ClaimOptions,
+    ) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let (entries, consumer_group) = data.group_mut(key, group, Instant::now())?.ok_or_else(|| no_group(key, group))?;
+        let now = now_ms();
+        touch(&mut consumer_group.consumers, consumer, now);
+        if let Some(last_id) = options.last_id {
+            consumer_group.last_delivered = consumer_group.last_delivered.max(last_id);
+        }
+        let mut claimed = Vec::new();
+        for id in ids {
+            if let Claim::Claimed = consumer_group.claim(entries, *id, consumer, min_idle, options, now) {
+                claimed.push(match entries.get(id) {
+                    Some(fields) if !options.justid => entry_reply(*id, fields),
+                    _ => RespType::BulkString(id.to_bytes()),
+                });
+            }
+        }
+        if !claimed.is_empty() {
+            touch(&mut consumer_group.consumers, consumer, now).active_time = Some(now);
+        }
+        Ok(RespType::Array(claimed))
+    }
+
+    /// XAUTOCLAIM. Claims up to `count` entries idle for at least `min_idle`
+    /// milliseconds, scanning the PEL from `start`. Replies with the cursor to continue
+    /// from (0-0 when done), the claimed entries, and the IDs of deleted entries that
+    /// were dropped from the PEL.
+    #[allow(clippy::too_many_arguments)]
+    pub fn xautoclaim(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &Bytes,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        justid: bool,
+    ) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let (entries, consumer_group) = data.group_mut(key, group, Instant::now())?.ok_or_else(|| no_group(key, group))?;
+        let now = now_ms();
+        touch(&mut consumer_group.consumers, consumer, now);
+        // Like Redis, look at no more than ten entries per requested one.
+        let attempts = count.saturating_mul(10);
+        let candidates: Vec<StreamId> = consumer_group
+            .pending
+            .range(start..)
+            .take(attempts.saturating_add(1))
+            .map(|(id, _)| *id)
+            .collect();
+        let options = ClaimOptions {
+            justid,
+            ..Default::default()
+        };
+
+        let mut next = StreamId::MIN;
+        let mut claimed = Vec::new();
+        let mut deleted = Vec::new();
+        for (attempt, id) in candidates.into_iter().enumerate() {
+            if claimed.len() == count || attempt == attempts {
+                next = id;
+                break;
+            }
+            match consumer_group.claim(entries, id, consumer, min_idle, &options, now) {
+                Claim::Claimed => claimed.push(match entries.get(&id) {
+                    Some(fields) if !justid => entry_reply(id, fields),
+                    _ => RespType::BulkString(id.to_bytes()),
+                }),
+                Claim::Deleted => deleted.push(RespType::BulkString(id.to_bytes())),
+                Claim::Skipped => {}
+            }
+        }
+        if !claimed.is_empty() {
+            touch(&mut consumer_group.consumers, consumer, now).active_time = Some(now);
+        }
+        Ok(RespType::Array(vec![
+            RespType::BulkString(next.to_bytes()),
+            RespType::Array(claimed),
+            RespType::Array(deleted),
+        ]))
+    }
+
+    /// XINFO STREAM. With `full`, the reply lists up to that many entries, pending
+    /// entries and consumers per group instead of summarising (0 lists everything).
+    pub fn xinfo_stream(&self, key: &[u8], full: Option<usize>) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let stream = data.value(key, Instant::now(), Value::as_stream)?.ok_or_else(no_such_key)?;
+        let first_id = stream.entries.keys().next().copied().unwrap_or_default();
+        let mut info = vec![
+            (field("length"), RespType::Integer(stream.len() as i64)),
+            (field("last-generated-id"), RespType::BulkString(stream.last_id.to_bytes())),
+            (field("max-deleted-entry-id"), RespType::BulkString(stream.max_deleted_id.to_bytes())),
+            (field("entries-added"), RespType::Integer(stream.entries_added as i64)),
+            (field("recorded-first-entry-id"), RespType::BulkString(first_id.to_bytes())),
+        ];
+        match full {
+            None => {
+                let edge = |entry: Option<(&StreamId, &Fields)>| entry.map_or(RespType::Null, |(id, fields)| entry_reply(*id, fields));
+                info.push((field("groups"), RespType::Integer(stream.groups.len() as i64)));
+                info.push((field("first-entry"), edge(stream.entries.first_key_value())));
+                info.push((field("last-entry"), edge(stream.entries.last_key_value())));
+            }
+            Some(count) => {
+                let count = if count == 0 { usize::MAX } else { count };
+                let entries = stream.range(StreamId::MIN, StreamId::MAX, Some(count), false);
+                let groups = stream
+                    .groups
+                    .iter()
+                    .map(|(name, group)| stream.full_group_info(name, group, count))
+                    .collect();
+                info.push((field("entries"), RespType::Array(entries)));
+                info.push((field("groups"), RespType::Array(groups)));
+            }
+        }
+        Ok(RespType::Map(info))
+    }
+
+    pub fn xinfo_groups(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let stream = data.value(key, Instant::now(), Value::as_stream)?.ok_or_else(no_such_key)?;
+        let groups = stream.groups.iter().map(|(name, group)| stream.group_info(name, group)).collect();
+        Ok(RespType::Array(groups))
+    }
+
+    pub fn xinfo_consumers(&self, key: &[u8], group: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let stream = data.value(key, Instant::now(), Value::as_stream)?.ok_or_else(no_such_key)?;
+        let consumer_group = stream.groups.get(group).ok_or_else(|| no_such_group(key, group))?;
+        let now = now_ms();
+        let consumers = consumer_group
+            .consumers
+            .iter()
+            .map(|(name, consumer)| {
+                let inactive = consumer.active_time.map_or(-1, |active| now.saturating_sub(active) as i64);
+                RespType::Map(vec![
+                    (field("name"), RespType::BulkString(name.clone())),
+                    (field("pending"), RespType::Integer(consumer.pending.len() as i64)),
+                    (field("idle"), RespType::Integer(now.saturating_sub(consumer.seen_time) as i64)),
+                    (field("inactive"), RespType::Integer(inactive)),
+                ])
+            })
+            .collect();
+        Ok(RespType::Array(consumers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::stream::{XaddId, XaddOptions};
+
+    fn add(db: &Db, ms: u64) {
+        let fields = vec![(Bytes::from("n"), Bytes::from(ms.to_string()))];
+        db.xadd(b"s", XaddId::Explicit(StreamId::new(ms, 0)), fields, XaddOptions::default()).unwrap();
+    }
+
+    fn read(consumer: &str) -> GroupRead {
+        GroupRead {
+            group: Bytes::from("g"),
+            consumer: Bytes::from(consumer.to_string()),
+            noack: false,
+        }
+    }
+
+    fn delivered(blocking: Blocking) -> usize {
+        let Blocking::Ready(RespType::Array(streams)) = blocking else {
+            return 0;
+        };
+        match &streams[0] {
+            RespType::Array(stream) => match &stream[1] {
+                RespType::Array(entries) => entries.len(),
+                other => panic!("Unexpected entries {:?}", other),
+            },
+            other => panic!("Unexpected stream {:?}", other),
+        }
+    }
+
+    fn pending_count(db: &Db) -> RespType {
+        let RespType::Array(summary) = db.xpending(b"s", b"g", None).unwrap() else {
+            panic!("Expected an array");
+        };
+        summary[0].clone()
+    }
+
+    #[test]
+    fn test_create_and_read() {
+        let db = Db::new();
+        assert!(db.xgroup_create(b"s", Bytes::from("g"), StreamPosition::Latest, false, None).is_err());
+        for ms in 1..=3 {
+            add(&db, ms);
+        }
+        db.xgroup_create(b"s", Bytes::from("g"), StreamPosition::After(StreamId::MIN), false, None).unwrap();
+        assert!(db.xgroup_create(b"s", Bytes::from("g"), StreamPosition::Latest, false, None).is_err());
+
+        let undelivered = [(Bytes::from("s"), GroupPosition::Undelivered)];
+        assert_eq!(delivered(db.xreadgroup(&read("alice"), &undelivered, Some(2), None).unwrap()), 2);
+        assert_eq!(delivered(db.xreadgroup(&read("bob"), &undelivered, None, None).unwrap()), 1);
+        assert_eq!(db.xreadgroup(&read("bob"), &undelivered, None, None).map(delivered).unwrap(), 0);
+        assert_eq!(pending_count(&db), RespType::Integer(3));
+
+        // Reading history returns the consumer's own pending entries.
+        let history = [(Bytes::from("s"), GroupPosition::Pending(StreamId::MIN))];
+        assert_eq!(delivered(db.xreadgroup(&read("alice"), &history, None, None).unwrap()), 2);
+
+        assert_eq!(db.xack(b"s", b"g", &[StreamId::new(1, 0), StreamId::new(1, 0), StreamId::new(9, 0)]).unwrap(), RespType::Integer(1));
+        assert_eq!(pending_count(&db), RespType::Integer(2));
+        let RespType::Array(groups) = db.xinfo_groups(b"s").unwrap() else {
+            panic!("Expected an array");
+        };
+        let RespType::Map(info) = &groups[0] else {
+            panic!("Expected a map");
+        };
+        assert_eq!(info[5], (field("lag"), RespType::Integer(0)));
+    }
+
+    #[test]
+    fn test_blocking_read() {
+        let db = Db::new();
+        db.xgroup_create(b"s", Bytes::from("g"), StreamPosition::Latest, true, None).unwrap();
+        let undelivered = [(Bytes::from("s"), GroupPosition::Undelivered)];
+        let Blocking::Blocked(mut receiver, None) = db.xreadgroup(&read("alice"), &undelivered, None, Some(None)).unwrap() else {
+            panic!("Expected to block");
+        };
+        add(&db, 1);
+        assert!(matches!(receiver.try_recv().unwrap(), RespType::Array(_)));
+        assert_eq!(pending_count(&db), RespType::Integer(1));
+
+        let Blocking::Blocked(mut receiver, None) = db.xreadgroup(&read("alice"), &undelivered, None, Some(None)).unwrap() else {
+            panic!("Expected to block");
+        };
+        assert_eq!(db.xgroup_destroy(b"s", b"g").unwrap(), RespType::Integer(1));
+        assert!(matches!(receiver.try_recv().unwrap(), RespType::Error(_)));
+    }
+
+    #[test]
+    fn test_claim() {
+        let db = Db::new();
+        for ms in 1..=3 {
+            add(&db, ms);
+        }
+        db.xgroup_create(b"s", Bytes::from("g"), StreamPosition::After(StreamId::MIN), false, None).unwrap();
+        let undelivered = [(Bytes::from("s"), GroupPosition::Undelivered)];
+        db.xreadgroup(&read("alice"), &undelivered, None, None).unwrap();
+
+        let bob = Bytes::from("bob");
+        let ids = [StreamId::new(1, 0), StreamId::new(2, 0)];
+        // Nothing has been idle for an hour yet.
+        assert_eq!(db.xclaim(b"s", b"g", &bob, 3_600_000, &ids, &ClaimOptions::default()).unwrap(), RespType::Array(vec![]));
+        let justid = ClaimOptions { justid: true, ..Default::default() };
+        assert_eq!(
+            db.xclaim(b"s", b"g", &bob, 0, &ids, &justid).unwrap(),
+            RespType::Array(vec![RespType::BulkString(Bytes::from("1-0")), RespType::BulkString(Bytes::from("2-0"))])
+        );
+        let range = PendingRange {
+            min_idle: 0,
+            start: StreamId::MIN,
+            end: StreamId::MAX,
+            count: 10,
+            consumer: Some(bob.clone()),
+        };
+        let RespType::Array(pending) = db.xpending(b"s", b"g", Some(&range)).unwrap() else {
+            panic!("Expected an array");
+        };
+        assert_eq!(pending.len(), 2);
+
+        // Deleted entries are reported by XAUTOCLAIM and leave the PEL.
+        db.xtrim(b"s", crate::storage::stream::TrimOptions {
+            strategy: crate::storage::stream::TrimStrategy::MaxLen(2),
+            limit: None,
+        })
+        .unwrap();
+        let RespType::Array(reply) = db.xautoclaim(b"s", b"g", &Bytes::from("carol"), 0, StreamId::MIN, 1, false).unwrap() else {
+            panic!("Expected an array");
+        };
+        assert_eq!(reply[0], RespType::BulkString(Bytes::from("3-0")));
+        assert_eq!(reply[2], RespType::Array(vec![RespType::BulkString(Bytes::from("1-0"))]));
+        assert_eq!(db.xgroup_delete_consumer(b"s", b"g", b"carol").unwrap(), RespType::Integer(1));
+        assert_eq!(pending_count(&db), RespType::Integer(1));
+    }
+}
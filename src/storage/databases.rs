@@ -0,0 +1,369 @@
+use bytes::Bytes;
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::ops::Index;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use crate::error::BifrostError;
+use crate::resp::RespType;
+use crate::pubsub::Broker;
+use super::db::{Db, State};
+use super::notify::{EventClasses, Notifier};
+use super::tracking::Tracker;
+use super::value::Value;
+
+/// How many databases a server has unless configured otherwise, as in Redis.
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// The most keys one expiry cycle removes, and the longest it runs, like the bounds of
+/// Redis's activeExpireCycle. What is left is swept on the next cycle.
+pub const EXPIRY_CYCLE_KEYS: usize = 2000;
+pub const EXPIRY_CYCLE_TIME: Duration = Duration::from_millis(25);
+
+/// Runs `f`, which may keep the thread busy for long, after handing the other tasks of
+/// this worker thread to another one. Otherwise connections that could make progress,
+/// if only to reply BUSY to a long script, would wait for `f` on this thread.
+pub(crate) fn hand_off_other_tasks<R>(f: impl FnOnce() -> R) -> R {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => tokio::task::block_in_place(f),
+        _ => f(),
+    }
+}
+
+/// The numbered keyspaces of a server. Connections start on database 0 and switch
+/// between them with SELECT.
+#[derive(Debug, Clone)]
+pub struct Databases {
+    dbs: Arc<[Db]>,
+    // Held shared while a command runs and exclusively while EXEC runs a transaction or
+    // a script runs.
+    exec: Arc<RwLock<()>>,
+    notifier: Arc<Notifier>,
+    tracker: Arc<Tracker>,
+    broker: Broker,
+    // The database the next expiry cycle starts from, so that one with many expired
+    // keys does not keep the cycle from reaching the others.
+    next_sweep: Arc<AtomicUsize>,
+}
+
+impl Default for Databases {
+    fn default() -> Self {
+        Self::new(DEFAULT_DATABASES)
+    }
+}
+
+impl Index<usize> for Databases {
+    type Output = Db;
+
+    fn index(&self, index: usize) -> &Db {
+        &self.dbs[index]
+    }
+}
+
+// Stores a copied value and deadline under `key` unless the key exists and may not be
+// replaced. Returns whether it was stored.
+fn paste(target: &mut State, key: Bytes, copy: Option<(Value, Option<Instant>)>, replace: bool, now: Instant) -> bool {
+    let Some((value, expires_at)) = copy else {
+        return false;
+    };
+    if !replace && target.get(&key, now).is_some() {
+        return false;
+    }
+    target.insert(key.clone(), value, expires_at);
+    target.notify(EventClasses::GENERIC, "copy_to", &key);
+    target.serve_waiters(&key, now);
+    true
+}
+
+fn out_of_range() -> BifrostError {
+    BifrostError::StorageError("ERR DB index is out of range".to_string())
+}
+
+impl Databases {
+    pub fn new(count: usize) -> Self {
+        Self::with_broker(count, Broker::default())
+    }
+
+    /// Databases that publish their keyspace events and deliver tracking invalidations
+    /// through `broker`.
+    pub fn with_broker(count: usize, broker: Broker) -> Self {
+        let notifier = Arc::new(Notifier::new(broker.clone()));
+        let tracker = Arc::new(Tracker::new(broker.clone()));
+        Databases {
+            dbs: (0..count).map(|index| Db::with_listeners(index, notifier.clone(), tracker.clone())).collect(),
+            exec: Arc::default(),
+            notifier,
+            tracker,
+            broker,
+            next_sweep: Arc::default(),
+        }
+    }
+
+    /// Controls which keyspace events are published, as set by notify-keyspace-events.
+    pub fn notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+
+    /// Keeps track of the keys connections cache, as set up by CLIENT TRACKING.
+    pub fn tracker(&self) -> &Tracker {
+        &self.tracker
+    }
+
+    /// The broker the databases publish through, for PUBLISH from scripts.
+    pub fn broker(&self) -> &Broker {
+        &self.broker
+    }
+
+    /// Taken by every command, so that none runs while a transaction or a script does.
+    /// Waiting for it happens off the async worker, as a script may hold it for long.
+    pub fn shared(&self) -> RwLockReadGuard<'_, ()> {
+        self.exec.try_read().unwrap_or_else(|| hand_off_other_tasks(|| self.exec.read()))
+    }
+
+    /// Taken by EXEC and by scripts, so that their commands run without any other in
+    /// between.
+    pub fn exclusive(&self) -> RwLockWriteGuard<'_, ()> {
+        self.exec.try_write().unwrap_or_else(|| hand_off_other_tasks(|| self.exec.write()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.dbs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dbs.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Db> {
+        self.dbs.get(index)
+    }
+
+    /// Fails with Redis' error unless `index` names a database.
+    pub fn check_index(&self, index: usize) -> Result<(), BifrostError> {
+        if index < self.len() { Ok(()) } else { Err(out_of_range()) }
+    }
+
+    // Write locks two different databases, always in index order so that concurrent
+    // MOVEs and SWAPDBs between the same pair cannot deadlock.
+    fn lock_pair(&self, a: usize, b: usize) -> (RwLockWriteGuard<'_, State>, RwLockWriteGuard<'_, State>) {
+        if a < b {
+            let first = self.dbs[a].data.write();
+            (first, self.dbs[b].data.write())
+        } else {
+            let second = self.dbs[b].data.write();
+            (self.dbs[a].data.write(), second)
+        }
+    }
+
+    /// SWAPDB. Connections that selected either database see the other's keys from
+    /// then on.
+    pub fn swap(&self, a: usize, b: usize) -> Result<RespType, BifrostError> {
+        self.check_index(a)?;
+        self.check_index(b)?;
+        if a != b {
+            let (mut first, mut second) = self.lock_pair(a, b);
+            first.swap_keys(&mut second, Instant::now());
+        }
+        Ok(RespType::SimpleString("OK".to_string()))
+    }
+
+    /// MOVE. Moves a key and its time to live to another database, unless the key is
+    /// missing or the target already has it. Replies with whether it was moved.
+    pub fn move_key(&self, key: &[u8], from: usize, to: usize) -> Result<RespType, BifrostError> {
+        self.check_index(to)?;
+        if from == to {
+            return Err(BifrostError::StorageError(
+                "ERR source and destination objects are the same".to_string(),
+            ));
+        }
+        let now = Instant::now();
+        let (mut source, mut target) = self.lock_pair(from, to);
+        source.remove_if_expired(key, now);
+        if source.get(key, now).is_none() || target.get(key, now).is_some() {
+            return Ok(RespType::Integer(0));
+        }
+        let Some(entry) = source.remove(key) else {
+            return Ok(RespType::Integer(0));
+        };
+        target.insert(Bytes::copy_from_slice(key), entry.value, entry.expires_at);
+        source.notify(EventClasses::GENERIC, "move_from", key);
+        target.notify(EventClasses::GENERIC, "move_to", key);
+        target.serve_waiters(key, now);
+        Ok(RespType::Integer(1))
+    }
+
+    /// COPY. Copies a key and its time to live, to another database if `to` differs
+    /// from `from`. Without `replace` an existing destination is left alone. Replies
+    /// with whether the key was copied.
+    pub fn copy(&self, key: &[u8], from: usize, destination: Bytes, to: usize, replace: bool) -> Result<RespType, BifrostError> {
+        self.check_index(to)?;
+        let now = Instant::now();
+        let copied = if from == to {
+            if key == destination {
+                return Err(BifrostError::StorageError(
+                    "ERR source and destination objects are the same".to_string(),
+                ));
+            }
+            let mut data = self.dbs[from].data.write();
+            let copy = data.get(key, now).map(|entry| (entry.value.clone(), entry.expires_at));
+            paste(&mut data, destination, copy, replace, now)
+        } else {
+            let (source, mut target) = self.lock_pair(from, to);
+            let copy = source.get(key, now).map(|entry| (entry.value.clone(), entry.expires_at));
+            paste(&mut target, destination, copy, replace, now)
+        };
+        Ok(RespType::Integer(copied as i64))
+    }
+
+    /// FLUSHALL.
+    pub fn flush_all(&self) -> RespType {
+        for db in self.dbs.iter() {
+            db.data.write().clear();
+        }
+        self.tracker.invalidate_all();
+        RespType::SimpleString("OK".to_string())
+    }
+
+    /// Runs one expiry cycle, sweeping expired keys from the databases in turn until
+    /// [`EXPIRY_CYCLE_KEYS`] were removed or [`EXPIRY_CYCLE_TIME`] ran out. Returns how
+    /// many keys were removed. Like any other command, a cycle waits for a running
+    /// transaction or script, so keys do not expire halfway through one.
+    pub fn purge_expired_keys(&self) -> usize {
+        let _shared = self.shared();
+        let stop_at = Instant::now() + EXPIRY_CYCLE_TIME;
+        let start = self.next_sweep.load(Ordering::Relaxed);
+        let mut purged = 0;
+        for offset in 0..self.dbs.len() {
+            let index = (start + offset) % self.dbs.len();
+            purged += self.dbs[index].purge_expired_keys(EXPIRY_CYCLE_KEYS - purged, stop_at);
+            if purged == EXPIRY_CYCLE_KEYS || Instant::now() >= stop_at {
+                self.next_sweep.store(index, Ordering::Relaxed);
+                break;
+            }
+        }
+        purged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::db::Expiration;
+    use crate::storage::list::ListEnd;
+    use std::time::Duration;
+
+    #[test]
+    fn test_move_key() {
+        let databases = Databases::new(2);
+        databases[0].set(Bytes::from("key"), Bytes::from("value"));
+        databases[0].expire(b"key", Expiration::In(Duration::from_secs(100)), Default::default(), "expire");
+
+        assert_eq!(databases.move_key(b"key", 0, 1).unwrap(), RespType::Integer(1));
+        assert_eq!(databases[0].exists(&[Bytes::from("key")]), RespType::Integer(0));
+        assert_eq!(databases[1].get(b"key").unwrap(), Some(Bytes::from("value")));
+        assert_eq!(databases[1].ttl(b"key"), RespType::Integer(100));
+
+        // Keys are not moved onto existing ones, and missing keys are not moved at all.
+        databases[0].set(Bytes::from("key"), Bytes::from("other"));
+        assert_eq!(databases.move_key(b"key", 0, 1).unwrap(), RespType::Integer(0));
+        assert_eq!(databases.move_key(b"missing", 0, 1).unwrap(), RespType::Integer(0));
+        assert!(databases.move_key(b"key", 0, 0).is_err());
+        assert!(databases.move_key(b"key", 0, 2).is_err());
+    }
+
+    #[test]
+    fn test_copy() {
+        let databases = Databases::new(2);
+        databases[0].push(b"list", &[Bytes::from("a")], ListEnd::Right).unwrap();
+        databases[0].expire(b"list", Expiration::In(Duration::from_secs(100)), Default::default(), "expire");
+
+        assert_eq!(databases.copy(b"list", 0, Bytes::from("copy"), 0, false).unwrap(), RespType::Integer(1));
+        assert_eq!(databases[0].ttl(b"copy"), RespType::Integer(100));
+        databases[0].push(b"copy", &[Bytes::from("b")], ListEnd::Right).unwrap();
+        assert_eq!(databases[0].llen(b"list").unwrap(), RespType::Integer(1));
+
+        assert_eq!(databases.copy(b"copy", 0, Bytes::from("list"), 0, false).unwrap(), RespType::Integer(0));
+        assert_eq!(databases.copy(b"copy", 0, Bytes::from("list"), 0, true).unwrap(), RespType::Integer(1));
+        assert_eq!(databases[0].llen(b"list").unwrap(), RespType::Integer(2));
+
+        assert_eq!(databases.copy(b"list", 0, Bytes::from("list"), 1, false).unwrap(), RespType::Integer(1));
+        assert_eq!(databases[1].llen(b"list").unwrap(), RespType::Integer(2));
+        assert_eq!(databases.copy(b"missing", 0, Bytes::from("x"), 1, false).unwrap(), RespType::Integer(0));
+        assert!(databases.copy(b"list", 0, Bytes::from("list"), 0, false).is_err());
+    }
+
+    #[test]
+    fn test_expiry_cycle_is_bounded() {
+        let databases = Databases::new(2);
+        for db in [0, 1] {
+            for i in 0..EXPIRY_CYCLE_KEYS {
+                databases[db].set(Bytes::from(format!("key{}", i)), Bytes::from("value"));
+                databases[db].expire(format!("key{}", i).as_bytes(), Expiration::In(Duration::from_millis(1)), Default::default(), "expire");
+            }
+        }
+        std::thread::sleep(Duration::from_millis(5));
+
+        // Each cycle stops at its key limit, and the next one picks up where it stopped.
+        let mut cycles = 0;
+        while databases[0].dbsize() != RespType::Integer(0) || databases[1].dbsize() != RespType::Integer(0) {
+            assert!(databases.purge_expired_keys() <= EXPIRY_CYCLE_KEYS);
+            cycles += 1;
+        }
+        assert!(cycles >= 2);
+        assert_eq!(databases.purge_expired_keys(), 0);
+    }
+
+    #[test]
+    fn test_expiry_cycle_waits_for_exec() {
+        let databases = Databases::new(1);
+        databases[0].set(Bytes::from("key"), Bytes::from("value"));
+        databases[0].expire(b"key", Expiration::In(Duration::from_millis(1)), Default::default(), "expire");
+        std::thread::sleep(Duration::from_millis(5));
+
+        let exclusive = databases.exclusive();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let sweeper = databases.clone();
+        let cycle = std::thread::spawn(move || sender.send(sweeper.purge_expired_keys()));
+        assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+        drop(exclusive);
+        assert_eq!(receiver.recv().unwrap(), 1);
+        cycle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_swap_and_flush() {
+        let databases = Databases::new(3);
+        databases[0].set(Bytes::from("a"), Bytes::from("1"));
+        databases[1].set(Bytes::from("b"), Bytes::from("2"));
+        databases[1].set(Bytes::from("c"), Bytes::from("3"));
+
+        assert!(databases.swap(0, 1).is_ok());
+        assert_eq!(databases[0].dbsize(), RespType::Integer(2));
+        assert_eq!(databases[1].get(b"a").unwrap(), Some(Bytes::from("1")));
+        assert!(databases.swap(0, 3).is_err());
+
+        assert_eq!(databases[0].flushdb(), RespType::SimpleString("OK".to_string()));
+        assert_eq!(databases[0].dbsize(), RespType::Integer(0));
+        assert_eq!(databases[1].dbsize(), RespType::Integer(1));
+        databases.flush_all();
+        assert_eq!(databases[1].dbsize(), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_swap_serves_blocked_clients() {
+        let databases = Databases::new(2);
+        databases[1].push(b"list", &[Bytes::from("value")], ListEnd::Right).unwrap();
+        let Ok(crate::storage::blocking::Blocking::Blocked(mut receiver, _)) =
+            databases[0].blocking_pop(&[Bytes::from("list")], ListEnd::Left, None)
+        else {
+            panic!("expected the pop to block");
+        };
+        databases.swap(0, 1).unwrap();
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            RespType::Array(vec![RespType::BulkString(Bytes::from("list")), RespType::BulkString(Bytes::from("value"))])
+        );
+    }
+}
@@ -1,12 +1,336 @@
 use crate::resp::RespType;
+use bytes::Bytes;
 use parking_lot::RwLock;
-use std::collections::HashMap;
+use rand::Rng;
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use crate::error::BifrostError;
+use super::blocking::{ListWaiter, StreamWaiter};
+use super::notify::{EventClasses, Notifier};
+use super::scan::ScanMap;
+use super::tracking::Tracker;
+use super::value::Value;
+use super::watch::Watch;
+
+/// How many expired keys the active sweep removes each time it takes the write lock.
+pub const EXPIRY_BATCH: usize = 20;
+
+// How many random positions RANDOMKEY tries before it settles for a walk to a live key.
+const RANDOM_KEY_TRIES: usize = 100;
+
+/// When a key should expire, either relative to now or as an absolute unix time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiration {
+    In(Duration),
+    At(SystemTime),
+}
+
+impl Expiration {
+    /// The instant a key set to expire now expires at, or `None` if that is too far
+    /// ahead to represent, either as an instant or, like Redis, as unix milliseconds.
+    pub fn deadline(&self, now: Instant) -> Option<Instant> {
+        let unix_time = match self {
+            Expiration::In(duration) => SystemTime::now().checked_add(*duration)?,
+            Expiration::At(time) => *time,
+        };
+        let unix_millis = unix_time.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |since| since.as_millis());
+        if unix_millis > i64::MAX as u128 {
+            return None;
+        }
+        match self {
+            Expiration::In(duration) => now.checked_add(*duration),
+            Expiration::At(time) => match time.duration_since(SystemTime::now()) {
+                Ok(remaining) => now.checked_add(remaining),
+                Err(_) => Some(now),
+            },
+        }
+    }
+}
+
+/// The reply to an expiration that [`Expiration::deadline`] cannot represent.
+pub fn invalid_expire_time(command: &str) -> String {
+    format!("ERR invalid expire time in '{}' command", command)
+}
+
+/// The NX/XX condition of a SET command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SetCondition {
+    #[default]
+    Always,
+    IfNotExists,
+    IfExists,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SetOptions {
+    pub condition: SetCondition,
+    pub expiration: Option<Expiration>,
+    pub keep_ttl: bool,
+}
+
+/// The NX/XX/GT/LT condition of the EXPIRE command family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExpireCondition {
+    #[default]
+    Always,
+    IfNoExpiry,
+    IfHasExpiry,
+    IfGreater,
+    IfLess,
+}
+
+#[derive(Debug)]
+pub(super) struct Entry {
+    pub(super) value: Value,
+    pub(super) expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+pub(super) fn wrong_type() -> BifrostError {
+    BifrostError::StorageError(
+        "WRONGTYPE Operation against a key holding the wrong kind of value".to_string(),
+    )
+}
+
+#[derive(Debug, Default)]
+pub(super) struct State {
+    entries: ScanMap<Entry>,
+    // Keys with a TTL ordered by deadline, so the sweep only visits what is due.
+    expirations: BTreeSet<(Instant, Bytes)>,
+    // Clients blocked on each list key, in the order they blocked.
+    pub(super) list_waiters: HashMap<Bytes, VecDeque<ListWaiter>>,
+    // Clients blocked in XREAD on each stream key.
+    pub(super) stream_waiters: HashMap<Bytes, Vec<StreamWaiter>>,
+    // Version counters of the keys some client has WATCHed.
+    pub(super) watched: HashMap<Bytes, Watch>,
+    // The number of the database, which keyspace events are published under.
+    index: usize,
+    notifier: Arc<Notifier>,
+    tracker: Arc<Tracker>,
+}
+
+impl State {
+    pub(super) fn get(&self, key: &[u8], now: Instant) -> Option<&Entry> {
+        self.entries.get(key).filter(|entry| !entry.is_expired(now))
+    }
+
+    /// Every key that has not expired, in no particular order.
+    pub(super) fn live_entries(&self, now: Instant) -> impl Iterator<Item = (&Bytes, &Entry)> {
+        self.entries.iter().filter(move |(_, entry)| !entry.is_expired(now))
+    }
+
+    /// A random key that has not expired, found without visiting every key: each try
+    /// looks at the key at a random position, and should they all find expired keys,
+    /// the last walks on from there to the next live one.
+    pub(super) fn random_entry(&self, now: Instant) -> Option<(&Bytes, &Entry)> {
+        let mut rng = rand::thread_rng();
+        let live = |(_, entry): &(&Bytes, &Entry)| !entry.is_expired(now);
+        (0..RANDOM_KEY_TRIES)
+            .find_map(|_| self.entries.iter_from(rng.gen()).next().filter(live))
+            .or_else(|| self.entries.iter_from(rng.gen()).find(live))
+    }
+
+    /// One SCAN step over the keys, leaving out the ones that expired.
+    pub(super) fn scan(&self, cursor: u64, count: usize, now: Instant) -> (u64, Vec<(&Bytes, &Entry)>) {
+        let (next, mut batch) = self.entries.scan(cursor, count);
+        batch.retain(|(_, entry)| !entry.is_expired(now));
+        (next, batch)
+    }
+
+    /// Write access to an entry. Callers that change it must [`State::touch`] it after.
+    pub(super) fn get_mut(&mut self, key: &[u8], now: Instant) -> Option<&mut Entry> {
+        self.remove_if_expired(key, now);
+        self.entries.get_mut(key)
+    }
+
+    /// Looks up `key` as the type selected by `cast`, failing with WRONGTYPE when the
+    /// key holds another type.
+    pub(super) fn value<'a, T: ?Sized>(
+        &'a self,
+        key: &[u8],
+        now: Instant,
+        cast: impl FnOnce(&'a Value) -> Option<&'a T>,
+    ) -> Result<Option<&'a T>, BifrostError> {
+        match self.get(key, now) {
+            Some(entry) => cast(&entry.value).map(Some).ok_or_else(wrong_type),
+            None => Ok(None),
+        }
+    }
+
+    pub(super) fn value_mut<T: ?Sized>(
+        &mut self,
+        key: &[u8],
+        now: Instant,
+        cast: impl FnOnce(&mut Value) -> Option<&mut T>,
+    ) -> Result<Option<&mut T>, BifrostError> {
+        match self.get_mut(key, now) {
+            Some(entry) => cast(&mut entry.value).map(Some).ok_or_else(wrong_type),
+            None => Ok(None),
+        }
+    }
+
+    /// Like [`State::value_mut`], but first stores `create()` under `key` if it is missing.
+    /// The new key is left for the caller to touch, once it stored something in it.
+    pub(super) fn value_or_insert<T: ?Sized>(
+        &mut self,
+        key: &[u8],
+        now: Instant,
+        cast: impl FnOnce(&mut Value) -> Option<&mut T>,
+        create: impl FnOnce() -> Value,
+    ) -> Result<&mut T, BifrostError> {
+        if self.get_mut(key, now).is_none() {
+            let key = Bytes::copy_from_slice(key);
+            self.notify(EventClasses::NEW, "new", &key);
+            self.entries.insert(key, Entry { value: create(), expires_at: None });
+        }
+        self.value_mut(key, now, cast)?.ok_or_else(wrong_type)
+    }
+
+    /// Deletes `key` if it holds a collection that no longer has any elements. Like
+    /// emptying it, that is for the caller to touch.
+    pub(super) fn remove_if_empty(&mut self, key: &[u8]) {
+        if self.entries.get(key).is_some_and(|entry| entry.value.is_empty()) {
+            self.unlink(key);
+            self.notify(EventClasses::GENERIC, "del", key);
+        }
+    }
+
+    pub(super) fn insert(&mut self, key: Bytes, value: Value, expires_at: Option<Instant>) {
+        if self.remove(&key).is_none() {
+            self.notify(EventClasses::NEW, "new", &key);
+        }
+        self.touch(&key);
+        if let Some(at) = expires_at {
+            self.expirations.insert((at, key.clone()));
+        }
+        self.entries.insert(key, Entry { value, expires_at });
+    }
+
+    pub(super) fn remove(&mut self, key: &[u8]) -> Option<Entry> {
+        let entry = self.unlink(key)?;
+        self.touch(key);
+        Some(entry)
+    }
+
+    // Removes `key` without touching it.
+    fn unlink(&mut self, key: &[u8]) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        if let Some(at) = entry.expires_at {
+            self.expirations.remove(&(at, Bytes::copy_from_slice(key)));
+        }
+        Some(entry)
+    }
+
+    pub(super) fn remove_if_expired(&mut self, key: &[u8], now: Instant) {
+        if self.entries.get(key).is_some_and(|entry| entry.is_expired(now)) {
+            self.remove(key);
+            self.notify(EventClasses::EXPIRED, "expired", key);
+        }
+    }
+
+    pub(super) fn set_expiry(&mut self, key: &[u8], expires_at: Option<Instant>) {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return;
+        };
+        let old_expiry = std::mem::replace(&mut entry.expires_at, expires_at);
+        self.touch(key);
+        if let Some(old_at) = old_expiry {
+            self.expirations.remove(&(old_at, Bytes::copy_from_slice(key)));
+        }
+        if let Some(at) = expires_at {
+            self.expirations.insert((at, Bytes::copy_from_slice(key)));
+        }
+    }
+
+    /// Wakes the clients blocked on `key` after it was given a value by something other
+    /// than a push, such as MOVE or SWAPDB.
+    pub(super) fn serve_waiters(&mut self, key: &[u8], now: Instant) {
+        self.serve_list_waiters(key, now);
+        self.serve_stream_waiters(key, now);
+    }
+
+    /// Exchanges the keys of two databases. Blocked clients stay with their database and
+    /// are served if the keys they wait on now have data.
+    pub(super) fn swap_keys(&mut self, other: &mut State, now: Instant) {
+        std::mem::swap(&mut self.entries, &mut other.entries);
+        std::mem::swap(&mut self.expirations, &mut other.expirations);
+        self.tracker.invalidate_all();
+        for state in [self, other] {
+            state.touch_all();
+            let waited_on: Vec<Bytes> = state.list_waiters.keys().chain(state.stream_waiters.keys()).cloned().collect();
+            for key in waited_on {
+                state.serve_waiters(&key, now);
+            }
+        }
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+        self.expirations.clear();
+        self.touch_all();
+    }
+
+    /// Marks `key` as modified for the clients watching it, and invalidates it for the
+    /// clients caching it.
+    pub(super) fn touch(&mut self, key: &[u8]) {
+        if let Some(watch) = self.watched.get_mut(key) {
+            watch.version += 1;
+        }
+        self.tracker.invalidate(key);
+    }
+
+    /// Publishes a keyspace event about `key`, if notify-keyspace-events enables it.
+    pub(super) fn notify(&self, class: EventClasses, event: &str, key: &[u8]) {
+        self.notifier.notify(class, event, key, self.index);
+    }
+
+    fn touch_all(&mut self) {
+        for watch in self.watched.values_mut() {
+            watch.version += 1;
+        }
+    }
+
+    // Waiters that timed out or disconnected are only dropped lazily, so this clears out
+    // the ones left on keys that never received a push.
+    fn drop_gone_waiters(&mut self) {
+        self.list_waiters.retain(|_, waiters| {
+            waiters.retain(ListWaiter::is_pending);
+            !waiters.is_empty()
+        });
+        self.stream_waiters.retain(|_, waiters| {
+            waiters.retain(StreamWaiter::is_pending);
+            !waiters.is_empty()
+        });
+    }
+
+    // Removes at most `limit` of the keys whose deadline has passed, earliest first.
+    fn purge_expired(&mut self, now: Instant, limit: usize) -> usize {
+        let mut purged = 0;
+        while purged < limit {
+            match self.expirations.first() {
+                Some((at, _)) if *at <= now => {}
+                _ => break,
+            }
+            if let Some((_, key)) = self.expirations.pop_first() {
+                self.entries.remove(&key);
+                self.touch(&key);
+                self.notify(EventClasses::EXPIRED, "expired", &key);
+                purged += 1;
+            }
+        }
+        purged
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Db {
-    data: Arc<RwLock<HashMap<String, RespType>>>,
+    pub(super) data: Arc<RwLock<State>>,
 }
 
 impl Default for Db {
@@ -18,68 +342,194 @@ impl Default for Db {
 impl Db {
     pub fn new() -> Self {
         Db {
-            data: Arc::new(RwLock::new(HashMap::new())),
+            data: Arc::new(RwLock::new(State::default())),
         }
     }
 
-    pub fn get(&self, key: &str) -> Option<RespType> {
-        self.data.read().get(key).cloned()
+    /// A database numbered `index` that publishes its keyspace events through `notifier`
+    /// and reports modified keys to `tracker`.
+    pub(super) fn with_listeners(index: usize, notifier: Arc<Notifier>, tracker: Arc<Tracker>) -> Self {
+        let state = State { index, notifier, tracker, ..State::default() };
+        Db { data: Arc::new(RwLock::new(state)) }
     }
 
-    pub fn set(&self, key: String, value: RespType) -> RespType {
-        self.data.write().insert(key, value);
-        RespType::SimpleString("OK".to_string())
+    pub fn get(&self, key: &[u8]) -> Result<Option<Bytes>, BifrostError> {
+        let data = self.data.read();
+        let value = data.value(key, Instant::now(), Value::as_string)?;
+        Ok(value.cloned())
     }
 
-    pub fn del(&self, key: &str) -> RespType {
+    pub fn set(&self, key: Bytes, value: Bytes) -> RespType {
+        self.set_with_options(key, value, SetOptions::default())
+    }
+
+    /// SET with NX/XX and EX/PX/EXAT/PXAT/KEEPTTL applied. Returns a null reply when
+    /// the NX/XX condition is not met.
+    pub fn set_with_options(&self, key: Bytes, value: Bytes, options: SetOptions) -> RespType {
+        let now = Instant::now();
         let mut data = self.data.write();
-        match data.remove(key) {
-            Some(_) => RespType::Integer(1),
-            None => RespType::Integer(0),
+        data.remove_if_expired(&key, now);
+
+        let existing = data.entries.get(&key);
+        let allowed = match options.condition {
+            SetCondition::Always => true,
+            SetCondition::IfNotExists => existing.is_none(),
+            SetCondition::IfExists => existing.is_some(),
+        };
+        if !allowed {
+            return RespType::Null;
         }
+
+        let expires_at = match options.expiration {
+            Some(expiration) => match expiration.deadline(now) {
+                Some(deadline) => Some(deadline),
+                None => return RespType::Error(invalid_expire_time("set")),
+            },
+            None if options.keep_ttl => existing.and_then(|entry| entry.expires_at),
+            None => None,
+        };
+        data.insert(key.clone(), Value::String(value), expires_at);
+        data.notify(EventClasses::STRING, "set", &key);
+        if options.expiration.is_some() {
+            data.notify(EventClasses::GENERIC, "expire", &key);
+        }
+        RespType::SimpleString("OK".to_string())
+    }
+
+    /// DEL and UNLINK. Replies with the number of keys removed.
+    pub fn del(&self, keys: &[Bytes]) -> RespType {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        let removed = keys
+            .iter()
+            .filter(|key| {
+                data.remove_if_expired(key, now);
+                let removed = data.remove(key).is_some();
+                if removed {
+                    data.notify(EventClasses::GENERIC, "del", key);
+                }
+                removed
+            })
+            .count();
+        RespType::Integer(removed as i64)
+    }
+
+    /// EXISTS. A key given more than once is counted each time.
+    pub fn exists(&self, keys: &[Bytes]) -> RespType {
+        let now = Instant::now();
+        let data = self.data.read();
+        let existing = keys.iter().filter(|key| data.get(key, now).is_some()).count();
+        RespType::Integer(existing as i64)
+    }
+
+    pub fn incr(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        self.incrby(key, 1)
     }
 
-    pub fn exists(&self, key: &str) -> RespType {
-        let exists = self.data.read().contains_key(key);
-        RespType::Integer(if exists { 1 } else { 0 })
+    pub fn decr(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        self.incrby(key, -1)
     }
 
-    pub fn incr(&self, key: &str) -> Result<RespType, BifrostError> {
+    /// Sets a timeout on `key` for `command`, one of the EXPIRE family. Returns 1 if the
+    /// timeout was set and 0 if the key does not exist or the condition was not met. A
+    /// deadline in the past deletes the key.
+    pub fn expire(&self, key: &[u8], expiration: Expiration, condition: ExpireCondition, command: &str) -> RespType {
+        let now = Instant::now();
+        let Some(deadline) = expiration.deadline(now) else {
+            return RespType::Error(invalid_expire_time(command));
+        };
         let mut data = self.data.write();
+        let Some(entry) = data.get_mut(key, now) else {
+            return RespType::Integer(0);
+        };
 
-        match data.get(key) {
-            Some(RespType::Integer(value)) => {
-                let new_value = value + 1;
-                data.insert(key.to_string(), RespType::Integer(new_value));
-                Ok(RespType::Integer(new_value))
+        // A key without a TTL counts as having an infinite one for GT and LT.
+        let allowed = match (condition, entry.expires_at) {
+            (ExpireCondition::Always, _) => true,
+            (ExpireCondition::IfNoExpiry, current) => current.is_none(),
+            (ExpireCondition::IfHasExpiry, current) => current.is_some(),
+            (ExpireCondition::IfGreater, Some(current)) => deadline > current,
+            (ExpireCondition::IfGreater, None) => false,
+            (ExpireCondition::IfLess, Some(current)) => deadline < current,
+            (ExpireCondition::IfLess, None) => true,
+        };
+        if !allowed {
+            return RespType::Integer(0);
+        }
+
+        if deadline <= now {
+            data.remove(key);
+            data.notify(EventClasses::GENERIC, "del", key);
+        } else {
+            data.set_expiry(key, Some(deadline));
+            data.notify(EventClasses::GENERIC, "expire", key);
+        }
+        RespType::Integer(1)
+    }
+
+    /// Removes the timeout from `key`. Returns 1 if a timeout was removed.
+    pub fn persist(&self, key: &[u8]) -> RespType {
+        let mut data = self.data.write();
+        match data.get_mut(key, Instant::now()) {
+            Some(entry) if entry.expires_at.is_some() => {
+                data.set_expiry(key, None);
+                data.notify(EventClasses::GENERIC, "persist", key);
+                RespType::Integer(1)
             }
-            Some(_) => Err(BifrostError::StorageError(
-                "ERR value is not an integer".to_string()
-            )),
-            None => {
-                data.insert(key.to_string(), RespType::Integer(1));
-                Ok(RespType::Integer(1))
+            _ => RespType::Integer(0),
+        }
+    }
+
+    /// Remaining time to live in seconds, -1 if the key has no TTL and -2 if it is missing.
+    pub fn ttl(&self, key: &[u8]) -> RespType {
+        self.remaining(key, |ttl| ((ttl.as_millis() + 500) / 1000) as i64)
+    }
+
+    /// Like [`Db::ttl`] but in milliseconds.
+    pub fn pttl(&self, key: &[u8]) -> RespType {
+        self.remaining(key, |ttl| ttl.as_millis() as i64)
+    }
+
+    fn remaining(&self, key: &[u8], convert: impl Fn(Duration) -> i64) -> RespType {
+        let now = Instant::now();
+        match self.data.read().get(key, now) {
+            None => RespType::Integer(-2),
+            Some(Entry { expires_at: None, .. }) => RespType::Integer(-1),
+            Some(Entry { expires_at: Some(at), .. }) => {
+                RespType::Integer(convert(at.saturating_duration_since(now)))
             }
         }
     }
 
-    pub fn decr(&self, key: &str) -> Result<RespType, BifrostError> {
+    /// DBSIZE. Keys that expired but were not yet swept are still counted, as in Redis.
+    pub fn dbsize(&self) -> RespType {
+        RespType::Integer(self.data.read().entries.len() as i64)
+    }
+
+    /// FLUSHDB. Deletes every key; blocked clients keep waiting.
+    pub fn flushdb(&self) -> RespType {
         let mut data = self.data.write();
+        data.clear();
+        data.tracker.invalidate_all();
+        RespType::SimpleString("OK".to_string())
+    }
 
-        match data.get(key) {
-            Some(RespType::Integer(value)) => {
-                let new_value = value - 1;
-                data.insert(key.to_string(), RespType::Integer(new_value));
-                Ok(RespType::Integer(new_value))
-            }
-            Some(_) => Err(BifrostError::StorageError(
-                "ERR value is not an integer".to_string()
-            )),
-            None => {
-                data.insert(key.to_string(), RespType::Integer(-1));
-                Ok(RespType::Integer(-1))
+    /// Actively removes keys whose deadline has passed, along with waiters of blocked
+    /// clients that have gone away. Stops after `limit` keys or once `stop_at` is
+    /// reached, and lets other clients in every [`EXPIRY_BATCH`] keys. Returns how many
+    /// keys were removed.
+    pub fn purge_expired_keys(&self, limit: usize, stop_at: Instant) -> usize {
+        self.data.write().drop_gone_waiters();
+        let mut purged = 0;
+        while purged < limit && Instant::now() < stop_at {
+            let batch = EXPIRY_BATCH.min(limit - purged);
+            let removed = self.data.write().purge_expired(Instant::now(), batch);
+            purged += removed;
+            if removed < batch {
+                break;
             }
         }
+        purged
     }
 }
 
@@ -93,29 +543,23 @@ mod tests {
 
         // Test SET and GET
         assert_eq!(
-            db.set(
-                "key1".to_string(),
-                RespType::BulkString("value1".to_string())
-            ),
+            db.set(Bytes::from("key1"), Bytes::from("value1")),
             RespType::SimpleString("OK".to_string())
         );
 
         assert_eq!(
-            db.get("key1"),
-            Some(RespType::BulkString("value1".to_string()))
+            db.get(b"key1").unwrap(),
+            Some(Bytes::from("value1"))
         );
 
         // Test DEL
-        assert_eq!(db.del("key1"), RespType::Integer(1));
-        assert_eq!(db.get("key1"), None);
+        assert_eq!(db.del(&[Bytes::from("key1")]), RespType::Integer(1));
+        assert_eq!(db.get(b"key1").unwrap(), None);
 
         // Test EXISTS
-        assert_eq!(db.exists("key1"), RespType::Integer(0));
-        db.set(
-            "key1".to_string(),
-            RespType::BulkString("value1".to_string()),
-        );
-        assert_eq!(db.exists("key1"), RespType::Integer(1));
+        assert_eq!(db.exists(&[Bytes::from("key1")]), RespType::Integer(0));
+        db.set(Bytes::from("key1"), Bytes::from("value1"));
+        assert_eq!(db.exists(&[Bytes::from("key1")]), RespType::Integer(1));
     }
 
     #[test]
@@ -123,11 +567,103 @@ mod tests {
         let db = Db::new();
 
         // Test INCR
-        assert_eq!(db.incr("counter").unwrap(), RespType::Integer(1));
-        assert_eq!(db.incr("counter").unwrap(), RespType::Integer(2));
+        assert_eq!(db.incr(b"counter").unwrap(), RespType::Integer(1));
+        assert_eq!(db.incr(b"counter").unwrap(), RespType::Integer(2));
 
         // Test DECR
-        assert_eq!(db.decr("counter").unwrap(), RespType::Integer(1));
-        assert_eq!(db.decr("counter").unwrap(), RespType::Integer(0));
+        assert_eq!(db.decr(b"counter").unwrap(), RespType::Integer(1));
+        assert_eq!(db.decr(b"counter").unwrap(), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_expiration() {
+        let db = Db::new();
+        let value = Bytes::from("value");
+
+        // Test TTL/PTTL on missing and persistent keys
+        assert_eq!(db.ttl(b"key"), RespType::Integer(-2));
+        db.set(Bytes::from("key"), value.clone());
+        assert_eq!(db.ttl(b"key"), RespType::Integer(-1));
+
+        // Test EXPIRE and lazy expiry
+        let expiration = Expiration::In(Duration::from_millis(20));
+        assert_eq!(db.expire(b"key", expiration, ExpireCondition::Always, "expire"), RespType::Integer(1));
+        assert_eq!(db.ttl(b"key"), RespType::Integer(0));
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(db.get(b"key").unwrap(), None);
+        assert_eq!(db.exists(&[Bytes::from("key")]), RespType::Integer(0));
+        assert_eq!(db.purge_expired_keys(usize::MAX, Instant::now() + Duration::from_secs(1)), 1);
+
+        // Test PERSIST
+        db.set(Bytes::from("key"), value.clone());
+        db.expire(b"key", Expiration::In(Duration::from_secs(100)), ExpireCondition::Always, "expire");
+        assert_eq!(db.ttl(b"key"), RespType::Integer(100));
+        assert_eq!(db.persist(b"key"), RespType::Integer(1));
+        assert_eq!(db.persist(b"key"), RespType::Integer(0));
+        assert_eq!(db.pttl(b"key"), RespType::Integer(-1));
+
+        // Test a deadline in the past deletes the key
+        db.expire(b"key", Expiration::At(SystemTime::UNIX_EPOCH), ExpireCondition::Always, "expire");
+        assert_eq!(db.exists(&[Bytes::from("key")]), RespType::Integer(0));
+
+        // Test a deadline too far ahead to represent is refused rather than overflowing
+        db.set(Bytes::from("key"), value);
+        let too_far = Expiration::In(Duration::from_secs(i64::MAX as u64));
+        assert_eq!(
+            db.expire(b"key", too_far, ExpireCondition::Always, "pexpire"),
+            RespType::Error("ERR invalid expire time in 'pexpire' command".to_string())
+        );
+        assert_eq!(db.ttl(b"key"), RespType::Integer(-1));
+    }
+
+    #[test]
+    fn test_expire_conditions() {
+        let db = Db::new();
+        db.set(Bytes::from("key"), Bytes::from("value"));
+        let short = Expiration::In(Duration::from_secs(10));
+        let long = Expiration::In(Duration::from_secs(100));
+
+        assert_eq!(db.expire(b"key", long, ExpireCondition::IfHasExpiry, "expire"), RespType::Integer(0));
+        assert_eq!(db.expire(b"key", long, ExpireCondition::IfGreater, "expire"), RespType::Integer(0));
+        assert_eq!(db.expire(b"key", long, ExpireCondition::IfNoExpiry, "expire"), RespType::Integer(1));
+        assert_eq!(db.expire(b"key", short, ExpireCondition::IfNoExpiry, "expire"), RespType::Integer(0));
+        assert_eq!(db.expire(b"key", short, ExpireCondition::IfGreater, "expire"), RespType::Integer(0));
+        assert_eq!(db.expire(b"key", short, ExpireCondition::IfLess, "expire"), RespType::Integer(1));
+        assert_eq!(db.ttl(b"key"), RespType::Integer(10));
+    }
+
+    #[test]
+    fn test_set_options() {
+        let db = Db::new();
+        let value = Bytes::from("value");
+        let ok = RespType::SimpleString("OK".to_string());
+
+        let xx = SetOptions { condition: SetCondition::IfExists, ..Default::default() };
+        assert_eq!(db.set_with_options(Bytes::from("key"), value.clone(), xx), RespType::Null);
+
+        let nx = SetOptions {
+            condition: SetCondition::IfNotExists,
+            expiration: Some(Expiration::In(Duration::from_secs(50))),
+            ..Default::default()
+        };
+        assert_eq!(db.set_with_options(Bytes::from("key"), value.clone(), nx), ok);
+        assert_eq!(db.set_with_options(Bytes::from("key"), value.clone(), nx), RespType::Null);
+        assert_eq!(db.ttl(b"key"), RespType::Integer(50));
+
+        // Test KEEPTTL retains the deadline and a plain SET clears it
+        let keep = SetOptions { keep_ttl: true, ..Default::default() };
+        assert_eq!(db.set_with_options(Bytes::from("key"), value.clone(), keep), ok);
+        assert_eq!(db.ttl(b"key"), RespType::Integer(50));
+        db.set(Bytes::from("key"), value.clone());
+        assert_eq!(db.ttl(b"key"), RespType::Integer(-1));
+
+        let too_far = SetOptions {
+            expiration: Some(Expiration::In(Duration::from_secs(i64::MAX as u64))),
+            ..Default::default()
+        };
+        assert_eq!(
+            db.set_with_options(Bytes::from("key"), value, too_far),
+            RespType::Error("ERR invalid expire time in 'set' command".to_string())
+        );
     }
 }
@@ -0,0 +1,609 @@
+use bytes::Bytes;
+use std::time::Instant;
+
+use crate::error::BifrostError;
+use crate::resp::RespType;
+use super::db::Db;
+use super::notify::EventClasses;
+use super::sorted_set::{RangeBy, RangeQuery, ScoreBound, SortedSet, ZaddOptions};
+use super::value::Value;
+
+// Positions are stored as sorted set members whose score is a 52 bit geohash: 26 bits
+// of latitude and 26 of longitude, interleaved. Latitudes are limited to the range of
+// the Web Mercator projection, as in Redis.
+const LONGITUDE_LIMIT: f64 = 180.0;
+const LATITUDE_LIMIT: f64 = 85.05112878;
+const STEP: u32 = 26;
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+const MERCATOR_MAX: f64 = 20037726.37;
+const GEOHASH_ALPHABET: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// The unit of a distance argument or reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DistanceUnit {
+    #[default]
+    Meters,
+    Kilometers,
+    Feet,
+    Miles,
+}
+
+impl DistanceUnit {
+    fn meters(&self) -> f64 {
+        match self {
+            DistanceUnit::Meters => 1.0,
+            DistanceUnit::Kilometers => 1000.0,
+            DistanceUnit::Feet => 0.3048,
+            DistanceUnit::Miles => 1609.34,
+        }
+    }
+}
+
+/// The center of a GEOSEARCH: FROMMEMBER or FROMLONLAT.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoOrigin {
+    Member(Bytes),
+    LonLat(f64, f64),
+}
+
+/// The area of a GEOSEARCH, in its unit: BYRADIUS or BYBOX with a width and height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    Box(f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GeoOrder {
+    #[default]
+    Unsorted,
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoSearch {
+    pub origin: GeoOrigin,
+    pub shape: GeoShape,
+    pub unit: DistanceUnit,
+    pub order: GeoOrder,
+    /// COUNT, and whether ANY allows returning the first matches found instead of the
+    /// closest ones.
+    pub count: Option<(usize, bool)>,
+    pub with_coord: bool,
+    pub with_dist: bool,
+    pub with_hash: bool,
+}
+
+fn invalid_coordinates(longitude: f64, latitude: f64) -> BifrostError {
+    BifrostError::StorageError(format!(
+        "ERR invalid longitude,latitude pair {:.6},{:.6}",
+        longitude, latitude
+    ))
+}
+
+/// Fails unless the position is within the longitudes and latitudes that can be stored.
+pub fn check_coordinates(longitude: f64, latitude: f64) -> Result<(), BifrostError> {
+    if longitude.abs() > LONGITUDE_LIMIT || latitude.abs() > LATITUDE_LIMIT {
+        return Err(invalid_coordinates(longitude, latitude));
+    }
+    Ok(())
+}
+
+// Spreads the bits of a 32 bit value over the even bits of a u64.
+fn spread(value: u32) -> u64 {
+    let mut x = value as u64;
+    x = (x | x << 16) & 0x0000_FFFF_0000_FFFF;
+    x = (x | x << 8) & 0x00FF_00FF_00FF_00FF;
+    x = (x | x << 4) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | x << 2) & 0x3333_3333_3333_3333;
+    (x | x << 1) & 0x5555_5555_5555_5555
+}
+
+// The inverse of `spread`: gathers the even bits of a u64.
+fn squash(value: u64) -> u32 {
+    let mut x = value & 0x5555_5555_5555_5555;
+    x = (x | x >> 1) & 0x3333_3333_3333_3333;
+    x = (x | x >> 2) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | x >> 4) & 0x00FF_00FF_00FF_00FF;
+    x = (x | x >> 8) & 0x0000_FFFF_0000_FFFF;
+    (x | x >> 16) as u32
+}
+
+/// A geohash cell: `step` bits of each coordinate, latitude in the even bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GeoHash {
+    bits: u64,
+    step: u32,
+}
+
+/// The bounds of a cell, as longitude and latitude ranges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Area {
+    longitude: (f64, f64),
+    latitude: (f64, f64),
+}
+
+impl GeoHash {
+    fn encode(longitude: f64, latitude: f64, latitude_limit: f64, step: u32) -> GeoHash {
+        let cells = (1u64 << step) as f64;
+        let latitude_offset = (latitude + latitude_limit) / (2.0 * latitude_limit) * cells;
+        let longitude_offset = (longitude + LONGITUDE_LIMIT) / (2.0 * LONGITUDE_LIMIT) * cells;
+        let bits = spread(latitude_offset as u32) | spread(longitude_offset as u32) << 1;
+        GeoHash { bits, step }
+    }
+
+    fn area(&self, latitude_limit: f64) -> Area {
+        let cells = (1u64 << self.step) as f64;
+        let latitude = squash(self.bits) as f64;
+        let longitude = squash(self.bits >> 1) as f64;
+        let latitude_scale = 2.0 * latitude_limit;
+        let longitude_scale = 2.0 * LONGITUDE_LIMIT;
+        Area {
+            longitude: (
+                -LONGITUDE_LIMIT + longitude / cells * longitude_scale,
+                -LONGITUDE_LIMIT + (longitude + 1.0) / cells * longitude_scale,
+            ),
+            latitude: (
+                -latitude_limit + latitude / cells * latitude_scale,
+                -latitude_limit + (latitude + 1.0) / cells * latitude_scale,
+            ),
+        }
+    }
+
+    // The center of the cell, which is what a stored position decodes to.
+    fn center(&self) -> (f64, f64) {
+        let area = self.area(LATITUDE_LIMIT);
+        let longitude = (area.longitude.0 + area.longitude.1) / 2.0;
+        let latitude = (area.latitude.0 + area.latitude.1) / 2.0;
+        (
+            longitude.clamp(-LONGITUDE_LIMIT, LONGITUDE_LIMIT),
+            latitude.clamp(-LATITUDE_LIMIT, LATITUDE_LIMIT),
+        )
+    }
+
+    // The neighboring cell `dx` cells east and `dy` cells north, wrapping around.
+    fn moved(&self, dx: i8, dy: i8) -> GeoHash {
+        const ODD: u64 = 0xAAAA_AAAA_AAAA_AAAA;
+        const EVEN: u64 = 0x5555_5555_5555_5555;
+        let shift = 64 - self.step * 2;
+        let step_along = |bits: u64, own: u64, other: u64, direction: i8| {
+            let mut value = bits & own;
+            let filler = other >> shift;
+            if direction > 0 {
+                value = value.wrapping_add(filler + 1);
+            } else if direction < 0 {
+                value = (value | filler).wrapping_sub(filler + 1);
+            }
+            value & (own >> shift)
+        };
+        let longitude = step_along(self.bits, ODD, EVEN, dx);
+        let latitude = step_along(self.bits, EVEN, ODD, dy);
+        GeoHash { bits: longitude | latitude, step: self.step }
+    }
+
+    // The range of 52 bit scores inside the cell, end exclusive.
+    fn scores(&self) -> (f64, f64) {
+        let shift = (STEP - self.step) * 2;
+        ((self.bits << shift) as f64, ((self.bits + 1) << shift) as f64)
+    }
+}
+
+fn decode_score(score: f64) -> (f64, f64) {
+    GeoHash { bits: score as u64, step: STEP }.center()
+}
+
+// The standard 11 character geohash of a position, which unlike the scores uses the
+// full -90 to 90 latitude range.
+fn geohash_string(longitude: f64, latitude: f64) -> Bytes {
+    let hash = GeoHash::encode(longitude, latitude, 90.0, STEP);
+    let chars: Vec<u8> = (0..11)
+        .map(|i| {
+            let index = if i == 10 { 0 } else { (hash.bits >> (52 - (i + 1) * 5)) & 0x1f };
+            GEOHASH_ALPHABET[index as usize]
+        })
+        .collect();
+    Bytes::from(chars)
+}
+
+/// The great-circle distance between two positions in meters, by the haversine formula.
+fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lon1, lat1) = (from.0.to_radians(), from.1.to_radians());
+    let (lon2, lat2) = (to.0.to_radians(), to.1.to_radians());
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let v = ((lon2 - lon1) / 2.0).sin();
+    2.0 * EARTH_RADIUS_IN_METERS * (u * u + lat1.cos() * lat2.cos() * v * v).sqrt().asin()
+}
+
+fn distance_reply(meters: f64, unit: DistanceUnit) -> RespType {
+    RespType::BulkString(Bytes::from(format!("{:.4}", meters / unit.meters())))
+}
+
+/// A search area in meters around a center.
+struct Region {
+    center: (f64, f64),
+    shape: GeoShape,
+}
+
+impl Region {
+    // Returns the distance to the center in meters if the position is inside the region.
+    fn contains(&self, position: (f64, f64)) -> Option<f64> {
+        match self.shape {
+            GeoShape::Radius(radius) => Some(distance(self.center, position)).filter(|d| *d <= radius),
+            GeoShape::Box(width, height) => {
+                let latitude_distance =
+                    EARTH_RADIUS_IN_METERS * (position.1.to_radians() - self.center.1.to_radians()).abs();
+                if latitude_distance > height / 2.0 {
+                    return None;
+                }
+                if distance((self.center.0, position.1), position) > width / 2.0 {
+                    return None;
+                }
+                Some(distance(self.center, position))
+            }
+        }
+    }
+
+    // The longitude and latitude bounds of the region.
+    fn bounds(&self) -> Area {
+        let (longitude, latitude) = self.center;
+        let (half_width, half_height) = match self.shape {
+            GeoShape::Radius(radius) => (radius, radius),
+            GeoShape::Box(width, height) => (width / 2.0, height / 2.0),
+        };
+        let latitude_delta = (half_height / EARTH_RADIUS_IN_METERS).to_degrees();
+        // The region is widest in longitude on its side nearest a pole.
+        let widest = if latitude < 0.0 { latitude - latitude_delta } else { latitude + latitude_delta };
+        let longitude_delta = (half_width / EARTH_RADIUS_IN_METERS / widest.to_radians().cos()).to_degrees();
+        Area {
+            longitude: (longitude - longitude_delta, longitude + longitude_delta),
+            latitude: (latitude - latitude_delta, latitude + latitude_delta),
+        }
+    }
+
+    // The cells to scan: one around the center, large enough that it and its eight
+    // neighbors cover the region, minus neighbors that lie outside the region's bounds.
+    fn cells(&self) -> Vec<GeoHash> {
+        let (longitude, latitude) = self.center;
+        let radius = match self.shape {
+            GeoShape::Radius(radius) => radius,
+            GeoShape::Box(width, height) => (width / 2.0).hypot(height / 2.0),
+        };
+        let bounds = self.bounds();
+        let mut step = estimate_step(radius, latitude);
+        let mut center = GeoHash::encode(longitude, latitude, LATITUDE_LIMIT, step);
+
+        // Near the edge of the center cell a neighbor may be too small to reach the
+        // edge of the region, in which case the next larger cells are used.
+        let too_small = center.moved(0, 1).area(LATITUDE_LIMIT).latitude.1 < bounds.latitude.1
+            || center.moved(0, -1).area(LATITUDE_LIMIT).latitude.0 > bounds.latitude.0
+            || center.moved(1, 0).area(LATITUDE_LIMIT).longitude.1 < bounds.longitude.1
+            || center.moved(-1, 0).area(LATITUDE_LIMIT).longitude.0 > bounds.longitude.0;
+        if step > 1 && too_small {
+            step -= 1;
+            center = GeoHash::encode(longitude, latitude, LATITUDE_LIMIT, step);
+        }
+
+        let area = center.area(LATITUDE_LIMIT);
+        let neighbors = [(0, 1), (0, -1), (1, 0), (-1, 0), (1, 1), (-1, 1), (1, -1), (-1, -1)];
+        let mut cells = vec![center];
+        for (dx, dy) in neighbors {
+            let useless = step >= 2
+                && ((dy < 0 && area.latitude.0 < bounds.latitude.0)
+                    || (dy > 0 && area.latitude.1 > bounds.latitude.1)
+                    || (dx < 0 && area.longitude.0 < bounds.longitude.0)
+                    || (dx > 0 && area.longitude.1 > bounds.longitude.1));
+            let cell = center.moved(dx, dy);
+            if !useless && !cells.contains(&cell) {
+                cells.push(cell);
+            }
+        }
+        cells
+    }
+}
+
+// The geohash precision whose cells are about as large as the search radius.
+fn estimate_step(mut radius: f64, latitude: f64) -> u32 {
+    if radius == 0.0 {
+        return STEP;
+    }
+    let mut step: i32 = 1;
+    while radius < MERCATOR_MAX {
+        radius *= 2.0;
+        step += 1;
+    }
+    step -= 2;
+    // Cells shrink towards the poles.
+    if latitude.abs() > 66.0 {
+        step -= 1;
+        if latitude.abs() > 80.0 {
+            step -= 1;
+        }
+    }
+    step.clamp(1, STEP as i32) as u32
+}
+
+/// A member found by GEOSEARCH.
+struct GeoMatch {
+    member: Bytes,
+    score: f64,
+    distance: f64,
+}
+
+impl SortedSet {
+    fn position(&self, member: &[u8]) -> Option<(f64, f64)> {
+        self.score(member).map(decode_score)
+    }
+
+    fn search(&self, search: &GeoSearch) -> Result<Vec<GeoMatch>, BifrostError> {
+        let center = match &search.origin {
+            GeoOrigin::Member(member) => self.position(member).ok_or_else(|| {
+                BifrostError::StorageError("ERR could not decode requested zset member".to_string())
+            })?,
+            GeoOrigin::LonLat(longitude, latitude) => (*longitude, *latitude),
+        };
+        let meters = search.unit.meters();
+        let shape = match search.shape {
+            GeoShape::Radius(radius) => GeoShape::Radius(radius * meters),
+            GeoShape::Box(width, height) => GeoShape::Box(width * meters, height * meters),
+        };
+        let region = Region { center, shape };
+
+        let (limit, any) = search.count.unwrap_or((usize::MAX, false));
+        let mut matches = Vec::new();
+        'cells: for cell in region.cells() {
+            let (min, max) = cell.scores();
+            let query = RangeQuery {
+                by: RangeBy::Score(ScoreBound::Inclusive(min), ScoreBound::Exclusive(max)),
+                reverse: false,
+                limit: None,
+                with_scores: true,
+            };
+            for (member, score) in self.range(&query) {
+                if let Some(distance) = region.contains(decode_score(score)) {
+                    matches.push(GeoMatch { member, score, distance });
+                    if any && matches.len() >= limit {
+                        break 'cells;
+                    }
+                }
+            }
+        }
+
+        // COUNT without ANY returns the closest matches.
+        let order = match search.order {
+            GeoOrder::Unsorted if search.count.is_some() && !any => GeoOrder::Ascending,
+            order => order,
+        };
+        match order {
+            GeoOrder::Unsorted => {}
+            GeoOrder::Ascending => matches.sort_by(|a, b| a.distance.total_cmp(&b.distance)),
+            GeoOrder::Descending => matches.sort_by(|a, b| b.distance.total_cmp(&a.distance)),
+        }
+        matches.truncate(limit);
+        Ok(matches)
+    }
+}
+
+impl Db {
+    /// GEOADD, with the NX, XX and CH options of ZADD. Nothing is added if any of the
+    /// positions is invalid.
+    pub fn geoadd(&self, key: &[u8], positions: &[(f64, f64, Bytes)], options: ZaddOptions) -> Result<RespType, BifrostError> {
+        let mut pairs = Vec::with_capacity(positions.len());
+        for (longitude, latitude, member) in positions {
+            check_coordinates(*longitude, *latitude)?;
+            let hash = GeoHash::encode(*longitude, *latitude, LATITUDE_LIMIT, STEP);
+            pairs.push((hash.bits as f64, member.clone()));
+        }
+        self.zadd(key, &pairs, options)
+    }
+
+    /// GEODIST. Replies with null if either member is missing.
+    pub fn geodist(&self, key: &[u8], from: &[u8], to: &[u8], unit: DistanceUnit) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let Some(zset) = data.value(key, Instant::now(), Value::as_sorted_set)? else {
+            return Ok(RespType::Null);
+        };
+        Ok(match (zset.position(from), zset.position(to)) {
+            (Some(from), Some(to)) => distance_reply(distance(from, to), unit),
+            _ => RespType::Null,
+        })
+    }
+
+    pub fn geopos(&self, key: &[u8], members: &[Bytes]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let zset = data.value(key, Instant::now(), Value::as_sorted_set)?;
+        let positions = members
+            .iter()
+            .map(|member| match zset.and_then(|zset| zset.position(member)) {
+                Some((longitude, latitude)) => {
+                    RespType::Array(vec![RespType::Double(longitude), RespType::Double(latitude)])
+                }
+                None => RespType::Null,
+            })
+            .collect();
+        Ok(RespType::Array(positions))
+    }
+
+    pub fn geohash(&self, key: &[u8], members: &[Bytes]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let zset = data.value(key, Instant::now(), Value::as_sorted_set)?;
+        let hashes = members
+            .iter()
+            .map(|member| match zset.and_then(|zset| zset.position(member)) {
+                Some((longitude, latitude)) => RespType::BulkString(geohash_string(longitude, latitude)),
+                None => RespType::Null,
+            })
+            .collect();
+        Ok(RespType::Array(hashes))
+    }
+
+    /// GEOSEARCH. Each match is its member name, or an array of the name followed by
+    /// whichever of the distance, geohash score and position were asked for.
+    pub fn geosearch(&self, key: &[u8], search: &GeoSearch) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let Some(zset) = data.value(key, Instant::now(), Value::as_sorted_set)? else {
+            return Ok(RespType::Array(vec![]));
+        };
+        let with_any = search.with_dist || search.with_hash || search.with_coord;
+        let reply = zset
+            .search(search)?
+            .into_iter()
+            .map(|found| {
+                if !with_any {
+                    return RespType::BulkString(found.member);
+                }
+                let mut item = vec![RespType::BulkString(found.member)];
+                if search.with_dist {
+                    item.push(distance_reply(found.distance, search.unit));
+                }
+                if search.with_hash {
+                    item.push(RespType::Integer(found.score as i64));
+                }
+                if search.with_coord {
+                    let (longitude, latitude) = decode_score(found.score);
+                    item.push(RespType::Array(vec![RespType::Double(longitude), RespType::Double(latitude)]));
+                }
+                RespType::Array(item)
+            })
+            .collect();
+        Ok(RespType::Array(reply))
+    }
+
+    /// GEOSEARCHSTORE. Stores the matches with their geohash scores, or with STOREDIST
+    /// their distances in the search's unit, replacing or deleting the destination.
+    pub fn geosearchstore(
+        &self,
+        destination: Bytes,
+        key: &[u8],
+        search: &GeoSearch,
+        store_dist: bool,
+    ) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let matches = match data.value(key, Instant::now(), Value::as_sorted_set)? {
+            Some(zset) => zset.search(search)?,
+            None => vec![],
+        };
+        let mut result = SortedSet::default();
+        for found in matches {
+            let score = if store_dist { found.distance / search.unit.meters() } else { found.score };
+            result.insert(found.member, score);
+        }
+        let len = result.len();
+        if result.is_empty() {
+            if data.remove(&destination).is_some() {
+                data.notify(EventClasses::GENERIC, "del", &destination);
+            }
+        } else {
+            data.insert(destination.clone(), Value::SortedSet(result), None);
+            data.notify(EventClasses::ZSET, "geosearchstore", &destination);
+        }
+        Ok(RespType::Integer(len as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk(value: &str) -> RespType {
+        RespType::BulkString(Bytes::copy_from_slice(value.as_bytes()))
+    }
+
+    fn sicily() -> Db {
+        let db = Db::new();
+        let positions = [
+            (13.361389, 38.115556, Bytes::from("Palermo")),
+            (15.087269, 37.502669, Bytes::from("Catania")),
+            (12.758489, 38.788135, Bytes::from("edge1")),
+            (17.241510, 38.788135, Bytes::from("edge2")),
+        ];
+        assert_eq!(db.geoadd(b"Sicily", &positions, ZaddOptions::default()).unwrap(), RespType::Integer(4));
+        db
+    }
+
+    fn search(origin: GeoOrigin, shape: GeoShape) -> GeoSearch {
+        GeoSearch {
+            origin,
+            shape,
+            unit: DistanceUnit::Kilometers,
+            order: GeoOrder::Ascending,
+            count: None,
+            with_coord: false,
+            with_dist: false,
+            with_hash: false,
+        }
+    }
+
+    #[test]
+    fn test_encoding() {
+        let hash = GeoHash::encode(13.361389, 38.115556, LATITUDE_LIMIT, STEP);
+        assert_eq!(hash.bits, 3479099956230698);
+        let (longitude, latitude) = decode_score(hash.bits as f64);
+        assert!((longitude - 13.361389).abs() < 1e-5 && (latitude - 38.115556).abs() < 1e-5);
+        assert_eq!(geohash_string(longitude, latitude), Bytes::from("sqc8b49rny0"));
+        assert_eq!(GeoHash { bits: 0b0110, step: 2 }.moved(1, 0), GeoHash { bits: 0b1100, step: 2 });
+        assert_eq!(GeoHash { bits: 0b0110, step: 2 }.moved(0, -1), GeoHash { bits: 0b0011, step: 2 });
+    }
+
+    #[test]
+    fn test_geodist_geopos_geohash() {
+        let db = sicily();
+        assert_eq!(db.geodist(b"Sicily", b"Palermo", b"Catania", DistanceUnit::Meters).unwrap(), bulk("166274.1516"));
+        assert_eq!(db.geodist(b"Sicily", b"Palermo", b"Catania", DistanceUnit::Kilometers).unwrap(), bulk("166.2742"));
+        assert_eq!(db.geodist(b"Sicily", b"Palermo", b"Nowhere", DistanceUnit::Meters).unwrap(), RespType::Null);
+
+        let members = [Bytes::from("Palermo"), Bytes::from("Nowhere")];
+        let RespType::Array(positions) = db.geopos(b"Sicily", &members).unwrap() else {
+            panic!("expected an array");
+        };
+        assert_eq!(positions[1], RespType::Null);
+        assert_eq!(db.geohash(b"Sicily", &members).unwrap(), RespType::Array(vec![bulk("sqc8b49rny0"), RespType::Null]));
+
+        let invalid = [(200.0, 10.0, Bytes::from("x"))];
+        assert!(db.geoadd(b"Sicily", &invalid, ZaddOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_geosearch() {
+        let db = sicily();
+        let origin = GeoOrigin::LonLat(15.0, 37.0);
+        let query = search(origin.clone(), GeoShape::Radius(200.0));
+        assert_eq!(db.geosearch(b"Sicily", &query).unwrap(), RespType::Array(vec![bulk("Catania"), bulk("Palermo")]));
+
+        let query = GeoSearch { order: GeoOrder::Descending, with_dist: true, ..search(origin.clone(), GeoShape::Box(400.0, 400.0)) };
+        let reply = db.geosearch(b"Sicily", &query).unwrap();
+        let names: Vec<RespType> = match reply {
+            RespType::Array(items) => items
+                .into_iter()
+                .map(|item| match item {
+                    RespType::Array(item) => item[0].clone(),
+                    _ => panic!("expected an array"),
+                })
+                .collect(),
+            _ => panic!("expected an array"),
+        };
+        assert_eq!(names, vec![bulk("edge1"), bulk("edge2"), bulk("Palermo"), bulk("Catania")]);
+
+        let query = GeoSearch { count: Some((1, false)), ..search(GeoOrigin::Member(Bytes::from("Palermo")), GeoShape::Radius(500.0)) };
+        assert_eq!(db.geosearch(b"Sicily", &query).unwrap(), RespType::Array(vec![bulk("Palermo")]));
+
+        let query = search(GeoOrigin::Member(Bytes::from("Nowhere")), GeoShape::Radius(1.0));
+        assert!(db.geosearch(b"Sicily", &query).is_err());
+        assert_eq!(db.geosearch(b"missing", &query).unwrap(), RespType::Array(vec![]));
+    }
+
+    #[test]
+    fn test_geosearchstore() {
+        let db = sicily();
+        let query = search(GeoOrigin::LonLat(15.0, 37.0), GeoShape::Radius(200.0));
+        assert_eq!(db.geosearchstore(Bytes::from("near"), b"Sicily", &query, true).unwrap(), RespType::Integer(2));
+        let RespType::Double(catania) = db.zscore(b"near", b"Catania").unwrap() else {
+            panic!("expected a double");
+        };
+        assert!((catania - 56.4413).abs() < 1e-3);
+
+        let query = search(GeoOrigin::LonLat(0.0, 0.0), GeoShape::Radius(1.0));
+        assert_eq!(db.geosearchstore(Bytes::from("near"), b"Sicily", &query, false).unwrap(), RespType::Integer(0));
+        assert_eq!(db.exists(&[Bytes::from("near")]), RespType::Integer(0));
+    }
+}
@@ -0,0 +1,318 @@
+use bytes::Bytes;
+use std::time::Instant;
+
+use crate::error::BifrostError;
+use crate::resp::{format_double, RespType};
+use super::db::Db;
+use super::notify::EventClasses;
+use super::scan::{ScanMap, ScanOptions};
+use super::value::Value;
+
+fn hash_or_create() -> Value {
+    Value::Hash(ScanMap::default())
+}
+
+fn bulk_or_null(value: Option<&Bytes>) -> RespType {
+    value.cloned().map(RespType::BulkString).unwrap_or(RespType::Null)
+}
+
+impl Db {
+    /// HSET. Returns the number of fields that were added rather than updated.
+    pub fn hset(&self, key: &[u8], pairs: &[(Bytes, Bytes)]) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let hash = data.value_or_insert(key, Instant::now(), Value::as_hash_mut, hash_or_create)?;
+        let added = pairs
+            .iter()
+            .filter(|(field, value)| hash.insert(field.clone(), value.clone()).is_none())
+            .count();
+        data.touch(key);
+        data.notify(EventClasses::HASH, "hset", key);
+        Ok(RespType::Integer(added as i64))
+    }
+
+    pub fn hsetnx(&self, key: &[u8], field: Bytes, value: Bytes) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let hash = data.value_or_insert(key, Instant::now(), Value::as_hash_mut, hash_or_create)?;
+        if hash.contains_key(&field) {
+            return Ok(RespType::Integer(0));
+        }
+        hash.insert(field, value);
+        data.touch(key);
+        data.notify(EventClasses::HASH, "hset", key);
+        Ok(RespType::Integer(1))
+    }
+
+    pub fn hget(&self, key: &[u8], field: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let hash = data.value(key, Instant::now(), Value::as_hash)?;
+        Ok(bulk_or_null(hash.and_then(|hash| hash.get(field))))
+    }
+
+    pub fn hmget(&self, key: &[u8], fields: &[Bytes]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let hash = data.value(key, Instant::now(), Value::as_hash)?;
+        Ok(RespType::Array(
+            fields
+                .iter()
+                .map(|field| bulk_or_null(hash.and_then(|hash| hash.get(field))))
+                .collect(),
+        ))
+    }
+
+    pub fn hdel(&self, key: &[u8], fields: &[Bytes]) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let Some(hash) = data.value_mut(key, Instant::now(), Value::as_hash_mut)? else {
+            return Ok(RespType::Integer(0));
+        };
+        let removed = fields.iter().filter(|field| hash.remove(field).is_some()).count();
+        if removed > 0 {
+            data.touch(key);
+            data.notify(EventClasses::HASH, "hdel", key);
+        }
+        data.remove_if_empty(key);
+        Ok(RespType::Integer(removed as i64))
+    }
+
+    /// HGETALL. Replies with a map, which RESP2 clients receive as a flat array of
+    /// fields and values.
+    pub fn hgetall(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let pairs = data
+            .value(key, Instant::now(), Value::as_hash)?
+            .map(|hash| {
+                hash.iter()
+                    .map(|(field, value)| {
+                        (RespType::BulkString(field.clone()), RespType::BulkString(value.clone()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(RespType::Map(pairs))
+    }
+
+    pub fn hkeys(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let fields = data
+            .value(key, Instant::now(), Value::as_hash)?
+            .map(|hash| hash.keys().cloned().map(RespType::BulkString).collect())
+            .unwrap_or_default();
+        Ok(RespType::Array(fields))
+    }
+
+    pub fn hvals(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let values = data
+            .value(key, Instant::now(), Value::as_hash)?
+            .map(|hash| hash.values().cloned().map(RespType::BulkString).collect())
+            .unwrap_or_default();
+        Ok(RespType::Array(values))
+    }
+
+    pub fn hlen(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let len = data.value(key, Instant::now(), Value::as_hash)?.map_or(0, |hash| hash.len());
+        Ok(RespType::Integer(len as i64))
+    }
+
+    pub fn hexists(&self, key: &[u8], field: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let exists = data
+            .value(key, Instant::now(), Value::as_hash)?
+            .is_some_and(|hash| hash.contains_key(field));
+        Ok(RespType::Integer(exists as i64))
+    }
+
+    pub fn hincrby(&self, key: &[u8], field: Bytes, delta: i64) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let hash = data.value_or_insert(key, Instant::now(), Value::as_hash_mut, hash_or_create)?;
+        let current = match hash.get(&field) {
+            Some(value) => std::str::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .ok_or_else(|| {
+                    BifrostError::StorageError("ERR hash value is not an integer".to_string())
+                })?,
+            None => 0,
+        };
+        let result = current.checked_add(delta).ok_or_else(|| {
+            BifrostError::StorageError("ERR increment or decrement would overflow".to_string())
+        })?;
+        hash.insert(field, Bytes::from(result.to_string()));
+        data.touch(key);
+        data.notify(EventClasses::HASH, "hincrby", key);
+        Ok(RespType::Integer(result))
+    }
+
+    pub fn hincrbyfloat(&self, key: &[u8], field: Bytes, delta: f64) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let hash = data.value_or_insert(key, Instant::now(), Value::as_hash_mut, hash_or_create)?;
+        let current = match hash.get(&field) {
+            Some(value) => std::str::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse::<f64>().ok())
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| {
+                    BifrostError::StorageError("ERR hash value is not a float".to_string())
+                })?,
+            None => 0.0,
+        };
+        let result = current + delta;
+        if !result.is_finite() {
+            return Err(BifrostError::StorageError(
+                "ERR increment would produce NaN or Infinity".to_string(),
+            ));
+        }
+        let result = Bytes::from(format_double(result));
+        hash.insert(field, result.clone());
+        data.touch(key);
+        data.notify(EventClasses::HASH, "hincrbyfloat", key);
+        Ok(RespType::BulkString(result))
+    }
+
+    /// HSCAN. Replies with the next cursor and a flat array of the fields (and, unless
+    /// `novalues` is set, their values) visited by this call.
+    pub fn hscan(
+        &self,
+        key: &[u8],
+        cursor: u64,
+        options: &ScanOptions,
+        novalues: bool,
+    ) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let Some(hash) = data.value(key, Instant::now(), Value::as_hash)? else {
+            return Ok(RespType::Array(vec![
+                RespType::BulkString(Bytes::from_static(b"0")),
+                RespType::Array(vec![]),
+            ]));
+        };
+
+        let (next, batch) = hash.scan(cursor, options.count);
+        let mut items = Vec::new();
+        for (field, value) in batch.into_iter().filter(|(field, _)| options.matches(field)) {
+            items.push(RespType::BulkString(field.clone()));
+            if !novalues {
+                items.push(RespType::BulkString(value.clone()));
+            }
+        }
+        Ok(RespType::Array(vec![
+            RespType::BulkString(Bytes::from(next.to_string())),
+            RespType::Array(items),
+        ]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk(value: &str) -> RespType {
+        RespType::BulkString(Bytes::copy_from_slice(value.as_bytes()))
+    }
+
+    fn pairs(values: &[(&str, &str)]) -> Vec<(Bytes, Bytes)> {
+        values
+            .iter()
+            .map(|(field, value)| {
+                (Bytes::copy_from_slice(field.as_bytes()), Bytes::copy_from_slice(value.as_bytes()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_set_get_delete() {
+        let db = Db::new();
+        assert_eq!(db.hset(b"user", &pairs(&[("name", "ada"), ("age", "36")])).unwrap(), RespType::Integer(2));
+        assert_eq!(db.hset(b"user", &pairs(&[("age", "37"), ("lang", "en")])).unwrap(), RespType::Integer(1));
+        assert_eq!(db.hget(b"user", b"age").unwrap(), bulk("37"));
+        assert_eq!(db.hget(b"user", b"missing").unwrap(), RespType::Null);
+        assert_eq!(db.hget(b"missing", b"age").unwrap(), RespType::Null);
+        assert_eq!(
+            db.hmget(b"user", &[Bytes::from("name"), Bytes::from("missing")]).unwrap(),
+            RespType::Array(vec![bulk("ada"), RespType::Null])
+        );
+        assert_eq!(db.hlen(b"user").unwrap(), RespType::Integer(3));
+        assert_eq!(db.hexists(b"user", b"lang").unwrap(), RespType::Integer(1));
+
+        assert_eq!(db.hsetnx(b"user", Bytes::from("name"), Bytes::from("bob")).unwrap(), RespType::Integer(0));
+        assert_eq!(db.hsetnx(b"user", Bytes::from("city"), Bytes::from("london")).unwrap(), RespType::Integer(1));
+        assert_eq!(db.hget(b"user", b"name").unwrap(), bulk("ada"));
+
+        let fields = [Bytes::from("name"), Bytes::from("age"), Bytes::from("lang"), Bytes::from("city")];
+        assert_eq!(db.hdel(b"user", &fields[..1]).unwrap(), RespType::Integer(1));
+        assert_eq!(db.hdel(b"user", &fields).unwrap(), RespType::Integer(3));
+        assert_eq!(db.exists(&[Bytes::from("user")]), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_getall_keys_vals() {
+        let db = Db::new();
+        assert_eq!(db.hgetall(b"user").unwrap(), RespType::Map(vec![]));
+        db.hset(b"user", &pairs(&[("name", "ada")])).unwrap();
+        assert_eq!(db.hgetall(b"user").unwrap(), RespType::Map(vec![(bulk("name"), bulk("ada"))]));
+        assert_eq!(db.hkeys(b"user").unwrap(), RespType::Array(vec![bulk("name")]));
+        assert_eq!(db.hvals(b"user").unwrap(), RespType::Array(vec![bulk("ada")]));
+    }
+
+    #[test]
+    fn test_increments() {
+        let db = Db::new();
+        assert_eq!(db.hincrby(b"h", Bytes::from("n"), 5).unwrap(), RespType::Integer(5));
+        assert_eq!(db.hincrby(b"h", Bytes::from("n"), -7).unwrap(), RespType::Integer(-2));
+        assert!(db.hincrby(b"h", Bytes::from("n"), i64::MIN).is_err());
+
+        assert_eq!(db.hincrbyfloat(b"h", Bytes::from("f"), 10.5).unwrap(), bulk("10.5"));
+        assert_eq!(db.hincrbyfloat(b"h", Bytes::from("f"), 0.1).unwrap(), bulk("10.6"));
+        assert_eq!(db.hincrbyfloat(b"h", Bytes::from("n"), 2.5).unwrap(), bulk("0.5"));
+        assert!(db.hincrbyfloat(b"h", Bytes::from("f"), f64::MAX).is_ok());
+        assert!(db.hincrbyfloat(b"h", Bytes::from("f"), f64::MAX).is_err());
+
+        db.hset(b"h", &pairs(&[("text", "abc")])).unwrap();
+        assert!(db.hincrby(b"h", Bytes::from("text"), 1).is_err());
+        assert!(db.hincrbyfloat(b"h", Bytes::from("text"), 1.0).is_err());
+    }
+
+    #[test]
+    fn test_hscan() {
+        let db = Db::new();
+        let fields: Vec<(Bytes, Bytes)> = (0..50)
+            .map(|i| (Bytes::from(format!("field:{}", i)), Bytes::from(i.to_string())))
+            .collect();
+        db.hset(b"h", &fields).unwrap();
+        db.hset(b"h", &pairs(&[("other", "x")])).unwrap();
+
+        let options = ScanOptions {
+            pattern: Some(Bytes::from("field:*")),
+            count: 8,
+        };
+        let mut cursor = 0;
+        let mut seen = Vec::new();
+        loop {
+            let RespType::Array(reply) = db.hscan(b"h", cursor, &options, true).unwrap() else {
+                panic!("Expected an array");
+            };
+            let (RespType::BulkString(next), RespType::Array(items)) = (&reply[0], &reply[1]) else {
+                panic!("Unexpected reply {:?}", reply);
+            };
+            seen.extend(items.iter().cloned());
+            cursor = std::str::from_utf8(next).unwrap().parse().unwrap();
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(seen.len(), 50);
+        assert!(!seen.contains(&bulk("other")));
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let db = Db::new();
+        db.set(Bytes::from("string"), Bytes::from("value"));
+        assert!(db.hset(b"string", &pairs(&[("a", "b")])).is_err());
+        assert!(db.hget(b"string", b"a").is_err());
+        assert!(db.hgetall(b"string").is_err());
+
+        db.hset(b"hash", &pairs(&[("a", "b")])).unwrap();
+        assert!(db.get(b"hash").is_err());
+        assert!(db.llen(b"hash").is_err());
+    }
+}
@@ -0,0 +1,453 @@
+use bytes::Bytes;
+use std::time::Instant;
+
+use crate::error::BifrostError;
+use crate::resp::RespType;
+use super::db::Db;
+use super::notify::EventClasses;
+use super::value::Value;
+
+// HyperLogLogs are stored as strings in the same format as Redis, so they can be moved
+// between the two with GET and SET. The 16 byte header holds the magic "HYLL", the
+// encoding, three unused bytes and the cached cardinality in little endian, whose most
+// significant bit marks it as stale. The 2^14 registers of 6 bits follow, either packed
+// (dense) or run-length encoded (sparse).
+const MAGIC: &[u8] = b"HYLL";
+const HEADER_LEN: usize = 16;
+const DENSE: u8 = 0;
+const SPARSE: u8 = 1;
+const P: u32 = 14;
+const REGISTERS: usize = 1 << P;
+const BITS: usize = 6;
+const DENSE_LEN: usize = HEADER_LEN + (REGISTERS * BITS).div_ceil(8);
+const SEED: u64 = 0xadc83b19;
+
+// Sparse HyperLogLogs switch to the dense encoding once a register exceeds what a VAL
+// opcode can hold or they grow past Redis' default hll-sparse-max-bytes.
+const SPARSE_MAX_VALUE: u8 = 32;
+const SPARSE_MAX_BYTES: usize = 3000;
+const ZERO_MAX_LEN: usize = 64;
+const XZERO_MAX_LEN: usize = 16384;
+const VAL_MAX_LEN: usize = 4;
+
+// Constants of the estimator from Otmar Ertl's "New cardinality estimation algorithms
+// for HyperLogLog sketches", as used by Redis.
+const Q: usize = 64 - P as usize;
+const ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+
+fn not_hyperloglog() -> BifrostError {
+    BifrostError::StorageError("WRONGTYPE Key is not a valid HyperLogLog string value.".to_string())
+}
+
+fn corrupted() -> BifrostError {
+    BifrostError::StorageError("INVALIDOBJ Corrupted HLL object detected".to_string())
+}
+
+// MurmurHash64A, the hash Redis uses to pick an element's register.
+fn murmurhash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate() {
+            h ^= (*byte as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+// The register an element falls into and the value it proposes for it: the position
+// of the first set bit in the rest of the hash.
+fn register_for(element: &[u8]) -> (usize, u8) {
+    let hash = murmurhash64a(element, SEED);
+    let index = (hash as usize) & (REGISTERS - 1);
+    let rest = (hash >> P) | (1 << Q);
+    (index, rest.trailing_zeros() as u8 + 1)
+}
+
+fn dense_get(registers: &[u8], index: usize) -> u8 {
+    let byte = index * BITS / 8;
+    let shift = index * BITS % 8;
+    let low = registers[byte] as u16;
+    let high = registers.get(byte + 1).copied().unwrap_or(0) as u16;
+    (((low | high << 8) >> shift) & 0x3f) as u8
+}
+
+fn dense_set(registers: &mut [u8], index: usize, value: u8) {
+    let byte = index * BITS / 8;
+    let shift = index * BITS % 8;
+    registers[byte] &= !(0x3f << shift);
+    registers[byte] |= value << shift;
+    if shift > 8 - BITS {
+        registers[byte + 1] &= !(0x3f >> (8 - shift));
+        registers[byte + 1] |= value >> (8 - shift);
+    }
+}
+
+fn sparse_decode(opcodes: &[u8]) -> Result<Vec<u8>, BifrostError> {
+    let mut registers = Vec::with_capacity(REGISTERS);
+    let mut i = 0;
+    while i < opcodes.len() {
+        let opcode = opcodes[i];
+        let (value, len) = match opcode >> 6 {
+            0 => (0, (opcode & 0x3f) as usize + 1),
+            1 => {
+                let low = *opcodes.get(i + 1).ok_or_else(corrupted)?;
+                i += 1;
+                (0, (((opcode & 0x3f) as usize) << 8 | low as usize) + 1)
+            }
+            _ => ((opcode >> 2 & 0x1f) + 1, (opcode & 0x3) as usize + 1),
+        };
+        if registers.len() + len > REGISTERS {
+            return Err(corrupted());
+        }
+        registers.resize(registers.len() + len, value);
+        i += 1;
+    }
+    if registers.len() != REGISTERS {
+        return Err(corrupted());
+    }
+    Ok(registers)
+}
+
+// Run-length encodes the registers, or returns None if they can't be stored sparsely.
+fn sparse_encode(registers: &[u8]) -> Option<Vec<u8>> {
+    let mut opcodes = Vec::new();
+    let mut i = 0;
+    while i < registers.len() {
+        let value = registers[i];
+        if value > SPARSE_MAX_VALUE {
+            return None;
+        }
+        let run = registers[i..].iter().take_while(|register| **register == value).count();
+        if value == 0 {
+            let mut left = run;
+            while left > 0 {
+                let len = left.min(XZERO_MAX_LEN);
+                if len > ZERO_MAX_LEN {
+                    opcodes.push(0x40 | ((len - 1) >> 8) as u8);
+                    opcodes.push((len - 1) as u8);
+                } else {
+                    opcodes.push((len - 1) as u8);
+                }
+                left -= len;
+            }
+        } else {
+            let mut left = run;
+            while left > 0 {
+                let len = left.min(VAL_MAX_LEN);
+                opcodes.push(0x80 | (value - 1) << 2 | (len - 1) as u8);
+                left -= len;
+            }
+        }
+        i += run;
+        if HEADER_LEN + opcodes.len() > SPARSE_MAX_BYTES {
+            return None;
+        }
+    }
+    Some(opcodes)
+}
+
+// Ertl's tau and sigma functions for the improved raw estimator.
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if z == previous {
+            return z / 3.0;
+        }
+    }
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+        if z == previous {
+            return z;
+        }
+    }
+}
+
+/// A HyperLogLog with its registers unpacked, one byte each.
+#[derive(Debug, Clone, PartialEq)]
+struct HyperLogLog {
+    dense: bool,
+    cached: Option<u64>,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new() -> Self {
+        HyperLogLog { dense: false, cached: Some(0), registers: vec![0; REGISTERS] }
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, BifrostError> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(not_hyperloglog());
+        }
+        let registers = match bytes[4] {
+            DENSE if bytes.len() == DENSE_LEN => {
+                (0..REGISTERS).map(|index| dense_get(&bytes[HEADER_LEN..], index)).collect()
+            }
+            SPARSE => sparse_decode(&bytes[HEADER_LEN..])?,
+            _ => return Err(not_hyperloglog()),
+        };
+        let cache = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+        Ok(HyperLogLog {
+            dense: bytes[4] == DENSE,
+            cached: (cache >> 63 == 0).then_some(cache),
+            registers,
+        })
+    }
+
+    // Encodes the HyperLogLog sparsely if it was sparse and still fits, densely otherwise.
+    fn encode(&self) -> Bytes {
+        let sparse = if self.dense { None } else { sparse_encode(&self.registers) };
+        let mut bytes = Vec::with_capacity(DENSE_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&[if sparse.is_some() { SPARSE } else { DENSE }, 0, 0, 0]);
+        bytes.extend_from_slice(&self.cached.unwrap_or(1 << 63).to_le_bytes());
+        match sparse {
+            Some(opcodes) => bytes.extend_from_slice(&opcodes),
+            None => {
+                bytes.resize(DENSE_LEN, 0);
+                for (index, value) in self.registers.iter().enumerate() {
+                    dense_set(&mut bytes[HEADER_LEN..], index, *value);
+                }
+            }
+        }
+        Bytes::from(bytes)
+    }
+
+    /// Adds an element, returning whether a register changed.
+    fn add(&mut self, element: &[u8]) -> bool {
+        let (index, value) = register_for(element);
+        if self.registers[index] >= value {
+            return false;
+        }
+        self.registers[index] = value;
+        self.cached = None;
+        true
+    }
+
+    fn merge(&mut self, other: &HyperLogLog) {
+        for (register, value) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*value);
+        }
+        self.dense |= other.dense;
+        self.cached = None;
+    }
+
+    fn estimate(&self) -> u64 {
+        let mut histogram = [0u32; 64];
+        for value in &self.registers {
+            histogram[*value as usize] += 1;
+        }
+        let m = REGISTERS as f64;
+        let mut z = m * tau((m - histogram[Q + 1] as f64) / m);
+        for count in histogram[1..=Q].iter().rev() {
+            z += *count as f64;
+            z *= 0.5;
+        }
+        z += m * sigma(histogram[0] as f64 / m);
+        (ALPHA_INF * m * m / z).round() as u64
+    }
+
+    fn count(&mut self) -> u64 {
+        let count = self.cached.unwrap_or_else(|| self.estimate());
+        self.cached = Some(count);
+        count
+    }
+}
+
+impl Db {
+    /// PFADD. Replies with 1 if the key was created or a register changed.
+    pub fn pfadd(&self, key: &[u8], elements: &[Bytes]) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        let Some(string) = data.value_mut(key, now, Value::as_string_mut)? else {
+            let mut hll = HyperLogLog::new();
+            for element in elements {
+                hll.add(element);
+            }
+            data.insert(Bytes::copy_from_slice(key), Value::String(hll.encode()), None);
+            data.notify(EventClasses::STRING, "pfadd", key);
+            return Ok(RespType::Integer(1));
+        };
+        let mut hll = HyperLogLog::decode(string)?;
+        let mut changed = false;
+        for element in elements {
+            changed |= hll.add(element);
+        }
+        if changed {
+            *string = hll.encode();
+            data.touch(key);
+            data.notify(EventClasses::STRING, "pfadd", key);
+        }
+        Ok(RespType::Integer(changed as i64))
+    }
+
+    /// PFCOUNT. A single key's count is cached in its header until the next change; the
+    /// count of several keys is that of their union.
+    pub fn pfcount(&self, keys: &[Bytes]) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        if let [key] = keys {
+            let Some(string) = data.value_mut(key, now, Value::as_string_mut)? else {
+                return Ok(RespType::Integer(0));
+            };
+            let mut hll = HyperLogLog::decode(string)?;
+            if hll.cached.is_none() {
+                let count = hll.count();
+                let mut bytes = string.to_vec();
+                bytes[8..16].copy_from_slice(&count.to_le_bytes());
+                *string = Bytes::from(bytes);
+                data.touch(key);
+            }
+            return Ok(RespType::Integer(hll.count() as i64));
+        }
+
+        let mut union = HyperLogLog::new();
+        for key in keys {
+            if let Some(string) = data.value(key, now, Value::as_string)? {
+                union.merge(&HyperLogLog::decode(string)?);
+            }
+        }
+        Ok(RespType::Integer(union.count() as i64))
+    }
+
+    /// PFMERGE. Stores the union of the sources and the destination in the destination,
+    /// which is dense if any of them is.
+    pub fn pfmerge(&self, destination: &[u8], sources: &[Bytes]) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        let mut union = match data.value(destination, now, Value::as_string)? {
+            Some(string) => HyperLogLog::decode(string)?,
+            None => HyperLogLog::new(),
+        };
+        for key in sources {
+            if let Some(string) = data.value(key, now, Value::as_string)? {
+                union.merge(&HyperLogLog::decode(string)?);
+            }
+        }
+        union.cached = None;
+        match data.value_mut(destination, now, Value::as_string_mut)? {
+            Some(string) => {
+                *string = union.encode();
+                data.touch(destination);
+            }
+            None => data.insert(Bytes::copy_from_slice(destination), Value::String(union.encode()), None),
+        }
+        // Redis reports a merge the way it does an add.
+        data.notify(EventClasses::STRING, "pfadd", destination);
+        Ok(RespType::SimpleString("OK".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elements(range: std::ops::Range<u32>) -> Vec<Bytes> {
+        range.map(|i| Bytes::from(i.to_string())).collect()
+    }
+
+    #[test]
+    fn test_encoding() {
+        let mut hll = HyperLogLog::new();
+        let empty = hll.encode();
+        assert_eq!(&empty[..], b"HYLL\x01\0\0\0\0\0\0\0\0\0\0\0\x7f\xff");
+
+        for i in 0..100 {
+            hll.add(i.to_string().as_bytes());
+        }
+        let sparse = hll.encode();
+        assert_eq!(sparse[4], SPARSE);
+        assert_eq!(HyperLogLog::decode(&sparse).unwrap(), hll);
+
+        hll.dense = true;
+        let dense = hll.encode();
+        assert_eq!(dense.len(), DENSE_LEN);
+        assert_eq!(HyperLogLog::decode(&dense).unwrap(), hll);
+
+        assert!(HyperLogLog::decode(b"HYLL\x01\0\0\0\0\0\0\0\0\0\0\0\x7f").is_err());
+        assert!(HyperLogLog::decode(b"not a hyperloglog").is_err());
+    }
+
+    #[test]
+    fn test_pfadd_pfcount() {
+        let db = Db::new();
+        assert_eq!(db.pfadd(b"hll", &[]).unwrap(), RespType::Integer(1));
+        assert_eq!(db.pfadd(b"hll", &[]).unwrap(), RespType::Integer(0));
+        assert_eq!(db.pfadd(b"hll", &elements(1..6)).unwrap(), RespType::Integer(1));
+        assert_eq!(db.pfadd(b"hll", &elements(1..6)).unwrap(), RespType::Integer(0));
+        assert_eq!(db.pfcount(&[Bytes::from("hll")]).unwrap(), RespType::Integer(5));
+        assert_eq!(db.pfcount(&[Bytes::from("missing")]).unwrap(), RespType::Integer(0));
+
+        // The count is cached in the header until the next change.
+        let cached = db.get(b"hll").unwrap().unwrap();
+        assert_eq!(u64::from_le_bytes(cached[8..16].try_into().unwrap()), 5);
+
+        db.set(Bytes::from("string"), Bytes::from("value"));
+        assert!(db.pfadd(b"string", &elements(1..2)).is_err());
+        assert!(db.pfcount(&[Bytes::from("string")]).is_err());
+    }
+
+    #[test]
+    fn test_accuracy_and_promotion() {
+        let db = Db::new();
+        db.pfadd(b"hll", &elements(0..100_000)).unwrap();
+        assert_eq!(db.get(b"hll").unwrap().unwrap()[4], DENSE);
+        let RespType::Integer(count) = db.pfcount(&[Bytes::from("hll")]).unwrap() else {
+            panic!("expected an integer");
+        };
+        assert!((count - 100_000).abs() < 2_000, "estimate {} is off", count);
+    }
+
+    #[test]
+    fn test_pfmerge() {
+        let db = Db::new();
+        db.pfadd(b"a", &elements(0..10)).unwrap();
+        db.pfadd(b"b", &elements(5..15)).unwrap();
+        assert_eq!(
+            db.pfcount(&[Bytes::from("a"), Bytes::from("b"), Bytes::from("missing")]).unwrap(),
+            RespType::Integer(15)
+        );
+        assert_eq!(db.pfmerge(b"c", &[Bytes::from("a"), Bytes::from("b")]).unwrap(), RespType::SimpleString("OK".to_string()));
+        assert_eq!(db.pfcount(&[Bytes::from("c")]).unwrap(), RespType::Integer(15));
+        assert_eq!(db.get(b"c").unwrap().unwrap()[4], SPARSE);
+
+        db.pfadd(b"d", &elements(100..200)).unwrap();
+        db.pfmerge(b"d", &[Bytes::from("c")]).unwrap();
+        assert_eq!(db.pfcount(&[Bytes::from("d")]).unwrap(), RespType::Integer(115));
+    }
+}
@@ -0,0 +1,206 @@
+use bytes::Bytes;
+use std::time::Instant;
+
+use crate::error::BifrostError;
+use crate::resp::RespType;
+use super::db::Db;
+use super::notify::EventClasses;
+use super::scan::{glob_match, ScanOptions};
+
+fn no_such_key() -> BifrostError {
+    BifrostError::StorageError("ERR no such key".to_string())
+}
+
+impl Db {
+    /// KEYS. Replies with every key matching the glob-style pattern, in no particular order.
+    pub fn keys(&self, pattern: &[u8]) -> RespType {
+        let now = Instant::now();
+        let data = self.data.read();
+        let keys = data
+            .live_entries(now)
+            .filter(|(key, _)| glob_match(pattern, key))
+            .map(|(key, _)| RespType::BulkString(key.clone()))
+            .collect();
+        RespType::Array(keys)
+    }
+
+    /// SCAN. Replies with the next cursor and the keys visited by this call that match
+    /// the pattern and, if given, hold the type named by `key_type`.
+    pub fn scan(&self, cursor: u64, options: &ScanOptions, key_type: Option<&str>) -> RespType {
+        let now = Instant::now();
+        let data = self.data.read();
+        let (next, batch) = data.scan(cursor, options.count, now);
+        let keys = batch
+            .into_iter()
+            .filter(|(key, entry)| {
+                options.matches(key) && key_type.is_none_or(|name| entry.value.type_name() == name)
+            })
+            .map(|(key, _)| RespType::BulkString(key.clone()))
+            .collect();
+        RespType::Array(vec![
+            RespType::BulkString(Bytes::from(next.to_string())),
+            RespType::Array(keys),
+        ])
+    }
+
+    /// TYPE. Replies with "none" for a missing key.
+    pub fn key_type(&self, key: &[u8]) -> RespType {
+        let data = self.data.read();
+        let name = data.get(key, Instant::now()).map_or("none", |entry| entry.value.type_name());
+        RespType::SimpleString(name.to_string())
+    }
+
+    pub fn randomkey(&self) -> RespType {
+        let data = self.data.read();
+        match data.random_entry(Instant::now()) {
+            Some((key, _)) => RespType::BulkString(key.clone()),
+            None => RespType::Null,
+        }
+    }
+
+    /// RENAME, and RENAMENX with `only_if_new`. The key keeps its time to live and
+    /// replaces whatever `destination` held.
+    pub fn rename(&self, key: &[u8], destination: Bytes, only_if_new: bool) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        data.remove_if_expired(key, now);
+        if data.get(key, now).is_none() {
+            return Err(no_such_key());
+        }
+        if only_if_new && data.get(&destination, now).is_some() {
+            return Ok(RespType::Integer(0));
+        }
+        if key != destination {
+            let Some(entry) = data.remove(key) else {
+                return Err(no_such_key());
+            };
+            data.insert(destination.clone(), entry.value, entry.expires_at);
+            data.notify(EventClasses::GENERIC, "rename_from", key);
+            data.notify(EventClasses::GENERIC, "rename_to", &destination);
+            data.serve_waiters(&destination, now);
+        }
+        Ok(if only_if_new { RespType::Integer(1) } else { RespType::SimpleString("OK".to_string()) })
+    }
+
+    /// TOUCH. Nothing tracks when keys were last accessed, so this only counts the keys
+    /// that exist.
+    pub fn touch(&self, keys: &[Bytes]) -> RespType {
+        self.exists(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::db::Expiration;
+    use crate::storage::list::ListEnd;
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    fn bulk(value: &str) -> RespType {
+        RespType::BulkString(Bytes::copy_from_slice(value.as_bytes()))
+    }
+
+    #[test]
+    fn test_keys_and_type() {
+        let db = Db::new();
+        db.set(Bytes::from("user:1"), Bytes::from("a"));
+        db.set(Bytes::from("user:2"), Bytes::from("b"));
+        db.push(b"queue", &[Bytes::from("job")], ListEnd::Right).unwrap();
+
+        let RespType::Array(mut keys) = db.keys(b"user:*") else {
+            panic!("expected an array");
+        };
+        keys.sort_by_key(|key| format!("{:?}", key));
+        assert_eq!(keys, vec![bulk("user:1"), bulk("user:2")]);
+
+        assert_eq!(db.key_type(b"user:1"), RespType::SimpleString("string".to_string()));
+        assert_eq!(db.key_type(b"queue"), RespType::SimpleString("list".to_string()));
+        assert_eq!(db.key_type(b"missing"), RespType::SimpleString("none".to_string()));
+        assert_ne!(db.randomkey(), RespType::Null);
+        assert_eq!(Db::new().randomkey(), RespType::Null);
+    }
+
+    #[test]
+    fn test_randomkey_skips_expired_keys() {
+        let db = Db::new();
+        for i in 0..200 {
+            db.set(Bytes::from(format!("expired{}", i)), Bytes::from("value"));
+            db.expire(format!("expired{}", i).as_bytes(), Expiration::In(Duration::from_millis(1)), Default::default(), "expire");
+        }
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(db.randomkey(), RespType::Null);
+
+        db.set(Bytes::from("a"), Bytes::from("1"));
+        db.set(Bytes::from("b"), Bytes::from("2"));
+        for _ in 0..100 {
+            let key = db.randomkey();
+            assert!(key == bulk("a") || key == bulk("b"));
+        }
+    }
+
+    #[test]
+    fn test_scan() {
+        let db = Db::new();
+        for i in 0..50 {
+            db.set(Bytes::from(format!("key:{}", i)), Bytes::from("value"));
+        }
+        db.push(b"list", &[Bytes::from("a")], ListEnd::Right).unwrap();
+
+        let options = ScanOptions { pattern: Some(Bytes::from("key:*")), count: 7 };
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        loop {
+            let RespType::Array(reply) = db.scan(cursor, &options, Some("string")) else {
+                panic!("expected an array");
+            };
+            let (RespType::BulkString(next), RespType::Array(keys)) = (&reply[0], &reply[1]) else {
+                panic!("unexpected reply {:?}", reply);
+            };
+            for key in keys {
+                let RespType::BulkString(key) = key else {
+                    panic!("expected a key, got {:?}", key);
+                };
+                assert!(seen.insert(key.clone()));
+            }
+            cursor = std::str::from_utf8(next).unwrap().parse().unwrap();
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(seen.len(), 50);
+
+        let RespType::Array(reply) = db.scan(0, &ScanOptions { count: 100, ..Default::default() }, Some("list")) else {
+            panic!("expected an array");
+        };
+        assert_eq!(reply[1], RespType::Array(vec![bulk("list")]));
+    }
+
+    #[test]
+    fn test_rename() {
+        let db = Db::new();
+        db.set(Bytes::from("a"), Bytes::from("1"));
+        db.expire(b"a", Expiration::In(Duration::from_secs(100)), Default::default(), "expire");
+        db.set(Bytes::from("b"), Bytes::from("2"));
+
+        assert_eq!(db.rename(b"a", Bytes::from("b"), true).unwrap(), RespType::Integer(0));
+        assert_eq!(db.rename(b"a", Bytes::from("b"), false).unwrap(), RespType::SimpleString("OK".to_string()));
+        assert_eq!(db.get(b"b").unwrap(), Some(Bytes::from("1")));
+        assert_eq!(db.ttl(b"b"), RespType::Integer(100));
+        assert_eq!(db.exists(&[Bytes::from("a")]), RespType::Integer(0));
+        assert!(db.rename(b"a", Bytes::from("c"), false).is_err());
+        assert_eq!(db.rename(b"b", Bytes::from("c"), true).unwrap(), RespType::Integer(1));
+    }
+
+    #[test]
+    fn test_del_exists_touch() {
+        let db = Db::new();
+        db.set(Bytes::from("a"), Bytes::from("1"));
+        db.set(Bytes::from("b"), Bytes::from("2"));
+        let keys = [Bytes::from("a"), Bytes::from("a"), Bytes::from("b"), Bytes::from("c")];
+        assert_eq!(db.exists(&keys), RespType::Integer(3));
+        assert_eq!(db.touch(&keys), RespType::Integer(3));
+        assert_eq!(db.del(&keys), RespType::Integer(2));
+        assert_eq!(db.dbsize(), RespType::Integer(0));
+    }
+}
@@ -0,0 +1,628 @@
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::error::BifrostError;
+use crate::resp::RespType;
+use super::blocking::{shared_reply, Blocking, ListWaiter};
+use super::db::{Db, State};
+use super::notify::EventClasses;
+use super::value::Value;
+
+/// Which end of a list an operation works on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+/// The RANK, COUNT and MAXLEN options of LPOS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LposOptions {
+    pub rank: i64,
+    pub count: Option<usize>,
+    pub max_len: usize,
+}
+
+impl Default for LposOptions {
+    fn default() -> Self {
+        LposOptions {
+            rank: 1,
+            count: None,
+            max_len: 0,
+        }
+    }
+}
+
+/// Converts an inclusive `start..=stop` pair, where negative indexes count from the
+/// end, into a range of valid positions. Returns `None` if the range is empty.
+pub fn normalize_range(start: i64, stop: i64, len: usize) -> Option<Range<usize>> {
+    let len = len as i64;
+    let start = if start < 0 { (len + start).max(0) } else { start };
+    let stop = if stop < 0 { len + stop } else { stop.min(len - 1) };
+    if start > stop || start >= len {
+        return None;
+    }
+    Some(start as usize..stop as usize + 1)
+}
+
+// Resolves an index where negative values count from the end.
+fn normalize_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    usize::try_from(index).ok().filter(|index| *index < len)
+}
+
+fn list_or_create() -> Value {
+    Value::List(VecDeque::new())
+}
+
+fn pop_end(list: &mut VecDeque<Bytes>, end: ListEnd) -> Option<Bytes> {
+    match end {
+        ListEnd::Left => list.pop_front(),
+        ListEnd::Right => list.pop_back(),
+    }
+}
+
+fn push_end(list: &mut VecDeque<Bytes>, end: ListEnd, value: Bytes) {
+    match end {
+        ListEnd::Left => list.push_front(value),
+        ListEnd::Right => list.push_back(value),
+    }
+}
+
+fn pop_event(end: ListEnd) -> &'static str {
+    match end {
+        ListEnd::Left => "lpop",
+        ListEnd::Right => "rpop",
+    }
+}
+
+fn push_event(end: ListEnd) -> &'static str {
+    match end {
+        ListEnd::Left => "lpush",
+        ListEnd::Right => "rpush",
+    }
+}
+
+impl State {
+    // Pops from `source` and pushes onto `destination`, checking both types first so
+    // that nothing is popped when the push would fail.
+    fn move_element(
+        &mut self,
+        source: &[u8],
+        destination: &[u8],
+        from: ListEnd,
+        to: ListEnd,
+        now: Instant,
+    ) -> Result<Option<Bytes>, BifrostError> {
+        self.value(destination, now, Value::as_list)?;
+        let Some(list) = self.value_mut(source, now, Value::as_list_mut)? else {
+            return Ok(None);
+        };
+        let Some(value) = pop_end(list, from) else {
+            return Ok(None);
+        };
+        self.notify(EventClasses::LIST, pop_event(from), source);
+        self.remove_if_empty(source);
+        let target = self.value_or_insert(destination, now, Value::as_list_mut, list_or_create)?;
+        push_end(target, to, value.clone());
+        self.notify(EventClasses::LIST, push_event(to), destination);
+        self.touch(source);
+        if destination != source {
+            self.touch(destination);
+        }
+        Ok(Some(value))
+    }
+
+    /// Hands elements of the list at `key` to clients blocked on it, oldest first. A
+    /// BLMOVE waiter pushes onto another list, whose own waiters are served in turn.
+    pub(super) fn serve_list_waiters(&mut self, key: &[u8], now: Instant) {
+        let mut keys = vec![Bytes::copy_from_slice(key)];
+        while let Some(key) = keys.pop() {
+            loop {
+                let available = matches!(
+                    self.value(&key, now, Value::as_list),
+                    Ok(Some(list)) if !list.is_empty()
+                );
+                if !available {
+                    break;
+                }
+                let Some(waiter) = self.list_waiters.get_mut(&key).and_then(VecDeque::pop_front) else {
+                    break;
+                };
+                let Some(sender) = waiter.reply.lock().take() else {
+                    continue;
+                };
+                if sender.is_closed() {
+                    continue;
+                }
+
+                // A client that timed out or disconnected after the check above gets
+                // nothing, so what was taken for it goes back where it came from.
+                match &waiter.destination {
+                    Some((destination, to)) => {
+                        let expires_at = self.get(&key, now).and_then(|entry| entry.expires_at);
+                        match self.move_element(&key, destination, waiter.end, *to, now) {
+                            Ok(Some(value)) => {
+                                if sender.send(RespType::BulkString(value)).is_ok() {
+                                    keys.push(destination.clone());
+                                } else if let Ok(Some(_)) = self.move_element(destination, &key, *to, waiter.end, now) {
+                                    // Moving it back recreates a source it emptied, without its TTL.
+                                    if expires_at.is_some() {
+                                        self.set_expiry(&key, expires_at);
+                                    }
+                                }
+                            }
+                            Ok(None) => continue,
+                            Err(BifrostError::StorageError(msg)) => {
+                                let _ = sender.send(RespType::Error(msg));
+                            }
+                            Err(_) => {
+                                let _ = sender.send(RespType::Error("ERR internal error".to_string()));
+                            }
+                        }
+                    }
+                    None => {
+                        let Ok(Some(list)) = self.value_mut(&key, now, Value::as_list_mut) else {
+                            break;
+                        };
+                        let Some(value) = pop_end(list, waiter.end) else {
+                            break;
+                        };
+                        let reply = RespType::Array(vec![RespType::BulkString(key.clone()), RespType::BulkString(value.clone())]);
+                        if sender.send(reply).is_err() {
+                            push_end(list, waiter.end, value);
+                        } else {
+                            self.touch(&key);
+                            self.notify(EventClasses::LIST, pop_event(waiter.end), &key);
+                        }
+                        self.remove_if_empty(&key);
+                    }
+                }
+            }
+            if self.list_waiters.get(&key).is_some_and(VecDeque::is_empty) {
+                self.list_waiters.remove(&key);
+            }
+        }
+    }
+
+    fn block_on_lists(
+        &mut self,
+        keys: &[Bytes],
+        end: ListEnd,
+        destination: Option<(Bytes, ListEnd)>,
+        timeout: Option<Duration>,
+    ) -> Blocking {
+        let (reply, receiver) = shared_reply();
+        for key in keys {
+            self.list_waiters.entry(key.clone()).or_default().push_back(ListWaiter {
+                end,
+                destination: destination.clone(),
+                reply: reply.clone(),
+            });
+        }
+        Blocking::Blocked(receiver, timeout)
+    }
+}
+
+impl Db {
+    /// LPUSH/RPUSH. Returns the length of the list after the push.
+    pub fn push(&self, key: &[u8], values: &[Bytes], end: ListEnd) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        let list = data.value_or_insert(key, now, Value::as_list_mut, list_or_create)?;
+        for value in values {
+            push_end(list, end, value.clone());
+        }
+        let len = list.len();
+        data.touch(key);
+        data.notify(EventClasses::LIST, push_event(end), key);
+        data.serve_list_waiters(key, now);
+        Ok(RespType::Integer(len as i64))
+    }
+
+    /// LMOVE. Returns the moved element, or null if the source list is empty.
+    pub fn lmove(&self, source: &[u8], destination: &[u8], from: ListEnd, to: ListEnd) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        match data.move_element(source, destination, from, to, now)? {
+            Some(value) => {
+                data.serve_list_waiters(destination, now);
+                Ok(RespType::BulkString(value))
+            }
+            None => Ok(RespType::Null),
+        }
+    }
+
+    /// BLPOP/BRPOP. Pops from the first non-empty list among `keys`, or blocks on all of
+    /// them until one receives a push. Replies with the key and the popped element.
+    pub fn blocking_pop(&self, keys: &[Bytes], end: ListEnd, timeout: Option<Duration>) -> Result<Blocking, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        for key in keys {
+            if let Some(list) = data.value_mut(key, now, Value::as_list_mut)? {
+                if let Some(value) = pop_end(list, end) {
+                    data.touch(key);
+                    data.notify(EventClasses::LIST, pop_event(end), key);
+                    data.remove_if_empty(key);
+                    return Ok(Blocking::Ready(RespType::Array(vec![
+                        RespType::BulkString(key.clone()),
+                        RespType::BulkString(value),
+                    ])));
+                }
+            }
+        }
+        Ok(data.block_on_lists(keys, end, None, timeout))
+    }
+
+    /// BLMOVE. Like LMOVE, but blocks until the source list receives a push.
+    pub fn blocking_move(
+        &self,
+        source: &Bytes,
+        destination: &Bytes,
+        from: ListEnd,
+        to: ListEnd,
+        timeout: Option<Duration>,
+    ) -> Result<Blocking, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        if let Some(value) = data.move_element(source, destination, from, to, now)? {
+            data.serve_list_waiters(destination, now);
+            return Ok(Blocking::Ready(RespType::BulkString(value)));
+        }
+        let destination = Some((destination.clone(), to));
+        Ok(data.block_on_lists(std::slice::from_ref(source), from, destination, timeout))
+    }
+
+    /// LPOP/RPOP. Without a count a single element or null is returned, with a count
+    /// an array of up to `count` elements, or a null array if the key is missing.
+    pub fn pop(&self, key: &[u8], end: ListEnd, count: Option<usize>) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let Some(list) = data.value_mut(key, Instant::now(), Value::as_list_mut)? else {
+            return Ok(count.map_or(RespType::Null, |_| RespType::NullArray));
+        };
+
+        let mut popped = Vec::new();
+        for _ in 0..count.unwrap_or(1) {
+            match pop_end(list, end) {
+                Some(value) => popped.push(RespType::BulkString(value)),
+                None => break,
+            }
+        }
+        if !popped.is_empty() {
+            data.touch(key);
+            data.notify(EventClasses::LIST, pop_event(end), key);
+        }
+        data.remove_if_empty(key);
+
+        Ok(match count {
+            Some(_) => RespType::Array(popped),
+            None => popped.pop().unwrap_or(RespType::Null),
+        })
+    }
+
+    pub fn lrange(&self, key: &[u8], start: i64, stop: i64) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let Some(list) = data.value(key, Instant::now(), Value::as_list)? else {
+            return Ok(RespType::Array(vec![]));
+        };
+        let items = match normalize_range(start, stop, list.len()) {
+            Some(range) => list.range(range).cloned().map(RespType::BulkString).collect(),
+            None => vec![],
+        };
+        Ok(RespType::Array(items))
+    }
+
+    pub fn llen(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let len = data.value(key, Instant::now(), Value::as_list)?.map_or(0, |list| list.len());
+        Ok(RespType::Integer(len as i64))
+    }
+
+    pub fn lindex(&self, key: &[u8], index: i64) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let Some(list) = data.value(key, Instant::now(), Value::as_list)? else {
+            return Ok(RespType::Null);
+        };
+        Ok(normalize_index(index, list.len())
+            .map(|index| RespType::BulkString(list[index].clone()))
+            .unwrap_or(RespType::Null))
+    }
+
+    pub fn lset(&self, key: &[u8], index: i64, value: Bytes) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let Some(list) = data.value_mut(key, Instant::now(), Value::as_list_mut)? else {
+            return Err(BifrostError::StorageError("ERR no such key".to_string()));
+        };
+        let index = normalize_index(index, list.len())
+            .ok_or_else(|| BifrostError::StorageError("ERR index out of range".to_string()))?;
+        list[index] = value;
+        data.touch(key);
+        data.notify(EventClasses::LIST, "lset", key);
+        Ok(RespType::SimpleString("OK".to_string()))
+    }
+
+    /// Removes up to `count` occurrences of `value`: from the head when positive, from
+    /// the tail when negative and all of them when zero.
+    pub fn lrem(&self, key: &[u8], count: i64, value: &[u8]) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let Some(list) = data.value_mut(key, Instant::now(), Value::as_list_mut)? else {
+            return Ok(RespType::Integer(0));
+        };
+
+        let limit = if count == 0 { usize::MAX } else { count.unsigned_abs() as usize };
+        let mut removed = 0;
+        if count >= 0 {
+            list.retain(|item| {
+                let remove = removed < limit && item.as_ref() == value;
+                removed += remove as usize;
+                !remove
+            });
+        } else {
+            let mut index = list.len();
+            while index > 0 && removed < limit {
+                index -= 1;
+                if list[index].as_ref() == value {
+                    list.remove(index);
+                    removed += 1;
+                }
+            }
+        }
+        if removed > 0 {
+            data.touch(key);
+            data.notify(EventClasses::LIST, "lrem", key);
+        }
+        data.remove_if_empty(key);
+        Ok(RespType::Integer(removed as i64))
+    }
+
+    pub fn ltrim(&self, key: &[u8], start: i64, stop: i64) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        if let Some(list) = data.value_mut(key, Instant::now(), Value::as_list_mut)? {
+            match normalize_range(start, stop, list.len()) {
+                Some(range) => {
+                    list.truncate(range.end);
+                    list.drain(..range.start);
+                }
+                None => list.clear(),
+            }
+            data.touch(key);
+            data.notify(EventClasses::LIST, "ltrim", key);
+            data.remove_if_empty(key);
+        }
+        Ok(RespType::SimpleString("OK".to_string()))
+    }
+
+    /// Inserts `value` next to the first occurrence of `pivot`. Returns the new length,
+    /// -1 if the pivot was not found and 0 if the key does not exist.
+    pub fn linsert(&self, key: &[u8], end: ListEnd, pivot: &[u8], value: Bytes) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let Some(list) = data.value_mut(key, Instant::now(), Value::as_list_mut)? else {
+            return Ok(RespType::Integer(0));
+        };
+        let Some(position) = list.iter().position(|item| item.as_ref() == pivot) else {
+            return Ok(RespType::Integer(-1));
+        };
+        match end {
+            ListEnd::Left => list.insert(position, value),
+            ListEnd::Right => list.insert(position + 1, value),
+        }
+        let len = list.len();
+        data.touch(key);
+        data.notify(EventClasses::LIST, "linsert", key);
+        Ok(RespType::Integer(len as i64))
+    }
+
+    /// Returns the index of matching elements. RANK picks which match to start from
+    /// (negative ranks search from the tail), COUNT asks for several matches (0 for all)
+    /// and MAXLEN bounds how many elements are compared (0 for no bound).
+    pub fn lpos(&self, key: &[u8], value: &[u8], options: LposOptions) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let list = data.value(key, Instant::now(), Value::as_list)?;
+
+        let mut matches = Vec::new();
+        if let Some(list) = list {
+            let wanted = match options.count {
+                Some(0) => usize::MAX,
+                Some(count) => count,
+                None => 1,
+            };
+            let max_len = if options.max_len == 0 { list.len() } else { options.max_len };
+            let mut skip = options.rank.unsigned_abs() - 1;
+            let indexes: Box<dyn Iterator<Item = usize>> = if options.rank > 0 {
+                Box::new(0..list.len())
+            } else {
+                Box::new((0..list.len()).rev())
+            };
+
+            for index in indexes.take(max_len) {
+                if list[index].as_ref() != value {
+                    continue;
+                }
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                matches.push(RespType::Integer(index as i64));
+                if matches.len() >= wanted {
+                    break;
+                }
+            }
+        }
+
+        Ok(match options.count {
+            Some(_) => RespType::Array(matches),
+            None => matches.pop().unwrap_or(RespType::Null),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk(value: &str) -> RespType {
+        RespType::BulkString(Bytes::copy_from_slice(value.as_bytes()))
+    }
+
+    fn items(values: &[&str]) -> Vec<Bytes> {
+        values.iter().map(|value| Bytes::copy_from_slice(value.as_bytes())).collect()
+    }
+
+    #[test]
+    fn test_normalize_range() {
+        assert_eq!(normalize_range(0, -1, 5), Some(0..5));
+        assert_eq!(normalize_range(-2, 100, 5), Some(3..5));
+        assert_eq!(normalize_range(-100, 1, 5), Some(0..2));
+        assert_eq!(normalize_range(3, 1, 5), None);
+        assert_eq!(normalize_range(5, 10, 5), None);
+        assert_eq!(normalize_range(0, -1, 0), None);
+    }
+
+    #[test]
+    fn test_push_pop_range() {
+        let db = Db::new();
+        assert_eq!(db.push(b"list", &items(&["b", "a"]), ListEnd::Left).unwrap(), RespType::Integer(2));
+        assert_eq!(db.push(b"list", &items(&["c", "d"]), ListEnd::Right).unwrap(), RespType::Integer(4));
+        assert_eq!(
+            db.lrange(b"list", 0, -1).unwrap(),
+            RespType::Array(vec![bulk("a"), bulk("b"), bulk("c"), bulk("d")])
+        );
+        assert_eq!(db.lindex(b"list", -1).unwrap(), bulk("d"));
+        assert_eq!(db.lindex(b"list", 10).unwrap(), RespType::Null);
+
+        assert_eq!(db.pop(b"list", ListEnd::Left, None).unwrap(), bulk("a"));
+        assert_eq!(
+            db.pop(b"list", ListEnd::Right, Some(5)).unwrap(),
+            RespType::Array(vec![bulk("d"), bulk("c"), bulk("b")])
+        );
+        // The key is deleted along with its last element.
+        assert_eq!(db.exists(&[Bytes::from("list")]), RespType::Integer(0));
+        assert_eq!(db.pop(b"list", ListEnd::Left, None).unwrap(), RespType::Null);
+    }
+
+    #[test]
+    fn test_modify() {
+        let db = Db::new();
+        db.push(b"list", &items(&["a", "x", "b", "x", "c", "x"]), ListEnd::Right).unwrap();
+
+        assert_eq!(db.lrem(b"list", -1, b"x").unwrap(), RespType::Integer(1));
+        assert_eq!(db.lrem(b"list", 1, b"x").unwrap(), RespType::Integer(1));
+        assert_eq!(db.llen(b"list").unwrap(), RespType::Integer(4));
+
+        assert_eq!(db.linsert(b"list", ListEnd::Left, b"x", Bytes::from("y")).unwrap(), RespType::Integer(5));
+        assert_eq!(db.linsert(b"list", ListEnd::Right, b"z", Bytes::from("y")).unwrap(), RespType::Integer(-1));
+        assert_eq!(
+            db.lrange(b"list", 0, -1).unwrap(),
+            RespType::Array(vec![bulk("a"), bulk("b"), bulk("y"), bulk("x"), bulk("c")])
+        );
+
+        db.lset(b"list", -1, Bytes::from("z")).unwrap();
+        assert!(db.lset(b"list", 5, Bytes::from("z")).is_err());
+        db.ltrim(b"list", 1, -2).unwrap();
+        assert_eq!(
+            db.lrange(b"list", 0, -1).unwrap(),
+            RespType::Array(vec![bulk("b"), bulk("y"), bulk("x")])
+        );
+        db.ltrim(b"list", 5, 10).unwrap();
+        assert_eq!(db.exists(&[Bytes::from("list")]), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_lpos() {
+        let db = Db::new();
+        db.push(b"list", &items(&["a", "b", "c", "1", "2", "3", "c", "c"]), ListEnd::Right).unwrap();
+
+        assert_eq!(db.lpos(b"list", b"c", LposOptions::default()).unwrap(), RespType::Integer(2));
+        let rank = LposOptions { rank: -1, ..Default::default() };
+        assert_eq!(db.lpos(b"list", b"c", rank).unwrap(), RespType::Integer(7));
+        let all = LposOptions { count: Some(0), ..Default::default() };
+        assert_eq!(
+            db.lpos(b"list", b"c", all).unwrap(),
+            RespType::Array(vec![RespType::Integer(2), RespType::Integer(6), RespType::Integer(7)])
+        );
+        let bounded = LposOptions { rank: 2, count: Some(0), max_len: 7 };
+        assert_eq!(db.lpos(b"list", b"c", bounded).unwrap(), RespType::Array(vec![RespType::Integer(6)]));
+        assert_eq!(db.lpos(b"list", b"z", LposOptions::default()).unwrap(), RespType::Null);
+    }
+
+    fn blocked(blocking: Blocking) -> tokio::sync::oneshot::Receiver<RespType> {
+        match blocking {
+            Blocking::Blocked(receiver, _) => receiver,
+            Blocking::Ready(reply) => panic!("Expected to block, got {:?}", reply),
+        }
+    }
+
+    #[test]
+    fn test_blocking_pop() {
+        let db = Db::new();
+        let keys = items(&["a", "b"]);
+        let mut first = blocked(db.blocking_pop(&keys, ListEnd::Left, None).unwrap());
+        let mut second = blocked(db.blocking_pop(&keys[1..], ListEnd::Left, None).unwrap());
+        let dropped = blocked(db.blocking_pop(&keys[1..], ListEnd::Left, None).unwrap());
+        drop(dropped);
+
+        // Waiters are served in the order they blocked and take elements off the list.
+        db.push(b"b", &items(&["x", "y", "z"]), ListEnd::Right).unwrap();
+        assert_eq!(first.try_recv().unwrap(), RespType::Array(vec![bulk("b"), bulk("x")]));
+        assert_eq!(second.try_recv().unwrap(), RespType::Array(vec![bulk("b"), bulk("y")]));
+        assert_eq!(db.lrange(b"b", 0, -1).unwrap(), RespType::Array(vec![bulk("z")]));
+
+        // Served waiters are not woken again by the other key they blocked on.
+        db.push(b"a", &items(&["w"]), ListEnd::Right).unwrap();
+        assert_eq!(db.llen(b"a").unwrap(), RespType::Integer(1));
+
+        match db.blocking_pop(&keys, ListEnd::Right, None).unwrap() {
+            Blocking::Ready(reply) => assert_eq!(reply, RespType::Array(vec![bulk("a"), bulk("w")])),
+            Blocking::Blocked(..) => panic!("Expected an element to be ready"),
+        }
+    }
+
+    #[test]
+    fn test_blocking_move() {
+        let db = Db::new();
+        let source = Bytes::from("source");
+        let destination = Bytes::from("destination");
+        let mut mover = blocked(
+            db.blocking_move(&source, &destination, ListEnd::Left, ListEnd::Right, None).unwrap(),
+        );
+        let mut popper = blocked(db.blocking_pop(std::slice::from_ref(&destination), ListEnd::Left, None).unwrap());
+
+        // The moved element wakes the client blocked on the destination in turn.
+        db.push(b"source", &items(&["x"]), ListEnd::Left).unwrap();
+        assert_eq!(mover.try_recv().unwrap(), bulk("x"));
+        assert_eq!(popper.try_recv().unwrap(), RespType::Array(vec![bulk("destination"), bulk("x")]));
+        assert_eq!(db.exists(&[Bytes::from("destination")]), RespType::Integer(0));
+
+        db.push(b"source", &items(&["y"]), ListEnd::Left).unwrap();
+        assert_eq!(db.lmove(b"source", b"destination", ListEnd::Right, ListEnd::Left).unwrap(), bulk("y"));
+        assert_eq!(db.lmove(b"source", b"destination", ListEnd::Right, ListEnd::Left).unwrap(), RespType::Null);
+    }
+
+    #[test]
+    fn test_gone_waiter_keeps_element() {
+        let db = Db::new();
+        let source = Bytes::from("source");
+        let destination = Bytes::from("destination");
+        drop(blocked(db.blocking_pop(std::slice::from_ref(&source), ListEnd::Left, None).unwrap()));
+        drop(blocked(db.blocking_move(&source, &destination, ListEnd::Left, ListEnd::Right, None).unwrap()));
+
+        // Nobody is left to receive the element, so it stays where it was pushed.
+        db.push(b"source", &items(&["x"]), ListEnd::Right).unwrap();
+        assert_eq!(db.lrange(b"source", 0, -1).unwrap(), RespType::Array(vec![bulk("x")]));
+        assert_eq!(db.exists(&[destination]), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let db = Db::new();
+        db.set(Bytes::from("string"), Bytes::from("value"));
+        assert!(db.push(b"string", &items(&["a"]), ListEnd::Left).is_err());
+        assert!(db.llen(b"string").is_err());
+
+        db.push(b"list", &items(&["a"]), ListEnd::Left).unwrap();
+        assert!(db.get(b"list").is_err());
+        assert!(db.incr(b"list").is_err());
+    }
+}
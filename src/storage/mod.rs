@@ -1 +1,20 @@
+pub mod bitmap;
+pub mod blocking;
+pub mod consumer_group;
+pub mod databases;
 pub mod db;
+pub mod geo;
+pub mod hash;
+pub mod hyperloglog;
+pub mod keyspace;
+pub mod list;
+pub mod notify;
+pub mod scan;
+pub mod set;
+pub mod skiplist;
+pub mod sorted_set;
+pub mod stream;
+pub mod string;
+pub mod tracking;
+pub mod value;
+pub mod watch;
@@ -0,0 +1,244 @@
+use bytes::Bytes;
+use std::fmt;
+use std::sync::atomic::{AtomicU16, Ordering};
+
+use crate::pubsub::Broker;
+
+/// The keyspace event classes selected by notify-keyspace-events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EventClasses(u16);
+
+impl EventClasses {
+    pub const KEYSPACE: Self = Self(1 << 0);
+    pub const KEYEVENT: Self = Self(1 << 1);
+    pub const GENERIC: Self = Self(1 << 2);
+    pub const STRING: Self = Self(1 << 3);
+    pub const LIST: Self = Self(1 << 4);
+    pub const SET: Self = Self(1 << 5);
+    pub const HASH: Self = Self(1 << 6);
+    pub const ZSET: Self = Self(1 << 7);
+    pub const EXPIRED: Self = Self(1 << 8);
+    pub const STREAM: Self = Self(1 << 9);
+    pub const NEW: Self = Self(1 << 10);
+    // The classes 'A' stands for. New keys must be asked for explicitly.
+    const ALL: Self = Self(
+        Self::GENERIC.0 | Self::STRING.0 | Self::LIST.0 | Self::SET.0 | Self::HASH.0 | Self::ZSET.0
+            | Self::EXPIRED.0 | Self::STREAM.0,
+    );
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Parses the characters of a notify-keyspace-events value, failing on any it does
+    /// not know. Redis' evicted (`e`), module (`d`) and key miss (`m`) classes are
+    /// rejected, since nothing here would ever publish them.
+    pub fn parse(flags: &[u8]) -> Option<Self> {
+        flags.iter().try_fold(Self::default(), |classes, &flag| {
+            let class = match flag {
+                b'A' => Self::ALL,
+                flag => CLASSES.iter().find(|(c, _)| *c == flag)?.1,
+            };
+            Some(Self(classes.0 | class.0))
+        })
+    }
+}
+
+// Each class with the character that selects it, in the order Redis lists them.
+const CLASSES: [(u8, EventClasses); 11] = [
+    (b'g', EventClasses::GENERIC),
+    (b'$', EventClasses::STRING),
+    (b'l', EventClasses::LIST),
+    (b's', EventClasses::SET),
+    (b'h', EventClasses::HASH),
+    (b'z', EventClasses::ZSET),
+    (b'x', EventClasses::EXPIRED),
+    (b't', EventClasses::STREAM),
+    (b'K', EventClasses::KEYSPACE),
+    (b'E', EventClasses::KEYEVENT),
+    (b'n', EventClasses::NEW),
+];
+
+/// Formats the classes the way CONFIG GET reports them, with 'A' standing in for the
+/// classes it covers.
+impl fmt::Display for EventClasses {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let all = self.contains(Self::ALL);
+        if all {
+            f.write_str("A")?;
+        }
+        for (flag, class) in CLASSES {
+            if self.contains(class) && !(all && Self::ALL.contains(class)) {
+                write!(f, "{}", flag as char)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Publishes keyspace events to the broker on `__keyspace@<db>__:<key>` and
+/// `__keyevent@<db>__:<event>`, for the classes notify-keyspace-events enables.
+/// Shared by every database; nothing is published by default.
+#[derive(Debug, Default)]
+pub struct Notifier {
+    classes: AtomicU16,
+    broker: Broker,
+}
+
+impl Notifier {
+    pub fn new(broker: Broker) -> Self {
+        Notifier { classes: AtomicU16::new(0), broker }
+    }
+
+    pub fn classes(&self) -> EventClasses {
+        EventClasses(self.classes.load(Ordering::Relaxed))
+    }
+
+    pub fn set_classes(&self, classes: EventClasses) {
+        self.classes.store(classes.0, Ordering::Relaxed);
+    }
+
+    /// Publishes `event` on `key` of database `db` if its class is enabled.
+    pub(super) fn notify(&self, class: EventClasses, event: &str, key: &[u8], db: usize) {
+        let classes = self.classes();
+        if !classes.contains(class) {
+            return;
+        }
+        if classes.contains(EventClasses::KEYSPACE) {
+            let channel = [format!("__keyspace@{}__:", db).as_bytes(), key].concat();
+            self.broker.publish(&Bytes::from(channel), &Bytes::copy_from_slice(event.as_bytes()));
+        }
+        if classes.contains(EventClasses::KEYEVENT) {
+            let channel = format!("__keyevent@{}__:{}", db, event);
+            self.broker.publish(&Bytes::from(channel), &Bytes::copy_from_slice(key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pubsub::{PubSubCommand, Subscriptions};
+    use crate::resp::RespType;
+    use crate::storage::databases::Databases;
+    use crate::storage::db::{Expiration, SetCondition};
+    use crate::storage::bitmap::{BitFieldOp, BitFieldType, BitOperation, Overflow};
+    use crate::storage::list::ListEnd;
+    use crate::storage::sorted_set::ZaddOptions;
+    use crate::storage::stream::XaddId;
+    use std::time::Duration;
+
+    #[test]
+    fn test_event_classes() {
+        let classes = EventClasses::parse(b"Kx$").unwrap();
+        assert!(classes.contains(EventClasses::EXPIRED));
+        assert!(!classes.contains(EventClasses::GENERIC));
+        assert_eq!(classes.to_string(), "$xK");
+        assert_eq!(EventClasses::parse(b"AKEn").unwrap().to_string(), "AKEn");
+        assert_eq!(EventClasses::parse(b"g$lshzxt").unwrap().to_string(), "A");
+        assert_eq!(EventClasses::parse(b"").unwrap().to_string(), "");
+        assert_eq!(EventClasses::parse(b"Kw"), None);
+        assert_eq!(EventClasses::parse(b"Ke"), None);
+        assert_eq!(EventClasses::parse(b"Km"), None);
+    }
+
+    fn event(channel: &str, message: &str) -> Option<RespType> {
+        Some(RespType::Push(vec![
+            RespType::BulkString(Bytes::from_static(b"pmessage")),
+            RespType::BulkString(Bytes::from_static(b"__key*")),
+            RespType::BulkString(Bytes::copy_from_slice(channel.as_bytes())),
+            RespType::BulkString(Bytes::copy_from_slice(message.as_bytes())),
+        ]))
+    }
+
+    #[tokio::test]
+    async fn test_notifications() {
+        let broker = Broker::default();
+        let databases = Databases::with_broker(2, broker.clone());
+        let mut subscriptions = Subscriptions::new(1, broker);
+        subscriptions.execute(PubSubCommand::PSubscribe(vec![Bytes::from("__key*")]));
+
+        // Nothing is published until events are enabled.
+        databases[1].set(Bytes::from("quiet"), Bytes::from("1"));
+        databases.notifier().set_classes(EventClasses::parse(b"KEg$x").unwrap());
+
+        databases[1].set(Bytes::from("key"), Bytes::from("1"));
+        assert_eq!(subscriptions.next_message().await, event("__keyspace@1__:key", "set"));
+        assert_eq!(subscriptions.next_message().await, event("__keyevent@1__:set", "key"));
+
+        databases[1].incr(b"key").unwrap();
+        assert_eq!(subscriptions.next_message().await, event("__keyspace@1__:key", "incrby"));
+        assert_eq!(subscriptions.next_message().await, event("__keyevent@1__:incrby", "key"));
+
+        databases[1].del(&[Bytes::from("key"), Bytes::from("missing")]);
+        assert_eq!(subscriptions.next_message().await, event("__keyspace@1__:key", "del"));
+        assert_eq!(subscriptions.next_message().await, event("__keyevent@1__:del", "key"));
+
+        databases.notifier().set_classes(EventClasses::parse(b"Ex").unwrap());
+        databases[0].expire(b"quiet", Expiration::In(Duration::from_secs(1)), Default::default(), "expire");
+        databases[1].expire(b"quiet", Expiration::In(Duration::from_millis(10)), Default::default(), "expire");
+        std::thread::sleep(Duration::from_millis(20));
+        databases.purge_expired_keys();
+        assert_eq!(subscriptions.next_message().await, event("__keyevent@1__:expired", "quiet"));
+    }
+
+    #[tokio::test]
+    async fn test_collection_notifications() {
+        let broker = Broker::default();
+        let databases = Databases::with_broker(1, broker.clone());
+        let mut subscriptions = Subscriptions::new(1, broker);
+        subscriptions.execute(PubSubCommand::PSubscribe(vec![Bytes::from("__key*")]));
+        databases.notifier().set_classes(EventClasses::parse(b"EA").unwrap());
+        let db = &databases[0];
+        let values = |values: &[&str]| values.iter().map(|value| Bytes::copy_from_slice(value.as_bytes())).collect::<Vec<_>>();
+
+        // Emptying a collection deletes it, which is reported after the event that did it.
+        db.push(b"source", &values(&["a"]), ListEnd::Left).unwrap();
+        db.lmove(b"source", b"list", ListEnd::Left, ListEnd::Right).unwrap();
+        db.pop(b"list", ListEnd::Left, None).unwrap();
+        for (name, key) in [("lpush", "source"), ("lpop", "source"), ("del", "source"), ("rpush", "list"), ("lpop", "list"), ("del", "list")] {
+            assert_eq!(subscriptions.next_message().await, event(&format!("__keyevent@0__:{}", name), key));
+        }
+
+        db.sadd(b"set", &values(&["a"])).unwrap();
+        db.srem(b"set", &values(&["b"])).unwrap();
+        db.srem(b"set", &values(&["a"])).unwrap();
+        db.hset(b"hash", &[(Bytes::from("field"), Bytes::from("1"))]).unwrap();
+        db.hincrby(b"hash", Bytes::from("field"), 1).unwrap();
+        db.hdel(b"hash", &values(&["field"])).unwrap();
+        for (name, key) in [("sadd", "set"), ("srem", "set"), ("del", "set"), ("hset", "hash"), ("hincrby", "hash"), ("hdel", "hash"), ("del", "hash")] {
+            assert_eq!(subscriptions.next_message().await, event(&format!("__keyevent@0__:{}", name), key));
+        }
+
+        // ZADD XX does not create the key, so neither it nor ZREM of a missing member
+        // publish anything.
+        let xx = ZaddOptions { condition: SetCondition::IfExists, ..Default::default() };
+        db.zadd(b"zset", &[(1.0, Bytes::from("a"))], xx).unwrap();
+        db.zadd(b"zset", &[(1.0, Bytes::from("a"))], Default::default()).unwrap();
+        db.zrem(b"zset", &values(&["b"])).unwrap();
+        db.zpop(b"zset", None, false).unwrap();
+        db.xadd(b"stream", XaddId::Auto, vec![(Bytes::from("f"), Bytes::from("v"))], Default::default()).unwrap();
+        for (name, key) in [("zadd", "zset"), ("zpopmin", "zset"), ("del", "zset"), ("xadd", "stream")] {
+            assert_eq!(subscriptions.next_message().await, event(&format!("__keyevent@0__:{}", name), key));
+        }
+
+        // A BITFIELD whose only write fails with OVERFLOW FAIL publishes nothing.
+        let u8 = BitFieldType { signed: false, bits: 8 };
+        db.setbit(b"bits", 7, true).unwrap();
+        db.bitop(BitOperation::Not, Bytes::from("not"), &[Bytes::from("bits")]).unwrap();
+        db.bitop(BitOperation::And, Bytes::from("not"), &[Bytes::from("missing")]).unwrap();
+        db.bitfield(b"bits", &[BitFieldOp::Overflow(Overflow::Fail), BitFieldOp::IncrBy(u8, 0, 255)]).unwrap();
+        db.bitfield(b"bits", &[BitFieldOp::Set(u8, 0, 2)]).unwrap();
+        for (name, key) in [("setbit", "bits"), ("set", "not"), ("del", "not"), ("setbit", "bits")] {
+            assert_eq!(subscriptions.next_message().await, event(&format!("__keyevent@0__:{}", name), key));
+        }
+
+        db.pfadd(b"hll", &values(&["a"])).unwrap();
+        db.pfadd(b"hll", &values(&["a"])).unwrap();
+        db.pfmerge(b"union", &values(&["hll"])).unwrap();
+        db.geoadd(b"geo", &[(13.361389, 38.115556, Bytes::from("Palermo"))], Default::default()).unwrap();
+        for (name, key) in [("pfadd", "hll"), ("pfadd", "union"), ("zadd", "geo")] {
+            assert_eq!(subscriptions.next_message().await, event(&format!("__keyevent@0__:{}", name), key));
+        }
+    }
+}
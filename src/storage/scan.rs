@@ -0,0 +1,240 @@
+use bytes::Bytes;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Deref;
+
+/// The MATCH and COUNT options shared by the SCAN command family.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanOptions {
+    pub pattern: Option<Bytes>,
+    pub count: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            pattern: None,
+            count: 10,
+        }
+    }
+}
+
+impl ScanOptions {
+    pub fn matches(&self, key: &[u8]) -> bool {
+        self.pattern.as_ref().is_none_or(|pattern| glob_match(pattern, key))
+    }
+}
+
+// Cursors are positions in the order of a fixed hash of each key rather than in the
+// container's own iteration order, so they stay valid while the container is modified
+// or rehashed: every key present for the whole iteration is returned exactly once.
+fn cursor_of(key: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A map that also keeps its keys in cursor order, so that a scan picks up right where
+/// the previous call stopped. Reads go through the inner `HashMap`.
+#[derive(Debug, Clone)]
+pub struct ScanMap<V> {
+    map: HashMap<Bytes, V>,
+    order: BTreeSet<(u64, Bytes)>,
+}
+
+impl<V> Default for ScanMap<V> {
+    fn default() -> Self {
+        ScanMap { map: HashMap::new(), order: BTreeSet::new() }
+    }
+}
+
+impl<V: PartialEq> PartialEq for ScanMap<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<V> Deref for ScanMap<V> {
+    type Target = HashMap<Bytes, V>;
+
+    fn deref(&self) -> &HashMap<Bytes, V> {
+        &self.map
+    }
+}
+
+impl<V> ScanMap<V> {
+    pub fn insert(&mut self, key: Bytes, value: V) -> Option<V> {
+        let previous = self.map.insert(key.clone(), value);
+        if previous.is_none() {
+            self.order.insert((cursor_of(&key), key));
+        }
+        previous
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        let (key, value) = self.map.remove_entry(key)?;
+        self.order.remove(&(cursor_of(&key), key));
+        Some(value)
+    }
+
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut V> {
+        self.map.get_mut(key)
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+
+    /// Every item in cursor order, starting at `cursor` and wrapping around to the
+    /// beginning, so a random cursor gives a random starting point.
+    pub fn iter_from(&self, cursor: u64) -> impl Iterator<Item = (&Bytes, &V)> {
+        let start = (cursor, Bytes::new());
+        self.order
+            .range(start.clone()..)
+            .chain(self.order.range(..start))
+            .map(|(_, key)| (key, &self.map[key]))
+    }
+
+    /// Returns up to `count` items at or after `cursor`, along with the cursor to
+    /// continue from, which is 0 once the iteration is complete. Keys sharing a hash
+    /// are never split across calls, so a call may return slightly more than `count`
+    /// items.
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&Bytes, &V)>) {
+        let mut remaining = self.order.range((cursor, Bytes::new())..).peekable();
+        let mut batch = Vec::new();
+        while let Some((position, key)) = remaining.next() {
+            batch.push((key, &self.map[key]));
+            if batch.len() >= count && remaining.peek().is_none_or(|(next, _)| next != position) {
+                break;
+            }
+        }
+        (remaining.next().map_or(0, |(position, _)| *position), batch)
+    }
+}
+
+impl<V> FromIterator<(Bytes, V)> for ScanMap<V> {
+    fn from_iter<I: IntoIterator<Item = (Bytes, V)>>(iter: I) -> Self {
+        let mut map = ScanMap::default();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+/// Matches `string` against a glob-style pattern the way Redis does: `*` and `?`
+/// wildcards, `[...]` classes with ranges and `^` negation, and `\` escapes.
+pub fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => string.is_empty(),
+        Some((b'*', rest)) => {
+            let rest = {
+                let mut rest = rest;
+                while let Some((b'*', tail)) = rest.split_first() {
+                    rest = tail;
+                }
+                rest
+            };
+            if rest.is_empty() {
+                return true;
+            }
+            (0..=string.len()).any(|skip| glob_match(rest, &string[skip..]))
+        }
+        Some((b'?', rest)) => !string.is_empty() && glob_match(rest, &string[1..]),
+        Some((b'[', rest)) => {
+            let Some((&byte, string)) = string.split_first() else {
+                return false;
+            };
+            let (matched, rest) = match_class(rest, byte);
+            matched && glob_match(rest, string)
+        }
+        Some((b'\\', rest)) if !rest.is_empty() => {
+            string.first() == Some(&rest[0]) && glob_match(&rest[1..], &string[1..])
+        }
+        Some((&expected, rest)) => {
+            string.first() == Some(&expected) && glob_match(rest, &string[1..])
+        }
+    }
+}
+
+// Matches `byte` against the class that starts right after a `[`. Returns whether it
+// matched and the pattern following the closing `]`.
+fn match_class(mut pattern: &[u8], byte: u8) -> (bool, &[u8]) {
+    let negate = pattern.first() == Some(&b'^');
+    if negate {
+        pattern = &pattern[1..];
+    }
+    let mut matched = false;
+    loop {
+        match pattern {
+            [] => break,
+            [b']', rest @ ..] => {
+                pattern = rest;
+                break;
+            }
+            [b'\\', escaped, rest @ ..] => {
+                matched |= *escaped == byte;
+                pattern = rest;
+            }
+            [start, b'-', end, rest @ ..] if *end != b']' => {
+                let (low, high) = if start <= end { (*start, *end) } else { (*end, *start) };
+                matched |= (low..=high).contains(&byte);
+                pattern = rest;
+            }
+            [single, rest @ ..] => {
+                matched |= *single == byte;
+                pattern = rest;
+            }
+        }
+    }
+    (matched != negate, pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"user:*", b"user:42"));
+        assert!(!glob_match(b"user:*", b"session:42"));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(!glob_match(b"h?llo", b"hllo"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[ae]llo", b"hillo"));
+        assert!(glob_match(b"h[^e]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"h[a-c]llo", b"hbllo"));
+        assert!(glob_match(b"h\\*llo", b"h*llo"));
+        assert!(!glob_match(b"h\\*llo", b"hello"));
+        assert!(glob_match(b"*a*b*", b"xxaxxbxx"));
+    }
+
+    #[test]
+    fn test_scan_visits_every_key_once() {
+        let mut map: ScanMap<()> = (0..100).map(|i| (Bytes::from(format!("key:{}", i)), ())).collect();
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        loop {
+            let (next, batch) = map.scan(cursor, 7);
+            assert!(batch.len() >= 7 || next == 0);
+            let batch: Vec<Bytes> = batch.into_iter().map(|(key, _)| key.clone()).collect();
+            for key in batch {
+                assert!(seen.insert(key.clone()));
+                // Removing what was returned and adding keys does not disturb the rest.
+                map.remove(&key);
+                if key.starts_with(b"key:") {
+                    map.insert(Bytes::from(format!("new:{}", seen.len())), ());
+                }
+            }
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(seen.iter().filter(|key| key.starts_with(b"key:")).count(), 100);
+    }
+}
@@ -0,0 +1,436 @@
+use bytes::Bytes;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+use std::collections::HashSet;
+use std::time::Instant;
+
+use crate::error::BifrostError;
+use crate::resp::RespType;
+use super::db::{Db, State};
+use super::notify::EventClasses;
+use super::value::Value;
+
+/// Sets of integers are kept sorted in a vector until they grow past this many members.
+const MAX_INTSET_ENTRIES: usize = 512;
+
+/// The members of a set. Sets holding only integers use a compact sorted vector, like
+/// the Redis intset encoding, and are converted to a hash set once they outgrow it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetValue {
+    Ints(Vec<i64>),
+    Members(HashSet<Bytes>),
+}
+
+impl Default for SetValue {
+    fn default() -> Self {
+        SetValue::Ints(Vec::new())
+    }
+}
+
+// Only the canonical decimal form of an integer is stored as one, so that members
+// round-trip byte for byte.
+fn as_int(member: &[u8]) -> Option<i64> {
+    let value = std::str::from_utf8(member).ok()?.parse::<i64>().ok()?;
+    (value.to_string().as_bytes() == member).then_some(value)
+}
+
+impl SetValue {
+    /// The name reported by OBJECT ENCODING.
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            SetValue::Ints(_) => "intset",
+            SetValue::Members(_) => "hashtable",
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            SetValue::Ints(ints) => ints.len(),
+            SetValue::Members(members) => members.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, member: &[u8]) -> bool {
+        match self {
+            SetValue::Ints(ints) => as_int(member).is_some_and(|value| ints.binary_search(&value).is_ok()),
+            SetValue::Members(members) => members.contains(member),
+        }
+    }
+
+    /// Adds `member`, returning whether it was not already present.
+    pub fn insert(&mut self, member: Bytes) -> bool {
+        if let SetValue::Ints(ints) = self {
+            match as_int(&member).map(|value| (value, ints.binary_search(&value))) {
+                Some((_, Ok(_))) => return false,
+                Some((value, Err(position))) if ints.len() < MAX_INTSET_ENTRIES => {
+                    ints.insert(position, value);
+                    return true;
+                }
+                _ => self.convert(),
+            }
+        }
+        match self {
+            SetValue::Members(members) => members.insert(member),
+            SetValue::Ints(_) => unreachable!("integer sets are converted above"),
+        }
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self {
+            SetValue::Ints(ints) => match as_int(member).map(|value| ints.binary_search(&value)) {
+                Some(Ok(position)) => {
+                    ints.remove(position);
+                    true
+                }
+                _ => false,
+            },
+            SetValue::Members(members) => members.remove(member),
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = Bytes> + '_> {
+        match self {
+            SetValue::Ints(ints) => Box::new(ints.iter().map(|value| Bytes::from(value.to_string()))),
+            SetValue::Members(members) => Box::new(members.iter().cloned()),
+        }
+    }
+
+    fn random(&self) -> Option<Bytes> {
+        let mut rng = rand::thread_rng();
+        match self {
+            SetValue::Ints(ints) if !ints.is_empty() => {
+                Some(Bytes::from(ints[rng.gen_range(0..ints.len())].to_string()))
+            }
+            SetValue::Ints(_) => None,
+            SetValue::Members(members) => members.iter().choose(&mut rng).cloned(),
+        }
+    }
+
+    fn convert(&mut self) {
+        if let SetValue::Ints(ints) = self {
+            let members = ints.iter().map(|value| Bytes::from(value.to_string())).collect();
+            *self = SetValue::Members(members);
+        }
+    }
+}
+
+impl FromIterator<Bytes> for SetValue {
+    fn from_iter<I: IntoIterator<Item = Bytes>>(members: I) -> Self {
+        let mut set = SetValue::default();
+        for member in members {
+            set.insert(member);
+        }
+        set
+    }
+}
+
+/// How SINTER, SUNION and SDIFF combine their input sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    Intersection,
+    Union,
+    Difference,
+}
+
+fn set_or_create() -> Value {
+    Value::Set(SetValue::default())
+}
+
+fn members_reply(members: impl Iterator<Item = Bytes>) -> RespType {
+    RespType::Set(members.map(RespType::BulkString).collect())
+}
+
+impl State {
+    // Combines the sets stored at `keys`, treating missing keys as empty sets. Every key
+    // is type checked before any work is done.
+    fn combine_sets(
+        &self,
+        keys: &[Bytes],
+        operation: SetOperation,
+        now: Instant,
+    ) -> Result<SetValue, BifrostError> {
+        let sets = keys
+            .iter()
+            .map(|key| self.value(key, now, Value::as_set))
+            .collect::<Result<Vec<_>, _>>()?;
+        let empty = SetValue::default();
+        let sets: Vec<&SetValue> = sets.into_iter().map(|set| set.unwrap_or(&empty)).collect();
+        let Some((first, rest)) = sets.split_first() else {
+            return Ok(empty);
+        };
+
+        Ok(match operation {
+            SetOperation::Intersection => {
+                let smallest = sets.iter().min_by_key(|set| set.len()).unwrap_or(first);
+                smallest.iter().filter(|member| sets.iter().all(|set| set.contains(member))).collect()
+            }
+            SetOperation::Union => sets.iter().flat_map(|set| set.iter()).collect(),
+            SetOperation::Difference => first
+                .iter()
+                .filter(|member| !rest.iter().any(|set| set.contains(member)))
+                .collect(),
+        })
+    }
+}
+
+impl Db {
+    pub fn sadd(&self, key: &[u8], members: &[Bytes]) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let set = data.value_or_insert(key, Instant::now(), Value::as_set_mut, set_or_create)?;
+        let added = members.iter().filter(|member| set.insert((*member).clone())).count();
+        if added > 0 {
+            data.touch(key);
+            data.notify(EventClasses::SET, "sadd", key);
+        }
+        Ok(RespType::Integer(added as i64))
+    }
+
+    pub fn srem(&self, key: &[u8], members: &[Bytes]) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let Some(set) = data.value_mut(key, Instant::now(), Value::as_set_mut)? else {
+            return Ok(RespType::Integer(0));
+        };
+        let removed = members.iter().filter(|member| set.remove(member)).count();
+        if removed > 0 {
+            data.touch(key);
+            data.notify(EventClasses::SET, "srem", key);
+        }
+        data.remove_if_empty(key);
+        Ok(RespType::Integer(removed as i64))
+    }
+
+    pub fn smembers(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        Ok(match data.value(key, Instant::now(), Value::as_set)? {
+            Some(set) => members_reply(set.iter()),
+            None => RespType::Set(vec![]),
+        })
+    }
+
+    pub fn sismember(&self, key: &[u8], member: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let found = data
+            .value(key, Instant::now(), Value::as_set)?
+            .is_some_and(|set| set.contains(member));
+        Ok(RespType::Integer(found as i64))
+    }
+
+    pub fn smismember(&self, key: &[u8], members: &[Bytes]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let set = data.value(key, Instant::now(), Value::as_set)?;
+        Ok(RespType::Array(
+            members
+                .iter()
+                .map(|member| RespType::Integer(set.is_some_and(|set| set.contains(member)) as i64))
+                .collect(),
+        ))
+    }
+
+    pub fn scard(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let len = data.value(key, Instant::now(), Value::as_set)?.map_or(0, |set| set.len());
+        Ok(RespType::Integer(len as i64))
+    }
+
+    /// SPOP. Without a count a single random member or null is returned, with a count a
+    /// set of up to `count` distinct members.
+    pub fn spop(&self, key: &[u8], count: Option<usize>) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let Some(set) = data.value_mut(key, Instant::now(), Value::as_set_mut)? else {
+            return Ok(count.map_or(RespType::Null, |_| RespType::Set(vec![])));
+        };
+        let len = set.len();
+
+        let reply = match count {
+            None => {
+                let member = set.random();
+                if let Some(member) = &member {
+                    set.remove(member);
+                }
+                member.map(RespType::BulkString).unwrap_or(RespType::Null)
+            }
+            Some(count) if count >= set.len() => {
+                members_reply(std::mem::take(set).iter())
+            }
+            Some(count) => {
+                let popped = set.iter().choose_multiple(&mut rand::thread_rng(), count);
+                for member in &popped {
+                    set.remove(member);
+                }
+                members_reply(popped.into_iter())
+            }
+        };
+        if set.len() != len {
+            data.touch(key);
+            data.notify(EventClasses::SET, "spop", key);
+        }
+        data.remove_if_empty(key);
+        Ok(reply)
+    }
+
+    /// SRANDMEMBER. A positive count returns up to that many distinct members, a negative
+    /// one exactly that many members, possibly repeated.
+    pub fn srandmember(&self, key: &[u8], count: Option<i64>) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let set = data.value(key, Instant::now(), Value::as_set)?;
+        let Some(count) = count else {
+            return Ok(set.and_then(SetValue::random).map(RespType::BulkString).unwrap_or(RespType::Null));
+        };
+        let Some(set) = set.filter(|set| !set.is_empty()) else {
+            return Ok(RespType::Array(vec![]));
+        };
+
+        let mut rng = rand::thread_rng();
+        let members: Vec<Bytes> = if count >= 0 {
+            set.iter().choose_multiple(&mut rng, count as usize)
+        } else {
+            let all: Vec<Bytes> = set.iter().collect();
+            (0..count.unsigned_abs()).map(|_| all[rng.gen_range(0..all.len())].clone()).collect()
+        };
+        Ok(RespType::Array(members.into_iter().map(RespType::BulkString).collect()))
+    }
+
+    /// SINTER, SUNION and SDIFF.
+    pub fn combine_sets(&self, keys: &[Bytes], operation: SetOperation) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let result = data.combine_sets(keys, operation, Instant::now())?;
+        Ok(members_reply(result.iter()))
+    }
+
+    /// SINTERSTORE, SUNIONSTORE and SDIFFSTORE. The destination is overwritten, or
+    /// deleted if the result is empty, and the size of the result is returned.
+    pub fn combine_sets_into(
+        &self,
+        destination: Bytes,
+        keys: &[Bytes],
+        operation: SetOperation,
+    ) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let result = data.combine_sets(keys, operation, Instant::now())?;
+        let len = result.len();
+        if result.is_empty() {
+            if data.remove(&destination).is_some() {
+                data.notify(EventClasses::GENERIC, "del", &destination);
+            }
+        } else {
+            let event = match operation {
+                SetOperation::Union => "sunionstore",
+                SetOperation::Intersection => "sinterstore",
+                SetOperation::Difference => "sdiffstore",
+            };
+            data.insert(destination.clone(), Value::Set(result), None);
+            data.notify(EventClasses::SET, event, &destination);
+        }
+        Ok(RespType::Integer(len as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk(value: &str) -> RespType {
+        RespType::BulkString(Bytes::copy_from_slice(value.as_bytes()))
+    }
+
+    fn items(values: &[&str]) -> Vec<Bytes> {
+        values.iter().map(|value| Bytes::copy_from_slice(value.as_bytes())).collect()
+    }
+
+    fn sorted(reply: RespType) -> Vec<RespType> {
+        let (RespType::Set(mut members) | RespType::Array(mut members)) = reply else {
+            panic!("Expected a set, got {:?}", reply);
+        };
+        members.sort_by_key(|member| format!("{:?}", member));
+        members
+    }
+
+    #[test]
+    fn test_intset_encoding() {
+        let mut set: SetValue = items(&["3", "1", "2", "1"]).into_iter().collect();
+        assert_eq!(set, SetValue::Ints(vec![1, 2, 3]));
+        assert!(set.contains(b"2"));
+        assert!(!set.contains(b"02"));
+
+        // Non-canonical integers are stored as strings.
+        assert!(set.insert(Bytes::from("02")));
+        assert_eq!(set.encoding(), "hashtable");
+        assert!(set.contains(b"2") && set.contains(b"02"));
+
+        let large: SetValue = (0..=MAX_INTSET_ENTRIES).map(|i| Bytes::from(i.to_string())).collect();
+        assert_eq!(large.encoding(), "hashtable");
+        assert_eq!(large.len(), MAX_INTSET_ENTRIES + 1);
+    }
+
+    #[test]
+    fn test_add_remove() {
+        let db = Db::new();
+        assert_eq!(db.sadd(b"tags", &items(&["a", "b", "a"])).unwrap(), RespType::Integer(2));
+        assert_eq!(db.sadd(b"tags", &items(&["b", "c"])).unwrap(), RespType::Integer(1));
+        assert_eq!(db.scard(b"tags").unwrap(), RespType::Integer(3));
+        assert_eq!(sorted(db.smembers(b"tags").unwrap()), vec![bulk("a"), bulk("b"), bulk("c")]);
+        assert_eq!(db.sismember(b"tags", b"a").unwrap(), RespType::Integer(1));
+        assert_eq!(
+            db.smismember(b"tags", &items(&["a", "z"])).unwrap(),
+            RespType::Array(vec![RespType::Integer(1), RespType::Integer(0)])
+        );
+        assert_eq!(db.srem(b"tags", &items(&["a", "z"])).unwrap(), RespType::Integer(1));
+        assert_eq!(db.srem(b"tags", &items(&["b", "c"])).unwrap(), RespType::Integer(2));
+        assert_eq!(db.exists(&[Bytes::from("tags")]), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_random() {
+        let db = Db::new();
+        db.sadd(b"s", &items(&["1", "2", "3"])).unwrap();
+        assert_eq!(sorted(db.srandmember(b"s", Some(10)).unwrap()).len(), 3);
+        assert_eq!(sorted(db.srandmember(b"s", Some(2)).unwrap()).len(), 2);
+        assert_eq!(sorted(db.srandmember(b"s", Some(-5)).unwrap()).len(), 5);
+        assert_eq!(db.srandmember(b"missing", None).unwrap(), RespType::Null);
+
+        assert_eq!(sorted(db.spop(b"s", Some(2)).unwrap()).len(), 2);
+        assert!(matches!(db.spop(b"s", None).unwrap(), RespType::BulkString(_)));
+        assert_eq!(db.exists(&[Bytes::from("s")]), RespType::Integer(0));
+        assert_eq!(db.spop(b"s", None).unwrap(), RespType::Null);
+    }
+
+    #[test]
+    fn test_algebra() {
+        let db = Db::new();
+        db.sadd(b"a", &items(&["1", "2", "3", "x"])).unwrap();
+        db.sadd(b"b", &items(&["2", "3", "4"])).unwrap();
+        let keys = items(&["a", "b"]);
+
+        let inter = db.combine_sets(&keys, SetOperation::Intersection).unwrap();
+        assert_eq!(sorted(inter), vec![bulk("2"), bulk("3")]);
+        let union = db.combine_sets(&keys, SetOperation::Union).unwrap();
+        assert_eq!(sorted(union).len(), 5);
+        let diff = db.combine_sets(&keys, SetOperation::Difference).unwrap();
+        assert_eq!(sorted(diff), vec![bulk("1"), bulk("x")]);
+        let missing = db.combine_sets(&items(&["a", "missing"]), SetOperation::Intersection).unwrap();
+        assert_eq!(sorted(missing), vec![]);
+
+        let stored = db.combine_sets_into(Bytes::from("dest"), &keys, SetOperation::Intersection);
+        assert_eq!(stored.unwrap(), RespType::Integer(2));
+        assert_eq!(db.scard(b"dest").unwrap(), RespType::Integer(2));
+        let stored = db.combine_sets_into(Bytes::from("dest"), &items(&["missing"]), SetOperation::Union);
+        assert_eq!(stored.unwrap(), RespType::Integer(0));
+        assert_eq!(db.exists(&[Bytes::from("dest")]), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let db = Db::new();
+        db.set(Bytes::from("string"), Bytes::from("value"));
+        db.sadd(b"set", &items(&["a"])).unwrap();
+        assert!(db.sadd(b"string", &items(&["a"])).is_err());
+        assert!(db.combine_sets(&items(&["set", "string"]), SetOperation::Union).is_err());
+        assert!(db.combine_sets_into(Bytes::from("set"), &items(&["string"]), SetOperation::Union).is_err());
+        assert_eq!(db.scard(b"set").unwrap(), RespType::Integer(1));
+        assert!(db.llen(b"set").is_err());
+    }
+}
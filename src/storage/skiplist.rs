@@ -0,0 +1,396 @@
+use bytes::Bytes;
+use rand::Rng;
+use std::cmp::Ordering;
+
+const MAX_LEVEL: usize = 32;
+const LEVEL_PROBABILITY: f64 = 0.25;
+// The header node, which holds no element, always lives in the first slot.
+const HEAD: usize = 0;
+
+#[derive(Debug, Clone, Copy)]
+struct Level {
+    forward: Option<usize>,
+    // The number of elements the forward link skips over, used to compute ranks.
+    span: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: Bytes,
+    score: f64,
+    backward: Option<usize>,
+    levels: Vec<Level>,
+}
+
+/// A skiplist of (score, member) pairs ordered by score and then member, with spans on
+/// every link like the Redis zskiplist so ranks are found in O(log n). Nodes live in an
+/// arena and refer to each other by index.
+#[derive(Debug, Clone)]
+pub struct SkipList {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    tail: Option<usize>,
+    level: usize,
+    len: usize,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        let head = Node {
+            member: Bytes::new(),
+            score: 0.0,
+            backward: None,
+            levels: vec![Level { forward: None, span: 0 }; MAX_LEVEL],
+        };
+        SkipList {
+            nodes: vec![head],
+            free: Vec::new(),
+            tail: None,
+            level: 1,
+            len: 0,
+        }
+    }
+}
+
+fn compare(score: f64, member: &[u8], other_score: f64, other_member: &[u8]) -> Ordering {
+    score
+        .partial_cmp(&other_score)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| member.cmp(other_member))
+}
+
+fn random_level() -> usize {
+    let mut rng = rand::thread_rng();
+    let mut level = 1;
+    while level < MAX_LEVEL && rng.gen_bool(LEVEL_PROBABILITY) {
+        level += 1;
+    }
+    level
+}
+
+impl SkipList {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn forward(&self, node: usize, level: usize) -> Option<usize> {
+        self.nodes[node].levels[level].forward
+    }
+
+    fn precedes(&self, node: usize, score: f64, member: &[u8]) -> bool {
+        let node = &self.nodes[node];
+        compare(node.score, &node.member, score, member) == Ordering::Less
+    }
+
+    // Finds, on every level, the last node ordered before (score, member).
+    fn predecessors(&self, score: f64, member: &[u8]) -> [usize; MAX_LEVEL] {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.forward(node, level).filter(|next| self.precedes(*next, score, member)) {
+                node = next;
+            }
+            update[level] = node;
+        }
+        update
+    }
+
+    /// Inserts an element. The caller guarantees `member` is not already present.
+    pub fn insert(&mut self, score: f64, member: Bytes) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            rank[level] = if level == self.level - 1 { 0 } else { rank[level + 1] };
+            while let Some(next) = self.forward(node, level).filter(|next| self.precedes(*next, score, &member)) {
+                rank[level] += self.nodes[node].levels[level].span;
+                node = next;
+            }
+            update[level] = node;
+        }
+
+        let level = random_level();
+        if level > self.level {
+            for new_level in self.level..level {
+                self.nodes[HEAD].levels[new_level].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let node = Node {
+            member,
+            score,
+            backward: (update[0] != HEAD).then_some(update[0]),
+            levels: vec![Level { forward: None, span: 0 }; level],
+        };
+        let node = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = node;
+                slot
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+
+        for (i, &previous) in update.iter().enumerate().take(level) {
+            let skipped = rank[0] - rank[i];
+            let previous_level = self.nodes[previous].levels[i];
+            self.nodes[node].levels[i] = Level {
+                forward: previous_level.forward,
+                span: previous_level.span - skipped,
+            };
+            self.nodes[previous].levels[i] = Level {
+                forward: Some(node),
+                span: skipped + 1,
+            };
+        }
+        for (i, &previous) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[previous].levels[i].span += 1;
+        }
+
+        match self.forward(node, 0) {
+            Some(next) => self.nodes[next].backward = Some(node),
+            None => self.tail = Some(node),
+        }
+        self.len += 1;
+    }
+
+    /// Removes an element, returning whether it was present.
+    pub fn remove(&mut self, score: f64, member: &[u8]) -> bool {
+        let update = self.predecessors(score, member);
+        let Some(node) = self.forward(update[0], 0) else {
+            return false;
+        };
+        if compare(self.nodes[node].score, &self.nodes[node].member, score, member) != Ordering::Equal {
+            return false;
+        }
+
+        for (i, &previous) in update.iter().enumerate().take(self.level) {
+            if self.forward(previous, i) == Some(node) {
+                let removed = self.nodes[node].levels[i];
+                let previous = &mut self.nodes[previous].levels[i];
+                previous.span += removed.span;
+                previous.span -= 1;
+                previous.forward = removed.forward;
+            } else {
+                self.nodes[previous].levels[i].span -= 1;
+            }
+        }
+        let backward = self.nodes[node].backward;
+        match self.forward(node, 0) {
+            Some(next) => self.nodes[next].backward = backward,
+            None => self.tail = backward,
+        }
+        while self.level > 1 && self.forward(HEAD, self.level - 1).is_none() {
+            self.level -= 1;
+        }
+
+        self.nodes[node].member = Bytes::new();
+        self.nodes[node].levels = Vec::new();
+        self.free.push(node);
+        self.len -= 1;
+        true
+    }
+
+    /// The 0-based rank of an element.
+    pub fn rank(&self, score: f64, member: &[u8]) -> Option<usize> {
+        let mut rank = 0;
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.forward(node, level).filter(|next| {
+                let next = &self.nodes[*next];
+                compare(next.score, &next.member, score, member) != Ordering::Greater
+            }) {
+                rank += self.nodes[node].levels[level].span;
+                node = next;
+            }
+            if node != HEAD && self.nodes[node].score == score && self.nodes[node].member == member {
+                return Some(rank - 1);
+            }
+        }
+        None
+    }
+
+    fn node_at(&self, rank: usize) -> Option<usize> {
+        let target = rank + 1;
+        let mut traversed = 0;
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.forward(node, level) {
+                let span = self.nodes[node].levels[level].span;
+                if traversed + span > target {
+                    break;
+                }
+                traversed += span;
+                node = next;
+            }
+            if traversed == target {
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    /// Iterates from the element at the 0-based `rank`, towards the tail or, when
+    /// `reverse` is set, towards the head.
+    pub fn iter_from_rank(&self, rank: usize, reverse: bool) -> Iter<'_> {
+        Iter {
+            list: self,
+            next: self.node_at(rank),
+            reverse,
+        }
+    }
+
+    /// Iterates towards the tail from the first element for which `reached` holds.
+    /// `reached` must be false for a prefix of the list and true for the rest.
+    pub fn iter_from(&self, reached: impl Fn(f64, &[u8]) -> bool) -> Iter<'_> {
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.forward(node, level).filter(|next| {
+                let next = &self.nodes[*next];
+                !reached(next.score, &next.member)
+            }) {
+                node = next;
+            }
+        }
+        Iter {
+            list: self,
+            next: self.forward(node, 0),
+            reverse: false,
+        }
+    }
+
+    /// Iterates towards the head from the last element for which `within` holds.
+    /// `within` must be true for a prefix of the list and false for the rest.
+    pub fn iter_back_from(&self, within: impl Fn(f64, &[u8]) -> bool) -> Iter<'_> {
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.forward(node, level).filter(|next| {
+                let next = &self.nodes[*next];
+                within(next.score, &next.member)
+            }) {
+                node = next;
+            }
+        }
+        Iter {
+            list: self,
+            next: (node != HEAD).then_some(node),
+            reverse: true,
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            list: self,
+            next: self.forward(HEAD, 0),
+            reverse: false,
+        }
+    }
+
+    pub fn iter_rev(&self) -> Iter<'_> {
+        Iter {
+            list: self,
+            next: self.tail,
+            reverse: true,
+        }
+    }
+}
+
+pub struct Iter<'a> {
+    list: &'a SkipList,
+    next: Option<usize>,
+    reverse: bool,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a Bytes, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = &self.list.nodes[self.next?];
+        self.next = if self.reverse { node.backward } else { node.levels[0].forward };
+        Some((&node.member, node.score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(iter: Iter<'_>) -> Vec<String> {
+        iter.map(|(member, _)| String::from_utf8(member.to_vec()).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_order_and_rank() {
+        let mut list = SkipList::default();
+        for i in (0..200).rev() {
+            list.insert((i / 2) as f64, Bytes::from(format!("m{:03}", i)));
+        }
+        assert_eq!(list.len(), 200);
+        let all = members(list.iter());
+        let mut sorted = all.clone();
+        sorted.sort();
+        assert_eq!(all, sorted);
+
+        for i in 0..200 {
+            assert_eq!(list.rank((i / 2) as f64, format!("m{:03}", i).as_bytes()), Some(i));
+            assert_eq!(list.iter_from_rank(i, false).next().unwrap().0, &Bytes::from(format!("m{:03}", i)));
+        }
+        assert_eq!(list.rank(1.0, b"m000"), None);
+        assert!(list.iter_from_rank(200, false).next().is_none());
+        assert_eq!(members(list.iter_rev()).first().unwrap(), "m199");
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut list = SkipList::default();
+        for i in 0..100 {
+            list.insert(i as f64, Bytes::from(i.to_string()));
+        }
+        for i in (0..100).step_by(2) {
+            assert!(list.remove(i as f64, i.to_string().as_bytes()));
+        }
+        assert!(!list.remove(0.0, b"0"));
+        assert!(!list.remove(2.0, b"1"));
+        assert_eq!(list.len(), 50);
+        for (rank, i) in (1..100).step_by(2).enumerate() {
+            assert_eq!(list.rank(i as f64, i.to_string().as_bytes()), Some(rank));
+        }
+
+        // Freed slots are reused.
+        list.insert(0.5, Bytes::from("half"));
+        assert_eq!(list.nodes.len(), 101);
+        assert_eq!(list.rank(0.5, b"half"), Some(0));
+        assert_eq!(members(list.iter_rev()).last().unwrap(), "half");
+    }
+
+    #[test]
+    fn test_iter_from() {
+        let mut list = SkipList::default();
+        for i in 0..10 {
+            list.insert(i as f64, Bytes::from(i.to_string()));
+        }
+        assert_eq!(members(list.iter_from(|score, _| score > 6.5)), vec!["7", "8", "9"]);
+        assert_eq!(members(list.iter_back_from(|score, _| score <= 2.0)), vec!["2", "1", "0"]);
+        assert!(list.iter_from(|score, _| score > 9.0).next().is_none());
+        assert!(list.iter_back_from(|score, _| score < 0.0).next().is_none());
+    }
+
+    #[test]
+    fn test_rank_between_scores() {
+        let mut list = SkipList::default();
+        list.insert(1.0, Bytes::from("member"));
+        list.insert(2.0, Bytes::from("other"));
+        // Under 1.5 the member would sort between the two, right after the node that
+        // has its name under another score.
+        assert_eq!(list.rank(1.5, b"member"), None);
+        assert_eq!(list.rank(1.0, b"member"), Some(0));
+        assert_eq!(list.rank(2.0, b"other"), Some(1));
+    }
+}
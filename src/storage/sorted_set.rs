@@ -0,0 +1,628 @@
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Instant;
+
+use crate::error::BifrostError;
+use crate::resp::RespType;
+use super::db::{wrong_type, Db, SetCondition, State};
+use super::list::normalize_range;
+use super::notify::EventClasses;
+use super::set::SetOperation;
+use super::skiplist::SkipList;
+use super::value::Value;
+
+/// The members of a sorted set, indexed both by member and by (score, member) order.
+#[derive(Clone, Default)]
+pub struct SortedSet {
+    scores: HashMap<Bytes, f64>,
+    ordered: SkipList,
+}
+
+impl fmt::Debug for SortedSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.ordered.iter()).finish()
+    }
+}
+
+impl PartialEq for SortedSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.scores == other.scores
+    }
+}
+
+impl SortedSet {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Adds `member` or updates its score, returning whether it was not already present.
+    pub fn insert(&mut self, member: Bytes, score: f64) -> bool {
+        match self.scores.insert(member.clone(), score) {
+            Some(old) => {
+                self.ordered.remove(old, &member);
+                self.ordered.insert(score, member);
+                false
+            }
+            None => {
+                self.ordered.insert(score, member);
+                true
+            }
+        }
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => self.ordered.remove(score, member),
+            None => false,
+        }
+    }
+
+    /// The 0-based position of `member` in ascending order, or descending with `reverse`.
+    pub fn rank(&self, member: &[u8], reverse: bool) -> Option<usize> {
+        let rank = self.ordered.rank(self.score(member)?, member)?;
+        Some(if reverse { self.len() - 1 - rank } else { rank })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, f64)> {
+        self.ordered.iter()
+    }
+
+    /// The members selected by a ZRANGE query, in reply order.
+    pub fn range(&self, query: &RangeQuery) -> Vec<(Bytes, f64)> {
+        let (offset, count) = match query.limit {
+            Some((offset, _)) if offset < 0 => return vec![],
+            Some((offset, count)) => (offset as usize, usize::try_from(count).unwrap_or(usize::MAX)),
+            None => (0, usize::MAX),
+        };
+        let items: Box<dyn Iterator<Item = (&Bytes, f64)>> = match (&query.by, query.reverse) {
+            (RangeBy::Rank(start, stop), reverse) => {
+                let Some(range) = normalize_range(*start, *stop, self.len()) else {
+                    return vec![];
+                };
+                let first = if reverse { self.len() - 1 - range.start } else { range.start };
+                Box::new(self.ordered.iter_from_rank(first, reverse).take(range.len()))
+            }
+            (RangeBy::Score(min, max), false) => Box::new(
+                self.ordered
+                    .iter_from(|score, _| min.admits_from_below(score))
+                    .take_while(|(_, score)| max.admits_from_above(*score)),
+            ),
+            (RangeBy::Score(min, max), true) => Box::new(
+                self.ordered
+                    .iter_back_from(|score, _| max.admits_from_above(score))
+                    .take_while(|(_, score)| min.admits_from_below(*score)),
+            ),
+            (RangeBy::Lex(min, max), false) => Box::new(
+                self.ordered
+                    .iter_from(|_, member| min.admits_from_below(member))
+                    .take_while(|(member, _)| max.admits_from_above(member)),
+            ),
+            (RangeBy::Lex(min, max), true) => Box::new(
+                self.ordered
+                    .iter_back_from(|_, member| max.admits_from_above(member))
+                    .take_while(|(member, _)| min.admits_from_below(member)),
+            ),
+        };
+        items
+            .skip(offset)
+            .take(count)
+            .map(|(member, score)| (member.clone(), score))
+            .collect()
+    }
+
+    fn pop(&mut self, count: usize, max: bool) -> Vec<(Bytes, f64)> {
+        let popped: Vec<(Bytes, f64)> = if max { self.ordered.iter_rev() } else { self.ordered.iter() }
+            .take(count)
+            .map(|(member, score)| (member.clone(), score))
+            .collect();
+        for (member, _) in &popped {
+            self.remove(member);
+        }
+        popped
+    }
+}
+
+/// A score range endpoint, as in `1.5`, `(1.5` or `-inf`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreBound {
+    Inclusive(f64),
+    Exclusive(f64),
+}
+
+impl ScoreBound {
+    fn admits_from_below(&self, score: f64) -> bool {
+        match self {
+            ScoreBound::Inclusive(min) => score >= *min,
+            ScoreBound::Exclusive(min) => score > *min,
+        }
+    }
+
+    fn admits_from_above(&self, score: f64) -> bool {
+        match self {
+            ScoreBound::Inclusive(max) => score <= *max,
+            ScoreBound::Exclusive(max) => score < *max,
+        }
+    }
+}
+
+/// A lexicographical range endpoint, as in `[a`, `(a`, `-` or `+`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(Bytes),
+    Exclusive(Bytes),
+}
+
+impl LexBound {
+    fn admits_from_below(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(min) => member >= &min[..],
+            LexBound::Exclusive(min) => member > &min[..],
+        }
+    }
+
+    fn admits_from_above(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(max) => member <= &max[..],
+            LexBound::Exclusive(max) => member < &max[..],
+        }
+    }
+}
+
+/// What a ZRANGE selects: ranks, or minimum and maximum scores or members.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeBy {
+    Rank(i64, i64),
+    Score(ScoreBound, ScoreBound),
+    Lex(LexBound, LexBound),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeQuery {
+    pub by: RangeBy,
+    pub reverse: bool,
+    pub limit: Option<(i64, i64)>,
+    pub with_scores: bool,
+}
+
+/// The GT and LT options of ZADD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScoreComparison {
+    #[default]
+    Any,
+    Greater,
+    Less,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ZaddOptions {
+    pub condition: SetCondition,
+    pub comparison: ScoreComparison,
+    pub changed: bool,
+    pub incr: bool,
+}
+
+/// The AGGREGATE option of ZUNIONSTORE and ZINTERSTORE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply(&self, current: f64, score: f64) -> f64 {
+        match self {
+            // inf + -inf is defined as 0, as in Redis.
+            Aggregate::Sum => zero_if_nan(current + score),
+            Aggregate::Min => current.min(score),
+            Aggregate::Max => current.max(score),
+        }
+    }
+}
+
+fn zero_if_nan(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+fn sorted_set_or_create() -> Value {
+    Value::SortedSet(SortedSet::default())
+}
+
+fn scored_reply(items: Vec<(Bytes, f64)>, with_scores: bool) -> RespType {
+    let mut reply = Vec::with_capacity(items.len() * if with_scores { 2 } else { 1 });
+    for (member, score) in items {
+        reply.push(RespType::BulkString(member));
+        if with_scores {
+            reply.push(RespType::Double(score));
+        }
+    }
+    RespType::Array(reply)
+}
+
+impl State {
+    // Reads the input of ZUNIONSTORE and ZINTERSTORE, where plain sets count as sorted
+    // sets whose members all score 1.
+    fn weighted_members(&self, key: &[u8], weight: f64, now: Instant) -> Result<HashMap<Bytes, f64>, BifrostError> {
+        let weighted = |score: f64| zero_if_nan(score * weight);
+        Ok(match self.get(key, now).map(|entry| &entry.value) {
+            None => HashMap::new(),
+            Some(Value::SortedSet(zset)) => zset
+                .iter()
+                .map(|(member, score)| (member.clone(), weighted(score)))
+                .collect(),
+            Some(Value::Set(set)) => set.iter().map(|member| (member, weighted(1.0))).collect(),
+            Some(_) => return Err(wrong_type()),
+        })
+    }
+}
+
+impl Db {
+    /// ZADD, and ZINCRBY through the `incr` option. Replies with the number of members
+    /// added (or changed, with CH), or with INCR the new score or null when the update
+    /// was skipped by a condition.
+    pub fn zadd(&self, key: &[u8], pairs: &[(f64, Bytes)], options: ZaddOptions) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        // XX never adds members, so a missing key is not created only to be deleted again.
+        if options.condition == SetCondition::IfExists && data.value(key, now, Value::as_sorted_set)?.is_none() {
+            return Ok(if options.incr { RespType::Null } else { RespType::Integer(0) });
+        }
+        let zset = data.value_or_insert(key, now, Value::as_sorted_set_mut, sorted_set_or_create)?;
+
+        let mut added = 0;
+        let mut changed = 0;
+        let mut result = None;
+        for (score, member) in pairs {
+            let current = zset.score(member);
+            let score = match (current, options.incr) {
+                (Some(current), true) => current + score,
+                _ => *score,
+            };
+            if score.is_nan() {
+                data.remove_if_empty(key);
+                return Err(BifrostError::StorageError(
+                    "ERR resulting score is not a number (NaN)".to_string(),
+                ));
+            }
+            let allowed = match (current, options.condition) {
+                (None, SetCondition::IfExists) | (Some(_), SetCondition::IfNotExists) => false,
+                (Some(current), _) => match options.comparison {
+                    ScoreComparison::Any => true,
+                    ScoreComparison::Greater => score > current,
+                    ScoreComparison::Less => score < current,
+                },
+                (None, _) => true,
+            };
+            if !allowed {
+                continue;
+            }
+            if current.is_none() {
+                added += 1;
+            } else if current != Some(score) {
+                changed += 1;
+            }
+            zset.insert(member.clone(), score);
+            result = Some(score);
+        }
+        if added + changed > 0 {
+            data.touch(key);
+            data.notify(EventClasses::ZSET, if options.incr { "zincr" } else { "zadd" }, key);
+        }
+        data.remove_if_empty(key);
+
+        Ok(if options.incr {
+            result.map(RespType::Double).unwrap_or(RespType::Null)
+        } else if options.changed {
+            RespType::Integer(added + changed)
+        } else {
+            RespType::Integer(added)
+        })
+    }
+
+    pub fn zrem(&self, key: &[u8], members: &[Bytes]) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let Some(zset) = data.value_mut(key, Instant::now(), Value::as_sorted_set_mut)? else {
+            return Ok(RespType::Integer(0));
+        };
+        let removed = members.iter().filter(|member| zset.remove(member)).count();
+        if removed > 0 {
+            data.touch(key);
+            data.notify(EventClasses::ZSET, "zrem", key);
+        }
+        data.remove_if_empty(key);
+        Ok(RespType::Integer(removed as i64))
+    }
+
+    pub fn zcard(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let len = data.value(key, Instant::now(), Value::as_sorted_set)?.map_or(0, |zset| zset.len());
+        Ok(RespType::Integer(len as i64))
+    }
+
+    pub fn zscore(&self, key: &[u8], member: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let score = data
+            .value(key, Instant::now(), Value::as_sorted_set)?
+            .and_then(|zset| zset.score(member));
+        Ok(score.map(RespType::Double).unwrap_or(RespType::Null))
+    }
+
+    /// ZRANK. With `with_score` the reply is the rank followed by the member's score.
+    pub fn zrank(&self, key: &[u8], member: &[u8], with_score: bool) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let Some(zset) = data.value(key, Instant::now(), Value::as_sorted_set)? else {
+            return Ok(RespType::Null);
+        };
+        let (Some(rank), Some(score)) = (zset.rank(member, false), zset.score(member)) else {
+            return Ok(RespType::Null);
+        };
+        Ok(if with_score {
+            RespType::Array(vec![RespType::Integer(rank as i64), RespType::Double(score)])
+        } else {
+            RespType::Integer(rank as i64)
+        })
+    }
+
+    pub fn zrange(&self, key: &[u8], query: &RangeQuery) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let items = data
+            .value(key, Instant::now(), Value::as_sorted_set)?
+            .map(|zset| zset.range(query))
+            .unwrap_or_default();
+        Ok(scored_reply(items, query.with_scores))
+    }
+
+    /// ZPOPMIN and ZPOPMAX. Replies with a flat array of members and their scores.
+    pub fn zpop(&self, key: &[u8], count: Option<usize>, max: bool) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let Some(zset) = data.value_mut(key, Instant::now(), Value::as_sorted_set_mut)? else {
+            return Ok(RespType::Array(vec![]));
+        };
+        let popped = zset.pop(count.unwrap_or(1), max);
+        if !popped.is_empty() {
+            data.touch(key);
+            data.notify(EventClasses::ZSET, if max { "zpopmax" } else { "zpopmin" }, key);
+        }
+        data.remove_if_empty(key);
+        Ok(scored_reply(popped, true))
+    }
+
+    /// ZUNIONSTORE and ZINTERSTORE. The destination is overwritten, or deleted if the
+    /// result is empty, and the size of the result is returned.
+    pub fn zcombine_into(
+        &self,
+        destination: Bytes,
+        keys: &[Bytes],
+        weights: &[f64],
+        aggregate: Aggregate,
+        operation: SetOperation,
+    ) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let now = Instant::now();
+        let inputs = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| data.weighted_members(key, weights.get(i).copied().unwrap_or(1.0), now))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut result = SortedSet::default();
+        if let Some((first, rest)) = inputs.split_first() {
+            match operation {
+                SetOperation::Union => {
+                    let mut scores: HashMap<Bytes, f64> = HashMap::new();
+                    for (member, score) in inputs.iter().flatten() {
+                        scores
+                            .entry(member.clone())
+                            .and_modify(|current| *current = aggregate.apply(*current, *score))
+                            .or_insert(*score);
+                    }
+                    for (member, score) in scores {
+                        result.insert(member, score);
+                    }
+                }
+                SetOperation::Intersection => {
+                    for (member, score) in first {
+                        let score = rest.iter().try_fold(*score, |current, input| {
+                            input.get(member).map(|score| aggregate.apply(current, *score))
+                        });
+                        if let Some(score) = score {
+                            result.insert(member.clone(), score);
+                        }
+                    }
+                }
+                SetOperation::Difference => {
+                    for (member, score) in first {
+                        if !rest.iter().any(|input| input.contains_key(member)) {
+                            result.insert(member.clone(), *score);
+                        }
+                    }
+                }
+            }
+        }
+
+        let len = result.len();
+        if result.is_empty() {
+            if data.remove(&destination).is_some() {
+                data.notify(EventClasses::GENERIC, "del", &destination);
+            }
+        } else {
+            let event = match operation {
+                SetOperation::Union => "zunionstore",
+                SetOperation::Intersection => "zinterstore",
+                SetOperation::Difference => "zdiffstore",
+            };
+            data.insert(destination.clone(), Value::SortedSet(result), None);
+            data.notify(EventClasses::ZSET, event, &destination);
+        }
+        Ok(RespType::Integer(len as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk(value: &str) -> RespType {
+        RespType::BulkString(Bytes::copy_from_slice(value.as_bytes()))
+    }
+
+    fn pairs(values: &[(f64, &str)]) -> Vec<(f64, Bytes)> {
+        values
+            .iter()
+            .map(|(score, member)| (*score, Bytes::copy_from_slice(member.as_bytes())))
+            .collect()
+    }
+
+    fn members(values: &[&str]) -> RespType {
+        RespType::Array(values.iter().map(|value| bulk(value)).collect())
+    }
+
+    fn query(by: RangeBy) -> RangeQuery {
+        RangeQuery {
+            by,
+            reverse: false,
+            limit: None,
+            with_scores: false,
+        }
+    }
+
+    fn leaderboard() -> Db {
+        let db = Db::new();
+        db.zadd(
+            b"board",
+            &pairs(&[(10.0, "alice"), (20.0, "bob"), (20.0, "carol"), (30.0, "dave")]),
+            ZaddOptions::default(),
+        )
+        .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_zadd_options() {
+        let db = leaderboard();
+        let add = |values: &[(f64, &str)], options| db.zadd(b"board", &pairs(values), options).unwrap();
+
+        let nx = ZaddOptions { condition: SetCondition::IfNotExists, ..Default::default() };
+        assert_eq!(add(&[(1.0, "alice"), (5.0, "erin")], nx), RespType::Integer(1));
+        assert_eq!(db.zscore(b"board", b"alice").unwrap(), RespType::Double(10.0));
+
+        let xx_ch = ZaddOptions { condition: SetCondition::IfExists, changed: true, ..Default::default() };
+        assert_eq!(add(&[(11.0, "alice"), (5.0, "frank")], xx_ch), RespType::Integer(1));
+        assert_eq!(db.zscore(b"board", b"frank").unwrap(), RespType::Null);
+
+        let gt = ZaddOptions { comparison: ScoreComparison::Greater, changed: true, ..Default::default() };
+        assert_eq!(add(&[(1.0, "alice"), (25.0, "bob")], gt), RespType::Integer(1));
+        assert_eq!(db.zscore(b"board", b"alice").unwrap(), RespType::Double(11.0));
+
+        let incr = ZaddOptions { incr: true, ..Default::default() };
+        assert_eq!(add(&[(1.5, "alice")], incr), RespType::Double(12.5));
+        let lt_incr = ZaddOptions { comparison: ScoreComparison::Less, incr: true, ..Default::default() };
+        assert_eq!(add(&[(1.0, "alice")], lt_incr), RespType::Null);
+
+        db.zadd(b"inf", &pairs(&[(f64::INFINITY, "a")]), ZaddOptions::default()).unwrap();
+        assert!(db.zadd(b"inf", &pairs(&[(f64::NEG_INFINITY, "a")]), incr).is_err());
+
+        let xx = ZaddOptions { condition: SetCondition::IfExists, ..Default::default() };
+        db.zadd(b"missing", &pairs(&[(1.0, "a")]), xx).unwrap();
+        assert_eq!(db.exists(&[Bytes::from("missing")]), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_zrange() {
+        let db = leaderboard();
+        assert_eq!(db.zrange(b"board", &query(RangeBy::Rank(0, -1))).unwrap(), members(&["alice", "bob", "carol", "dave"]));
+        assert_eq!(db.zrange(b"board", &query(RangeBy::Rank(1, 2))).unwrap(), members(&["bob", "carol"]));
+
+        let reverse = RangeQuery { reverse: true, ..query(RangeBy::Rank(0, 1)) };
+        assert_eq!(db.zrange(b"board", &reverse).unwrap(), members(&["dave", "carol"]));
+
+        let by_score = query(RangeBy::Score(ScoreBound::Exclusive(10.0), ScoreBound::Inclusive(30.0)));
+        assert_eq!(db.zrange(b"board", &by_score).unwrap(), members(&["bob", "carol", "dave"]));
+        let limited = RangeQuery { limit: Some((1, 1)), with_scores: true, ..by_score.clone() };
+        assert_eq!(db.zrange(b"board", &limited).unwrap(), RespType::Array(vec![bulk("carol"), RespType::Double(20.0)]));
+        let reverse = RangeQuery { reverse: true, ..by_score };
+        assert_eq!(db.zrange(b"board", &reverse).unwrap(), members(&["dave", "carol", "bob"]));
+
+        let lex = Db::new();
+        lex.zadd(b"names", &pairs(&[(0.0, "a"), (0.0, "b"), (0.0, "c"), (0.0, "d")]), ZaddOptions::default()).unwrap();
+        let by_lex = query(RangeBy::Lex(LexBound::Exclusive(Bytes::from("a")), LexBound::Inclusive(Bytes::from("c"))));
+        assert_eq!(lex.zrange(b"names", &by_lex).unwrap(), members(&["b", "c"]));
+        let reverse = RangeQuery { reverse: true, ..query(RangeBy::Lex(LexBound::Min, LexBound::Max)) };
+        assert_eq!(lex.zrange(b"names", &reverse).unwrap(), members(&["d", "c", "b", "a"]));
+    }
+
+    #[test]
+    fn test_rank_rem_pop() {
+        let db = leaderboard();
+        assert_eq!(db.zrank(b"board", b"carol", false).unwrap(), RespType::Integer(2));
+        assert_eq!(
+            db.zrank(b"board", b"bob", true).unwrap(),
+            RespType::Array(vec![RespType::Integer(1), RespType::Double(20.0)])
+        );
+        assert_eq!(db.zrank(b"board", b"nobody", false).unwrap(), RespType::Null);
+
+        assert_eq!(db.zrem(b"board", &[Bytes::from("bob"), Bytes::from("nobody")]).unwrap(), RespType::Integer(1));
+        assert_eq!(db.zrank(b"board", b"dave", false).unwrap(), RespType::Integer(2));
+
+        assert_eq!(db.zpop(b"board", None, true).unwrap(), RespType::Array(vec![bulk("dave"), RespType::Double(30.0)]));
+        assert_eq!(
+            db.zpop(b"board", Some(5), false).unwrap(),
+            RespType::Array(vec![bulk("alice"), RespType::Double(10.0), bulk("carol"), RespType::Double(20.0)])
+        );
+        assert_eq!(db.exists(&[Bytes::from("board")]), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_combine() {
+        let db = leaderboard();
+        db.zadd(b"bonus", &pairs(&[(1.0, "alice"), (2.0, "erin")]), ZaddOptions::default()).unwrap();
+        db.sadd(b"team", &[Bytes::from("alice"), Bytes::from("dave")]).unwrap();
+        let keys = [Bytes::from("board"), Bytes::from("bonus")];
+
+        let union = db.zcombine_into(Bytes::from("out"), &keys, &[1.0, 10.0], Aggregate::Sum, SetOperation::Union);
+        assert_eq!(union.unwrap(), RespType::Integer(5));
+        assert_eq!(db.zscore(b"out", b"alice").unwrap(), RespType::Double(20.0));
+        assert_eq!(db.zscore(b"out", b"erin").unwrap(), RespType::Double(20.0));
+
+        let keys = [Bytes::from("board"), Bytes::from("team")];
+        let inter = db.zcombine_into(Bytes::from("out"), &keys, &[], Aggregate::Max, SetOperation::Intersection);
+        assert_eq!(inter.unwrap(), RespType::Integer(2));
+        assert_eq!(db.zrange(b"out", &query(RangeBy::Rank(0, -1))).unwrap(), members(&["alice", "dave"]));
+
+        let keys = [Bytes::from("board"), Bytes::from("missing")];
+        let inter = db.zcombine_into(Bytes::from("out"), &keys, &[], Aggregate::Sum, SetOperation::Intersection);
+        assert_eq!(inter.unwrap(), RespType::Integer(0));
+        assert_eq!(db.exists(&[Bytes::from("out")]), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let db = leaderboard();
+        db.set(Bytes::from("string"), Bytes::from("value"));
+        assert!(db.zadd(b"string", &pairs(&[(1.0, "a")]), ZaddOptions::default()).is_err());
+        assert!(db.zrange(b"string", &query(RangeBy::Rank(0, -1))).is_err());
+        let keys = [Bytes::from("board"), Bytes::from("string")];
+        assert!(db.zcombine_into(Bytes::from("out"), &keys, &[], Aggregate::Sum, SetOperation::Union).is_err());
+        assert!(db.scard(b"board").is_err());
+    }
+}
@@ -0,0 +1,506 @@
+use bytes::Bytes;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Bound;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::BifrostError;
+use crate::resp::RespType;
+use super::blocking::{shared_reply, Blocking, StreamWaiter};
+use super::consumer_group::ConsumerGroup;
+use super::db::{Db, State};
+use super::notify::EventClasses;
+use super::value::Value;
+
+/// A stream entry ID: milliseconds since the epoch and a sequence number within them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId { ms: u64::MAX, seq: u64::MAX };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        StreamId { ms, seq }
+    }
+
+    /// The smallest ID greater than this one.
+    pub fn next(self) -> Option<StreamId> {
+        match (self.ms, self.seq) {
+            (u64::MAX, u64::MAX) => None,
+            (ms, u64::MAX) => Some(StreamId::new(ms + 1, 0)),
+            (ms, seq) => Some(StreamId::new(ms, seq + 1)),
+        }
+    }
+
+    /// The greatest ID smaller than this one.
+    pub fn prev(self) -> Option<StreamId> {
+        match (self.ms, self.seq) {
+            (0, 0) => None,
+            (ms, 0) => Some(StreamId::new(ms - 1, u64::MAX)),
+            (ms, seq) => Some(StreamId::new(ms, seq - 1)),
+        }
+    }
+
+    pub(super) fn to_bytes(self) -> Bytes {
+        Bytes::from(self.to_string())
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+pub type Fields = Vec<(Bytes, Bytes)>;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stream {
+    pub(super) entries: BTreeMap<StreamId, Fields>,
+    // The greatest ID ever added, which new IDs must exceed even after it is trimmed.
+    pub(super) last_id: StreamId,
+    pub(super) max_deleted_id: StreamId,
+    // The number of entries ever added, reported by XINFO and used to compute group lag.
+    pub(super) entries_added: u64,
+    pub(super) groups: BTreeMap<Bytes, ConsumerGroup>,
+}
+
+/// Milliseconds since the unix epoch, the clock stream IDs and delivery times use.
+pub(super) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+impl Stream {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    // Picks the ID for a new entry, failing if it would not exceed the last one.
+    fn next_id(&self, id: XaddId) -> Result<StreamId, BifrostError> {
+        let last = self.last_id;
+        let not_greater = || {
+            BifrostError::StorageError(
+                "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+                    .to_string(),
+            )
+        };
+        let exhausted = || {
+            BifrostError::StorageError(
+                "ERR The stream has exhausted the last possible ID, unable to add more items"
+                    .to_string(),
+            )
+        };
+        match id {
+            XaddId::Auto => {
+                let now = now_ms();
+                if now > last.ms {
+                    Ok(StreamId::new(now, 0))
+                } else {
+                    last.next().ok_or_else(exhausted)
+                }
+            }
+            XaddId::AutoSeq(ms) if ms > last.ms => Ok(StreamId::new(ms, 0)),
+            XaddId::AutoSeq(ms) if ms == last.ms => match last.seq.checked_add(1) {
+                Some(seq) => Ok(StreamId::new(ms, seq)),
+                None => Err(not_greater()),
+            },
+            XaddId::AutoSeq(_) => Err(not_greater()),
+            XaddId::Explicit(StreamId::MIN) => Err(BifrostError::StorageError(
+                "ERR The ID specified in XADD must be greater than 0-0".to_string(),
+            )),
+            XaddId::Explicit(id) if id > last => Ok(id),
+            XaddId::Explicit(_) => Err(not_greater()),
+        }
+    }
+
+    /// Applies MAXLEN or MINID trimming, returning the number of entries removed.
+    fn trim(&mut self, options: &TrimOptions) -> usize {
+        let limit = options.limit.unwrap_or(usize::MAX);
+        let mut removed = 0;
+        while removed < limit {
+            let len = self.entries.len();
+            let Some(entry) = self.entries.first_entry() else {
+                break;
+            };
+            let evict = match options.strategy {
+                TrimStrategy::MaxLen(max_len) => len > max_len,
+                TrimStrategy::MinId(min_id) => *entry.key() < min_id,
+            };
+            if !evict {
+                break;
+            }
+            self.max_deleted_id = self.max_deleted_id.max(*entry.key());
+            entry.remove();
+            removed += 1;
+        }
+        removed
+    }
+
+    pub(super) fn range(&self, start: StreamId, end: StreamId, count: Option<usize>, reverse: bool) -> Vec<RespType> {
+        if start > end {
+            return vec![];
+        }
+        let range = self.entries.range(start..=end);
+        let entries: Box<dyn Iterator<Item = (&StreamId, &Fields)>> = if reverse {
+            Box::new(range.rev())
+        } else {
+            Box::new(range)
+        };
+        entries
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(id, fields)| entry_reply(*id, fields))
+            .collect()
+    }
+
+    pub(super) fn has_entries_after(&self, after: StreamId) -> bool {
+        self.entries.range((Bound::Excluded(after), Bound::Unbounded)).next().is_some()
+    }
+
+    // Entries with an ID greater than `after`, oldest first.
+    pub(super) fn entries_after(&self, after: StreamId, count: Option<usize>) -> Vec<RespType> {
+        match after.next() {
+            Some(start) => self.range(start, StreamId::MAX, count, false),
+            None => vec![],
+        }
+    }
+}
+
+pub(super) fn entry_reply(id: StreamId, fields: &Fields) -> RespType {
+    let mut values = Vec::with_capacity(fields.len() * 2);
+    for (field, value) in fields {
+        values.push(RespType::BulkString(field.clone()));
+        values.push(RespType::BulkString(value.clone()));
+    }
+    RespType::Array(vec![RespType::BulkString(id.to_bytes()), RespType::Array(values)])
+}
+
+/// The ID argument of XADD: `*`, `<ms>-*` or a full ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XaddId {
+    Auto,
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+/// Whether trimming keeps the newest `n` entries or those with an ID of at least `id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimStrategy {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+/// The MAXLEN/MINID options of XADD and XTRIM. Approximate (`~`) trimming is done
+/// exactly, but honours LIMIT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrimOptions {
+    pub strategy: TrimStrategy,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct XaddOptions {
+    pub nomkstream: bool,
+    pub trim: Option<TrimOptions>,
+}
+
+/// Where XREAD starts reading a stream: after an ID, or after the last entry (`$`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamPosition {
+    After(StreamId),
+    Latest,
+}
+
+fn stream_or_create() -> Value {
+    Value::Stream(Stream::default())
+}
+
+impl State {
+    /// Wakes the clients blocked in XREAD or XREADGROUP on the stream at `key` that have
+    /// new entries to read.
+    pub(super) fn serve_stream_waiters(&mut self, key: &[u8], now: Instant) {
+        let Some(mut waiters) = self.stream_waiters.remove(key) else {
+            return;
+        };
+        let Ok(Some(stream)) = self.value_mut(key, now, Value::as_stream_mut) else {
+            // The key no longer holds a stream; the waiters only time out.
+            self.stream_waiters.insert(Bytes::copy_from_slice(key), waiters);
+            return;
+        };
+        waiters.retain(|waiter| {
+            let available = match &waiter.group {
+                None => stream.has_entries_after(waiter.after),
+                Some(read) => stream
+                    .groups
+                    .get(&read.group)
+                    .is_some_and(|group| stream.has_entries_after(group.last_delivered)),
+            };
+            if !available {
+                return waiter.is_pending();
+            }
+            let Some(sender) = waiter.reply.lock().take() else {
+                return false;
+            };
+            // Entries are only delivered to a group if the client is still there to
+            // receive them, since they would otherwise sit in its pending list.
+            if sender.is_closed() {
+                return false;
+            }
+            let entries = match &waiter.group {
+                None => stream.entries_after(waiter.after, waiter.count),
+                Some(read) => stream.read_undelivered(read, waiter.count, now_ms()),
+            };
+            let reply = RespType::Array(vec![RespType::Array(vec![
+                RespType::BulkString(Bytes::copy_from_slice(key)),
+                RespType::Array(entries),
+            ])]);
+            let _ = sender.send(reply);
+            false
+        });
+        if !waiters.is_empty() {
+            self.stream_waiters.insert(Bytes::copy_from_slice(key), waiters);
+        }
+    }
+}
+
+impl Db {
+    /// XADD. Replies with the ID of the new entry, or null if the stream does not exist
+    /// and NOMKSTREAM was given.
+    pub fn xadd(&self, key: &[u8], id: XaddId, fields: Fields, options: XaddOptions) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        let id = match data.value(key, now, Value::as_stream)? {
+            Some(stream) => stream.next_id(id)?,
+            None if options.nomkstream => return Ok(RespType::Null),
+            None => Stream::default().next_id(id)?,
+        };
+
+        let stream = data.value_or_insert(key, now, Value::as_stream_mut, stream_or_create)?;
+        stream.entries.insert(id, fields);
+        stream.last_id = id;
+        stream.entries_added += 1;
+        let trimmed = options.trim.as_ref().map_or(0, |trim| stream.trim(trim));
+        data.touch(key);
+        data.notify(EventClasses::STREAM, "xadd", key);
+        if trimmed > 0 {
+            data.notify(EventClasses::STREAM, "xtrim", key);
+        }
+        data.serve_stream_waiters(key, now);
+        Ok(RespType::BulkString(id.to_bytes()))
+    }
+
+    pub fn xlen(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let len = data.value(key, Instant::now(), Value::as_stream)?.map_or(0, |stream| stream.len());
+        Ok(RespType::Integer(len as i64))
+    }
+
+    /// XRANGE and, with `reverse`, XREVRANGE. Both bounds are inclusive.
+    pub fn xrange(
+        &self,
+        key: &[u8],
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        reverse: bool,
+    ) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let entries = data
+            .value(key, Instant::now(), Value::as_stream)?
+            .map(|stream| stream.range(start, end, count, reverse))
+            .unwrap_or_default();
+        Ok(RespType::Array(entries))
+    }
+
+    pub fn xtrim(&self, key: &[u8], options: TrimOptions) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let removed = data
+            .value_mut(key, Instant::now(), Value::as_stream_mut)?
+            .map_or(0, |stream| stream.trim(&options));
+        if removed > 0 {
+            data.touch(key);
+            data.notify(EventClasses::STREAM, "xtrim", key);
+        }
+        Ok(RespType::Integer(removed as i64))
+    }
+
+    /// XREAD. Replies with the new entries of every stream that has some, or blocks
+    /// until one of them receives an entry when `block` is set. Without `block`, or
+    /// when the timeout elapses, the reply is null.
+    pub fn xread(
+        &self,
+        streams: &[(Bytes, StreamPosition)],
+        count: Option<usize>,
+        block: Option<Option<Duration>>,
+    ) -> Result<Blocking, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        let mut positions = Vec::with_capacity(streams.len());
+        let mut replies = Vec::new();
+        for (key, position) in streams {
+            let stream = data.value(key, now, Value::as_stream)?;
+            let after = match position {
+                StreamPosition::After(id) => *id,
+                StreamPosition::Latest => stream.map_or(StreamId::MIN, Stream::last_id),
+            };
+            let entries = stream.map(|stream| stream.entries_after(after, count)).unwrap_or_default();
+            if !entries.is_empty() {
+                replies.push(RespType::Array(vec![
+                    RespType::BulkString(key.clone()),
+                    RespType::Array(entries),
+                ]));
+            }
+            positions.push((key, after));
+        }
+
+        let timeout = match block {
+            _ if !replies.is_empty() => return Ok(Blocking::Ready(RespType::Array(replies))),
+            None => return Ok(Blocking::Ready(RespType::NullArray)),
+            Some(timeout) => timeout,
+        };
+        let (reply, receiver) = shared_reply();
+        for (key, after) in positions {
+            data.stream_waiters.entry(key.clone()).or_default().push(StreamWaiter {
+                after,
+                count,
+                group: None,
+                reply: reply.clone(),
+            });
+        }
+        Ok(Blocking::Blocked(receiver, timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(values: &[(&str, &str)]) -> Fields {
+        values
+            .iter()
+            .map(|(field, value)| (Bytes::copy_from_slice(field.as_bytes()), Bytes::copy_from_slice(value.as_bytes())))
+            .collect()
+    }
+
+    fn id(ms: u64, seq: u64) -> XaddId {
+        XaddId::Explicit(StreamId::new(ms, seq))
+    }
+
+    fn ids(reply: RespType) -> Vec<String> {
+        let RespType::Array(entries) = reply else {
+            panic!("Expected an array, got {:?}", reply);
+        };
+        entries
+            .into_iter()
+            .map(|entry| match entry {
+                RespType::Array(mut parts) => match parts.remove(0) {
+                    RespType::BulkString(id) => String::from_utf8(id.to_vec()).unwrap(),
+                    other => panic!("Unexpected ID {:?}", other),
+                },
+                other => panic!("Unexpected entry {:?}", other),
+            })
+            .collect()
+    }
+
+    fn ready(blocking: Blocking) -> RespType {
+        match blocking {
+            Blocking::Ready(reply) => reply,
+            Blocking::Blocked(..) => panic!("Expected a reply"),
+        }
+    }
+
+    #[test]
+    fn test_ids() {
+        let db = Db::new();
+        let options = XaddOptions::default();
+        assert_eq!(db.xadd(b"s", id(5, 1), fields(&[("a", "1")]), options).unwrap(), RespType::BulkString(Bytes::from("5-1")));
+        assert!(db.xadd(b"s", id(5, 1), fields(&[("a", "1")]), options).is_err());
+        assert!(db.xadd(b"s", id(4, 9), fields(&[("a", "1")]), options).is_err());
+        assert_eq!(db.xadd(b"s", XaddId::AutoSeq(5), fields(&[("a", "1")]), options).unwrap(), RespType::BulkString(Bytes::from("5-2")));
+        assert_eq!(db.xadd(b"s", XaddId::AutoSeq(7), fields(&[("a", "1")]), options).unwrap(), RespType::BulkString(Bytes::from("7-0")));
+        assert!(matches!(db.xadd(b"s", XaddId::Auto, fields(&[("a", "1")]), options).unwrap(), RespType::BulkString(_)));
+        assert_eq!(db.xlen(b"s").unwrap(), RespType::Integer(4));
+
+        assert!(db.xadd(b"new", id(0, 0), fields(&[("a", "1")]), options).is_err());
+        assert_eq!(db.exists(&[Bytes::from("new")]), RespType::Integer(0));
+        let nomkstream = XaddOptions { nomkstream: true, ..options };
+        assert_eq!(db.xadd(b"new", XaddId::Auto, fields(&[("a", "1")]), nomkstream).unwrap(), RespType::Null);
+        assert_eq!(db.exists(&[Bytes::from("new")]), RespType::Integer(0));
+    }
+
+    #[test]
+    fn test_range_and_trim() {
+        let db = Db::new();
+        for ms in 1..=5 {
+            db.xadd(b"s", id(ms, 0), fields(&[("n", "1")]), XaddOptions::default()).unwrap();
+        }
+        assert_eq!(ids(db.xrange(b"s", StreamId::MIN, StreamId::MAX, None, false).unwrap()), ["1-0", "2-0", "3-0", "4-0", "5-0"]);
+        assert_eq!(ids(db.xrange(b"s", StreamId::new(2, 0), StreamId::new(4, 0), Some(2), false).unwrap()), ["2-0", "3-0"]);
+        assert_eq!(ids(db.xrange(b"s", StreamId::new(2, 0), StreamId::MAX, Some(2), true).unwrap()), ["5-0", "4-0"]);
+
+        let maxlen = TrimOptions { strategy: TrimStrategy::MaxLen(3), limit: None };
+        assert_eq!(db.xtrim(b"s", maxlen).unwrap(), RespType::Integer(2));
+        let minid = TrimOptions { strategy: TrimStrategy::MinId(StreamId::new(5, 0)), limit: Some(1) };
+        assert_eq!(db.xtrim(b"s", minid).unwrap(), RespType::Integer(1));
+        assert_eq!(ids(db.xrange(b"s", StreamId::MIN, StreamId::MAX, None, false).unwrap()), ["4-0", "5-0"]);
+
+        let trim = Some(TrimOptions { strategy: TrimStrategy::MaxLen(1), limit: None });
+        db.xadd(b"s", id(6, 0), fields(&[("n", "1")]), XaddOptions { trim, ..Default::default() }).unwrap();
+        assert_eq!(ids(db.xrange(b"s", StreamId::MIN, StreamId::MAX, None, false).unwrap()), ["6-0"]);
+        // The last ID is kept even when the entry holding it is trimmed away.
+        db.xtrim(b"s", TrimOptions { strategy: TrimStrategy::MaxLen(0), limit: None }).unwrap();
+        assert!(db.xadd(b"s", id(6, 0), fields(&[("n", "1")]), XaddOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_xread() {
+        let db = Db::new();
+        db.xadd(b"a", id(1, 0), fields(&[("n", "1")]), XaddOptions::default()).unwrap();
+        db.xadd(b"a", id(2, 0), fields(&[("n", "2")]), XaddOptions::default()).unwrap();
+
+        let streams = [(Bytes::from("a"), StreamPosition::After(StreamId::new(1, 0))), (Bytes::from("b"), StreamPosition::Latest)];
+        let RespType::Array(reply) = ready(db.xread(&streams, None, None).unwrap()) else {
+            panic!("Expected an array");
+        };
+        assert_eq!(reply.len(), 1);
+        let streams = [(Bytes::from("a"), StreamPosition::Latest)];
+        assert_eq!(ready(db.xread(&streams, None, None).unwrap()), RespType::NullArray);
+
+        let Blocking::Blocked(mut receiver, None) = db.xread(&streams, None, Some(None)).unwrap() else {
+            panic!("Expected to block");
+        };
+        db.xadd(b"a", id(3, 0), fields(&[("n", "3")]), XaddOptions::default()).unwrap();
+        let RespType::Array(reply) = receiver.try_recv().unwrap() else {
+            panic!("Expected an array");
+        };
+        let RespType::Array(stream) = &reply[0] else {
+            panic!("Expected an array");
+        };
+        assert_eq!(stream[0], RespType::BulkString(Bytes::from("a")));
+        assert_eq!(ids(stream[1].clone()), ["3-0"]);
+        assert!(db.data.read().stream_waiters.is_empty());
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let db = Db::new();
+        db.set(Bytes::from("string"), Bytes::from("value"));
+        assert!(db.xadd(b"string", XaddId::Auto, fields(&[("a", "1")]), XaddOptions::default()).is_err());
+        assert!(db.xlen(b"string").is_err());
+        assert!(db.xread(&[(Bytes::from("string"), StreamPosition::Latest)], None, None).is_err());
+    }
+}
@@ -0,0 +1,335 @@
+use bytes::Bytes;
+use std::time::Instant;
+
+use crate::error::BifrostError;
+use crate::resp::{format_double, RespType};
+use super::db::{invalid_expire_time, Db, Expiration, SetCondition, SetOptions};
+use super::notify::EventClasses;
+use super::value::Value;
+
+// The largest string SETRANGE and APPEND may build, Redis' default proto-max-bulk-len.
+const MAX_STRING_LEN: usize = 512 * 1024 * 1024;
+
+/// The expiry change requested by GETEX.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GetExpiry {
+    Set(Expiration),
+    Persist,
+}
+
+fn string_or_create() -> Value {
+    Value::String(Bytes::new())
+}
+
+fn zero_or_create() -> Value {
+    Value::String(Bytes::from_static(b"0"))
+}
+
+fn too_long() -> BifrostError {
+    BifrostError::StorageError("ERR string exceeds maximum allowed size (proto-max-bulk-len)".to_string())
+}
+
+// Like Redis, only the canonical decimal form of an integer counts as one, so " 1",
+// "+1" and "01" are rejected.
+fn as_integer(value: &[u8]) -> Option<i64> {
+    let integer = std::str::from_utf8(value).ok()?.parse::<i64>().ok()?;
+    (integer.to_string().as_bytes() == value).then_some(integer)
+}
+
+impl Db {
+    /// APPEND. Replies with the length of the string after the append.
+    pub fn append(&self, key: &[u8], value: &[u8]) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let string = data.value_or_insert(key, Instant::now(), Value::as_string_mut, string_or_create)?;
+        if string.len() + value.len() > MAX_STRING_LEN {
+            return Err(too_long());
+        }
+        let mut appended = Vec::with_capacity(string.len() + value.len());
+        appended.extend_from_slice(string);
+        appended.extend_from_slice(value);
+        *string = Bytes::from(appended);
+        let len = string.len();
+        data.touch(key);
+        data.notify(EventClasses::STRING, "append", key);
+        Ok(RespType::Integer(len as i64))
+    }
+
+    pub fn strlen(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let len = data.value(key, Instant::now(), Value::as_string)?.map_or(0, |string| string.len());
+        Ok(RespType::Integer(len as i64))
+    }
+
+    /// GETRANGE. Both offsets are inclusive and negative ones count from the end.
+    pub fn getrange(&self, key: &[u8], start: i64, end: i64) -> Result<RespType, BifrostError> {
+        let data = self.data.read();
+        let string = data.value(key, Instant::now(), Value::as_string)?.cloned().unwrap_or_default();
+        let len = string.len() as i64;
+        if start < 0 && end < 0 && start > end {
+            return Ok(RespType::BulkString(Bytes::new()));
+        }
+        let start = if start < 0 { (len + start).max(0) } else { start };
+        let end = if end < 0 { (len + end).max(0) } else { end.min(len - 1) };
+        if len == 0 || start > end {
+            return Ok(RespType::BulkString(Bytes::new()));
+        }
+        Ok(RespType::BulkString(string.slice(start as usize..=end as usize)))
+    }
+
+    /// SETRANGE. Overwrites part of the string starting at `offset`, padding it with
+    /// zero bytes if it is shorter. Replies with the new length.
+    pub fn setrange(&self, key: &[u8], offset: usize, value: &[u8]) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        // An empty value changes nothing and does not create the key.
+        if value.is_empty() {
+            let len = data.value(key, now, Value::as_string)?.map_or(0, |string| string.len());
+            return Ok(RespType::Integer(len as i64));
+        }
+        let end = offset.checked_add(value.len()).filter(|end| *end <= MAX_STRING_LEN).ok_or_else(too_long)?;
+        let string = data.value_or_insert(key, now, Value::as_string_mut, string_or_create)?;
+        let mut bytes = string.to_vec();
+        if bytes.len() < end {
+            bytes.resize(end, 0);
+        }
+        bytes[offset..end].copy_from_slice(value);
+        *string = Bytes::from(bytes);
+        let len = string.len();
+        data.touch(key);
+        data.notify(EventClasses::STRING, "setrange", key);
+        Ok(RespType::Integer(len as i64))
+    }
+
+    /// GETSET. Sets the value, clearing any TTL, and replies with the old one.
+    pub fn getset(&self, key: Bytes, value: Bytes) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let old = data.value(&key, Instant::now(), Value::as_string)?.cloned();
+        data.insert(key.clone(), Value::String(value), None);
+        data.notify(EventClasses::STRING, "set", &key);
+        Ok(old.map_or(RespType::Null, RespType::BulkString))
+    }
+
+    pub fn getdel(&self, key: &[u8]) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let Some(value) = data.value(key, Instant::now(), Value::as_string)?.cloned() else {
+            return Ok(RespType::Null);
+        };
+        data.remove(key);
+        data.notify(EventClasses::GENERIC, "del", key);
+        Ok(RespType::BulkString(value))
+    }
+
+    /// GETEX. Replies with the value after changing its TTL as requested. A deadline in
+    /// the past deletes the key.
+    pub fn getex(&self, key: &[u8], expiry: Option<GetExpiry>) -> Result<RespType, BifrostError> {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        let Some(value) = data.value(key, now, Value::as_string)?.cloned() else {
+            return Ok(RespType::Null);
+        };
+        match expiry {
+            Some(GetExpiry::Set(expiration)) => {
+                let deadline = expiration
+                    .deadline(now)
+                    .ok_or_else(|| BifrostError::StorageError(invalid_expire_time("getex")))?;
+                if deadline <= now {
+                    data.remove(key);
+                    data.notify(EventClasses::GENERIC, "del", key);
+                } else {
+                    data.set_expiry(key, Some(deadline));
+                    data.notify(EventClasses::GENERIC, "expire", key);
+                }
+            }
+            Some(GetExpiry::Persist) if data.get(key, now).is_some_and(|entry| entry.expires_at.is_some()) => {
+                data.set_expiry(key, None);
+                data.notify(EventClasses::GENERIC, "persist", key);
+            }
+            Some(GetExpiry::Persist) | None => {}
+        }
+        Ok(RespType::BulkString(value))
+    }
+
+    /// MGET. Keys that are missing or hold another type reply with null.
+    pub fn mget(&self, keys: &[Bytes]) -> RespType {
+        let now = Instant::now();
+        let data = self.data.read();
+        let values = keys
+            .iter()
+            .map(|key| match data.value(key, now, Value::as_string) {
+                Ok(Some(value)) => RespType::BulkString(value.clone()),
+                _ => RespType::Null,
+            })
+            .collect();
+        RespType::Array(values)
+    }
+
+    /// MSET. All keys are set at once, clearing their TTLs.
+    pub fn mset(&self, pairs: &[(Bytes, Bytes)]) -> RespType {
+        let mut data = self.data.write();
+        for (key, value) in pairs {
+            data.insert(key.clone(), Value::String(value.clone()), None);
+            data.notify(EventClasses::STRING, "set", key);
+        }
+        RespType::SimpleString("OK".to_string())
+    }
+
+    /// MSETNX. Sets the keys only if none of them exist, replying with 1 if it did.
+    pub fn msetnx(&self, pairs: &[(Bytes, Bytes)]) -> RespType {
+        let now = Instant::now();
+        let mut data = self.data.write();
+        if pairs.iter().any(|(key, _)| data.get(key, now).is_some()) {
+            return RespType::Integer(0);
+        }
+        for (key, value) in pairs {
+            data.insert(key.clone(), Value::String(value.clone()), None);
+            data.notify(EventClasses::STRING, "set", key);
+        }
+        RespType::Integer(1)
+    }
+
+    pub fn setnx(&self, key: Bytes, value: Bytes) -> RespType {
+        let options = SetOptions {
+            condition: SetCondition::IfNotExists,
+            ..Default::default()
+        };
+        match self.set_with_options(key, value, options) {
+            RespType::Null => RespType::Integer(0),
+            _ => RespType::Integer(1),
+        }
+    }
+
+    /// INCRBY. A missing key counts as 0. The value is updated in place, so the key
+    /// keeps its TTL.
+    pub fn incrby(&self, key: &[u8], delta: i64) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let string = data.value_or_insert(key, Instant::now(), Value::as_string_mut, zero_or_create)?;
+        let current = as_integer(string).ok_or_else(|| {
+            BifrostError::StorageError("ERR value is not an integer or out of range".to_string())
+        })?;
+        let result = current.checked_add(delta).ok_or_else(|| {
+            BifrostError::StorageError("ERR increment or decrement would overflow".to_string())
+        })?;
+        *string = Bytes::from(result.to_string());
+        data.touch(key);
+        data.notify(EventClasses::STRING, "incrby", key);
+        Ok(RespType::Integer(result))
+    }
+
+    pub fn decrby(&self, key: &[u8], delta: i64) -> Result<RespType, BifrostError> {
+        let delta = delta.checked_neg().ok_or_else(|| {
+            BifrostError::StorageError("ERR decrement would overflow".to_string())
+        })?;
+        self.incrby(key, delta)
+    }
+
+    pub fn incrbyfloat(&self, key: &[u8], delta: f64) -> Result<RespType, BifrostError> {
+        let mut data = self.data.write();
+        let string = data.value_or_insert(key, Instant::now(), Value::as_string_mut, zero_or_create)?;
+        let current = std::str::from_utf8(string)
+            .ok()
+            .and_then(|value| value.parse::<f64>().ok())
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| BifrostError::StorageError("ERR value is not a valid float".to_string()))?;
+        let result = current + delta;
+        if !result.is_finite() {
+            return Err(BifrostError::StorageError(
+                "ERR increment would produce NaN or Infinity".to_string(),
+            ));
+        }
+        *string = Bytes::from(format_double(result));
+        let reply = RespType::BulkString(string.clone());
+        data.touch(key);
+        data.notify(EventClasses::STRING, "incrbyfloat", key);
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::list::ListEnd;
+
+    fn bulk(value: &str) -> RespType {
+        RespType::BulkString(Bytes::copy_from_slice(value.as_bytes()))
+    }
+
+    #[test]
+    fn test_counters() {
+        let db = Db::new();
+        db.set(Bytes::from("counter"), Bytes::from("5"));
+        assert_eq!(db.incr(b"counter").unwrap(), RespType::Integer(6));
+        assert_eq!(db.incrby(b"counter", 10).unwrap(), RespType::Integer(16));
+        assert_eq!(db.decrby(b"counter", 20).unwrap(), RespType::Integer(-4));
+        assert_eq!(db.get(b"counter").unwrap(), Some(Bytes::from("-4")));
+
+        db.set(Bytes::from("max"), Bytes::from(i64::MAX.to_string()));
+        assert!(db.incr(b"max").is_err());
+        assert!(db.decrby(b"max", i64::MIN).is_err());
+        for invalid in ["abc", " 1", "+1", "01", ""] {
+            db.set(Bytes::from("invalid"), Bytes::from(invalid));
+            assert!(db.incr(b"invalid").is_err(), "{:?} is not an integer", invalid);
+        }
+
+        assert_eq!(db.incrbyfloat(b"float", 10.5).unwrap(), bulk("10.5"));
+        assert_eq!(db.incrbyfloat(b"float", 0.1).unwrap(), bulk("10.6"));
+        assert_eq!(db.incrbyfloat(b"counter", 1.5).unwrap(), bulk("-2.5"));
+        assert!(db.incrbyfloat(b"float", f64::MAX).is_ok());
+        assert!(db.incrbyfloat(b"float", f64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_ranges() {
+        let db = Db::new();
+        assert_eq!(db.append(b"key", b"Hello").unwrap(), RespType::Integer(5));
+        assert_eq!(db.append(b"key", b" World").unwrap(), RespType::Integer(11));
+        assert_eq!(db.strlen(b"key").unwrap(), RespType::Integer(11));
+        assert_eq!(db.getrange(b"key", 0, 4).unwrap(), bulk("Hello"));
+        assert_eq!(db.getrange(b"key", -5, -1).unwrap(), bulk("World"));
+        assert_eq!(db.getrange(b"key", 6, 100).unwrap(), bulk("World"));
+        assert_eq!(db.getrange(b"key", -1, -5).unwrap(), bulk(""));
+        assert_eq!(db.getrange(b"missing", 0, -1).unwrap(), bulk(""));
+
+        assert_eq!(db.setrange(b"key", 6, b"Redis").unwrap(), RespType::Integer(11));
+        assert_eq!(db.get(b"key").unwrap(), Some(Bytes::from("Hello Redis")));
+        assert_eq!(db.setrange(b"padded", 3, b"!").unwrap(), RespType::Integer(4));
+        assert_eq!(db.get(b"padded").unwrap(), Some(Bytes::from_static(b"\0\0\0!")));
+        assert_eq!(db.setrange(b"empty", 5, b"").unwrap(), RespType::Integer(0));
+        assert_eq!(db.exists(&[Bytes::from("empty")]), RespType::Integer(0));
+        assert!(db.setrange(b"key", MAX_STRING_LEN, b"x").is_err());
+    }
+
+    #[test]
+    fn test_multiple_keys() {
+        let db = Db::new();
+        let pairs = [(Bytes::from("a"), Bytes::from("1")), (Bytes::from("b"), Bytes::from("2"))];
+        db.mset(&pairs);
+        db.push(b"list", &[Bytes::from("x")], ListEnd::Right).unwrap();
+        let keys = [Bytes::from("a"), Bytes::from("list"), Bytes::from("b"), Bytes::from("c")];
+        assert_eq!(db.mget(&keys), RespType::Array(vec![bulk("1"), RespType::Null, bulk("2"), RespType::Null]));
+
+        let pairs = [(Bytes::from("c"), Bytes::from("3")), (Bytes::from("a"), Bytes::from("4"))];
+        assert_eq!(db.msetnx(&pairs), RespType::Integer(0));
+        assert_eq!(db.exists(&[Bytes::from("c")]), RespType::Integer(0));
+        assert_eq!(db.setnx(Bytes::from("c"), Bytes::from("3")), RespType::Integer(1));
+        assert_eq!(db.setnx(Bytes::from("c"), Bytes::from("5")), RespType::Integer(0));
+
+        assert_eq!(db.getset(Bytes::from("c"), Bytes::from("6")).unwrap(), bulk("3"));
+        assert_eq!(db.getdel(b"c").unwrap(), bulk("6"));
+        assert_eq!(db.getdel(b"c").unwrap(), RespType::Null);
+        assert!(db.getset(Bytes::from("list"), Bytes::from("6")).is_err());
+    }
+
+    #[test]
+    fn test_getex() {
+        let db = Db::new();
+        db.set(Bytes::from("key"), Bytes::from("value"));
+        let expiry = GetExpiry::Set(Expiration::In(std::time::Duration::from_secs(100)));
+        assert_eq!(db.getex(b"key", Some(expiry)).unwrap(), bulk("value"));
+        assert_eq!(db.ttl(b"key"), RespType::Integer(100));
+        assert_eq!(db.getex(b"key", Some(GetExpiry::Persist)).unwrap(), bulk("value"));
+        assert_eq!(db.ttl(b"key"), RespType::Integer(-1));
+        let past = GetExpiry::Set(Expiration::At(std::time::SystemTime::UNIX_EPOCH));
+        assert_eq!(db.getex(b"key", Some(past)).unwrap(), bulk("value"));
+        assert_eq!(db.exists(&[Bytes::from("key")]), RespType::Integer(0));
+    }
+}
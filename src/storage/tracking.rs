@@ -0,0 +1,288 @@
+use bytes::Bytes;
+use parking_lot::Mutex;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::BifrostError;
+use crate::pubsub::Broker;
+use crate::resp::RespType;
+
+thread_local! {
+    // The client whose command is running on this thread, if any.
+    static CALLER: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Runs `f` on behalf of client `id`, so that keys it modifies are not reported back to
+/// it when it asked for NOLOOP.
+pub fn on_behalf_of<R>(id: u64, f: impl FnOnce() -> R) -> R {
+    let previous = CALLER.replace(Some(id));
+    let result = f();
+    CALLER.set(previous);
+    result
+}
+
+/// The options of CLIENT TRACKING ON.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackingOptions {
+    /// The client that receives the invalidations instead of this one.
+    pub redirect: Option<u64>,
+    /// Whether to report every modified key matching `prefixes` rather than only the
+    /// keys the client read.
+    pub bcast: bool,
+    pub prefixes: Vec<Bytes>,
+    /// Only track reads right after CLIENT CACHING YES.
+    pub optin: bool,
+    /// Track reads except right after CLIENT CACHING NO.
+    pub optout: bool,
+    /// Leave out the keys the client modified itself.
+    pub noloop: bool,
+}
+
+impl TrackingOptions {
+    fn matches(&self, key: &[u8]) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|prefix| key.starts_with(prefix))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Table {
+    clients: HashMap<u64, TrackingOptions>,
+    // The clients that read each key since it was last modified. Like Redis, keys are
+    // tracked by name alone, whatever database they were read from.
+    keys: HashMap<Bytes, HashSet<u64>>,
+}
+
+/// Remembers which keys clients with CLIENT TRACKING on have read, and sends them an
+/// invalidation when one of those keys is modified. Clients in BCAST mode are told
+/// about every modified key under their prefixes instead. Shared by every database.
+#[derive(Debug, Default)]
+pub struct Tracker {
+    broker: Broker,
+    // The number of clients with tracking on, so that writes can skip the table when
+    // nobody tracks anything.
+    active: AtomicUsize,
+    table: Mutex<Table>,
+}
+
+fn error(message: &str) -> BifrostError {
+    BifrostError::StorageError(format!("ERR {}", message))
+}
+
+impl Tracker {
+    pub fn new(broker: Broker) -> Self {
+        Tracker { broker, active: AtomicUsize::new(0), table: Mutex::default() }
+    }
+
+    /// CLIENT TRACKING ON. A client that already tracks keeps its mode, and any
+    /// prefixes given are added to the ones it has.
+    pub fn enable(&self, id: u64, mut options: TrackingOptions) -> Result<(), BifrostError> {
+        if options.redirect.is_some_and(|redirect| redirect != id && !self.broker.has_client(redirect)) {
+            return Err(error("The client ID you want redirect to does not exist"));
+        }
+        let mut table = self.table.lock();
+        if let Some(current) = table.clients.get(&id) {
+            if current.bcast != options.bcast {
+                return Err(error(
+                    "You can't switch BCAST mode on/off before disabling tracking for this client, and then re-enabling it with a different mode.",
+                ));
+            }
+            options.prefixes.splice(0..0, current.prefixes.iter().cloned());
+        }
+        // Once sorted, a prefix of another sorts right before everything starting with it.
+        options.prefixes.sort();
+        options.prefixes.dedup();
+        if let Some(pair) = options.prefixes.windows(2).find(|pair| pair[1].starts_with(&pair[0])) {
+            return Err(error(&format!(
+                "Prefix '{}' overlaps with an existing prefix '{}'. Prefixes for a single client must not overlap.",
+                String::from_utf8_lossy(&pair[1]),
+                String::from_utf8_lossy(&pair[0])
+            )));
+        }
+        if table.clients.insert(id, options).is_none() {
+            self.active.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// CLIENT TRACKING OFF, also run when the connection closes. Keys it read are
+    /// forgotten lazily, the next time they are modified.
+    pub fn disable(&self, id: u64) {
+        if self.table.lock().clients.remove(&id).is_some() {
+            self.active.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The tracking options of a client, or `None` if tracking is off.
+    pub fn options(&self, id: u64) -> Option<TrackingOptions> {
+        self.table.lock().clients.get(&id).cloned()
+    }
+
+    /// Remembers that client `id` read `keys`, unless its mode says otherwise. `caching`
+    /// is what CLIENT CACHING set for this command, if anything.
+    pub fn track(&self, id: u64, keys: &[Bytes], caching: Option<bool>) {
+        if keys.is_empty() || self.active.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let mut table = self.table.lock();
+        let Some(options) = table.clients.get(&id) else {
+            return;
+        };
+        let tracked = if options.bcast {
+            false
+        } else if options.optin {
+            caching == Some(true)
+        } else if options.optout {
+            caching != Some(false)
+        } else {
+            true
+        };
+        if tracked {
+            for key in keys {
+                table.keys.entry(key.clone()).or_default().insert(id);
+            }
+        }
+    }
+
+    /// Tells the clients that read `key`, and the BCAST clients it matches, that it was
+    /// modified. Clients are only told once about the keys they read until they read
+    /// them again.
+    pub(super) fn invalidate(&self, key: &[u8]) {
+        if self.active.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let caller = CALLER.get();
+        let mut table = self.table.lock();
+        let readers = table.keys.remove(key).unwrap_or_default();
+        let keys = RespType::Array(vec![RespType::BulkString(Bytes::copy_from_slice(key))]);
+        for (&id, options) in &table.clients {
+            let interested = if options.bcast { options.matches(key) } else { readers.contains(&id) };
+            if interested && !(options.noloop && caller == Some(id)) {
+                self.send(id, options, keys.clone());
+            }
+        }
+    }
+
+    /// Tells every tracking client to drop its whole cache, after FLUSHDB, FLUSHALL or
+    /// SWAPDB.
+    pub(super) fn invalidate_all(&self) {
+        if self.active.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let mut table = self.table.lock();
+        table.keys.clear();
+        for (&id, options) in &table.clients {
+            self.send(id, options, RespType::Null);
+        }
+    }
+
+    // Sends an invalidation to the client or to the one it redirects to. A client whose
+    // redirect target disconnected is told so instead.
+    fn send(&self, id: u64, options: &TrackingOptions, keys: RespType) {
+        let target = options.redirect.unwrap_or(id);
+        let invalidation = RespType::Push(vec![RespType::BulkString(Bytes::from_static(b"invalidate")), keys]);
+        if !self.broker.send_to(target, invalidation) && target != id {
+            self.broker.send_to(
+                id,
+                RespType::Push(vec![
+                    RespType::BulkString(Bytes::from_static(b"tracking-redir-broken")),
+                    RespType::Integer(target as i64),
+                ]),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pubsub::Subscriptions;
+    use futures::FutureExt;
+
+    fn invalidation(keys: &[&'static str]) -> Option<RespType> {
+        let keys = keys.iter().map(|key| RespType::BulkString(Bytes::from_static(key.as_bytes()))).collect();
+        Some(RespType::Push(vec![RespType::BulkString(Bytes::from_static(b"invalidate")), RespType::Array(keys)]))
+    }
+
+    #[tokio::test]
+    async fn test_default_mode() {
+        let broker = Broker::default();
+        let tracker = Tracker::new(broker.clone());
+        let mut subscriptions = Subscriptions::new(1, broker);
+        tracker.enable(1, TrackingOptions::default()).unwrap();
+
+        tracker.track(1, &[Bytes::from("a"), Bytes::from("b")], None);
+        tracker.invalidate(b"c");
+        tracker.invalidate(b"a");
+        assert_eq!(subscriptions.next_message().await, invalidation(&["a"]));
+
+        // Only the first modification after a read is reported.
+        tracker.invalidate(b"a");
+        tracker.invalidate(b"b");
+        assert_eq!(subscriptions.next_message().await, invalidation(&["b"]));
+
+        // Nor is anything once tracking is off.
+        tracker.track(1, &[Bytes::from("a")], None);
+        tracker.disable(1);
+        tracker.invalidate(b"a");
+        assert!(subscriptions.next_message().now_or_never().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_optin_and_noloop() {
+        let broker = Broker::default();
+        let tracker = Tracker::new(broker.clone());
+        let mut subscriptions = Subscriptions::new(1, broker);
+        tracker.enable(1, TrackingOptions { optin: true, noloop: true, ..Default::default() }).unwrap();
+
+        tracker.track(1, &[Bytes::from("uncached")], None);
+        tracker.track(1, &[Bytes::from("cached")], Some(true));
+        tracker.invalidate(b"uncached");
+        on_behalf_of(1, || tracker.invalidate(b"cached"));
+        tracker.track(1, &[Bytes::from("cached")], Some(true));
+        on_behalf_of(2, || tracker.invalidate(b"cached"));
+        assert_eq!(subscriptions.next_message().await, invalidation(&["cached"]));
+        assert!(subscriptions.next_message().now_or_never().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bcast_and_redirect() {
+        let broker = Broker::default();
+        let tracker = Tracker::new(broker.clone());
+        let mut tracking = Subscriptions::new(1, broker.clone());
+        let mut target = Subscriptions::new(2, broker.clone());
+        let bcast = |prefixes: &[&'static str]| TrackingOptions {
+            redirect: Some(2),
+            bcast: true,
+            prefixes: prefixes.iter().map(|prefix| Bytes::from_static(prefix.as_bytes())).collect(),
+            ..Default::default()
+        };
+        assert!(tracker.enable(1, TrackingOptions { redirect: Some(3), ..Default::default() }).is_err());
+        tracker.enable(1, bcast(&["user:"])).unwrap();
+        assert!(tracker.enable(1, bcast(&["user:1"])).is_err());
+        assert!(tracker.enable(1, TrackingOptions::default()).is_err());
+        tracker.enable(1, bcast(&["item:"])).unwrap();
+
+        tracker.invalidate(b"user:1");
+        tracker.invalidate(b"other");
+        tracker.invalidate(b"item:1");
+        assert_eq!(target.next_message().await, invalidation(&["user:1"]));
+        assert_eq!(target.next_message().await, invalidation(&["item:1"]));
+
+        tracker.invalidate_all();
+        assert_eq!(
+            target.next_message().await,
+            Some(RespType::Push(vec![RespType::BulkString(Bytes::from_static(b"invalidate")), RespType::Null]))
+        );
+
+        drop(target);
+        tracker.invalidate(b"user:2");
+        assert_eq!(
+            tracking.next_message().await,
+            Some(RespType::Push(vec![
+                RespType::BulkString(Bytes::from_static(b"tracking-redir-broken")),
+                RespType::Integer(2),
+            ]))
+        );
+    }
+}
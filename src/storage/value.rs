@@ -0,0 +1,128 @@
+use bytes::Bytes;
+use std::collections::VecDeque;
+
+use super::scan::ScanMap;
+use super::set::SetValue;
+use super::sorted_set::SortedSet;
+use super::stream::Stream;
+
+/// A value stored under a key, one variant per Redis data type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(Bytes),
+    List(VecDeque<Bytes>),
+    Hash(ScanMap<Bytes>),
+    Set(SetValue),
+    SortedSet(SortedSet),
+    Stream(Stream),
+}
+
+impl Value {
+    /// The name reported by the TYPE command.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::SortedSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    /// Collections are deleted once their last element is removed. Streams are kept,
+    /// since they remember their last ID.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Value::String(_) | Value::Stream(_) => false,
+            Value::List(list) => list.is_empty(),
+            Value::Hash(hash) => hash.is_empty(),
+            Value::Set(set) => set.is_empty(),
+            Value::SortedSet(zset) => zset.is_empty(),
+        }
+    }
+
+    pub fn as_string(&self) -> Option<&Bytes> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_string_mut(&mut self) -> Option<&mut Bytes> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&VecDeque<Bytes>> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Option<&mut VecDeque<Bytes>> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_hash(&self) -> Option<&ScanMap<Bytes>> {
+        match self {
+            Value::Hash(hash) => Some(hash),
+            _ => None,
+        }
+    }
+
+    pub fn as_hash_mut(&mut self) -> Option<&mut ScanMap<Bytes>> {
+        match self {
+            Value::Hash(hash) => Some(hash),
+            _ => None,
+        }
+    }
+
+    pub fn as_set(&self) -> Option<&SetValue> {
+        match self {
+            Value::Set(set) => Some(set),
+            _ => None,
+        }
+    }
+
+    pub fn as_set_mut(&mut self) -> Option<&mut SetValue> {
+        match self {
+            Value::Set(set) => Some(set),
+            _ => None,
+        }
+    }
+
+    pub fn as_sorted_set(&self) -> Option<&SortedSet> {
+        match self {
+            Value::SortedSet(zset) => Some(zset),
+            _ => None,
+        }
+    }
+
+    pub fn as_sorted_set_mut(&mut self) -> Option<&mut SortedSet> {
+        match self {
+            Value::SortedSet(zset) => Some(zset),
+            _ => None,
+        }
+    }
+
+    pub fn as_stream(&self) -> Option<&Stream> {
+        match self {
+            Value::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+
+    pub fn as_stream_mut(&mut self) -> Option<&mut Stream> {
+        match self {
+            Value::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+}
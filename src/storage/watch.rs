@@ -0,0 +1,134 @@
+use bytes::Bytes;
+use std::time::Instant;
+
+use super::db::Db;
+
+/// How many clients watch a key, and a counter bumped whenever the key is modified.
+#[derive(Debug, Default)]
+pub(super) struct Watch {
+    clients: usize,
+    pub(super) version: u64,
+}
+
+/// A key a client WATCHes, remembered as it was when the client started watching.
+/// Dropping it stops watching the key.
+#[derive(Debug)]
+pub struct WatchedKey {
+    db: Db,
+    key: Bytes,
+    version: u64,
+    existed: bool,
+}
+
+impl WatchedKey {
+    /// Whether the key was modified since it was watched. A key that expired in the
+    /// meantime counts as modified too.
+    pub fn is_modified(&self) -> bool {
+        let data = self.db.data.read();
+        let version = data.watched.get(&self.key).map_or(0, |watch| watch.version);
+        version != self.version || (self.existed && data.get(&self.key, Instant::now()).is_none())
+    }
+}
+
+impl Drop for WatchedKey {
+    fn drop(&mut self) {
+        let mut data = self.db.data.write();
+        if let Some(watch) = data.watched.get_mut(&self.key) {
+            watch.clients -= 1;
+            if watch.clients == 0 {
+                data.watched.remove(&self.key);
+            }
+        }
+    }
+}
+
+impl Db {
+    /// WATCH. EXEC aborts if the key is modified before the returned handle is dropped.
+    pub fn watch(&self, key: Bytes) -> WatchedKey {
+        let mut data = self.data.write();
+        let existed = data.get(&key, Instant::now()).is_some();
+        let watch = data.watched.entry(key.clone()).or_default();
+        watch.clients += 1;
+        WatchedKey { db: self.clone(), key, version: watch.version, existed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::db::{Expiration, SetCondition};
+    use crate::storage::list::ListEnd;
+    use crate::storage::sorted_set::ZaddOptions;
+    use std::time::Duration;
+
+    #[test]
+    fn test_watch() {
+        let db = Db::new();
+        db.set(Bytes::from("key"), Bytes::from("1"));
+        let watched = db.watch(Bytes::from("key"));
+        let other = db.watch(Bytes::from("other"));
+        assert!(!watched.is_modified());
+
+        db.set(Bytes::from("unrelated"), Bytes::from("1"));
+        db.get(b"key").unwrap();
+        assert!(!watched.is_modified());
+
+        db.set(Bytes::from("key"), Bytes::from("2"));
+        assert!(watched.is_modified());
+        assert!(!other.is_modified());
+
+        // A key created and deleted again still counts as modified.
+        db.set(Bytes::from("other"), Bytes::from("1"));
+        db.del(&[Bytes::from("other")]);
+        assert!(other.is_modified());
+
+        drop(watched);
+        drop(other);
+        assert!(db.data.read().watched.is_empty());
+    }
+
+    #[test]
+    fn test_watch_expiry_and_flush() {
+        let db = Db::new();
+        db.set(Bytes::from("key"), Bytes::from("1"));
+        db.expire(b"key", Expiration::In(Duration::from_millis(20)), Default::default(), "expire");
+        let expiring = db.watch(Bytes::from("key"));
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(expiring.is_modified());
+
+        let flushed = db.watch(Bytes::from("missing"));
+        db.flushdb();
+        assert!(flushed.is_modified());
+    }
+
+    #[test]
+    fn test_no_op_writes_leave_watch_alone() {
+        let db = Db::new();
+        db.set(Bytes::from("string"), Bytes::from("1"));
+        db.sadd(b"set", &[Bytes::from("a")]).unwrap();
+        let string = db.watch(Bytes::from("string"));
+        let set = db.watch(Bytes::from("set"));
+        let missing = db.watch(Bytes::from("missing"));
+
+        // Writes that fail, or that Redis skips because there is nothing to do, do not
+        // abort EXEC.
+        assert!(db.push(b"string", &[Bytes::from("a")], ListEnd::Left).is_err());
+        db.srem(b"set", &[Bytes::from("b")]).unwrap();
+        db.sadd(b"set", &[Bytes::from("a")]).unwrap();
+        db.del(&[Bytes::from("missing")]);
+        let only_existing = ZaddOptions { condition: SetCondition::IfExists, ..Default::default() };
+        db.zadd(b"missing", &[(1.0, Bytes::from("a"))], only_existing).unwrap();
+        assert!(!string.is_modified());
+        assert!(!set.is_modified());
+        assert!(!missing.is_modified());
+
+        // Any other successful write does, even if the value ends up the same.
+        db.incrby(b"string", 0).unwrap();
+        assert!(string.is_modified());
+        let string = db.watch(Bytes::from("string"));
+        db.set(Bytes::from("string"), Bytes::from("1"));
+        assert!(string.is_modified());
+        db.srem(b"set", &[Bytes::from("a")]).unwrap();
+        assert!(set.is_modified());
+    }
+}
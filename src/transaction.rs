@@ -0,0 +1,182 @@
+use bytes::Bytes;
+
+use crate::client::Client;
+use crate::commands::{execute_tracked, BlockingCommand, Command, ConnectionCommand};
+use crate::error::BifrostError;
+use crate::parser::{
+    parse_blocking_command, parse_command, parse_connection_command, parse_pubsub_command,
+    parse_script_command,
+};
+use crate::pubsub::{PubSubCommand, Subscriptions};
+use crate::scripting::{ScriptCommand, Scripts};
+use crate::resp::RespType;
+use crate::storage::blocking::Blocking;
+use crate::storage::databases::Databases;
+use crate::storage::watch::WatchedKey;
+
+/// The commands that start, run and guard a transaction.
+pub enum TransactionCommand {
+    Multi,
+    Exec,
+    Discard,
+    Watch(Vec<Bytes>),
+    Unwatch,
+}
+
+// A command queued between MULTI and EXEC.
+enum Queued {
+    Command(Box<dyn Command>),
+    Connection(Box<dyn ConnectionCommand>),
+    Blocking(Box<dyn BlockingCommand>),
+    PubSub(PubSubCommand),
+    Script(ScriptCommand),
+    Unwatch,
+}
+
+/// The transaction state of a connection: the keys it watches and, after MULTI, the
+/// commands it queued for EXEC.
+#[derive(Default)]
+pub struct Transaction {
+    watched: Vec<WatchedKey>,
+    queued: Option<Vec<Queued>>,
+    // Set when a command could not be queued, so that EXEC discards the transaction.
+    failed: bool,
+}
+
+fn ok() -> RespType {
+    RespType::SimpleString("OK".to_string())
+}
+
+impl Transaction {
+    /// Whether the connection is between MULTI and EXEC, so requests are queued rather
+    /// than run.
+    pub fn is_queuing(&self) -> bool {
+        self.queued.is_some()
+    }
+
+    /// Queues a request for EXEC. A request that does not parse is rejected right away
+    /// and makes EXEC fail, as in Redis.
+    pub fn queue(&mut self, request: RespType) -> Result<RespType, BifrostError> {
+        let command = parse_queued(request).inspect_err(|_| self.fail())?;
+        if let Some(queued) = &mut self.queued {
+            queued.push(command);
+        }
+        Ok(RespType::SimpleString("QUEUED".to_string()))
+    }
+
+    /// Makes EXEC fail after a malformed command was sent during MULTI.
+    pub fn fail(&mut self) {
+        if self.is_queuing() {
+            self.failed = true;
+        }
+    }
+
+    pub fn execute(
+        &mut self,
+        command: TransactionCommand,
+        client: &mut Client,
+        databases: &Databases,
+        subscriptions: &mut Subscriptions,
+        scripts: &Scripts,
+    ) -> RespType {
+        match command {
+            TransactionCommand::Multi if self.is_queuing() => {
+                RespType::Error("ERR MULTI calls can not be nested".to_string())
+            }
+            TransactionCommand::Multi => {
+                self.queued = Some(Vec::new());
+                ok()
+            }
+            TransactionCommand::Exec => self.exec(client, databases, subscriptions, scripts),
+            TransactionCommand::Discard => match self.queued.take() {
+                Some(_) => {
+                    self.failed = false;
+                    self.watched.clear();
+                    ok()
+                }
+                None => RespType::Error("ERR DISCARD without MULTI".to_string()),
+            },
+            TransactionCommand::Watch(_) if self.is_queuing() => {
+                RespType::Error("ERR WATCH inside MULTI is not allowed".to_string())
+            }
+            TransactionCommand::Watch(keys) => {
+                let db = &databases[client.db];
+                self.watched.extend(keys.into_iter().map(|key| db.watch(key)));
+                ok()
+            }
+            TransactionCommand::Unwatch => match &mut self.queued {
+                Some(queued) => {
+                    queued.push(Queued::Unwatch);
+                    RespType::SimpleString("QUEUED".to_string())
+                }
+                None => {
+                    self.watched.clear();
+                    ok()
+                }
+            },
+        }
+    }
+
+    // EXEC. Runs the queued commands with every other command held off, unless a
+    // watched key was modified, in which case it replies with a null. The connection
+    // stops watching its keys either way.
+    fn exec(
+        &mut self,
+        client: &mut Client,
+        databases: &Databases,
+        subscriptions: &mut Subscriptions,
+        scripts: &Scripts,
+    ) -> RespType {
+        let Some(queued) = self.queued.take() else {
+            return RespType::Error("ERR EXEC without MULTI".to_string());
+        };
+        let watched = std::mem::take(&mut self.watched);
+        if std::mem::take(&mut self.failed) {
+            return RespType::Error(
+                "EXECABORT Transaction discarded because of previous errors.".to_string(),
+            );
+        }
+
+        let _exclusive = databases.exclusive();
+        if watched.iter().any(WatchedKey::is_modified) {
+            return RespType::NullArray;
+        }
+        let replies = queued
+            .into_iter()
+            .map(|command| match command {
+                Queued::Command(command) => execute_tracked(command.as_ref(), client, None, databases),
+                Queued::Connection(command) => command.execute(client, databases),
+                // Nothing can push while the transaction runs, so blocking commands
+                // reply as if they timed out.
+                Queued::Blocking(command) => match command.execute(&databases[client.db]) {
+                    Blocking::Ready(reply) => reply,
+                    Blocking::Blocked(..) => RespType::NullArray,
+                },
+                // Subscribing to several channels replies once for each of them.
+                Queued::PubSub(command) => {
+                    let mut replies = subscriptions.execute(command);
+                    if replies.len() == 1 { replies.remove(0) } else { RespType::Array(replies) }
+                }
+                Queued::Script(command) => scripts.execute_queued(command, client, databases),
+                Queued::Unwatch => ok(),
+            })
+            .collect();
+        RespType::Array(replies)
+    }
+}
+
+fn parse_queued(request: RespType) -> Result<Queued, BifrostError> {
+    if let Some(command) = parse_connection_command(&request) {
+        return command.map(Queued::Connection);
+    }
+    if let Some(command) = parse_blocking_command(&request) {
+        return command.map(Queued::Blocking);
+    }
+    if let Some(command) = parse_pubsub_command(&request) {
+        return command.map(Queued::PubSub);
+    }
+    if let Some(command) = parse_script_command(&request) {
+        return command.map(Queued::Script);
+    }
+    parse_command(request).map(Queued::Command)
+}